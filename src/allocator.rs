@@ -0,0 +1,319 @@
+use crate::{Algorithm, Vector2};
+
+pub use max_rects::MaxRectsHeuristic;
+
+mod max_rects;
+
+/// Per-algorithm tuning, each algorithm ignores the options meant for the others.
+#[derive(Copy, Clone, Debug)]
+pub struct AllocatorOptions {
+    pub max_rects_heuristic: MaxRectsHeuristic,
+    /// Etagere and guillotiere round every allocation up to a multiple of this.
+    pub alignment: u32,
+    pub etagere_columns: u32,
+    pub etagere_vertical_shelves: bool,
+    /// Guillotiere keeps free rectangles in separate lists for small, medium and large sizes,
+    /// the small threshold must not exceed the large one.
+    pub small_size_threshold: u32,
+    pub large_size_threshold: u32,
+}
+
+impl Default for AllocatorOptions {
+    fn default() -> Self {
+        Self {
+            max_rects_heuristic: MaxRectsHeuristic::default(),
+            alignment: 1,
+            etagere_columns: 1,
+            etagere_vertical_shelves: false,
+            small_size_threshold: 32,
+            large_size_threshold: 256,
+        }
+    }
+}
+
+pub enum Allocator {
+    Etagere(etagere::AtlasAllocator),
+    Guillotiere(guillotiere::AtlasAllocator),
+    MaxRects(max_rects::MaxRects),
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Allocation {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// Identifies an allocation to [`Allocator::deallocate`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct AllocationId(Id);
+
+// Max rects has no ids of its own, the rectangle is what gets freed
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Id {
+    Etagere(etagere::AllocId),
+    Guillotiere(guillotiere::AllocId),
+    MaxRects(Allocation),
+}
+
+impl Allocation {
+    // Sprites are drawn from the allocation's corner even when the allocator rounded its size
+    // up, the center is floored to whole pixels
+    pub fn center(&self, width: u32, height: u32) -> Vector2 {
+        Vector2::new(
+            (self.x + width as i32 / 2) as f32,
+            (self.y + height as i32 / 2) as f32,
+        )
+    }
+}
+
+impl Allocator {
+    pub fn new(algorithm: Algorithm, width: u32, height: u32) -> Self {
+        Self::with_options(algorithm, width, height, AllocatorOptions::default())
+    }
+
+    pub fn with_options(
+        algorithm: Algorithm,
+        width: u32,
+        height: u32,
+        options: AllocatorOptions,
+    ) -> Self {
+        match algorithm {
+            Algorithm::Etagere => {
+                // Etagere divides by the column width, so every column has to be at least one
+                // alignment wide, even on pages narrower than the alignment itself
+                let extent = if options.etagere_vertical_shelves {
+                    height
+                } else {
+                    width
+                };
+                let alignment = options.alignment.min(extent).max(1);
+
+                Self::Etagere(etagere::AtlasAllocator::with_options(
+                    etagere::size2(width as i32, height as i32),
+                    &etagere::AllocatorOptions {
+                        alignment: etagere::size2(alignment as i32, alignment as i32),
+                        vertical_shelves: options.etagere_vertical_shelves,
+                        num_columns: options.etagere_columns.min(extent / alignment).max(1) as i32,
+                    },
+                ))
+            }
+            Algorithm::Guillotiere => Self::Guillotiere(guillotiere::AtlasAllocator::with_options(
+                guillotiere::size2(width as i32, height as i32),
+                &guillotiere::AllocatorOptions {
+                    alignment: guillotiere::size2(
+                        options.alignment as i32,
+                        options.alignment as i32,
+                    ),
+                    small_size_threshold: options.small_size_threshold as i32,
+                    large_size_threshold: options.large_size_threshold as i32,
+                },
+            )),
+            Algorithm::MaxRects => Self::MaxRects(max_rects::MaxRects::new(
+                width,
+                height,
+                options.max_rects_heuristic,
+            )),
+        }
+    }
+
+    pub fn allocate(&mut self, width: u32, height: u32) -> Option<Allocation> {
+        self.allocate_with_id(width, height)
+            .map(|(allocation, _)| allocation)
+    }
+
+    /// Allocates like [`Allocator::allocate`], along with the id that hands the space back with
+    /// [`Allocator::deallocate`].
+    pub fn allocate_with_id(
+        &mut self,
+        width: u32,
+        height: u32,
+    ) -> Option<(Allocation, AllocationId)> {
+        let (rectangle, id) = match self {
+            Self::Etagere(allocator) => {
+                let allocation = allocator.allocate(etagere::size2(width as i32, height as i32))?;
+
+                (allocation.rectangle, Id::Etagere(allocation.id))
+            }
+            Self::Guillotiere(allocator) => {
+                let allocation =
+                    allocator.allocate(guillotiere::size2(width as i32, height as i32))?;
+
+                (allocation.rectangle, Id::Guillotiere(allocation.id))
+            }
+            Self::MaxRects(allocator) => {
+                let (x, y) = allocator.allocate(width, height)?;
+                let allocation = Allocation {
+                    x: x as i32,
+                    y: y as i32,
+                    width: width as i32,
+                    height: height as i32,
+                };
+
+                return Some((allocation, AllocationId(Id::MaxRects(allocation))));
+            }
+        };
+
+        Some((
+            Allocation {
+                x: rectangle.min.x,
+                y: rectangle.min.y,
+                width: rectangle.width(),
+                height: rectangle.height(),
+            },
+            AllocationId(id),
+        ))
+    }
+
+    /// Frees an allocation made by this allocator, its space can be allocated again.
+    pub fn deallocate(&mut self, id: AllocationId) {
+        match (self, id.0) {
+            (Self::Etagere(allocator), Id::Etagere(id)) => allocator.deallocate(id),
+            (Self::Guillotiere(allocator), Id::Guillotiere(id)) => allocator.deallocate(id),
+            (Self::MaxRects(allocator), Id::MaxRects(allocation)) => allocator.deallocate(
+                allocation.x as u32,
+                allocation.y as u32,
+                allocation.width as u32,
+                allocation.height as u32,
+            ),
+            _ => panic!("deallocated an id from another kind of allocator"),
+        }
+    }
+
+    /// Sets a rectangle of the page aside under a name, allocations from the returned
+    /// allocator stay inside it and nothing else allocated here can overlap it.
+    pub fn reserve(
+        &mut self,
+        name: impl Into<String>,
+        algorithm: Algorithm,
+        width: u32,
+        height: u32,
+        options: AllocatorOptions,
+    ) -> Option<ScopedAllocator> {
+        let region = self.allocate(width, height)?;
+
+        Some(ScopedAllocator {
+            name: name.into(),
+            region,
+            allocator: Self::with_options(algorithm, width, height, options),
+        })
+    }
+}
+
+/// Allocates within a region reserved with [`Allocator::reserve`], in the page's coordinates.
+pub struct ScopedAllocator {
+    name: String,
+    region: Allocation,
+    allocator: Allocator,
+}
+
+impl ScopedAllocator {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn region(&self) -> Allocation {
+        self.region
+    }
+
+    pub fn allocate(&mut self, width: u32, height: u32) -> Option<Allocation> {
+        self.allocate_with_id(width, height)
+            .map(|(allocation, _)| allocation)
+    }
+
+    /// Allocates like [`ScopedAllocator::allocate`], the id frees the space within the region
+    /// again with [`ScopedAllocator::deallocate`].
+    pub fn allocate_with_id(
+        &mut self,
+        width: u32,
+        height: u32,
+    ) -> Option<(Allocation, AllocationId)> {
+        let (allocation, id) = self.allocator.allocate_with_id(width, height)?;
+
+        Some((
+            Allocation {
+                x: self.region.x + allocation.x,
+                y: self.region.y + allocation.y,
+                ..allocation
+            },
+            id,
+        ))
+    }
+
+    pub fn deallocate(&mut self, id: AllocationId) {
+        self.allocator.deallocate(id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Allocation, Allocator, AllocatorOptions};
+    use crate::{Algorithm, Vector2};
+
+    fn overlaps(a: &Allocation, b: &Allocation) -> bool {
+        a.x < b.x + b.width && b.x < a.x + a.width && a.y < b.y + b.height && b.y < a.y + a.height
+    }
+
+    #[test]
+    fn scoped_allocations_stay_inside_the_reservation() {
+        for algorithm in [
+            Algorithm::Etagere,
+            Algorithm::Guillotiere,
+            Algorithm::MaxRects,
+        ] {
+            let mut page = Allocator::new(algorithm, 64, 64);
+            let mut decals = page
+                .reserve("decals", algorithm, 16, 16, AllocatorOptions::default())
+                .unwrap();
+            let region = decals.region();
+
+            assert_eq!(decals.name(), "decals");
+            assert_eq!((region.width, region.height), (16, 16));
+
+            let mut scoped = Vec::new();
+
+            while let Some(allocation) = decals.allocate(4, 4) {
+                assert!(
+                    allocation.x >= region.x
+                        && allocation.y >= region.y
+                        && allocation.x + allocation.width <= region.x + region.width
+                        && allocation.y + allocation.height <= region.y + region.height,
+                    "{algorithm:?}: {allocation:?} outside {region:?}"
+                );
+
+                assert!(!scoped.iter().any(|other| overlaps(other, &allocation)));
+
+                scoped.push(allocation);
+            }
+
+            assert!(!scoped.is_empty(), "{algorithm:?}");
+
+            while let Some(allocation) = page.allocate(8, 8) {
+                assert!(!overlaps(&allocation, &region), "{algorithm:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn center_follows_the_drawn_sprite_when_the_slot_is_larger() {
+        // An 8x9 sprite in an 8x10 slot, the allocator rounded its height up by an odd amount
+        let allocation = Allocation {
+            x: 0,
+            y: 0,
+            width: 8,
+            height: 10,
+        };
+
+        assert_eq!(allocation.center(8, 9), Vector2::new(4.0, 4.0));
+
+        let allocation = Allocation {
+            x: 3,
+            y: 5,
+            width: 12,
+            height: 7,
+        };
+
+        assert_eq!(allocation.center(5, 4), Vector2::new(5.0, 7.0));
+    }
+}
@@ -0,0 +1,281 @@
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+pub enum MaxRectsHeuristic {
+    #[default]
+    BestShortSideFit,
+    BestAreaFit,
+}
+
+impl MaxRectsHeuristic {
+    /// The name the command line uses for it.
+    pub fn name(self) -> &'static str {
+        match self {
+            MaxRectsHeuristic::BestShortSideFit => "best-short-side-fit",
+            MaxRectsHeuristic::BestAreaFit => "best-area-fit",
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+struct Rectangle {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+impl Rectangle {
+    fn right(&self) -> u32 {
+        self.x + self.width
+    }
+
+    fn bottom(&self) -> u32 {
+        self.y + self.height
+    }
+
+    fn intersects(&self, other: &Rectangle) -> bool {
+        self.x < other.right()
+            && other.x < self.right()
+            && self.y < other.bottom()
+            && other.y < self.bottom()
+    }
+
+    fn contains(&self, other: &Rectangle) -> bool {
+        self.x <= other.x
+            && self.y <= other.y
+            && other.right() <= self.right()
+            && other.bottom() <= self.bottom()
+    }
+}
+
+// Keeps every maximal free rectangle, overlapping each other, instead of a disjoint partition
+pub struct MaxRects {
+    heuristic: MaxRectsHeuristic,
+    free: Vec<Rectangle>,
+}
+
+impl MaxRects {
+    pub fn new(width: u32, height: u32, heuristic: MaxRectsHeuristic) -> Self {
+        Self {
+            heuristic,
+            free: vec![Rectangle {
+                x: 0,
+                y: 0,
+                width,
+                height,
+            }],
+        }
+    }
+
+    pub fn allocate(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        let placed = self
+            .free
+            .iter()
+            .filter(|free| free.width >= width && free.height >= height)
+            .min_by_key(|free| {
+                let leftover_width = free.width - width;
+                let leftover_height = free.height - height;
+                let short_side = leftover_width.min(leftover_height);
+                let long_side = leftover_width.max(leftover_height);
+                let leftover_area =
+                    free.width as u64 * free.height as u64 - width as u64 * height as u64;
+
+                // Ties go to the top left most position so results don't depend on list order
+                match self.heuristic {
+                    MaxRectsHeuristic::BestShortSideFit => {
+                        (short_side as u64, long_side as u64, free.y, free.x)
+                    }
+                    MaxRectsHeuristic::BestAreaFit => {
+                        (leftover_area, short_side as u64, free.y, free.x)
+                    }
+                }
+            })
+            .map(|free| Rectangle {
+                x: free.x,
+                y: free.y,
+                width,
+                height,
+            })?;
+
+        let mut free = Vec::with_capacity(self.free.len() + 4);
+
+        for rectangle in &self.free {
+            if !rectangle.intersects(&placed) {
+                free.push(*rectangle);
+                continue;
+            }
+
+            if placed.x > rectangle.x {
+                free.push(Rectangle {
+                    width: placed.x - rectangle.x,
+                    ..*rectangle
+                });
+            }
+
+            if placed.right() < rectangle.right() {
+                free.push(Rectangle {
+                    x: placed.right(),
+                    width: rectangle.right() - placed.right(),
+                    ..*rectangle
+                });
+            }
+
+            if placed.y > rectangle.y {
+                free.push(Rectangle {
+                    height: placed.y - rectangle.y,
+                    ..*rectangle
+                });
+            }
+
+            if placed.bottom() < rectangle.bottom() {
+                free.push(Rectangle {
+                    y: placed.bottom(),
+                    height: rectangle.bottom() - placed.bottom(),
+                    ..*rectangle
+                });
+            }
+        }
+
+        // Drop rectangles that another one fully covers, keeping the first of identical pairs
+        let mut index = 0;
+
+        while index < free.len() {
+            let covered = free.iter().enumerate().any(|(other, rectangle)| {
+                other != index
+                    && rectangle.contains(&free[index])
+                    && (other < index || !free[index].contains(rectangle))
+            });
+
+            if covered {
+                free.swap_remove(index);
+            } else {
+                index += 1;
+            }
+        }
+
+        self.free = free;
+
+        Some((placed.x, placed.y))
+    }
+
+    // The freed rectangle goes back as it is, merged with free neighbours it lines up with on a
+    // whole edge. Free space split up any other way is only reused in its pieces
+    pub fn deallocate(&mut self, x: u32, y: u32, width: u32, height: u32) {
+        let mut freed = Rectangle {
+            x,
+            y,
+            width,
+            height,
+        };
+
+        while let Some(index) = self.free.iter().position(|free| {
+            (free.y == freed.y
+                && free.height == freed.height
+                && (free.right() == freed.x || freed.right() == free.x))
+                || (free.x == freed.x
+                    && free.width == freed.width
+                    && (free.bottom() == freed.y || freed.bottom() == free.y))
+        }) {
+            let free = self.free.swap_remove(index);
+
+            freed = Rectangle {
+                x: free.x.min(freed.x),
+                y: free.y.min(freed.y),
+                width: free.right().max(freed.right()) - free.x.min(freed.x),
+                height: free.bottom().max(freed.bottom()) - free.y.min(freed.y),
+            };
+        }
+
+        self.free.retain(|free| !freed.contains(free));
+        self.free.push(freed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MaxRects, MaxRectsHeuristic, Rectangle};
+
+    const HEURISTICS: [MaxRectsHeuristic; 2] = [
+        MaxRectsHeuristic::BestShortSideFit,
+        MaxRectsHeuristic::BestAreaFit,
+    ];
+
+    #[test]
+    fn placements_stay_inside_and_never_overlap() {
+        for heuristic in HEURISTICS {
+            let mut allocator = MaxRects::new(256, 256, heuristic);
+            let mut placed = Vec::<Rectangle>::new();
+            let mut state = 0x2545_f491_u32;
+
+            for _ in 0..200 {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                let (width, height) = (1 + (state >> 8) % 40, 1 + (state >> 20) % 40);
+
+                let Some((x, y)) = allocator.allocate(width, height) else {
+                    continue;
+                };
+                let rectangle = Rectangle {
+                    x,
+                    y,
+                    width,
+                    height,
+                };
+
+                assert!(rectangle.right() <= 256 && rectangle.bottom() <= 256);
+                assert!(placed.iter().all(|other| !other.intersects(&rectangle)));
+
+                placed.push(rectangle);
+            }
+
+            assert!(placed.len() > 20);
+        }
+    }
+
+    #[test]
+    fn freed_quarters_merge_back_into_the_page() {
+        for heuristic in HEURISTICS {
+            let mut allocator = MaxRects::new(128, 128, heuristic);
+            let corners = (0..4)
+                .map(|_| allocator.allocate(64, 64).unwrap())
+                .collect::<Vec<_>>();
+
+            for (x, y) in corners {
+                allocator.deallocate(x, y, 64, 64);
+            }
+
+            assert_eq!(allocator.allocate(128, 128), Some((0, 0)));
+        }
+    }
+
+    #[test]
+    fn rejects_what_does_not_fit() {
+        for heuristic in HEURISTICS {
+            let mut allocator = MaxRects::new(128, 128, heuristic);
+
+            assert_eq!(allocator.allocate(129, 1), None);
+            assert_eq!(allocator.allocate(1, 129), None);
+            assert_eq!(allocator.allocate(0, 8), None);
+
+            // Four quarters fill the space exactly, nothing is left after them
+            let mut corners = (0..4)
+                .map(|_| allocator.allocate(64, 64).unwrap())
+                .collect::<Vec<_>>();
+            corners.sort();
+
+            assert_eq!(corners, [(0, 0), (0, 64), (64, 0), (64, 64)]);
+            assert_eq!(allocator.allocate(1, 1), None);
+        }
+    }
+
+    #[test]
+    fn a_rejected_sprite_leaves_the_free_space_alone() {
+        let mut allocator = MaxRects::new(100, 50, MaxRectsHeuristic::default());
+
+        assert_eq!(allocator.allocate(60, 50), Some((0, 0)));
+        assert_eq!(allocator.allocate(50, 10), None);
+        assert_eq!(allocator.allocate(40, 50), Some((60, 0)));
+    }
+}
@@ -0,0 +1,47 @@
+use std::{path::Path, str::FromStr};
+
+use image::{DynamicImage, RgbaImage};
+
+use crate::pattern;
+
+#[derive(Clone)]
+pub struct AlphaThreshold {
+    pattern: Option<String>,
+    pub threshold: u8,
+}
+
+impl AlphaThreshold {
+    pub fn includes(&self, key: &Path) -> bool {
+        self.pattern
+            .as_ref()
+            .is_none_or(|pattern| pattern::matches_key(pattern, key))
+    }
+}
+
+impl FromStr for AlphaThreshold {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (pattern, threshold) = match value.rsplit_once('=') {
+            Some((pattern, threshold)) => (Some(pattern.to_string()), threshold),
+            None => (None, value),
+        };
+
+        let threshold = threshold
+            .parse::<u8>()
+            .map_err(|_| format!("invalid alpha threshold '{threshold}', expected 0-255"))?;
+
+        Ok(Self { pattern, threshold })
+    }
+}
+
+// Alpha at or above the threshold becomes fully opaque, everything else fully transparent
+pub fn binarize(image: &DynamicImage, threshold: u8) -> DynamicImage {
+    let mut image: RgbaImage = image.to_rgba8();
+
+    for pixel in image.pixels_mut() {
+        pixel.0[3] = if pixel.0[3] >= threshold { 255 } else { 0 };
+    }
+
+    DynamicImage::ImageRgba8(image)
+}
@@ -0,0 +1,183 @@
+use std::{
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+
+use image::{
+    codecs::{gif::GifDecoder, png::PngDecoder, webp::WebPDecoder},
+    io::Reader,
+    AnimationDecoder, DynamicImage, ImageFormat,
+};
+
+use crate::{
+    aseprite,
+    decode::{self, DecodeLimits},
+};
+
+pub struct Frame {
+    pub image: DynamicImage,
+    pub timing: Timing,
+}
+
+// Counting a GIF's frames decodes all of them, so an input that turns out to have only one is
+// kept as it is instead of being decoded again
+pub enum Decoded {
+    Animation(Vec<Frame>),
+    Still(DynamicImage),
+}
+
+// What the metadata keeps about a frame of an animated input
+#[derive(Clone)]
+pub struct Timing {
+    pub duration: u32,
+    pub tags: Vec<String>,
+}
+
+// Frames are packed as `{key}#{index}`, counting from 0 in playback order
+pub fn frame_key(key: &Path, index: usize) -> PathBuf {
+    let mut frame_key = key.as_os_str().to_os_string();
+    frame_key.push(format!("#{index}"));
+
+    PathBuf::from(frame_key)
+}
+
+// The other way around for exporters that name frames their own way, `walk.gif#3` is frame 3 of
+// `walk.gif`
+pub fn split_frame_key(name: &str) -> (&str, Option<usize>) {
+    match name.rsplit_once('#') {
+        Some((key, index))
+            if !key.is_empty()
+                && !index.is_empty()
+                && index.bytes().all(|byte| byte.is_ascii_digit()) =>
+        {
+            match index.parse() {
+                Ok(index) => (key, Some(index)),
+                Err(_) => (name, None),
+            }
+        }
+        _ => (name, None),
+    }
+}
+
+// Aseprite files always come out as frames, GIFs, PNGs and WebPs only when they hold more than one
+// frame. Stills that aren't GIFs or animated PNGs and WebPs are left to decode::open
+pub fn open(path: &Path, limits: Option<&DecodeLimits>) -> Result<Option<Decoded>, String> {
+    if aseprite::is_aseprite(path) {
+        return aseprite::open(path, limits).map(|frames| Some(Decoded::Animation(frames)));
+    }
+
+    let format = match ImageFormat::from_path(path) {
+        Ok(format @ (ImageFormat::Gif | ImageFormat::Png | ImageFormat::WebP)) => format,
+        _ => return Ok(None),
+    };
+
+    let bytes = decode::read(path, limits)?;
+
+    let mut frames = match limits {
+        Some(limits) => {
+            let (width, height) = Reader::with_format(Cursor::new(&bytes), format)
+                .into_dimensions()
+                .map_err(|error| error.to_string())?;
+
+            limits.check_dimensions(width, height)?;
+
+            let max_decoded_bytes = limits.max_decoded_bytes;
+
+            decode::with_timeout(limits.timeout, move || {
+                frames(&bytes, format, Some(max_decoded_bytes))
+            })?
+        }
+        None => frames(&bytes, format, None)?,
+    };
+
+    Ok(match frames.len() {
+        0 => None,
+        1 => Some(Decoded::Still(frames.remove(0).image)),
+        _ => Some(Decoded::Animation(frames)),
+    })
+}
+
+fn frames(
+    bytes: &[u8],
+    format: ImageFormat,
+    max_decoded_bytes: Option<u64>,
+) -> Result<Vec<Frame>, String> {
+    let cursor = Cursor::new(bytes);
+
+    let decoded = match format {
+        ImageFormat::Gif => GifDecoder::new(cursor)
+            .map_err(|error| error.to_string())?
+            .into_frames(),
+        ImageFormat::Png => {
+            let decoder = PngDecoder::new(cursor).map_err(|error| error.to_string())?;
+
+            if !decoder.is_apng() {
+                return Ok(Vec::new());
+            }
+
+            decoder.apng().into_frames()
+        }
+        _ => {
+            let decoder = WebPDecoder::new(cursor).map_err(|error| error.to_string())?;
+
+            if !decoder.has_animation() {
+                return Ok(Vec::new());
+            }
+
+            decoder.into_frames()
+        }
+    };
+
+    let mut frames = Vec::new();
+    let mut decoded_bytes = 0;
+
+    for frame in decoded {
+        let frame = frame.map_err(|error| error.to_string())?;
+        let (numerator, denominator) = frame.delay().numer_denom_ms();
+        let image = frame.into_buffer();
+
+        // Every frame is a full canvas, so the limit has to cover all of them together
+        decoded_bytes += image.as_raw().len() as u64;
+
+        if let Some(max_decoded_bytes) = max_decoded_bytes.filter(|&max| decoded_bytes > max) {
+            return Err(format!(
+                "animation decodes to more than {max_decoded_bytes} bytes, exceeding the untrusted input limit"
+            ));
+        }
+
+        frames.push(Frame {
+            image: DynamicImage::ImageRgba8(image),
+            timing: Timing {
+                duration: numerator / denominator.max(1),
+                tags: Vec::new(),
+            },
+        });
+    }
+
+    Ok(frames)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{codecs::gif::GifEncoder, Frame, Rgba, RgbaImage};
+
+    use super::{open, Decoded};
+
+    #[test]
+    fn still_gifs_keep_their_only_frame() {
+        let path = std::env::temp_dir().join(format!("atlas-still-{}.gif", std::process::id()));
+        let image = RgbaImage::from_pixel(3, 2, Rgba([255, 0, 0, 255]));
+
+        GifEncoder::new(std::fs::File::create(&path).unwrap())
+            .encode_frame(Frame::new(image.clone()))
+            .unwrap();
+
+        let decoded = open(&path, None);
+        std::fs::remove_file(&path).unwrap();
+
+        match decoded {
+            Ok(Some(Decoded::Still(still))) => assert_eq!(still.to_rgba8(), image),
+            _ => panic!("a single frame GIF should decode to a still"),
+        }
+    }
+}
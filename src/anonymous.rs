@@ -0,0 +1,66 @@
+use std::{io::Read, path::PathBuf};
+
+use image::DynamicImage;
+
+use crate::sha256;
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+// Splits a stream of back to back PNG files on their IEND chunks
+pub fn read_png_stream(mut reader: impl Read) -> Result<Vec<Vec<u8>>, String> {
+    let mut stream = Vec::new();
+    reader
+        .read_to_end(&mut stream)
+        .map_err(|error| error.to_string())?;
+
+    let mut files = Vec::new();
+    let mut offset = 0;
+
+    while offset < stream.len() {
+        if !stream[offset..].starts_with(&PNG_SIGNATURE) {
+            return Err(format!("expected a PNG file at byte {offset}"));
+        }
+
+        let start = offset;
+        offset += PNG_SIGNATURE.len();
+
+        loop {
+            let header = stream
+                .get(offset..offset + 8)
+                .ok_or_else(|| format!("truncated PNG file at byte {start}"))?;
+            let length = u32::from_be_bytes(header[..4].try_into().unwrap()) as usize;
+            let is_end = &header[4..] == b"IEND";
+
+            // Length and type, the data, then the CRC
+            offset += 8 + length + 4;
+
+            if offset > stream.len() {
+                return Err(format!("truncated PNG file at byte {start}"));
+            }
+
+            if is_end {
+                break;
+            }
+        }
+
+        files.push(stream[start..offset].to_vec());
+    }
+
+    Ok(files)
+}
+
+// Hashes decoded pixels rather than file bytes so re-encoding the same sprite keeps its key
+pub fn content_key(prefix: &str, image: &DynamicImage) -> PathBuf {
+    PathBuf::from(format!("{prefix}{}", &content_hash(image)[..16]))
+}
+
+pub fn content_hash(image: &DynamicImage) -> String {
+    let image = image.to_rgba8();
+
+    let mut content = Vec::with_capacity(8 + image.as_raw().len());
+    content.extend_from_slice(&image.width().to_be_bytes());
+    content.extend_from_slice(&image.height().to_be_bytes());
+    content.extend_from_slice(image.as_raw());
+
+    sha256::hex_digest(&content)
+}
@@ -0,0 +1,620 @@
+use std::{io::Read, path::Path};
+
+use flate2::read::ZlibDecoder;
+use image::{DynamicImage, Rgba, RgbaImage};
+
+use crate::{
+    animation::{Frame, Timing},
+    decode::{self, DecodeLimits},
+};
+
+const FILE_MAGIC: u16 = 0xa5e0;
+const FRAME_MAGIC: u16 = 0xf1fa;
+
+const OLD_PALETTE_CHUNK: u16 = 0x0004;
+const LAYER_CHUNK: u16 = 0x2004;
+const CEL_CHUNK: u16 = 0x2005;
+const TAGS_CHUNK: u16 = 0x2018;
+const PALETTE_CHUNK: u16 = 0x2019;
+
+const LAYER_OPACITY_VALID: u32 = 1;
+
+const LAYER_VISIBLE: u16 = 1;
+const LAYER_BACKGROUND: u16 = 8;
+const LAYER_REFERENCE: u16 = 64;
+
+const RAW_CEL: u16 = 0;
+const LINKED_CEL: u16 = 1;
+const COMPRESSED_CEL: u16 = 2;
+
+struct Layer {
+    flags: u16,
+    opacity: u8,
+    visible: bool,
+}
+
+struct Cel {
+    layer: usize,
+    x: i32,
+    y: i32,
+    opacity: u8,
+    z_index: i16,
+    content: CelContent,
+}
+
+enum CelContent {
+    Pixels {
+        width: u32,
+        height: u32,
+        data: Vec<u8>,
+    },
+    Linked(usize),
+}
+
+struct Tag {
+    from: usize,
+    to: usize,
+    name: String,
+}
+
+pub fn is_aseprite(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| {
+        extension.eq_ignore_ascii_case("ase") || extension.eq_ignore_ascii_case("aseprite")
+    })
+}
+
+pub fn open(path: &Path, limits: Option<&DecodeLimits>) -> Result<Vec<Frame>, String> {
+    let bytes = decode::read(path, limits)?;
+
+    decode(&bytes, limits)
+}
+
+// Visible layers are flattened with normal blending, other blend modes are treated as normal and
+// tilemap layers are skipped, which covers what sprite work usually uses
+fn decode(bytes: &[u8], limits: Option<&DecodeLimits>) -> Result<Vec<Frame>, String> {
+    let mut reader = Reader(bytes);
+
+    reader.skip(4)?;
+
+    if reader.u16()? != FILE_MAGIC {
+        return Err("not an Aseprite file".to_string());
+    }
+
+    let frame_count = reader.u16()? as usize;
+    let width = reader.u16()? as u32;
+    let height = reader.u16()? as u32;
+    let depth = reader.u16()?;
+    let flags = reader.u32()?;
+    reader.skip(10)?;
+    let transparent_index = reader.u8()?;
+    reader.skip(99)?;
+
+    if !matches!(depth, 8 | 16 | 32) {
+        return Err(format!("unsupported color depth {depth}"));
+    }
+
+    if let Some(limits) = limits {
+        limits.check_dimensions(width, height)?;
+    }
+
+    let mut layers = Vec::new();
+    let mut visible_levels = Vec::new();
+    let mut palette = vec![[0; 4]; 256];
+    let mut tags = Vec::new();
+    let mut durations = Vec::with_capacity(frame_count);
+    let mut frames = Vec::<Vec<Cel>>::with_capacity(frame_count);
+
+    for _ in 0..frame_count {
+        let length = reader.u32()? as usize;
+        let mut frame = Reader(reader.slice(length.saturating_sub(4))?);
+
+        if frame.u16()? != FRAME_MAGIC {
+            return Err("frame has an invalid magic number".to_string());
+        }
+
+        let old_chunk_count = frame.u16()? as u32;
+        durations.push(frame.u16()? as u32);
+        frame.skip(2)?;
+        let chunk_count = match frame.u32()? {
+            0 => old_chunk_count,
+            chunk_count => chunk_count,
+        };
+
+        let mut cels = Vec::new();
+
+        for _ in 0..chunk_count {
+            let length = frame.u32()? as usize;
+            let kind = frame.u16()?;
+            let mut chunk = Reader(frame.slice(length.saturating_sub(6))?);
+
+            match kind {
+                OLD_PALETTE_CHUNK => {
+                    let mut index = 0;
+
+                    for _ in 0..chunk.u16()? {
+                        index += chunk.u8()? as usize;
+
+                        let count = match chunk.u8()? {
+                            0 => 256,
+                            count => count as usize,
+                        };
+
+                        for _ in 0..count {
+                            let [red, green, blue] = chunk.take()?;
+
+                            if let Some(color) = palette.get_mut(index) {
+                                *color = [red, green, blue, 255];
+                            }
+
+                            index += 1;
+                        }
+                    }
+                }
+                PALETTE_CHUNK => {
+                    // The size isn't trusted, indexed pixels are a byte so only 256 entries can
+                    // ever be used and the palette already has them
+                    chunk.skip(4)?;
+                    let first = chunk.u32()? as usize;
+                    let last = chunk.u32()? as usize;
+                    chunk.skip(8)?;
+
+                    for index in first..=last {
+                        let entry_flags = chunk.u16()?;
+                        let color = chunk.take()?;
+
+                        if entry_flags & 1 != 0 {
+                            chunk.string()?;
+                        }
+
+                        if let Some(entry) = palette.get_mut(index) {
+                            *entry = color;
+                        }
+                    }
+                }
+                LAYER_CHUNK => {
+                    let layer_flags = chunk.u16()?;
+                    chunk.skip(2)?;
+                    let level = chunk.u16()? as usize;
+                    chunk.skip(6)?;
+                    let opacity = chunk.u8()?;
+
+                    // A layer only shows when every group above it is visible too
+                    let parent_visible =
+                        level == 0 || visible_levels.get(level - 1).copied().unwrap_or(true);
+                    let visible = layer_flags & LAYER_VISIBLE != 0 && parent_visible;
+
+                    visible_levels.truncate(level);
+                    visible_levels.push(visible);
+
+                    layers.push(Layer {
+                        flags: layer_flags,
+                        opacity: if flags & LAYER_OPACITY_VALID != 0 {
+                            opacity
+                        } else {
+                            255
+                        },
+                        visible,
+                    });
+                }
+                CEL_CHUNK => {
+                    let layer = chunk.u16()? as usize;
+                    let x = chunk.i16()? as i32;
+                    let y = chunk.i16()? as i32;
+                    let opacity = chunk.u8()?;
+                    let cel_kind = chunk.u16()?;
+                    let z_index = chunk.i16()?;
+                    chunk.skip(5)?;
+
+                    let content = match cel_kind {
+                        RAW_CEL | COMPRESSED_CEL => {
+                            let cel_width = chunk.u16()? as u32;
+                            let cel_height = chunk.u16()? as u32;
+
+                            if let Some(limits) = limits {
+                                limits.check_dimensions(cel_width, cel_height)?;
+                            }
+
+                            let expected =
+                                cel_width as usize * cel_height as usize * depth as usize / 8;
+                            let data = if cel_kind == RAW_CEL {
+                                chunk.slice(expected)?.to_vec()
+                            } else {
+                                inflate(chunk.0, expected)?
+                            };
+
+                            CelContent::Pixels {
+                                width: cel_width,
+                                height: cel_height,
+                                data,
+                            }
+                        }
+                        LINKED_CEL => CelContent::Linked(chunk.u16()? as usize),
+                        _ => continue,
+                    };
+
+                    cels.push(Cel {
+                        layer,
+                        x,
+                        y,
+                        opacity,
+                        z_index,
+                        content,
+                    });
+                }
+                TAGS_CHUNK => {
+                    let count = chunk.u16()?;
+                    chunk.skip(8)?;
+
+                    for _ in 0..count {
+                        let from = chunk.u16()? as usize;
+                        let to = chunk.u16()? as usize;
+                        chunk.skip(13)?;
+
+                        tags.push(Tag {
+                            from,
+                            to,
+                            name: chunk.string()?,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        frames.push(cels);
+    }
+
+    Ok(frames
+        .iter()
+        .enumerate()
+        .map(|(index, cels)| {
+            let mut canvas = RgbaImage::new(width, height);
+
+            // Z-indices move a cel relative to the other layers, ties go to the higher z-index
+            let mut order = cels.iter().collect::<Vec<_>>();
+            order.sort_by_key(|cel| (cel.layer as i32 + cel.z_index as i32, cel.z_index));
+
+            for cel in order {
+                let Some(layer) = layers.get(cel.layer) else {
+                    continue;
+                };
+
+                if !layer.visible || layer.flags & LAYER_REFERENCE != 0 {
+                    continue;
+                }
+
+                // Linked cels reuse the position, opacity and pixels of the cel they point to
+                let source = match cel.content {
+                    CelContent::Linked(frame) => frames.get(frame).and_then(|cels| {
+                        cels.iter().find(|linked| {
+                            linked.layer == cel.layer
+                                && matches!(linked.content, CelContent::Pixels { .. })
+                        })
+                    }),
+                    CelContent::Pixels { .. } => Some(cel),
+                };
+
+                if let Some(source) = source {
+                    draw(
+                        &mut canvas,
+                        source,
+                        layer,
+                        depth,
+                        &palette,
+                        transparent_index,
+                    );
+                }
+            }
+
+            Frame {
+                image: DynamicImage::ImageRgba8(canvas),
+                timing: Timing {
+                    duration: durations[index],
+                    tags: tags
+                        .iter()
+                        .filter(|tag| (tag.from..=tag.to).contains(&index))
+                        .map(|tag| tag.name.clone())
+                        .collect(),
+                },
+            }
+        })
+        .collect())
+}
+
+fn draw(
+    canvas: &mut RgbaImage,
+    cel: &Cel,
+    layer: &Layer,
+    depth: u16,
+    palette: &[[u8; 4]],
+    transparent_index: u8,
+) {
+    let CelContent::Pixels {
+        width,
+        height,
+        data,
+    } = &cel.content
+    else {
+        return;
+    };
+
+    let opacity = cel.opacity as f32 / 255.0 * layer.opacity as f32 / 255.0;
+    let bytes_per_pixel = depth as usize / 8;
+
+    for y in 0..*height {
+        for x in 0..*width {
+            let (canvas_x, canvas_y) = (cel.x + x as i32, cel.y + y as i32);
+
+            if canvas_x < 0
+                || canvas_y < 0
+                || canvas_x as u32 >= canvas.width()
+                || canvas_y as u32 >= canvas.height()
+            {
+                continue;
+            }
+
+            let offset = (y as usize * *width as usize + x as usize) * bytes_per_pixel;
+            let pixel = &data[offset..offset + bytes_per_pixel];
+
+            let [red, green, blue, alpha] = match depth {
+                32 => [pixel[0], pixel[1], pixel[2], pixel[3]],
+                16 => [pixel[0], pixel[0], pixel[0], pixel[1]],
+                // The transparent index is only see-through outside the background layer
+                _ if pixel[0] == transparent_index && layer.flags & LAYER_BACKGROUND == 0 => [0; 4],
+                _ => palette.get(pixel[0] as usize).copied().unwrap_or([0; 4]),
+            };
+
+            let destination = canvas.get_pixel_mut(canvas_x as u32, canvas_y as u32);
+            *destination = over(*destination, Rgba([red, green, blue, alpha]), opacity);
+        }
+    }
+}
+
+// Straight alpha source over, which is what Aseprite's normal blend mode does
+fn over(destination: Rgba<u8>, source: Rgba<u8>, opacity: f32) -> Rgba<u8> {
+    let source_alpha = source[3] as f32 / 255.0 * opacity;
+    let destination_alpha = destination[3] as f32 / 255.0;
+    let alpha = source_alpha + destination_alpha * (1.0 - source_alpha);
+
+    if alpha == 0.0 {
+        return Rgba([0; 4]);
+    }
+
+    let channel = |index: usize| {
+        ((source[index] as f32 * source_alpha
+            + destination[index] as f32 * destination_alpha * (1.0 - source_alpha))
+            / alpha)
+            .round() as u8
+    };
+
+    Rgba([
+        channel(0),
+        channel(1),
+        channel(2),
+        (alpha * 255.0).round() as u8,
+    ])
+}
+
+fn inflate(compressed: &[u8], expected: usize) -> Result<Vec<u8>, String> {
+    let mut data = Vec::with_capacity(expected);
+
+    ZlibDecoder::new(compressed)
+        .take(expected as u64 + 1)
+        .read_to_end(&mut data)
+        .map_err(|error| format!("cel data is corrupt: {error}"))?;
+
+    if data.len() != expected {
+        return Err("cel data doesn't match the cel size".to_string());
+    }
+
+    Ok(data)
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn slice(&mut self, length: usize) -> Result<&'a [u8], String> {
+        if self.0.len() < length {
+            return Err("file ends unexpectedly".to_string());
+        }
+
+        let (slice, rest) = self.0.split_at(length);
+        self.0 = rest;
+
+        Ok(slice)
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        Ok(self.slice(N)?.try_into().unwrap())
+    }
+
+    fn skip(&mut self, length: usize) -> Result<(), String> {
+        self.slice(length).map(|_| ())
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_le_bytes(self.take()?))
+    }
+
+    fn i16(&mut self) -> Result<i16, String> {
+        Ok(i16::from_le_bytes(self.take()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let length = self.u16()? as usize;
+
+        Ok(String::from_utf8_lossy(self.slice(length)?).into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        decode, Frame, CEL_CHUNK, FILE_MAGIC, FRAME_MAGIC, LAYER_BACKGROUND, LAYER_CHUNK,
+        LAYER_OPACITY_VALID, LAYER_VISIBLE, LINKED_CEL, PALETTE_CHUNK, RAW_CEL,
+    };
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+
+    fn file(
+        width: u16,
+        height: u16,
+        depth: u16,
+        transparent_index: u8,
+        frames: &[Vec<Vec<u8>>],
+    ) -> Vec<u8> {
+        let mut bytes = vec![0; 4];
+        bytes.extend(FILE_MAGIC.to_le_bytes());
+        bytes.extend((frames.len() as u16).to_le_bytes());
+        bytes.extend(width.to_le_bytes());
+        bytes.extend(height.to_le_bytes());
+        bytes.extend(depth.to_le_bytes());
+        bytes.extend(LAYER_OPACITY_VALID.to_le_bytes());
+        bytes.extend([0; 10]);
+        bytes.push(transparent_index);
+        bytes.extend([0; 99]);
+
+        for chunks in frames {
+            let body = chunks.concat();
+
+            bytes.extend((16 + body.len() as u32).to_le_bytes());
+            bytes.extend(FRAME_MAGIC.to_le_bytes());
+            bytes.extend((chunks.len() as u16).to_le_bytes());
+            bytes.extend(100u16.to_le_bytes());
+            bytes.extend([0; 2]);
+            bytes.extend((chunks.len() as u32).to_le_bytes());
+            bytes.extend(body);
+        }
+
+        bytes
+    }
+
+    fn chunk(kind: u16, data: Vec<u8>) -> Vec<u8> {
+        let mut chunk = (6 + data.len() as u32).to_le_bytes().to_vec();
+        chunk.extend(kind.to_le_bytes());
+        chunk.extend(data);
+
+        chunk
+    }
+
+    fn layer(flags: u16) -> Vec<u8> {
+        let mut data = flags.to_le_bytes().to_vec();
+        data.extend([0; 10]);
+        data.push(255);
+
+        chunk(LAYER_CHUNK, data)
+    }
+
+    fn cel(layer: u16, x: i16, z_index: i16, kind: u16, content: Vec<u8>) -> Vec<u8> {
+        let mut data = layer.to_le_bytes().to_vec();
+        data.extend(x.to_le_bytes());
+        data.extend(0i16.to_le_bytes());
+        data.push(255);
+        data.extend(kind.to_le_bytes());
+        data.extend(z_index.to_le_bytes());
+        data.extend([0; 5]);
+        data.extend(content);
+
+        chunk(CEL_CHUNK, data)
+    }
+
+    fn pixels(width: u16, height: u16, data: &[u8]) -> Vec<u8> {
+        let mut content = width.to_le_bytes().to_vec();
+        content.extend(height.to_le_bytes());
+        content.extend(data);
+
+        content
+    }
+
+    fn pixel(frame: &Frame, x: u32) -> [u8; 4] {
+        frame.image.as_rgba8().unwrap().get_pixel(x, 0).0
+    }
+
+    #[test]
+    fn linked_cels_reuse_the_pixels_they_point_to() {
+        let bytes = file(
+            3,
+            1,
+            32,
+            0,
+            &[
+                vec![
+                    layer(LAYER_VISIBLE),
+                    cel(0, 1, 0, RAW_CEL, pixels(2, 1, &[RED, BLUE].concat())),
+                ],
+                vec![cel(0, 0, 0, LINKED_CEL, 0u16.to_le_bytes().to_vec())],
+            ],
+        );
+
+        let frames = decode(&bytes, None).unwrap();
+
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[1].image, frames[0].image);
+        assert_eq!([0, 1, 2].map(|x| pixel(&frames[1], x)), [[0; 4], RED, BLUE]);
+    }
+
+    #[test]
+    fn z_index_moves_a_cel_above_the_layers_after_it() {
+        let frame = |z_index| {
+            let bytes = file(
+                1,
+                1,
+                32,
+                0,
+                &[vec![
+                    layer(LAYER_VISIBLE),
+                    layer(LAYER_VISIBLE),
+                    cel(0, 0, z_index, RAW_CEL, pixels(1, 1, &RED)),
+                    cel(1, 0, 0, RAW_CEL, pixels(1, 1, &BLUE)),
+                ]],
+            );
+
+            pixel(&decode(&bytes, None).unwrap()[0], 0)
+        };
+
+        assert_eq!(frame(0), BLUE);
+        assert_eq!(frame(1), RED);
+    }
+
+    #[test]
+    fn the_transparent_index_is_opaque_only_on_the_background() {
+        // The palette claims billions of entries, only the two it sets are read
+        let mut palette = u32::MAX.to_le_bytes().to_vec();
+        palette.extend(1u32.to_le_bytes());
+        palette.extend(3u32.to_le_bytes());
+        palette.extend([0; 8]);
+
+        for color in [[0, 255, 0, 255], [0, 0, 0, 0], [255, 255, 255, 255]] {
+            palette.extend(0u16.to_le_bytes());
+            palette.extend(color);
+        }
+
+        let bytes = file(
+            2,
+            1,
+            8,
+            3,
+            &[vec![
+                chunk(PALETTE_CHUNK, palette),
+                layer(LAYER_VISIBLE | LAYER_BACKGROUND),
+                layer(LAYER_VISIBLE),
+                cel(0, 0, 0, RAW_CEL, pixels(1, 1, &[3])),
+                cel(1, 0, 0, RAW_CEL, pixels(2, 1, &[3, 1])),
+            ]],
+        );
+
+        let frames = decode(&bytes, None).unwrap();
+
+        assert_eq!(pixel(&frames[0], 0), [255, 255, 255, 255]);
+        assert_eq!(pixel(&frames[0], 1), [0, 255, 0, 255]);
+    }
+}
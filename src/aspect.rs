@@ -0,0 +1,49 @@
+use std::str::FromStr;
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct AspectRatio {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl AspectRatio {
+    // Pages grow along the shorter side in powers of two, the longer side follows the ratio
+    pub fn page(&self, short_side: u32) -> (u32, u32) {
+        if self.width >= self.height {
+            (
+                (short_side as u64 * self.width as u64 / self.height as u64) as u32,
+                short_side,
+            )
+        } else {
+            (
+                short_side,
+                (short_side as u64 * self.height as u64 / self.width as u64) as u32,
+            )
+        }
+    }
+}
+
+impl FromStr for AspectRatio {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (width, height) = value
+            .split_once(':')
+            .ok_or_else(|| format!("expected W:H, got '{value}'"))?;
+
+        let width = width
+            .parse::<u32>()
+            .map_err(|_| format!("invalid aspect ratio width '{width}'"))?;
+        let height = height
+            .parse::<u32>()
+            .map_err(|_| format!("invalid aspect ratio height '{height}'"))?;
+
+        if width == 0 || height == 0 {
+            return Err(format!(
+                "aspect ratio must be non-zero, got {width}:{height}"
+            ));
+        }
+
+        Ok(Self { width, height })
+    }
+}
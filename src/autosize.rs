@@ -0,0 +1,119 @@
+use atlas::{
+    allocator::{Allocator, AllocatorOptions},
+    Algorithm,
+};
+
+use crate::aspect::AspectRatio;
+
+// Packers aren't strictly monotonic in page size, so this finds a small fit rather than the smallest
+pub fn smallest(
+    sizes: &[(u32, u32)],
+    algorithm: Algorithm,
+    options: AllocatorOptions,
+    max_width: u32,
+    max_height: u32,
+    aspect_ratio: Option<AspectRatio>,
+) -> Option<(u32, u32)> {
+    let ratio = aspect_ratio.unwrap_or(AspectRatio {
+        width: 1,
+        height: 1,
+    });
+
+    let within = |(width, height): (u32, u32)| width <= max_width && height <= max_height;
+
+    // Double the short side until everything fits, then binary search back down
+    let mut short_side = 1;
+
+    let mut fitting = loop {
+        let page = ratio.page(short_side);
+
+        if !within(page) {
+            let clamped = (page.0.min(max_width), page.1.min(max_height));
+
+            if fits(sizes, algorithm, options, clamped) {
+                break clamped;
+            }
+
+            return None;
+        }
+
+        if fits(sizes, algorithm, options, page) {
+            break page;
+        }
+
+        short_side *= 2;
+    };
+
+    let (mut low, mut high) = (short_side / 2 + 1, short_side);
+
+    while low < high {
+        let middle = low + (high - low) / 2;
+        let page = ratio.page(middle);
+
+        if within(page) && fits(sizes, algorithm, options, page) {
+            fitting = page;
+            high = middle;
+        } else {
+            low = middle + 1;
+        }
+    }
+
+    // Without a fixed ratio, trim whatever is left unused along each axis independently
+    if aspect_ratio.is_none() {
+        fitting.1 = shrink(fitting.1, |height| {
+            fits(sizes, algorithm, options, (fitting.0, height))
+        });
+        fitting.0 = shrink(fitting.0, |width| {
+            fits(sizes, algorithm, options, (width, fitting.1))
+        });
+    }
+
+    Some(fitting)
+}
+
+fn shrink(mut high: u32, fits: impl Fn(u32) -> bool) -> u32 {
+    let mut low = 1;
+
+    while low < high {
+        let middle = low + (high - low) / 2;
+
+        if fits(middle) {
+            high = middle;
+        } else {
+            low = middle + 1;
+        }
+    }
+
+    high
+}
+
+// Few enough to try them all, smallest area first and the squarer page on a tie
+pub fn power_of_two_pages(max_width: u32, max_height: u32, square: bool) -> Vec<(u32, u32)> {
+    let sides = |max: u32| {
+        (0..32)
+            .map(|exponent| 1 << exponent)
+            .take_while(move |&side| side <= max)
+    };
+
+    let mut pages = sides(max_width)
+        .flat_map(|width| sides(max_height).map(move |height| (width, height)))
+        .filter(|&(width, height)| !square || width == height)
+        .collect::<Vec<(u32, u32)>>();
+
+    pages.sort_by_key(|&(width, height)| (width as u64 * height as u64, width.abs_diff(height)));
+
+    pages
+}
+
+pub fn fits(
+    sizes: &[(u32, u32)],
+    algorithm: Algorithm,
+    options: AllocatorOptions,
+    (width, height): (u32, u32),
+) -> bool {
+    let mut allocator = Allocator::with_options(algorithm, width, height, options);
+
+    sizes.iter().all(|&(sprite_width, sprite_height)| {
+        allocator.allocate(sprite_width, sprite_height).is_some()
+    })
+}
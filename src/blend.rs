@@ -0,0 +1,44 @@
+use image::{DynamicImage, GenericImageView};
+use serde::Serialize;
+
+// Whether a page can be drawn without blending, with --split-opaque every page holds only one kind
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Blend {
+    Opaque,
+    Translucent,
+}
+
+impl Blend {
+    pub fn of(image: &DynamicImage) -> Self {
+        if image.pixels().all(|(_, _, pixel)| pixel.0[3] == 255) {
+            Blend::Opaque
+        } else {
+            Blend::Translucent
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, Rgba, RgbaImage};
+
+    use super::Blend;
+
+    #[test]
+    fn any_alpha_below_opaque_needs_blending() {
+        let mut image = RgbaImage::from_pixel(2, 2, Rgba([10, 20, 30, 255]));
+
+        assert_eq!(
+            Blend::of(&DynamicImage::ImageRgba8(image.clone())),
+            Blend::Opaque
+        );
+
+        image.put_pixel(1, 1, Rgba([10, 20, 30, 254]));
+
+        assert_eq!(
+            Blend::of(&DynamicImage::ImageRgba8(image)),
+            Blend::Translucent
+        );
+    }
+}
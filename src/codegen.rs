@@ -0,0 +1,434 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::Write,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::{
+    export::{self, PackedFragment},
+    metadata::Rectangle,
+};
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum Codegen {
+    Rust,
+    CHeader,
+    Typescript,
+    TypescriptModule,
+}
+
+struct Sprite {
+    identifier: String,
+    key: String,
+    page: u32,
+    frame: Rectangle,
+    source: Rectangle,
+    source_size: (u32, u32),
+    rotated: bool,
+    uv: [f32; 4],
+}
+
+// Rust and C describe image space, rectangles and UVs have their origin at the top left corner of
+// the page whatever --origin and --flip-y do to the metadata, TypeScript types the written metadata
+pub fn write(
+    path: &Path,
+    codegen: Codegen,
+    fragments: &BTreeMap<PathBuf, PackedFragment>,
+    metadata: &impl Serialize,
+    width: u32,
+    height: u32,
+) -> io::Result<()> {
+    // Identifiers the generated code declares itself, sprites named like them get a suffix instead
+    let reserved: &[&str] = match codegen {
+        Codegen::Rust => &["ATLAS_WIDTH", "ATLAS_HEIGHT", "SPRITES"],
+        Codegen::CHeader | Codegen::Typescript | Codegen::TypescriptModule => &[],
+    };
+    let sprites = sprites(fragments, reserved, width, height);
+
+    let source = match codegen {
+        Codegen::Rust => rust(&sprites, width, height),
+        Codegen::CHeader => c_header(path, &sprites, width, height),
+        Codegen::Typescript => typescript(&serde_json::to_value(metadata).unwrap(), false),
+        Codegen::TypescriptModule => typescript(&serde_json::to_value(metadata).unwrap(), true),
+    };
+
+    fs::write(path, source)
+}
+
+fn sprites(
+    fragments: &BTreeMap<PathBuf, PackedFragment>,
+    reserved: &[&str],
+    width: u32,
+    height: u32,
+) -> Vec<Sprite> {
+    let mut used = reserved
+        .iter()
+        .map(|identifier| identifier.to_string())
+        .collect::<HashSet<_>>();
+
+    fragments
+        .iter()
+        .map(|(key, packed)| {
+            let (fragment, frame) = (&packed.fragment, packed.frame);
+            let (source, source_size) = export::source(fragment);
+
+            let uv = [
+                frame.x as f32 / width as f32,
+                frame.y as f32 / height as f32,
+                (frame.x + frame.width) as f32 / width as f32,
+                (frame.y + frame.height) as f32 / height as f32,
+            ];
+
+            Sprite {
+                identifier: identifier(key, &mut used),
+                key: export::name(key),
+                page: fragment.page.unwrap_or(0),
+                frame,
+                source,
+                source_size,
+                rotated: fragment.rotated,
+                uv,
+            }
+        })
+        .collect()
+}
+
+// `ui/button-hover.png` becomes UI_BUTTON_HOVER, keys that collapse onto the same identifier get
+// a numeric suffix in key order
+fn identifier(key: &Path, used: &mut HashSet<String>) -> String {
+    let mut base = key
+        .with_extension("")
+        .to_string_lossy()
+        .chars()
+        .map(|character| {
+            if character.is_ascii_alphanumeric() {
+                character.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+
+    if !base.starts_with(|character: char| character.is_ascii_alphabetic()) {
+        base.insert(0, '_');
+    }
+
+    let mut identifier = base.clone();
+    let mut suffix = 2;
+
+    while !used.insert(identifier.clone()) {
+        identifier = format!("{base}_{suffix}");
+        suffix += 1;
+    }
+
+    identifier
+}
+
+fn rust(sprites: &[Sprite], width: u32, height: u32) -> String {
+    let mut output = String::new();
+
+    writeln!(output, "// Generated by atlas, do not edit").unwrap();
+    writeln!(output).unwrap();
+    writeln!(output, "#[derive(Clone, Copy, Debug, PartialEq)]").unwrap();
+    writeln!(output, "pub struct Sprite {{").unwrap();
+    writeln!(output, "    pub key: &'static str,").unwrap();
+    writeln!(output, "    pub page: u32,").unwrap();
+    writeln!(output, "    pub x: u32,").unwrap();
+    writeln!(output, "    pub y: u32,").unwrap();
+    writeln!(output, "    pub width: u32,").unwrap();
+    writeln!(output, "    pub height: u32,").unwrap();
+    writeln!(output, "    pub rotated: bool,").unwrap();
+    writeln!(output, "    pub offset_x: u32,").unwrap();
+    writeln!(output, "    pub offset_y: u32,").unwrap();
+    writeln!(output, "    pub source_width: u32,").unwrap();
+    writeln!(output, "    pub source_height: u32,").unwrap();
+    writeln!(output, "    pub uv: [f32; 4],").unwrap();
+    writeln!(output, "}}").unwrap();
+    writeln!(output).unwrap();
+    writeln!(output, "pub const ATLAS_WIDTH: u32 = {width};").unwrap();
+    writeln!(output, "pub const ATLAS_HEIGHT: u32 = {height};").unwrap();
+
+    for sprite in sprites {
+        writeln!(output).unwrap();
+        writeln!(
+            output,
+            "pub const {}: Sprite = Sprite {{",
+            sprite.identifier
+        )
+        .unwrap();
+        writeln!(output, "    key: {:?},", sprite.key).unwrap();
+        writeln!(output, "    page: {},", sprite.page).unwrap();
+        writeln!(output, "    x: {},", sprite.frame.x).unwrap();
+        writeln!(output, "    y: {},", sprite.frame.y).unwrap();
+        writeln!(output, "    width: {},", sprite.frame.width).unwrap();
+        writeln!(output, "    height: {},", sprite.frame.height).unwrap();
+        writeln!(output, "    rotated: {},", sprite.rotated).unwrap();
+        writeln!(output, "    offset_x: {},", sprite.source.x).unwrap();
+        writeln!(output, "    offset_y: {},", sprite.source.y).unwrap();
+        writeln!(output, "    source_width: {},", sprite.source_size.0).unwrap();
+        writeln!(output, "    source_height: {},", sprite.source_size.1).unwrap();
+        writeln!(
+            output,
+            "    uv: [{:?}, {:?}, {:?}, {:?}],",
+            sprite.uv[0], sprite.uv[1], sprite.uv[2], sprite.uv[3]
+        )
+        .unwrap();
+        writeln!(output, "}};").unwrap();
+    }
+
+    writeln!(output).unwrap();
+    writeln!(output, "pub const SPRITES: [Sprite; {}] = [", sprites.len()).unwrap();
+
+    for sprite in sprites {
+        writeln!(output, "    {},", sprite.identifier).unwrap();
+    }
+
+    writeln!(output, "];").unwrap();
+
+    output
+}
+
+// Sprites are indices into a static table, so the header can be included from C and C++ alike
+fn c_header(path: &Path, sprites: &[Sprite], width: u32, height: u32) -> String {
+    let guard = format!(
+        "ATLAS_{}_H",
+        path.file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .chars()
+            .map(|character| if character.is_ascii_alphanumeric() {
+                character.to_ascii_uppercase()
+            } else {
+                '_'
+            })
+            .collect::<String>()
+    );
+
+    let mut output = String::new();
+
+    writeln!(output, "/* Generated by atlas, do not edit */").unwrap();
+    writeln!(output).unwrap();
+    writeln!(output, "#ifndef {guard}").unwrap();
+    writeln!(output, "#define {guard}").unwrap();
+    writeln!(output).unwrap();
+    writeln!(output, "typedef struct atlas_sprite {{").unwrap();
+    writeln!(output, "    const char *key;").unwrap();
+    writeln!(output, "    unsigned int page;").unwrap();
+    writeln!(output, "    unsigned int x;").unwrap();
+    writeln!(output, "    unsigned int y;").unwrap();
+    writeln!(output, "    unsigned int width;").unwrap();
+    writeln!(output, "    unsigned int height;").unwrap();
+    writeln!(output, "    int rotated;").unwrap();
+    writeln!(output, "    unsigned int offset_x;").unwrap();
+    writeln!(output, "    unsigned int offset_y;").unwrap();
+    writeln!(output, "    unsigned int source_width;").unwrap();
+    writeln!(output, "    unsigned int source_height;").unwrap();
+    writeln!(output, "    float uv[4];").unwrap();
+    writeln!(output, "}} atlas_sprite;").unwrap();
+    writeln!(output).unwrap();
+    writeln!(output, "#define ATLAS_WIDTH {width}").unwrap();
+    writeln!(output, "#define ATLAS_HEIGHT {height}").unwrap();
+    writeln!(output, "#define ATLAS_NUM_SPRITES {}", sprites.len()).unwrap();
+    writeln!(output).unwrap();
+
+    for (index, sprite) in sprites.iter().enumerate() {
+        writeln!(output, "#define ATLAS_SPRITE_{} {index}", sprite.identifier).unwrap();
+    }
+
+    writeln!(output).unwrap();
+    writeln!(
+        output,
+        "static const atlas_sprite atlas_sprites[ATLAS_NUM_SPRITES] = {{"
+    )
+    .unwrap();
+
+    for sprite in sprites {
+        writeln!(
+            output,
+            "    {{{}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {{{:?}f, {:?}f, {:?}f, {:?}f}}}},",
+            c_string(&sprite.key),
+            sprite.page,
+            sprite.frame.x,
+            sprite.frame.y,
+            sprite.frame.width,
+            sprite.frame.height,
+            sprite.rotated as u8,
+            sprite.source.x,
+            sprite.source.y,
+            sprite.source_size.0,
+            sprite.source_size.1,
+            sprite.uv[0],
+            sprite.uv[1],
+            sprite.uv[2],
+            sprite.uv[3]
+        )
+        .unwrap();
+    }
+
+    writeln!(output, "}};").unwrap();
+    writeln!(output).unwrap();
+    writeln!(output, "#endif").unwrap();
+
+    output
+}
+
+// Anything outside printable ASCII becomes an octal escape, which stays valid in C and C++
+fn c_string(text: &str) -> String {
+    let mut output = String::from('"');
+
+    for byte in text.bytes() {
+        match byte {
+            b'"' | b'\\' => {
+                output.push('\\');
+                output.push(byte as char);
+            }
+            b' '..=b'~' => output.push(byte as char),
+            _ => write!(output, "\\{byte:03o}").unwrap(),
+        }
+    }
+
+    output.push('"');
+    output
+}
+
+// The fragment interface is inferred from the metadata itself, so it has exactly the fields this
+// build wrote, fields only some fragments have are optional
+fn typescript(metadata: &Value, module: bool) -> String {
+    let fragments = metadata.as_object().cloned().unwrap_or_default();
+
+    let mut output = String::new();
+
+    writeln!(output, "// Generated by atlas, do not edit").unwrap();
+    writeln!(output).unwrap();
+
+    if fragments.is_empty() {
+        writeln!(output, "export type AtlasKey = never;").unwrap();
+    } else {
+        writeln!(output, "export type AtlasKey =").unwrap();
+
+        for key in fragments.keys() {
+            writeln!(output, "  | {}", Value::String(key.clone())).unwrap();
+        }
+
+        output.pop();
+        writeln!(output, ";").unwrap();
+    }
+
+    writeln!(output).unwrap();
+    writeln!(output, "export interface Fragment {{").unwrap();
+
+    for (field, (types, count)) in fields(fragments.values()) {
+        writeln!(
+            output,
+            "  {}{}: {};",
+            property(&field),
+            if count < fragments.len() { "?" } else { "" },
+            union(types)
+        )
+        .unwrap();
+    }
+
+    writeln!(output, "}}").unwrap();
+    writeln!(output).unwrap();
+    writeln!(
+        output,
+        "export type AtlasMetadata = Record<AtlasKey, Fragment>;"
+    )
+    .unwrap();
+
+    if module {
+        writeln!(output).unwrap();
+        writeln!(
+            output,
+            "export const atlas: AtlasMetadata = {};",
+            serde_json::to_string_pretty(&Value::Object(fragments)).unwrap()
+        )
+        .unwrap();
+        writeln!(output).unwrap();
+        writeln!(output, "export default atlas;").unwrap();
+    }
+
+    output
+}
+
+// Every field of the given objects with the types it was seen with and how many objects have it
+fn fields<'a>(objects: impl Iterator<Item = &'a Value>) -> BTreeMap<String, (Vec<String>, usize)> {
+    let mut fields = BTreeMap::<String, (Vec<String>, usize)>::new();
+
+    for object in objects.filter_map(Value::as_object) {
+        for (field, value) in object {
+            let (types, count) = fields.entry(field.clone()).or_default();
+            let value_type = value_type(value);
+
+            if !types.contains(&value_type) {
+                types.push(value_type);
+            }
+
+            *count += 1;
+        }
+    }
+
+    fields
+}
+
+fn value_type(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(_) => "boolean".to_string(),
+        Value::Number(_) => "number".to_string(),
+        Value::String(_) => "string".to_string(),
+        Value::Array(values) => {
+            let mut types = Vec::new();
+
+            for value_type in values.iter().map(value_type) {
+                if !types.contains(&value_type) {
+                    types.push(value_type);
+                }
+            }
+
+            match types.len() {
+                0 => "never[]".to_string(),
+                1 => format!("{}[]", types[0]),
+                _ => format!("({})[]", union(types)),
+            }
+        }
+        Value::Object(object) => object_type(object),
+    }
+}
+
+fn object_type(object: &Map<String, Value>) -> String {
+    let fields = object
+        .iter()
+        .map(|(field, value)| format!("{}: {}", property(field), value_type(value)))
+        .collect::<Vec<_>>();
+
+    if fields.is_empty() {
+        "{}".to_string()
+    } else {
+        format!("{{ {} }}", fields.join("; "))
+    }
+}
+
+fn union(mut types: Vec<String>) -> String {
+    types.sort();
+    types.join(" | ")
+}
+
+fn property(name: &str) -> String {
+    let identifier = name.starts_with(|character: char| {
+        character.is_ascii_alphabetic() || character == '_' || character == '$'
+    }) && name
+        .chars()
+        .all(|character| character.is_ascii_alphanumeric() || character == '_' || character == '$');
+
+    if identifier {
+        name.to_string()
+    } else {
+        Value::String(name.to_string()).to_string()
+    }
+}
@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+
+use clap::ValueEnum;
+use image::{DynamicImage, GenericImageView};
+
+use crate::Vector2;
+
+const ALPHA_THRESHOLD: u8 = 128;
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum CollisionShape {
+    BoundingBox,
+    ConvexHull,
+    Polygon,
+}
+
+pub fn extract(image: &DynamicImage, shape: CollisionShape, tolerance: f32) -> Vec<Vector2> {
+    let mask = Mask::new(image);
+
+    let points = match shape {
+        CollisionShape::BoundingBox => bounding_box(&mask),
+        CollisionShape::ConvexHull => convex_hull(&mask),
+        CollisionShape::Polygon => polygon(&mask, tolerance),
+    };
+
+    points
+        .into_iter()
+        .map(|(x, y)| Vector2::new(x as f32, y as f32))
+        .collect()
+}
+
+struct Mask {
+    width: u32,
+    height: u32,
+    solid: Vec<bool>,
+}
+
+impl Mask {
+    fn new(image: &DynamicImage) -> Self {
+        let solid = image
+            .pixels()
+            .map(|(_, _, pixel)| pixel.0[3] >= ALPHA_THRESHOLD)
+            .collect();
+
+        Self {
+            width: image.width(),
+            height: image.height(),
+            solid,
+        }
+    }
+
+    fn is_solid(&self, x: i64, y: i64) -> bool {
+        x >= 0
+            && y >= 0
+            && x < self.width as i64
+            && y < self.height as i64
+            && self.solid[(y * self.width as i64 + x) as usize]
+    }
+
+    fn row_extents(&self) -> impl Iterator<Item = (i64, i64, i64)> + '_ {
+        (0..self.height as i64).filter_map(move |y| {
+            let min = (0..self.width as i64).find(|&x| self.is_solid(x, y))?;
+            let max = (0..self.width as i64)
+                .rev()
+                .find(|&x| self.is_solid(x, y))?;
+
+            Some((y, min, max + 1))
+        })
+    }
+}
+
+fn bounding_box(mask: &Mask) -> Vec<(i64, i64)> {
+    let mut extents = mask.row_extents().peekable();
+
+    let Some(&(min_y, _, _)) = extents.peek() else {
+        return Vec::new();
+    };
+
+    let (max_y, min_x, max_x) = extents.fold(
+        (min_y, i64::MAX, i64::MIN),
+        |(_, min_x, max_x), (y, left, right)| (y + 1, min_x.min(left), max_x.max(right)),
+    );
+
+    vec![
+        (min_x, min_y),
+        (max_x, min_y),
+        (max_x, max_y),
+        (min_x, max_y),
+    ]
+}
+
+fn convex_hull(mask: &Mask) -> Vec<(i64, i64)> {
+    let mut points = mask
+        .row_extents()
+        .flat_map(|(y, left, right)| [(left, y), (right, y), (left, y + 1), (right, y + 1)])
+        .collect::<Vec<_>>();
+
+    points.sort_unstable();
+    points.dedup();
+
+    if points.len() < 3 {
+        return points;
+    }
+
+    // Andrew's monotone chain, producing a clockwise hull in image space (y down)
+    let mut hull: Vec<(i64, i64)> = Vec::with_capacity(points.len() * 2);
+
+    for pass in [points.clone(), points.into_iter().rev().collect()] {
+        let start = hull.len();
+
+        for point in pass {
+            while hull.len() >= start + 2
+                && cross(hull[hull.len() - 2], hull[hull.len() - 1], point) <= 0
+            {
+                hull.pop();
+            }
+
+            hull.push(point);
+        }
+
+        hull.pop();
+    }
+
+    hull
+}
+
+fn cross(origin: (i64, i64), a: (i64, i64), b: (i64, i64)) -> i64 {
+    (a.0 - origin.0) * (b.1 - origin.1) - (a.1 - origin.1) * (b.0 - origin.0)
+}
+
+fn polygon(mask: &Mask, tolerance: f32) -> Vec<(i64, i64)> {
+    let Some(outline) = trace_outline(mask) else {
+        return Vec::new();
+    };
+
+    let simplified = simplify(&outline, tolerance as f64);
+
+    if simplified.len() < 3 {
+        return outline;
+    }
+
+    simplified
+}
+
+// Follows pixel edges with the solid side on the right, returning the loop enclosing the largest area
+fn trace_outline(mask: &Mask) -> Option<Vec<(i64, i64)>> {
+    let mut edges: HashMap<(i64, i64), Vec<(i64, i64)>> = HashMap::new();
+
+    for y in 0..mask.height as i64 {
+        for x in 0..mask.width as i64 {
+            if !mask.is_solid(x, y) {
+                continue;
+            }
+
+            let mut add =
+                |from: (i64, i64), to: (i64, i64)| edges.entry(from).or_default().push(to);
+
+            if !mask.is_solid(x, y - 1) {
+                add((x, y), (x + 1, y));
+            }
+
+            if !mask.is_solid(x + 1, y) {
+                add((x + 1, y), (x + 1, y + 1));
+            }
+
+            if !mask.is_solid(x, y + 1) {
+                add((x + 1, y + 1), (x, y + 1));
+            }
+
+            if !mask.is_solid(x - 1, y) {
+                add((x, y + 1), (x, y));
+            }
+        }
+    }
+
+    let mut starts = edges.keys().copied().collect::<Vec<_>>();
+    starts.sort_unstable_by_key(|&(x, y)| (y, x));
+
+    let mut best: Option<(i64, Vec<(i64, i64)>)> = None;
+
+    for start in starts {
+        while let Some(first) = edges.get_mut(&start).and_then(|targets| targets.pop()) {
+            let mut outline = vec![start];
+            let mut previous = start;
+            let mut current = first;
+
+            while current != start {
+                outline.push(current);
+
+                let direction = (current.0 - previous.0, current.1 - previous.1);
+                let right_turn = (current.0 - direction.1, current.1 + direction.0);
+
+                let targets = edges.get_mut(&current).expect("Unclosed outline");
+                let index = targets
+                    .iter()
+                    .position(|&target| target == right_turn)
+                    .unwrap_or(0);
+                let next = targets.swap_remove(index);
+
+                previous = current;
+                current = next;
+            }
+
+            let area = outline
+                .iter()
+                .zip(outline.iter().cycle().skip(1))
+                .map(|(a, b)| a.0 * b.1 - b.0 * a.1)
+                .sum::<i64>()
+                .abs();
+
+            if best.as_ref().is_none_or(|(best_area, _)| area > *best_area) {
+                best = Some((area, outline));
+            }
+        }
+    }
+
+    best.map(|(_, outline)| outline)
+}
+
+fn simplify(outline: &[(i64, i64)], tolerance: f64) -> Vec<(i64, i64)> {
+    let far = (1..outline.len())
+        .max_by_key(|&index| {
+            let (dx, dy) = (
+                outline[index].0 - outline[0].0,
+                outline[index].1 - outline[0].1,
+            );
+
+            dx * dx + dy * dy
+        })
+        .unwrap_or(0);
+
+    let mut result = Vec::new();
+
+    simplify_chain(&outline[..=far], tolerance, &mut result);
+    result.pop();
+
+    let mut closing = outline[far..].to_vec();
+    closing.push(outline[0]);
+
+    simplify_chain(&closing, tolerance, &mut result);
+    result.pop();
+
+    result
+}
+
+// Ramer-Douglas-Peucker, pushing every kept point including both endpoints
+fn simplify_chain(chain: &[(i64, i64)], tolerance: f64, result: &mut Vec<(i64, i64)>) {
+    let (first, last) = (chain[0], chain[chain.len() - 1]);
+
+    let farthest = (1..chain.len().saturating_sub(1))
+        .map(|index| (index, distance_to_segment(chain[index], first, last)))
+        .max_by(|a, b| a.1.total_cmp(&b.1));
+
+    match farthest {
+        Some((index, distance)) if distance > tolerance => {
+            simplify_chain(&chain[..=index], tolerance, result);
+            result.pop();
+            simplify_chain(&chain[index..], tolerance, result);
+        }
+        _ => {
+            result.push(first);
+            result.push(last);
+        }
+    }
+}
+
+fn distance_to_segment(point: (i64, i64), a: (i64, i64), b: (i64, i64)) -> f64 {
+    let (px, py) = (point.0 as f64, point.1 as f64);
+    let (ax, ay) = (a.0 as f64, a.1 as f64);
+    let (bx, by) = (b.0 as f64, b.1 as f64);
+
+    let (dx, dy) = (bx - ax, by - ay);
+    let length_squared = dx * dx + dy * dy;
+
+    if length_squared == 0.0 {
+        return ((px - ax).powi(2) + (py - ay).powi(2)).sqrt();
+    }
+
+    let t = (((px - ax) * dx + (py - ay) * dy) / length_squared).clamp(0.0, 1.0);
+
+    ((px - (ax + t * dx)).powi(2) + (py - (ay + t * dy)).powi(2)).sqrt()
+}
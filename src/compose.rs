@@ -0,0 +1,225 @@
+use std::{path::PathBuf, str::FromStr};
+
+use image::{Rgba, RgbaImage};
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum BlendMode {
+    Normal,
+    Multiply,
+    Screen,
+}
+
+impl BlendMode {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "normal" => Some(BlendMode::Normal),
+            "multiply" => Some(BlendMode::Multiply),
+            "screen" => Some(BlendMode::Screen),
+            _ => None,
+        }
+    }
+
+    fn blend(self, backdrop: f32, source: f32) -> f32 {
+        match self {
+            BlendMode::Normal => source,
+            BlendMode::Multiply => backdrop * source,
+            BlendMode::Screen => backdrop + source - backdrop * source,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Layer {
+    pub path: PathBuf,
+    pub x: i32,
+    pub y: i32,
+    pub mode: BlendMode,
+}
+
+// A fragment drawn from a base image and the overlays on top of it, the base sets the size
+#[derive(Clone, Debug)]
+pub struct Composition {
+    pub key: String,
+    pub base: PathBuf,
+    pub layers: Vec<Layer>,
+}
+
+impl Composition {
+    pub fn render(&self, base: &RgbaImage, layers: &[RgbaImage]) -> RgbaImage {
+        let mut image = base.clone();
+
+        for (layer, overlay) in self.layers.iter().zip(layers) {
+            for (x, y, pixel) in overlay.enumerate_pixels() {
+                let (x, y) = (x as i64 + layer.x as i64, y as i64 + layer.y as i64);
+
+                if x < 0 || y < 0 || x >= image.width() as i64 || y >= image.height() as i64 {
+                    continue;
+                }
+
+                let backdrop = image.get_pixel_mut(x as u32, y as u32);
+
+                *backdrop = composite(*backdrop, *pixel, layer.mode);
+            }
+        }
+
+        image
+    }
+}
+
+// Source over with the W3C separable blend modes, on straight alpha
+fn composite(backdrop: Rgba<u8>, source: Rgba<u8>, mode: BlendMode) -> Rgba<u8> {
+    let backdrop_alpha = backdrop.0[3] as f32 / 255.0;
+    let source_alpha = source.0[3] as f32 / 255.0;
+    let alpha = source_alpha + backdrop_alpha * (1.0 - source_alpha);
+
+    if alpha == 0.0 {
+        return Rgba([0, 0, 0, 0]);
+    }
+
+    let mut output = [0; 4];
+
+    for (channel, output) in output.iter_mut().take(3).enumerate() {
+        let backdrop_color = backdrop.0[channel] as f32 / 255.0;
+        let source_color = source.0[channel] as f32 / 255.0;
+
+        let blended = (1.0 - backdrop_alpha) * source_color
+            + backdrop_alpha * mode.blend(backdrop_color, source_color);
+        let color = (source_alpha * blended
+            + backdrop_alpha * backdrop_color * (1.0 - source_alpha))
+            / alpha;
+
+        *output = (color * 255.0).round() as u8;
+    }
+
+    output[3] = (alpha * 255.0).round() as u8;
+
+    Rgba(output)
+}
+
+impl FromStr for Composition {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (key, layers) = value.split_once('=').ok_or_else(|| {
+            format!("expected KEY=BASE+OVERLAY[@X,Y][:MODE][+...], got '{value}'")
+        })?;
+
+        if key.is_empty() {
+            return Err("composition key must not be empty".to_string());
+        }
+
+        let mut layers = layers.split('+');
+        let base = PathBuf::from(layers.next().unwrap_or_default());
+
+        if base.as_os_str().is_empty() {
+            return Err(format!("composition '{key}' has no base image"));
+        }
+
+        let layers = layers.map(parse_layer).collect::<Result<Vec<_>, _>>()?;
+
+        if layers.is_empty() {
+            return Err(format!("composition '{key}' has no overlay"));
+        }
+
+        Ok(Self {
+            key: key.to_string(),
+            base,
+            layers,
+        })
+    }
+}
+
+// The mode and offset are only taken off the end when they parse, so paths keep their `:` and `@`
+fn parse_layer(layer: &str) -> Result<Layer, String> {
+    let (layer, mode) = match layer.rsplit_once(':') {
+        Some((path, mode)) => match BlendMode::parse(mode) {
+            Some(mode) => (path, mode),
+            None => (layer, BlendMode::Normal),
+        },
+        None => (layer, BlendMode::Normal),
+    };
+
+    let offset = layer.rsplit_once('@').and_then(|(path, offset)| {
+        let (x, y) = offset.split_once(',')?;
+
+        Some((path, x.trim().parse().ok()?, y.trim().parse().ok()?))
+    });
+
+    let (path, x, y) = offset.unwrap_or((layer, 0, 0));
+
+    if path.is_empty() {
+        return Err("composition overlay must not be empty".to_string());
+    }
+
+    Ok(Layer {
+        path: PathBuf::from(path),
+        x,
+        y,
+        mode,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use image::{Rgba, RgbaImage};
+
+    use super::{composite, BlendMode, Composition};
+
+    #[test]
+    fn layers_parse_offsets_and_modes() {
+        let composition = "hero_red=hero.png+tint.png@2,-1:multiply+hat.png"
+            .parse::<Composition>()
+            .unwrap();
+
+        assert_eq!(composition.key, "hero_red");
+        assert_eq!(composition.base, PathBuf::from("hero.png"));
+        assert_eq!(composition.layers.len(), 2);
+        assert_eq!(
+            (
+                composition.layers[0].x,
+                composition.layers[0].y,
+                composition.layers[0].mode
+            ),
+            (2, -1, BlendMode::Multiply)
+        );
+        assert_eq!(composition.layers[1].path, PathBuf::from("hat.png"));
+        assert_eq!(composition.layers[1].mode, BlendMode::Normal);
+
+        assert!("hero=hero.png".parse::<Composition>().is_err());
+        assert!("=hero.png+hat.png".parse::<Composition>().is_err());
+    }
+
+    #[test]
+    fn opaque_layers_blend_with_their_mode() {
+        let backdrop = Rgba([200, 100, 0, 255]);
+        let source = Rgba([128, 255, 255, 255]);
+
+        assert_eq!(
+            composite(backdrop, source, BlendMode::Normal),
+            Rgba([128, 255, 255, 255])
+        );
+        assert_eq!(
+            composite(backdrop, source, BlendMode::Multiply),
+            Rgba([100, 100, 0, 255])
+        );
+        assert_eq!(
+            composite(backdrop, source, BlendMode::Screen),
+            Rgba([228, 255, 255, 255])
+        );
+    }
+
+    #[test]
+    fn overlays_are_clipped_to_the_base() {
+        let composition = "icon=base.png+dot.png@1,1".parse::<Composition>().unwrap();
+        let base = RgbaImage::new(2, 2);
+        let dot = RgbaImage::from_pixel(2, 2, Rgba([255, 0, 0, 255]));
+
+        let image = composition.render(&base, &[dot]);
+
+        assert_eq!(image.dimensions(), (2, 2));
+        assert_eq!(image.get_pixel(0, 0), &Rgba([0, 0, 0, 0]));
+        assert_eq!(image.get_pixel(1, 1), &Rgba([255, 0, 0, 255]));
+    }
+}
@@ -0,0 +1,100 @@
+use std::{io::Cursor, path::PathBuf};
+
+use atlas::{
+    allocator::{Allocator, AllocatorOptions},
+    Algorithm,
+};
+use image::{DynamicImage, GenericImageView, ImageFormat, RgbaImage};
+
+const HUE_BUCKETS: u32 = 12;
+
+// Groups sprites by the hue of their average color, darkest first within a group, so neighbours
+// in packing order (and usually on the page) share colors the PNG filters and deflate can exploit
+pub fn order(images: &mut [(PathBuf, DynamicImage)]) {
+    images.sort_by_cached_key(|(_, image)| cluster(image));
+}
+
+// Encodes the pages a given order would produce, for comparing orders without writing anything
+pub fn encoded_size(
+    images: &[(PathBuf, DynamicImage)],
+    algorithm: Algorithm,
+    options: AllocatorOptions,
+    width: u32,
+    height: u32,
+) -> Option<usize> {
+    let mut pages: Vec<(Allocator, RgbaImage)> = Vec::new();
+
+    for (_, image) in images {
+        let existing = pages.iter_mut().find_map(|(allocator, page)| {
+            allocator
+                .allocate(image.width(), image.height())
+                .map(|allocation| (allocation, page))
+        });
+
+        let (allocation, page) = match existing {
+            Some(existing) => existing,
+            None => {
+                let mut allocator = Allocator::with_options(algorithm, width, height, options);
+                let allocation = allocator.allocate(image.width(), image.height())?;
+
+                pages.push((allocator, RgbaImage::new(width, height)));
+
+                (allocation, &mut pages.last_mut().unwrap().1)
+            }
+        };
+
+        image.pixels().for_each(|(x, y, pixel)| {
+            page.put_pixel(allocation.x as u32 + x, allocation.y as u32 + y, pixel);
+        });
+    }
+
+    pages.iter().try_fold(0, |total, (_, page)| {
+        let mut encoded = Cursor::new(Vec::new());
+        page.write_to(&mut encoded, ImageFormat::Png).ok()?;
+
+        Some(total + encoded.into_inner().len())
+    })
+}
+
+fn cluster(image: &DynamicImage) -> (u32, u32) {
+    // Weighted by alpha so fully transparent padding doesn't drag every sprite towards black
+    let (red, green, blue, weight) = image.pixels().fold(
+        (0.0, 0.0, 0.0, 0.0),
+        |(red, green, blue, weight), (_, _, pixel)| {
+            let alpha = pixel.0[3] as f64 / 255.0;
+
+            (
+                red + pixel.0[0] as f64 * alpha,
+                green + pixel.0[1] as f64 * alpha,
+                blue + pixel.0[2] as f64 * alpha,
+                weight + alpha,
+            )
+        },
+    );
+
+    if weight == 0.0 {
+        return (0, 0);
+    }
+
+    let (red, green, blue) = (red / weight, green / weight, blue / weight);
+    let max = red.max(green).max(blue);
+    let min = red.min(green).min(blue);
+    let luma = (0.299 * red + 0.587 * green + 0.114 * blue) as u32;
+
+    // Greys have no meaningful hue, so they get a bucket of their own ahead of the colors
+    if max - min < 16.0 {
+        return (1, luma);
+    }
+
+    let hue = if max == red {
+        ((green - blue) / (max - min)).rem_euclid(6.0)
+    } else if max == green {
+        (blue - red) / (max - min) + 2.0
+    } else {
+        (red - green) / (max - min) + 4.0
+    };
+
+    let bucket = (hue / 6.0 * HUE_BUCKETS as f64) as u32 % HUE_BUCKETS;
+
+    (2 + bucket, luma)
+}
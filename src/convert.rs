@@ -0,0 +1,426 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use atlas::{Fragment, Trim, Vector2, META_KEY};
+use clap::ValueEnum;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use crate::{
+    error::{Context, Error},
+    export::{
+        self, godot,
+        texture_packer::{Rect, Size},
+        PackedFragment, Rotation, Sheet,
+    },
+    format::{self, MetadataFormat},
+    html,
+    metadata::{self, Rectangle},
+    nine_slice::NineSlice,
+    Origin,
+};
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum Source {
+    Atlas,
+    // The JSON hash or array layout, one page per file
+    TexturePacker,
+}
+
+// Any --metadata-format, or the AtlasTexture resources --godot-output writes
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Target {
+    Format(MetadataFormat),
+    Godot,
+}
+
+impl Target {
+    pub fn needs_images(self) -> bool {
+        match self {
+            Target::Format(format) => format.is_export(),
+            Target::Godot => true,
+        }
+    }
+
+    fn supports_rotation(self) -> bool {
+        match self {
+            Target::Format(format) => format.supports_rotation(),
+            Target::Godot => godot::ROTATION == Rotation::Clockwise,
+        }
+    }
+
+    fn name(self) -> String {
+        match self {
+            Target::Format(format) => format.to_possible_value().unwrap().get_name().to_string(),
+            Target::Godot => "godot".to_string(),
+        }
+    }
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if value == "godot" {
+            return Ok(Target::Godot);
+        }
+
+        <MetadataFormat as ValueEnum>::from_str(value, false)
+            .map(Target::Format)
+            .map_err(|_| {
+                format!("invalid format '{value}', expected godot or a --metadata-format value")
+            })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TexturePackerFrame {
+    #[serde(default)]
+    filename: Option<String>,
+    frame: Rect,
+    #[serde(default)]
+    rotated: bool,
+    #[serde(default)]
+    trimmed: bool,
+    sprite_source_size: Rect,
+    source_size: Size,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TexturePackerFrames {
+    Hash(BTreeMap<String, TexturePackerFrame>),
+    Array(Vec<TexturePackerFrame>),
+}
+
+#[derive(Deserialize)]
+struct TexturePackerMeta {
+    image: PathBuf,
+    size: Size,
+}
+
+#[derive(Deserialize)]
+struct TexturePackerDocument {
+    frames: TexturePackerFrames,
+    meta: TexturePackerMeta,
+}
+
+// What every target is written from, the fragments in image space and the page images they
+// were packed into
+struct Packed {
+    fragments: BTreeMap<PathBuf, PackedFragment>,
+    width: u32,
+    height: u32,
+    images: Vec<PathBuf>,
+}
+
+// The atlas images are left alone, only the metadata describing them is written again. `atlas`
+// names the page images for atlas's own metadata, which doesn't refer to them
+pub fn convert(
+    input: &Path,
+    output: &Path,
+    source: Source,
+    target: Target,
+    atlas: Option<&Path>,
+) -> Result<(), Error> {
+    let text = fs::read_to_string(input).input_context(input)?;
+
+    // Between atlas's own formats every field carries over, only the encoding changes
+    if let (Source::Atlas, Target::Format(format)) = (source, target) {
+        if !format.is_export() {
+            let mut document =
+                serde_json::from_str::<Map<String, Value>>(&text).input_context(input)?;
+
+            if format == MetadataFormat::Binary {
+                let meta = document.remove(META_KEY).unwrap_or_default();
+
+                // Without a meta section the converted centers would be read as top-left ones
+                if meta["origin"] == "center" || meta["flip_y"] == true {
+                    return Err(Error::input(
+                        input,
+                        "binary metadata always measures from the top left, this was written \
+                         with --origin center or --flip-y",
+                    ));
+                }
+            }
+
+            let encoded = format::encode(&document, format).output_context(output)?;
+
+            return fs::write(output, encoded).output_context(output);
+        }
+    }
+
+    let packed = match source {
+        Source::Atlas => read_atlas(input, &text, atlas.expect("checked with the arguments"))?,
+        Source::TexturePacker => read_texture_packer(input, &text)?,
+    };
+
+    if !target.supports_rotation()
+        && packed
+            .fragments
+            .values()
+            .any(|packed| packed.fragment.rotated)
+    {
+        return Err(Error::input(
+            input,
+            format!(
+                "{} cannot describe sprites rotated clockwise",
+                target.name()
+            ),
+        ));
+    }
+
+    match target {
+        Target::Format(format) if format.is_export() => {
+            let sheet = Sheet {
+                width: packed.width,
+                height: packed.height,
+                images: packed
+                    .images
+                    .iter()
+                    .map(|image| html::relative_href(output, image))
+                    .collect::<Result<_, _>>()
+                    .output_context(output)?,
+            };
+
+            format::write_export(output, &packed.fragments, format, &sheet)?;
+        }
+        Target::Format(format) => {
+            let mut document = packed
+                .fragments
+                .iter()
+                .map(|(key, packed)| {
+                    (
+                        export::name(key),
+                        serde_json::to_value(&packed.fragment).unwrap(),
+                    )
+                })
+                .collect::<Map<_, _>>();
+
+            // Centers are rebuilt from whole pixel frames, so they're exact
+            if format != MetadataFormat::Binary {
+                document.insert(
+                    META_KEY.to_string(),
+                    json!({"rounding": "exact", "origin": "top-left", "flip_y": false}),
+                );
+            }
+
+            let encoded = format::encode(&document, format).output_context(output)?;
+
+            fs::write(output, encoded).output_context(output)?;
+        }
+        Target::Godot => {
+            let sheet = Sheet {
+                width: packed.width,
+                height: packed.height,
+                images: packed
+                    .images
+                    .iter()
+                    .map(|image| image.to_string_lossy().into_owned())
+                    .collect(),
+            };
+
+            godot::write(output, &export::pages(&packed.fragments, &sheet))
+                .output_context(output)?;
+        }
+    }
+
+    Ok(())
+}
+
+// Written centers follow $meta's origin and flip_y, frames are measured from the top left of the
+// page image again
+fn read_atlas(input: &Path, text: &str, atlas: &Path) -> Result<Packed, Error> {
+    let mut document = serde_json::from_str::<Map<String, Value>>(text).input_context(input)?;
+    let meta = document.remove(META_KEY).unwrap_or_default();
+    let origin = if meta["origin"] == "center" {
+        Origin::Center
+    } else {
+        Origin::TopLeft
+    };
+    let flip_y = meta["flip_y"] == true;
+
+    if document
+        .values()
+        .any(|fragment| fragment.get("layer").is_some())
+    {
+        return Err(Error::input(
+            input,
+            "array layouts are a single texture, there are no page images to refer to",
+        ));
+    }
+
+    let pages = document
+        .values()
+        .filter_map(|fragment| fragment["page"].as_u64())
+        .max()
+        .map_or(1, |page| page as u32 + 1);
+    let images = if pages == 1 {
+        vec![atlas.to_path_buf()]
+    } else {
+        (0..pages)
+            .map(|page| metadata::page_path(atlas, page))
+            .collect()
+    };
+    let (width, height) = image::image_dimensions(&images[0]).input_context(&images[0])?;
+
+    let fragments = document
+        .into_iter()
+        .map(|(key, value)| {
+            let nine_slice = value
+                .get("nine_slice")
+                .map(|slice| serde_json::from_value::<NineSlice>(slice.clone()))
+                .transpose()?;
+            let mut fragment = serde_json::from_value::<Fragment>(value)?;
+
+            fragment.center =
+                metadata::image_center(fragment.center, origin, flip_y, width, height);
+
+            let frame =
+                metadata::packed_rectangle(fragment.center, fragment.size, fragment.rotated);
+
+            Ok((
+                PathBuf::from(key),
+                PackedFragment {
+                    fragment,
+                    frame,
+                    nine_slice,
+                },
+            ))
+        })
+        .collect::<Result<_, serde_json::Error>>()
+        .input_context(input)?;
+
+    Ok(Packed {
+        fragments,
+        width,
+        height,
+        images,
+    })
+}
+
+// TexturePacker's frame is the unrotated size, the rectangle on the page is turned for rotated
+// sprites. Its image is relative to the data file
+fn read_texture_packer(input: &Path, text: &str) -> Result<Packed, Error> {
+    let document = serde_json::from_str::<TexturePackerDocument>(text).input_context(input)?;
+
+    let frames = match document.frames {
+        TexturePackerFrames::Hash(frames) => frames.into_iter().collect::<Vec<_>>(),
+        TexturePackerFrames::Array(frames) => frames
+            .into_iter()
+            .map(|frame| {
+                let name = frame.filename.clone().ok_or_else(|| {
+                    Error::input(input, "frames in the array layout need a filename")
+                })?;
+
+                Ok((name, frame))
+            })
+            .collect::<Result<_, Error>>()?,
+    };
+
+    let fragments = frames
+        .into_iter()
+        .map(|(name, frame)| {
+            let (width, height) = (frame.frame.w, frame.frame.h);
+            let frame_rectangle = Rectangle {
+                x: frame.frame.x,
+                y: frame.frame.y,
+                width: if frame.rotated { height } else { width },
+                height: if frame.rotated { width } else { height },
+            };
+
+            let fragment = Fragment {
+                center: Vector2::new(
+                    frame_rectangle.x as f32 + frame_rectangle.width as f32 / 2.0,
+                    frame_rectangle.y as f32 + frame_rectangle.height as f32 / 2.0,
+                ),
+                size: Vector2::new(width as f32, height as f32),
+                page: None,
+                rotated: frame.rotated,
+                trim: frame.trimmed.then(|| Trim {
+                    source_size: Vector2::new(
+                        frame.source_size.w as f32,
+                        frame.source_size.h as f32,
+                    ),
+                    offset: Vector2::new(
+                        frame.sprite_source_size.x as f32,
+                        frame.sprite_source_size.y as f32,
+                    ),
+                }),
+            };
+
+            (
+                PathBuf::from(name),
+                PackedFragment {
+                    fragment,
+                    frame: frame_rectangle,
+                    nine_slice: None,
+                },
+            )
+        })
+        .collect();
+
+    let directory = input.parent().unwrap_or(Path::new(""));
+
+    Ok(Packed {
+        fragments,
+        width: document.meta.size.w,
+        height: document.meta.size.h,
+        images: vec![directory.join(document.meta.image)],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_texture_packer, Target};
+    use crate::format::MetadataFormat;
+
+    #[test]
+    fn texture_packer_frames_become_page_rectangles() {
+        let text = r#"{
+            "frames": {
+                "hero.png": {
+                    "frame": {"x": 2, "y": 1, "w": 8, "h": 3},
+                    "rotated": true,
+                    "trimmed": true,
+                    "spriteSourceSize": {"x": 1, "y": 0, "w": 8, "h": 3},
+                    "sourceSize": {"w": 10, "h": 3}
+                }
+            },
+            "meta": {"image": "sheet.png", "size": {"w": 16, "h": 16}}
+        }"#;
+
+        let packed = read_texture_packer("art/sheet.json".as_ref(), text)
+            .ok()
+            .unwrap();
+        let hero = &packed.fragments[std::path::Path::new("hero.png")];
+
+        assert_eq!(
+            (
+                hero.frame.x,
+                hero.frame.y,
+                hero.frame.width,
+                hero.frame.height
+            ),
+            (2, 1, 3, 8)
+        );
+        assert_eq!((hero.fragment.center.x, hero.fragment.center.y), (3.5, 5.0));
+        assert_eq!(hero.fragment.trim.as_ref().unwrap().source_size.x, 10.0);
+        assert_eq!(packed.images, [std::path::PathBuf::from("art/sheet.png")]);
+    }
+
+    #[test]
+    fn targets_parse() {
+        assert!(matches!("godot".parse::<Target>(), Ok(Target::Godot)));
+        assert!(matches!(
+            "sparrow".parse::<Target>(),
+            Ok(Target::Format(MetadataFormat::Sparrow))
+        ));
+        assert!("tres".parse::<Target>().is_err());
+    }
+}
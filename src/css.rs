@@ -0,0 +1,60 @@
+use std::{collections::HashSet, fmt::Write, fs, io, path::Path};
+
+use crate::Placement;
+
+// Keys become `sprite-` classes with everything outside [A-Za-z0-9_-] replaced, keys that end up
+// with the same class get a numeric suffix in placement order
+pub fn class_names(placements: &[Placement]) -> Vec<String> {
+    let mut used = HashSet::new();
+
+    placements
+        .iter()
+        .map(|placement| {
+            let key = placement.key.with_extension("");
+            let base = format!(
+                "sprite-{}",
+                key.to_string_lossy()
+                    .chars()
+                    .map(|character| match character {
+                        'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '-' => character,
+                        _ => '-',
+                    })
+                    .collect::<String>()
+            );
+
+            let mut class_name = base.clone();
+            let mut suffix = 2;
+
+            while !used.insert(class_name.clone()) {
+                class_name = format!("{base}-{suffix}");
+                suffix += 1;
+            }
+
+            class_name
+        })
+        .collect()
+}
+
+pub fn write_stylesheet(
+    path: &Path,
+    page_hrefs: &[String],
+    placements: &[Placement],
+    class_names: &[String],
+) -> io::Result<()> {
+    let mut css = String::new();
+
+    for (placement, class_name) in placements.iter().zip(class_names) {
+        writeln!(
+            css,
+            ".{class_name} {{ display: inline-block; width: {}px; height: {}px; background: url('{}') no-repeat {}px {}px; }}",
+            placement.width,
+            placement.height,
+            page_hrefs[placement.page].replace('\'', "\\'"),
+            -(placement.x as i64),
+            -(placement.y as i64)
+        )
+        .unwrap();
+    }
+
+    fs::write(path, css)
+}
@@ -0,0 +1,296 @@
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, ErrorKind, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    panic::{self, AssertUnwindSafe},
+    path::{Component, Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use atlas::runtime::AtlasMetadata;
+use image::ImageFormat;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Map, Value};
+
+use crate::{
+    error::{Context, Error},
+    interrupt, subatlas,
+};
+
+// JSON-RPC's own codes, for requests that never reach a method. Failed methods use the exit code
+// the same run of the CLI would have had
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+
+// std retries accepts and reads a signal interrupted, so Ctrl-C is polled for this often instead
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Deserialize)]
+struct Request {
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Deserialize)]
+struct AppendParams {
+    atlas: PathBuf,
+    metadata: PathBuf,
+    #[serde(default)]
+    arguments: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct InspectParams {
+    metadata: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct UnpackParams {
+    atlas: PathBuf,
+    metadata: PathBuf,
+    output: PathBuf,
+}
+
+enum Failure {
+    Request(i64, String),
+    Run(Error),
+}
+
+// One JSON-RPC 2.0 request per line and one response line for each, connections are served one
+// at a time so runs never race for the same outputs. `generate` runs `atlas generate` with the
+// given arguments in this process, its summary goes to the daemon's own stdout
+pub fn serve(
+    socket: &Path,
+    mut generate: impl FnMut(Vec<String>) -> Result<(), Error>,
+) -> Result<(), Error> {
+    // A socket nobody answers on was left behind by a daemon that didn't shut down
+    if socket.exists() {
+        if UnixStream::connect(socket).is_ok() {
+            return Err(Error::output(
+                socket,
+                "another atlas daemon is already listening on it",
+            ));
+        }
+
+        fs::remove_file(socket).output_context(socket)?;
+    }
+
+    let listener = UnixListener::bind(socket).output_context(socket)?;
+    listener.set_nonblocking(true).output_context(socket)?;
+
+    eprintln!("Listening on {}", socket.display());
+
+    // Ctrl-C lets the request being served finish, then the daemon stops and removes its socket
+    'serving: while !interrupt::requested() {
+        let stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(error) if error.kind() == ErrorKind::WouldBlock => {
+                thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+            Err(_) => continue,
+        };
+
+        if stream.set_nonblocking(false).is_err()
+            || stream.set_read_timeout(Some(POLL_INTERVAL)).is_err()
+        {
+            continue;
+        }
+
+        let mut reader = BufReader::new(&stream);
+        let mut line = Vec::new();
+
+        while !interrupt::requested() {
+            // A timed out read keeps what it read so far in `line`
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(error) if timed_out(&error) => continue,
+                Err(_) => break,
+            }
+
+            let request = String::from_utf8_lossy(&line).into_owned();
+            line.clear();
+
+            if request.trim().is_empty() {
+                continue;
+            }
+
+            let (response, shutdown) = respond(&request, &mut generate);
+
+            if writeln!(&stream, "{response}").is_err() {
+                break;
+            }
+
+            if shutdown {
+                break 'serving;
+            }
+        }
+    }
+
+    fs::remove_file(socket).output_context(socket)
+}
+
+fn timed_out(error: &io::Error) -> bool {
+    matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut)
+}
+
+fn respond(
+    line: &str,
+    generate: &mut impl FnMut(Vec<String>) -> Result<(), Error>,
+) -> (Value, bool) {
+    let request = match serde_json::from_str::<Value>(line) {
+        Ok(request) => request,
+        Err(error) => return (failure(Value::Null, PARSE_ERROR, error), false),
+    };
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let request = match serde_json::from_value::<Request>(request) {
+        Ok(request) => request,
+        Err(error) => return (failure(id, INVALID_REQUEST, error), false),
+    };
+
+    if request.method == "shutdown" {
+        return (json!({"jsonrpc": "2.0", "id": id, "result": null}), true);
+    }
+
+    // A request that panics fails on its own, the daemon keeps serving the next one
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        call(&request.method, request.params, generate)
+    }))
+    .unwrap_or_else(|_| {
+        Err(Failure::Request(
+            INTERNAL_ERROR,
+            "the request panicked".to_string(),
+        ))
+    });
+
+    let response = match result {
+        Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+        Err(Failure::Request(code, message)) => failure(id, code, message),
+        Err(Failure::Run(error)) => failure(id, error.exit_code().into(), error),
+    };
+
+    (response, false)
+}
+
+fn call(
+    method: &str,
+    params: Value,
+    generate: &mut impl FnMut(Vec<String>) -> Result<(), Error>,
+) -> Result<Value, Failure> {
+    match method {
+        // The arguments `atlas generate` would be given
+        "pack" => {
+            generate(parse(params)?).map_err(Failure::Run)?;
+
+            Ok(Value::Null)
+        }
+        // Packs an existing atlas again together with more inputs, its sprites keep their keys
+        "append" => {
+            let params = parse::<AppendParams>(params)?;
+            let atlas = params.atlas.to_string_lossy().into_owned();
+            let metadata = params.metadata.to_string_lossy().into_owned();
+
+            let mut arguments = vec![
+                "--sub-atlas".to_string(),
+                atlas.clone(),
+                metadata.clone(),
+                "--atlas-output".to_string(),
+                atlas,
+                "--metadata-output".to_string(),
+                metadata,
+            ];
+            arguments.extend(params.arguments);
+
+            generate(arguments).map_err(Failure::Run)?;
+
+            Ok(Value::Null)
+        }
+        "inspect" => {
+            let params = parse::<InspectParams>(params)?;
+            let metadata = AtlasMetadata::load(&params.metadata)
+                .map_err(|error| Failure::Run(Error::input(&params.metadata, error)))?;
+
+            Ok(Value::Object(
+                metadata
+                    .names
+                    .iter()
+                    .zip(&metadata.fragments)
+                    .map(|(name, fragment)| {
+                        (
+                            name.to_string_lossy().into_owned(),
+                            serde_json::to_value(fragment).unwrap(),
+                        )
+                    })
+                    .collect::<Map<_, _>>(),
+            ))
+        }
+        "unpack" => {
+            let params = parse::<UnpackParams>(params)?;
+
+            unpack(&params.atlas, &params.metadata, &params.output)
+                .map(|written| json!(written))
+                .map_err(Failure::Run)
+        }
+        _ => Err(Failure::Request(
+            METHOD_NOT_FOUND,
+            format!("unknown method '{method}'"),
+        )),
+    }
+}
+
+// Every sprite as a PNG under `output`, at its key. Keys that would climb out of it are refused
+fn unpack(atlas: &Path, metadata: &Path, output: &Path) -> Result<Vec<PathBuf>, Error> {
+    let sprites = subatlas::load(atlas, metadata, None)?;
+    let mut written = Vec::new();
+
+    for (key, image) in sprites {
+        if !key
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(Error::input(
+                metadata,
+                format!("key {} would be unpacked outside the output", key.display()),
+            ));
+        }
+
+        let mut path = output.join(&key).into_os_string();
+
+        if key.extension().is_none_or(|extension| extension != "png") {
+            path.push(".png");
+        }
+
+        let path = PathBuf::from(path);
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).output_context(parent)?;
+        }
+
+        image
+            .save_with_format(&path, ImageFormat::Png)
+            .output_context(&path)?;
+
+        written.push(path);
+    }
+
+    Ok(written)
+}
+
+fn parse<T: DeserializeOwned>(params: Value) -> Result<T, Failure> {
+    serde_json::from_value(params)
+        .map_err(|error| Failure::Request(INVALID_PARAMS, error.to_string()))
+}
+
+fn failure(id: Value, code: i64, message: impl std::fmt::Display) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": {"code": code, "message": message.to_string()},
+    })
+}
@@ -0,0 +1,106 @@
+use std::{fs, io::Cursor, path::Path, sync::mpsc, thread, time::Duration};
+
+use image::{
+    io::{Limits, Reader},
+    DynamicImage,
+};
+
+pub struct DecodeLimits {
+    pub max_dimension: u32,
+    pub max_decoded_bytes: u64,
+    pub timeout: Duration,
+}
+
+impl DecodeLimits {
+    pub fn check_dimensions(&self, width: u32, height: u32) -> Result<(), String> {
+        if width > self.max_dimension || height > self.max_dimension {
+            return Err(format!(
+                "image is {width}x{height}, exceeding the untrusted input limit of {0}x{0}",
+                self.max_dimension
+            ));
+        }
+
+        // Everything is converted to RGBA8 before packing, so that is what ends up resident
+        let decoded_bytes = width as u64 * height as u64 * 4;
+
+        if decoded_bytes > self.max_decoded_bytes {
+            return Err(format!(
+                "image decodes to {decoded_bytes} bytes, exceeding the untrusted input limit of {} bytes",
+                self.max_decoded_bytes
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+pub fn open(path: &Path, limits: Option<&DecodeLimits>) -> Result<DynamicImage, String> {
+    let Some(limits) = limits else {
+        return image::open(path).map_err(|error| error.to_string());
+    };
+
+    from_bytes(read(path, Some(limits))?, Some(limits))
+}
+
+pub fn read(path: &Path, limits: Option<&DecodeLimits>) -> Result<Vec<u8>, String> {
+    if limits.is_some()
+        && fs::symlink_metadata(path)
+            .map_err(|error| error.to_string())?
+            .file_type()
+            .is_symlink()
+    {
+        return Err("refusing to follow a symlinked input in untrusted mode".to_string());
+    }
+
+    fs::read(path).map_err(|error| error.to_string())
+}
+
+pub fn from_bytes(bytes: Vec<u8>, limits: Option<&DecodeLimits>) -> Result<DynamicImage, String> {
+    let Some(limits) = limits else {
+        return image::load_from_memory(&bytes).map_err(|error| error.to_string());
+    };
+
+    let (width, height) = Reader::new(Cursor::new(&bytes))
+        .with_guessed_format()
+        .map_err(|error| error.to_string())?
+        .into_dimensions()
+        .map_err(|error| error.to_string())?;
+
+    limits.check_dimensions(width, height)?;
+
+    let mut decoder_limits = Limits::default();
+    decoder_limits.max_image_width = Some(limits.max_dimension);
+    decoder_limits.max_image_height = Some(limits.max_dimension);
+    decoder_limits.max_alloc = Some(limits.max_decoded_bytes);
+
+    with_timeout(limits.timeout, move || {
+        Reader::new(Cursor::new(bytes))
+            .with_guessed_format()
+            .map_err(image::ImageError::IoError)
+            .and_then(|mut reader| {
+                reader.limits(decoder_limits);
+                reader.decode()
+            })
+            .map_err(|error| error.to_string())
+    })
+}
+
+// A decoder stuck on a hostile file can't be interrupted, so it is left behind on timeout
+pub fn with_timeout<T: Send + 'static>(
+    timeout: Duration,
+    decode: impl FnOnce() -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let _ = sender.send(decode());
+    });
+
+    match receiver.recv_timeout(timeout) {
+        Ok(result) => result,
+        Err(_) => Err(format!(
+            "decoding took longer than {} seconds",
+            timeout.as_secs_f32()
+        )),
+    }
+}
@@ -0,0 +1,310 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use image::{DynamicImage, GenericImageView};
+use serde::Serialize;
+
+use crate::{
+    error::{Context, Error},
+    metadata::{self, Rectangle},
+    overlay::{Mark, Overlay, OverlayStyle},
+};
+
+const WINDOW: u32 = 8;
+const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+pub struct DiffOptions {
+    pub pixels: bool,
+    pub threshold: f64,
+    pub overlay: Option<PathBuf>,
+    pub overlay_style: OverlayStyle,
+    pub events: Option<PathBuf>,
+}
+
+// One entry per fragment that a client holding the old atlas has to update
+#[derive(Serialize)]
+struct Event<'a> {
+    key: &'a Path,
+    changes: Vec<Change>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    old: Option<Placement>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    new: Option<Placement>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum Change {
+    Added,
+    Removed,
+    Moved,
+    Resized,
+    Pixels,
+}
+
+#[derive(Serialize)]
+struct Placement {
+    page: u32,
+    #[serde(flatten)]
+    rectangle: Rectangle,
+}
+
+impl Placement {
+    fn new(rectangle: Rectangle, page: Option<u32>) -> Self {
+        Self {
+            page: page.unwrap_or(0),
+            rectangle,
+        }
+    }
+}
+
+// Returns whether any fragment changed beyond what the options tolerate
+pub fn diff(
+    old_atlas: &Path,
+    old_metadata: &Path,
+    new_atlas: &Path,
+    new_metadata: &Path,
+    options: &DiffOptions,
+) -> Result<bool, Error> {
+    let old_fragments = metadata::read(old_metadata)?;
+    let new_fragments = metadata::read(new_metadata)?;
+
+    let images = if options.pixels || options.overlay.is_some() {
+        for (metadata_path, fragments) in [
+            (old_metadata, &old_fragments),
+            (new_metadata, &new_fragments),
+        ] {
+            if fragments.values().any(|fragment| fragment.page.is_some()) {
+                return Err(Error::input(
+                    metadata_path,
+                    "--pixels and --overlay only support single page atlases",
+                ));
+            }
+        }
+
+        Some((
+            image::open(old_atlas).input_context(old_atlas)?,
+            image::open(new_atlas).input_context(new_atlas)?,
+        ))
+    } else {
+        None
+    };
+
+    let mut overlay = options.overlay.as_ref().map(|_| {
+        let (old_image, new_image) = images.as_ref().unwrap();
+
+        Overlay::new(
+            old_image.width().max(new_image.width()),
+            old_image.height().max(new_image.height()),
+            new_image,
+            options.overlay_style,
+        )
+    });
+
+    let mut keys = old_fragments
+        .keys()
+        .chain(new_fragments.keys())
+        .collect::<Vec<&PathBuf>>();
+    keys.sort();
+    keys.dedup();
+
+    let mut changed = false;
+    let mut events = Vec::new();
+
+    for key in keys {
+        let mut event = Event {
+            key,
+            changes: Vec::new(),
+            old: old_fragments
+                .get(key)
+                .map(|old| Placement::new(old.rectangle(), old.page)),
+            new: new_fragments
+                .get(key)
+                .map(|new| Placement::new(new.rectangle(), new.page)),
+        };
+
+        let (old, new, old_page, new_page) = match (old_fragments.get(key), new_fragments.get(key))
+        {
+            (Some(old), Some(new)) => (old.rectangle(), new.rectangle(), old.page, new.page),
+            (Some(old), None) => {
+                if let Some(overlay) = &mut overlay {
+                    overlay.outline(&old.rectangle(), Mark::Removed);
+                }
+
+                println!("removed {}", key.display());
+                changed = true;
+                event.changes.push(Change::Removed);
+                events.push(event);
+                continue;
+            }
+            (None, Some(new)) => {
+                if let Some(overlay) = &mut overlay {
+                    overlay.outline(&new.rectangle(), Mark::Added);
+                }
+
+                println!("added {}", key.display());
+                changed = true;
+                event.changes.push(Change::Added);
+                events.push(event);
+                continue;
+            }
+            (None, None) => unreachable!(),
+        };
+
+        if (old.width, old.height) != (new.width, new.height) {
+            if let Some(overlay) = &mut overlay {
+                overlay.outline(&new, Mark::Resized);
+            }
+
+            println!(
+                "resized {}: {}x{} -> {}x{}",
+                key.display(),
+                old.width,
+                old.height,
+                new.width,
+                new.height
+            );
+            changed = true;
+            event.changes.push(Change::Resized);
+            events.push(event);
+            continue;
+        }
+
+        if old_page.unwrap_or(0) != new_page.unwrap_or(0) {
+            event.changes.push(Change::Moved);
+
+            println!(
+                "moved {}: page {} -> page {}",
+                key.display(),
+                old_page.unwrap_or(0),
+                new_page.unwrap_or(0)
+            );
+        } else if (old.x, old.y) != (new.x, new.y) {
+            event.changes.push(Change::Moved);
+
+            if let Some(overlay) = &mut overlay {
+                overlay.outline(&new, Mark::Moved);
+                overlay.arrow(center(&old), center(&new), Mark::Moved);
+            }
+
+            println!(
+                "moved {}: {},{} -> {},{}",
+                key.display(),
+                old.x,
+                old.y,
+                new.x,
+                new.y
+            );
+        }
+
+        if let Some((old_image, new_image)) = images.as_ref().filter(|_| options.pixels) {
+            let difference = 1.0 - ssim(old_image, &old, new_image, &new);
+
+            if difference > options.threshold {
+                if let Some(overlay) = &mut overlay {
+                    overlay.tint(&new, Mark::Changed);
+                }
+
+                println!("changed {}: difference {difference:.4}", key.display());
+                changed = true;
+                event.changes.push(Change::Pixels);
+            }
+        }
+
+        if !event.changes.is_empty() {
+            events.push(event);
+        }
+    }
+
+    if let Some(events_output) = &options.events {
+        fs::write(
+            events_output,
+            serde_json::to_string_pretty(&events).unwrap(),
+        )
+        .output_context(events_output)?;
+    }
+
+    if let (Some(overlay), Some(overlay_output)) = (overlay, &options.overlay) {
+        overlay
+            .save(overlay_output)
+            .output_context(overlay_output)?;
+
+        for entry in options.overlay_style.legend() {
+            eprintln!("overlay {entry}");
+        }
+    }
+
+    Ok(changed)
+}
+
+fn center(rectangle: &Rectangle) -> (f32, f32) {
+    (
+        rectangle.x as f32 + rectangle.width as f32 / 2.0,
+        rectangle.y as f32 + rectangle.height as f32 / 2.0,
+    )
+}
+
+// Mean SSIM over non-overlapping windows, averaged across the RGBA channels
+fn ssim(
+    old_image: &DynamicImage,
+    old: &Rectangle,
+    new_image: &DynamicImage,
+    new: &Rectangle,
+) -> f64 {
+    let window_width = WINDOW.min(old.width).max(1);
+    let window_height = WINDOW.min(old.height).max(1);
+
+    let mut total = 0.0;
+    let mut windows = 0;
+
+    for window_y in (0..old.height).step_by(window_height as usize) {
+        for window_x in (0..old.width).step_by(window_width as usize) {
+            let width = window_width.min(old.width - window_x);
+            let height = window_height.min(old.height - window_y);
+
+            for channel in 0..4 {
+                let samples = (0..height)
+                    .flat_map(|y| (0..width).map(move |x| (x, y)))
+                    .map(|(x, y)| {
+                        let a = old_image.get_pixel(old.x + window_x + x, old.y + window_y + y);
+                        let b = new_image.get_pixel(new.x + window_x + x, new.y + window_y + y);
+
+                        (a.0[channel] as f64, b.0[channel] as f64)
+                    })
+                    .collect::<Vec<_>>();
+
+                total += window_ssim(&samples);
+                windows += 1;
+            }
+        }
+    }
+
+    if windows == 0 {
+        return 1.0;
+    }
+
+    total / windows as f64
+}
+
+fn window_ssim(samples: &[(f64, f64)]) -> f64 {
+    let count = samples.len() as f64;
+    let mean_a = samples.iter().map(|(a, _)| a).sum::<f64>() / count;
+    let mean_b = samples.iter().map(|(_, b)| b).sum::<f64>() / count;
+
+    let (variance_a, variance_b, covariance) = samples.iter().fold(
+        (0.0, 0.0, 0.0),
+        |(variance_a, variance_b, covariance), (a, b)| {
+            (
+                variance_a + (a - mean_a).powi(2) / count,
+                variance_b + (b - mean_b).powi(2) / count,
+                covariance + (a - mean_a) * (b - mean_b) / count,
+            )
+        },
+    );
+
+    ((2.0 * mean_a * mean_b + C1) * (2.0 * covariance + C2))
+        / ((mean_a.powi(2) + mean_b.powi(2) + C1) * (variance_a + variance_b + C2))
+}
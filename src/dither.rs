@@ -0,0 +1,59 @@
+use clap::ValueEnum;
+use image::{DynamicImage, RgbaImage};
+use serde::Serialize;
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DitherPattern {
+    Bayer2,
+    Bayer4,
+    Bayer8,
+    InterleavedGradient,
+}
+
+impl DitherPattern {
+    // Thresholds in (0, 1), indexed from the sprite's own origin so the pattern moves with it
+    fn threshold(self, x: u32, y: u32) -> f32 {
+        match self {
+            DitherPattern::Bayer2 => bayer(1, x, y),
+            DitherPattern::Bayer4 => bayer(2, x, y),
+            DitherPattern::Bayer8 => bayer(3, x, y),
+            DitherPattern::InterleavedGradient => {
+                let fract = |value: f32| value - value.floor();
+
+                fract(52.982_918 * fract(0.067_110_56 * x as f32 + 0.005_837_15 * y as f32))
+                    .clamp(0.5 / 256.0, 255.5 / 256.0)
+            }
+        }
+    }
+}
+
+pub fn dither(image: &DynamicImage, pattern: DitherPattern) -> DynamicImage {
+    let mut image: RgbaImage = image.to_rgba8();
+
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        let coverage = pixel.0[3] as f32 / 255.0;
+
+        pixel.0[3] = if coverage > pattern.threshold(x, y) {
+            255
+        } else {
+            0
+        };
+    }
+
+    DynamicImage::ImageRgba8(image)
+}
+
+// Interleaves coordinate bits, lowest first, into the 2^order Bayer matrix index
+fn bayer(order: u32, x: u32, y: u32) -> f32 {
+    let mut index = 0;
+
+    for bit in 0..order {
+        let x_bit = (x >> bit) & 1;
+        let y_bit = (y >> bit) & 1;
+
+        index = index << 2 | (x_bit ^ y_bit) << 1 | y_bit;
+    }
+
+    (index as f32 + 0.5) / (1 << (order * 2)) as f32
+}
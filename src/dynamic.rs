@@ -0,0 +1,464 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+
+use crate::{
+    allocator::{Allocation, AllocationId, Allocator, AllocatorOptions, ScopedAllocator},
+    Algorithm, Fragment, PackError, Vector2,
+};
+
+type OnEvict = Box<dyn FnMut(&Path, &Fragment)>;
+
+struct Entry {
+    fragment: Fragment,
+    // The reserved region it was allocated in, if any
+    region: Option<String>,
+    allocation: Allocation,
+    id: AllocationId,
+    last_used: u64,
+}
+
+/// A single atlas page sprites come and go from while it's in use, the way glyph and thumbnail
+/// caches fill one.
+///
+/// Looking a sprite up with [`DynamicAtlas::get`] marks it used in the current frame and
+/// [`DynamicAtlas::end_frame`] starts the next one. Nothing is evicted unless asked for:
+/// [`DynamicAtlas::evict_after`] reclaims sprites that went unused for a number of frames, and
+/// [`DynamicAtlas::evict_least_recently_used`] makes room for an insert that doesn't fit. The
+/// [`DynamicAtlas::on_evict`] callback hears about both, sprites removed with
+/// [`DynamicAtlas::remove`] aren't reported.
+///
+/// [`DynamicAtlas::reserve`] sets a named area aside that only [`DynamicAtlas::insert_into`]
+/// allocates from, so a system managing its own sprites can't collide with the rest of the
+/// page. Evicting from a region frees space in that region only.
+///
+/// ```no_run
+/// let mut glyphs = atlas::dynamic::DynamicAtlas::new(512, 512)
+///     .evict_after(120)
+///     .evict_least_recently_used()
+///     .on_evict(|key, _| println!("{} was evicted", key.display()));
+///
+/// let glyph = image::open("glyphs/a.png")?;
+///
+/// if glyphs.get("a").is_none() {
+///     glyphs.insert("a", &glyph)?;
+/// }
+///
+/// glyphs.end_frame();
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct DynamicAtlas {
+    algorithm: Algorithm,
+    options: AllocatorOptions,
+    allocator: Allocator,
+    regions: HashMap<String, ScopedAllocator>,
+    image: RgbaImage,
+    entries: HashMap<PathBuf, Entry>,
+    frame: u64,
+    evict_after: Option<u64>,
+    evict_least_recently_used: bool,
+    on_evict: Option<OnEvict>,
+}
+
+impl DynamicAtlas {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self::with_options(
+            width,
+            height,
+            Algorithm::Etagere,
+            AllocatorOptions::default(),
+        )
+    }
+
+    pub fn with_options(
+        width: u32,
+        height: u32,
+        algorithm: Algorithm,
+        options: AllocatorOptions,
+    ) -> Self {
+        Self {
+            algorithm,
+            options,
+            allocator: Allocator::with_options(algorithm, width, height, options),
+            regions: HashMap::new(),
+            image: RgbaImage::new(width, height),
+            entries: HashMap::new(),
+            frame: 0,
+            evict_after: None,
+            evict_least_recently_used: false,
+            on_evict: None,
+        }
+    }
+
+    /// Sprites not used in the last `frames` frames are evicted at the end of a frame, with 1
+    /// only what was used in the frame being ended is kept.
+    pub fn evict_after(mut self, frames: u64) -> Self {
+        assert!(frames > 0, "sprites have to be kept for at least one frame");
+
+        self.evict_after = Some(frames);
+        self
+    }
+
+    /// Inserts that don't fit evict the least recently used sprites until they do. Sprites used
+    /// in the current frame are never evicted, they may still be drawn.
+    pub fn evict_least_recently_used(mut self) -> Self {
+        self.evict_least_recently_used = true;
+        self
+    }
+
+    /// Called with the key and fragment of every evicted sprite, after its space was freed.
+    pub fn on_evict(mut self, callback: impl FnMut(&Path, &Fragment) + 'static) -> Self {
+        self.on_evict = Some(Box::new(callback));
+        self
+    }
+
+    /// Sets a `width` by `height` area of the page aside as the region `name`, allocated with the
+    /// atlas's own algorithm. `None` when the name is taken or the area doesn't fit.
+    pub fn reserve(
+        &mut self,
+        name: impl Into<String>,
+        width: u32,
+        height: u32,
+    ) -> Option<Allocation> {
+        let name = name.into();
+
+        if self.regions.contains_key(&name) {
+            return None;
+        }
+
+        let region =
+            self.allocator
+                .reserve(name.clone(), self.algorithm, width, height, self.options)?;
+        let allocation = region.region();
+
+        self.regions.insert(name, region);
+
+        Some(allocation)
+    }
+
+    /// The page, evicted sprites are cleared from it.
+    pub fn image(&self) -> &RgbaImage {
+        &self.image
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The sprite's fragment, marking it used in the current frame.
+    pub fn get(&mut self, key: impl AsRef<Path>) -> Option<&Fragment> {
+        let entry = self.entries.get_mut(key.as_ref())?;
+        entry.last_used = self.frame;
+
+        Some(&entry.fragment)
+    }
+
+    /// Packs and draws the sprite outside every reserved region, which counts as used in the
+    /// current frame.
+    pub fn insert(
+        &mut self,
+        key: impl Into<PathBuf>,
+        sprite: &DynamicImage,
+    ) -> Result<&Fragment, PackError> {
+        self.insert_in(None, key.into(), sprite)
+    }
+
+    /// Packs and draws the sprite inside a region from [`DynamicAtlas::reserve`].
+    pub fn insert_into(
+        &mut self,
+        region: &str,
+        key: impl Into<PathBuf>,
+        sprite: &DynamicImage,
+    ) -> Result<&Fragment, PackError> {
+        if !self.regions.contains_key(region) {
+            return Err(PackError::UnknownRegion(region.to_string()));
+        }
+
+        self.insert_in(Some(region), key.into(), sprite)
+    }
+
+    fn insert_in(
+        &mut self,
+        region: Option<&str>,
+        key: PathBuf,
+        sprite: &DynamicImage,
+    ) -> Result<&Fragment, PackError> {
+        if self.entries.contains_key(&key) {
+            return Err(PackError::DuplicateKey(key));
+        }
+
+        let (allocation, id) = loop {
+            let allocated = match region {
+                Some(region) => self
+                    .regions
+                    .get_mut(region)
+                    .unwrap()
+                    .allocate_with_id(sprite.width(), sprite.height()),
+                None => self
+                    .allocator
+                    .allocate_with_id(sprite.width(), sprite.height()),
+            };
+
+            if let Some(allocated) = allocated {
+                break allocated;
+            }
+
+            // Only sprites in the same region give back space this insert can use
+            let least_recently_used = self
+                .entries
+                .iter()
+                .filter(|(_, entry)| {
+                    self.evict_least_recently_used
+                        && entry.last_used < self.frame
+                        && entry.region.as_deref() == region
+                })
+                // Ties go to the smallest key so evictions don't depend on the map's order
+                .min_by(|(a_key, a), (b_key, b)| (a.last_used, a_key).cmp(&(b.last_used, b_key)))
+                .map(|(key, _)| key.clone());
+
+            match least_recently_used {
+                Some(evicted) => self.evict(&evicted),
+                None => {
+                    return Err(PackError::DoesNotFit {
+                        key,
+                        width: sprite.width(),
+                        height: sprite.height(),
+                    })
+                }
+            }
+        };
+
+        sprite.pixels().for_each(|(x, y, pixel)| {
+            self.image
+                .put_pixel(allocation.x as u32 + x, allocation.y as u32 + y, pixel);
+        });
+
+        let entry = Entry {
+            fragment: Fragment {
+                center: allocation.center(sprite.width(), sprite.height()),
+                size: Vector2::new(sprite.width() as f32, sprite.height() as f32),
+                page: None,
+                rotated: false,
+                trim: None,
+            },
+            region: region.map(str::to_string),
+            allocation,
+            id,
+            last_used: self.frame,
+        };
+
+        Ok(&self.entries.entry(key).or_insert(entry).fragment)
+    }
+
+    /// Frees the sprite's space without calling the eviction callback.
+    pub fn remove(&mut self, key: impl AsRef<Path>) -> Option<Fragment> {
+        let entry = self.entries.remove(key.as_ref())?;
+
+        self.free(&entry);
+
+        Some(entry.fragment)
+    }
+
+    /// Ends the current frame, evicting what [`DynamicAtlas::evict_after`] no longer keeps.
+    pub fn end_frame(&mut self) {
+        if let Some(frames) = self.evict_after {
+            let mut expired = self
+                .entries
+                .iter()
+                .filter(|(_, entry)| self.frame - entry.last_used >= frames)
+                .map(|(key, _)| key.clone())
+                .collect::<Vec<_>>();
+
+            expired.sort();
+
+            for key in expired {
+                self.evict(&key);
+            }
+        }
+
+        self.frame += 1;
+    }
+
+    fn evict(&mut self, key: &Path) {
+        let entry = self.entries.remove(key).unwrap();
+
+        self.free(&entry);
+
+        if let Some(on_evict) = &mut self.on_evict {
+            on_evict(key, &entry.fragment);
+        }
+    }
+
+    // Cleared so a smaller sprite drawn there later doesn't sit next to the old one's pixels
+    fn free(&mut self, entry: &Entry) {
+        let Allocation {
+            x,
+            y,
+            width,
+            height,
+        } = entry.allocation;
+
+        for y in y..y + height {
+            for x in x..x + width {
+                self.image.put_pixel(x as u32, y as u32, Rgba([0, 0, 0, 0]));
+            }
+        }
+
+        match &entry.region {
+            Some(region) => self.regions.get_mut(region).unwrap().deallocate(entry.id),
+            None => self.allocator.deallocate(entry.id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::RefCell,
+        path::{Path, PathBuf},
+        rc::Rc,
+    };
+
+    use image::{DynamicImage, Rgba, RgbaImage};
+
+    use super::DynamicAtlas;
+    use crate::{allocator::AllocatorOptions, Algorithm, Fragment, PackError};
+
+    type Evicted = Rc<RefCell<Vec<PathBuf>>>;
+
+    fn sprite(size: u32) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(size, size, Rgba([255, 0, 0, 255])))
+    }
+
+    fn recorder() -> (Evicted, impl FnMut(&Path, &Fragment)) {
+        let evicted = Rc::new(RefCell::new(Vec::new()));
+        let recorded = evicted.clone();
+
+        (evicted, move |key: &Path, _: &Fragment| {
+            recorded.borrow_mut().push(key.to_path_buf())
+        })
+    }
+
+    #[test]
+    fn unused_sprites_expire_after_their_frames() {
+        let (evicted, callback) = recorder();
+        let mut atlas = DynamicAtlas::new(64, 64).evict_after(2).on_evict(callback);
+
+        atlas.insert("a", &sprite(8)).unwrap();
+        atlas.insert("b", &sprite(8)).unwrap();
+        atlas.end_frame();
+
+        // a stays in use, b was last used in the first frame and is kept for one more
+        atlas.get("a").unwrap();
+        atlas.end_frame();
+
+        assert!(evicted.borrow().is_empty());
+
+        atlas.get("a").unwrap();
+        atlas.end_frame();
+
+        assert_eq!(*evicted.borrow(), [PathBuf::from("b")]);
+        assert!(atlas.get("b").is_none());
+        assert_eq!(atlas.len(), 1);
+
+        atlas.end_frame();
+        atlas.end_frame();
+
+        assert_eq!(*evicted.borrow(), [PathBuf::from("b"), PathBuf::from("a")]);
+        assert!(atlas.is_empty());
+        assert_eq!(atlas.image().get_pixel(0, 0), &Rgba([0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn full_atlases_evict_what_was_used_least_recently() {
+        for algorithm in [
+            Algorithm::Etagere,
+            Algorithm::Guillotiere,
+            Algorithm::MaxRects,
+        ] {
+            let (evicted, callback) = recorder();
+            let mut atlas =
+                DynamicAtlas::with_options(32, 32, algorithm, AllocatorOptions::default())
+                    .evict_least_recently_used()
+                    .on_evict(callback);
+
+            atlas.insert("old", &sprite(32)).unwrap();
+            atlas.end_frame();
+
+            let fragment = atlas.insert("new", &sprite(32)).unwrap().clone();
+
+            assert_eq!(*evicted.borrow(), [PathBuf::from("old")], "{algorithm:?}");
+            assert_eq!((fragment.center.x, fragment.center.y), (16.0, 16.0));
+
+            // Evicting new would pull a sprite out from under the frame that's drawing it
+            assert!(
+                matches!(
+                    atlas.insert("newer", &sprite(32)),
+                    Err(PackError::DoesNotFit { .. })
+                ),
+                "{algorithm:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn regions_evict_and_reuse_their_own_space() {
+        for algorithm in [
+            Algorithm::Etagere,
+            Algorithm::Guillotiere,
+            Algorithm::MaxRects,
+        ] {
+            let (evicted, callback) = recorder();
+            let mut atlas =
+                DynamicAtlas::with_options(64, 64, algorithm, AllocatorOptions::default())
+                    .evict_least_recently_used()
+                    .on_evict(callback);
+
+            let decals = atlas.reserve("decals", 16, 16).unwrap();
+
+            assert!(atlas.reserve("decals", 16, 16).is_none(), "{algorithm:?}");
+
+            atlas.insert_into("decals", "old", &sprite(16)).unwrap();
+            atlas.insert("static", &sprite(8)).unwrap();
+            atlas.end_frame();
+
+            // The region is full, only its own sprite makes room and the page's stays
+            let fragment = atlas
+                .insert_into("decals", "new", &sprite(16))
+                .unwrap()
+                .clone();
+
+            assert_eq!(*evicted.borrow(), [PathBuf::from("old")], "{algorithm:?}");
+            assert_eq!(
+                (fragment.center.x, fragment.center.y),
+                (decals.x as f32 + 8.0, decals.y as f32 + 8.0),
+                "{algorithm:?}"
+            );
+            assert!(atlas.get("static").is_some());
+            assert!(matches!(
+                atlas.insert_into("hud", "icon", &sprite(4)),
+                Err(PackError::UnknownRegion(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn removed_sprites_free_their_space_quietly() {
+        let (evicted, callback) = recorder();
+        let mut atlas = DynamicAtlas::new(16, 16).on_evict(callback);
+
+        atlas.insert("a", &sprite(16)).unwrap();
+
+        assert!(atlas.insert("b", &sprite(16)).is_err());
+        assert!(atlas.remove("a").is_some());
+        assert!(atlas.insert("b", &sprite(16)).is_ok());
+        assert!(evicted.borrow().is_empty());
+    }
+}
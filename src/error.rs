@@ -0,0 +1,123 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+pub enum Error {
+    // Arguments that parsed but don't go together, reported the way clap reports its own
+    Usage(Box<clap::Error>),
+    Input(Vec<InputError>),
+    Packing(String),
+    Output { path: PathBuf, message: String },
+    // A check the run was asked for failed: budget violations, denied warnings or a diff that
+    // found changes. Everything was still written
+    Check(String),
+    // Ctrl-C stopped the run at one of its checkpoints, before the page images were written
+    Interrupted,
+}
+
+pub struct InputError {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+impl Error {
+    pub fn input(path: impl Into<PathBuf>, message: impl fmt::Display) -> Self {
+        Error::Input(vec![InputError {
+            path: path.into(),
+            message: message.to_string(),
+        }])
+    }
+
+    pub fn output(path: impl Into<PathBuf>, message: impl fmt::Display) -> Self {
+        Error::Output {
+            path: path.into(),
+            message: message.to_string(),
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Check(_) => 1,
+            Error::Usage(error) => error.exit_code(),
+            Error::Input(_) => 3,
+            Error::Packing(_) => 4,
+            Error::Output { .. } => 5,
+            // What a shell reports for a process SIGINT killed
+            Error::Interrupted => 130,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            // clap's rendering starts with its own "error: "
+            Error::Usage(error) => {
+                let rendered = error.render().to_string();
+
+                write!(
+                    formatter,
+                    "{}",
+                    rendered.trim_end().trim_start_matches("error: ")
+                )
+            }
+            Error::Input(errors) => {
+                for (index, error) in errors.iter().enumerate() {
+                    if index > 0 {
+                        write!(formatter, "\nerror: ")?;
+                    }
+
+                    write!(
+                        formatter,
+                        "failed to read {}: {}",
+                        error.path.display(),
+                        error.message
+                    )?;
+                }
+
+                Ok(())
+            }
+            Error::Packing(message) | Error::Check(message) => write!(formatter, "{message}"),
+            Error::Output { path, message } => {
+                write!(formatter, "failed to write {}: {message}", path.display())
+            }
+            Error::Interrupted => write!(formatter, "interrupted"),
+        }
+    }
+}
+
+pub trait Context<T> {
+    fn input_context(self, path: &Path) -> Result<T, Error>;
+    fn output_context(self, path: &Path) -> Result<T, Error>;
+}
+
+impl<T, E: fmt::Display> Context<T> for Result<T, E> {
+    fn input_context(self, path: &Path) -> Result<T, Error> {
+        self.map_err(|error| Error::input(path, error))
+    }
+
+    fn output_context(self, path: &Path) -> Result<T, Error> {
+        self.map_err(|error| Error::output(path, error))
+    }
+}
+
+// Keeps going past broken inputs so a single run reports every one of them
+pub fn collect<T>(results: impl IntoIterator<Item = Result<T, Error>>) -> Result<Vec<T>, Error> {
+    let mut values = Vec::new();
+    let mut errors = Vec::new();
+
+    for result in results {
+        match result {
+            Ok(value) => values.push(value),
+            Err(Error::Input(mut input_errors)) => errors.append(&mut input_errors),
+            Err(error) => return Err(error),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(values)
+    } else {
+        Err(Error::Input(errors))
+    }
+}
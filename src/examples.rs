@@ -0,0 +1,158 @@
+use std::{ffi::OsString, fs, path::Path};
+
+use image::{Rgba, RgbaImage};
+
+use crate::{
+    error::{Context, Error},
+    shuffle::SplitMix64,
+};
+
+const WALK_FRAMES: u32 = 8;
+
+// Writes a sprite set covering the cases the pipeline treats differently: opaque boxes, soft alpha
+// edges, transparent borders to trim, an animation, an exact duplicate and a fully empty image
+pub fn write(directory: &Path, seed: u64) -> Result<(), Error> {
+    let mut random = SplitMix64(seed);
+    let mut range = |low: u32, high: u32| low + (random.next() % (high - low + 1) as u64) as u32;
+
+    let mut sprites = Vec::new();
+
+    for index in 0..12 {
+        let (width, height) = (range(8, 64), range(8, 64));
+        let color = Rgba([
+            range(64, 255) as u8,
+            range(64, 255) as u8,
+            range(64, 255) as u8,
+            255,
+        ]);
+
+        sprites.push((
+            format!("props/crate_{index}.png"),
+            crate_sprite(width, height, color),
+        ));
+    }
+
+    for index in 0..6 {
+        let radius = range(4, 24);
+        let margin = range(0, 8);
+        let color = Rgba([
+            range(64, 255) as u8,
+            range(64, 255) as u8,
+            range(64, 255) as u8,
+            255,
+        ]);
+
+        sprites.push((format!("props/orb_{index}.png"), orb(radius, margin, color)));
+    }
+
+    // Byte for byte the same as the first crate, so --dedupe has something to fold
+    sprites.push(("props/crate_copy.png".to_string(), sprites[0].1.clone()));
+
+    for frame in 0..WALK_FRAMES {
+        sprites.push((
+            format!("characters/hero/walk_{frame}.png"),
+            walk_frame(frame),
+        ));
+    }
+
+    sprites.push((
+        "ui/panel.png".to_string(),
+        RgbaImage::from_fn(96, 48, |x, y| {
+            if x == 0 || y == 0 || x == 95 || y == 47 {
+                Rgba([240, 240, 240, 255])
+            } else {
+                Rgba([32, 48, 96, 160])
+            }
+        }),
+    ));
+    sprites.push(("ui/empty.png".to_string(), RgbaImage::new(16, 16)));
+
+    for (name, image) in sprites {
+        let path = directory.join("sprites").join(name);
+
+        fs::create_dir_all(path.parent().unwrap()).output_context(&path)?;
+        image.save(&path).output_context(&path)?;
+    }
+
+    Ok(())
+}
+
+// The generate invocation run against the sprites, printed so it can be copied as a starting point
+pub fn pipeline(directory: &Path) -> Vec<OsString> {
+    let path = |name: &str| directory.join(name).into_os_string();
+
+    vec![
+        "atlas".into(),
+        "generate".into(),
+        "--files".into(),
+        path("sprites"),
+        "--recursive".into(),
+        "--trim".into(),
+        "--dedupe".into(),
+        "--auto-size".into(),
+        "--padding".into(),
+        "1".into(),
+        "--extrude".into(),
+        "1".into(),
+        "--atlas-output".into(),
+        path("atlas.png"),
+        "--metadata-output".into(),
+        path("atlas.json"),
+        "--layout-svg".into(),
+        path("layout.svg"),
+        "--contact-sheet".into(),
+        path("contact_sheet.html"),
+        "--gpu-report".into(),
+        path("gpu.json"),
+    ]
+}
+
+fn crate_sprite(width: u32, height: u32, color: Rgba<u8>) -> RgbaImage {
+    RgbaImage::from_fn(width, height, |x, y| {
+        if x == 0 || y == 0 || x == width - 1 || y == height - 1 || x == y * width / height {
+            Rgba([color.0[0] / 3, color.0[1] / 3, color.0[2] / 3, 255])
+        } else {
+            color
+        }
+    })
+}
+
+// Antialiased disc, the margin leaves fully transparent rows and columns for --trim
+fn orb(radius: u32, margin: u32, color: Rgba<u8>) -> RgbaImage {
+    let size = (radius + margin) * 2;
+    let center = size as f32 / 2.0;
+
+    RgbaImage::from_fn(size, size, |x, y| {
+        let distance =
+            ((x as f32 + 0.5 - center).powi(2) + (y as f32 + 0.5 - center).powi(2)).sqrt();
+        let coverage = (radius as f32 - distance + 0.5).clamp(0.0, 1.0);
+
+        Rgba([color.0[0], color.0[1], color.0[2], (coverage * 255.0) as u8])
+    })
+}
+
+// Every frame shares the canvas size, the body bobs and the legs swing so each frame differs
+fn walk_frame(frame: u32) -> RgbaImage {
+    let phase = frame as f32 / WALK_FRAMES as f32 * std::f32::consts::TAU;
+    let bob = (1.0 - phase.cos()) as u32;
+    let swing = (phase.sin() * 4.0) as i32;
+
+    let mut image = RgbaImage::new(24, 32);
+
+    for y in 4 + bob..20 + bob {
+        for x in 7..17 {
+            image.put_pixel(x, y, Rgba([200, 80, 60, 255]));
+        }
+    }
+
+    for y in 20 + bob..32 {
+        for (leg, direction) in [(8, 1), (14, -1)] {
+            let x = (leg + swing * direction * (y - 20 - bob) as i32 / 12).clamp(0, 21) as u32;
+
+            image.put_pixel(x, y, Rgba([60, 60, 140, 255]));
+            image.put_pixel(x + 1, y, Rgba([60, 60, 140, 255]));
+        }
+    }
+
+    image
+}
@@ -0,0 +1,290 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
+
+use atlas::Fragment;
+use serde::Serialize;
+
+use crate::{metadata::Rectangle, nine_slice::NineSlice};
+
+pub mod cocos2d;
+pub mod godot;
+pub mod libgdx;
+pub mod phaser;
+pub mod sparrow;
+pub mod texture_packer;
+pub mod unity;
+
+// What engine formats need besides the fragments, pages share the canvas size
+pub struct Sheet {
+    pub width: u32,
+    pub height: u32,
+    pub images: Vec<String>,
+}
+
+pub struct Page<'a> {
+    pub image: &'a str,
+    pub fragments: Vec<(String, &'a PackedFragment)>,
+}
+
+// The frame is the allocator's own rectangle, rebuilding it from the written center would
+// inherit its rounding and whatever --origin and --flip-y did to it. The nine-slice isn't part
+// of the library's Fragment, so it comes along separately for the formats that have borders
+pub struct PackedFragment {
+    pub fragment: Fragment,
+    pub frame: Rectangle,
+    pub nine_slice: Option<NineSlice>,
+}
+
+// Engine formats are written from the same fragments as every other format, read back through
+// the library's Fragment so they all see the same subset of fields
+pub fn fragments(
+    fragments: &HashMap<&PathBuf, &crate::Fragment>,
+) -> BTreeMap<PathBuf, PackedFragment> {
+    fragments
+        .iter()
+        .map(|(&key, &fragment)| {
+            (
+                key.clone(),
+                PackedFragment {
+                    fragment: serde_json::from_value(serde_json::to_value(fragment).unwrap())
+                        .unwrap(),
+                    frame: fragment.frame,
+                    nine_slice: fragment.nine_slice,
+                },
+            )
+        })
+        .collect()
+}
+
+// Which way a format's rotated flag means a sprite is turned on the page. atlas packs rotated
+// sprites turned clockwise, so formats expecting anything else can't be written with rotation
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Rotation {
+    Clockwise,
+    CounterClockwise,
+    Unsupported,
+}
+
+// The page corner a format measures rectangles from, y grows away from it
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Origin {
+    TopLeft,
+    BottomLeft,
+}
+
+// A sprite's page rectangle the way a format wants it: the corner nearest the format's origin,
+// the unrotated size, and whether it's turned the format's way
+#[derive(PartialEq, Eq, Debug)]
+pub struct Placement {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub rotated: bool,
+}
+
+pub fn placement(
+    packed: &PackedFragment,
+    page_height: u32,
+    origin: Origin,
+    rotation: Rotation,
+) -> Placement {
+    let (frame, rotated) = (packed.frame, packed.fragment.rotated);
+
+    // Generate refuses --allow-rotation for these formats before anything is packed
+    assert!(
+        !rotated || rotation == Rotation::Clockwise,
+        "a sprite turned clockwise can't be described as {rotation:?}"
+    );
+
+    let (width, height) = if rotated {
+        (frame.height, frame.width)
+    } else {
+        (frame.width, frame.height)
+    };
+
+    Placement {
+        x: frame.x,
+        y: match origin {
+            Origin::TopLeft => frame.y,
+            Origin::BottomLeft => page_height - frame.y - frame.height,
+        },
+        width,
+        height,
+        rotated,
+    }
+}
+
+// The other way around, what a reader of the format gets back as the rectangle in image space
+pub fn frame(placement: &Placement, page_height: u32, origin: Origin) -> Rectangle {
+    let (width, height) = if placement.rotated {
+        (placement.height, placement.width)
+    } else {
+        (placement.width, placement.height)
+    };
+
+    Rectangle {
+        x: placement.x,
+        y: match origin {
+            Origin::TopLeft => placement.y,
+            Origin::BottomLeft => page_height - placement.y - height,
+        },
+        width,
+        height,
+    }
+}
+
+pub fn pages<'a>(
+    fragments: &'a BTreeMap<PathBuf, PackedFragment>,
+    sheet: &'a Sheet,
+) -> Vec<Page<'a>> {
+    let mut pages = sheet
+        .images
+        .iter()
+        .map(|image| Page {
+            image,
+            fragments: Vec::new(),
+        })
+        .collect::<Vec<_>>();
+
+    for (key, packed) in fragments {
+        if let Some(page) = pages.get_mut(packed.fragment.page.unwrap_or(0) as usize) {
+            page.fragments.push((name(key), packed));
+        }
+    }
+
+    pages
+}
+
+pub fn name(key: &Path) -> String {
+    key.to_string_lossy().replace('\\', "/")
+}
+
+// Where the trimmed pixels sit within the untrimmed sprite, and the untrimmed size
+pub fn source(fragment: &Fragment) -> (Rectangle, (u32, u32)) {
+    let (width, height) = (fragment.size.x as u32, fragment.size.y as u32);
+
+    match &fragment.trim {
+        Some(trim) => (
+            Rectangle {
+                x: trim.offset.x as u32,
+                y: trim.offset.y as u32,
+                width,
+                height,
+            },
+            (trim.source_size.x as u32, trim.source_size.y as u32),
+        ),
+        None => (
+            Rectangle {
+                x: 0,
+                y: 0,
+                width,
+                height,
+            },
+            (width, height),
+        ),
+    }
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+pub fn packed(x: u32, y: u32, width: u32, height: u32, rotated: bool) -> PackedFragment {
+    let (frame_width, frame_height) = if rotated {
+        (height, width)
+    } else {
+        (width, height)
+    };
+
+    PackedFragment {
+        fragment: Fragment {
+            center: atlas::Vector2::new(
+                (x + frame_width / 2) as f32,
+                (y + frame_height / 2) as f32,
+            ),
+            size: atlas::Vector2::new(width as f32, height as f32),
+            page: None,
+            rotated,
+            trim: None,
+        },
+        frame: Rectangle {
+            x,
+            y,
+            width: frame_width,
+            height: frame_height,
+        },
+        nine_slice: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{frame, packed, placement, Origin, Placement, Rotation};
+
+    #[test]
+    fn rotated_sprites_keep_their_unrotated_size() {
+        // An 8x3 sprite turned clockwise covers 3x8 pixels at 2,1 on a 16 pixel high page
+        let sprite = packed(2, 1, 8, 3, true);
+
+        assert_eq!(
+            placement(&sprite, 16, Origin::TopLeft, Rotation::Clockwise),
+            Placement {
+                x: 2,
+                y: 1,
+                width: 8,
+                height: 3,
+                rotated: true,
+            }
+        );
+        assert_eq!(
+            placement(&sprite, 16, Origin::BottomLeft, Rotation::Clockwise).y,
+            7
+        );
+    }
+
+    #[test]
+    fn unrotated_sprites_fit_every_format() {
+        let sprite = packed(2, 1, 8, 3, false);
+
+        for rotation in [
+            Rotation::Clockwise,
+            Rotation::CounterClockwise,
+            Rotation::Unsupported,
+        ] {
+            let placement = placement(&sprite, 16, Origin::BottomLeft, rotation);
+
+            assert_eq!((placement.y, placement.width, placement.height), (12, 8, 3));
+            assert!(!placement.rotated);
+        }
+    }
+
+    #[test]
+    fn frames_read_back_from_either_corner() {
+        for sprite in [packed(2, 1, 8, 3, true), packed(2, 1, 8, 3, false)] {
+            for origin in [Origin::TopLeft, Origin::BottomLeft] {
+                let placement = placement(&sprite, 16, origin, Rotation::Clockwise);
+
+                assert_eq!(frame(&placement, 16, origin), sprite.frame);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn counter_clockwise_formats_refuse_rotated_sprites() {
+        placement(
+            &packed(0, 0, 8, 3, true),
+            16,
+            Origin::TopLeft,
+            Rotation::CounterClockwise,
+        );
+    }
+}
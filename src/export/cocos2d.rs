@@ -0,0 +1,135 @@
+use std::fmt::Write;
+
+use super::{escape, Origin, Page, Rotation, Sheet};
+
+// textureRotated means the sprite is turned 90 degrees clockwise, as TexturePacker writes it
+pub const ROTATION: Rotation = Rotation::Clockwise;
+
+// Frames are measured from the top left of the texture
+pub const ORIGIN: Origin = Origin::TopLeft;
+
+// Property list format 3, the one Cocos2d-x's SpriteFrameCache and TexturePacker agree on
+pub fn write(page: &Page, sheet: &Sheet) -> Vec<u8> {
+    let mut output = String::new();
+
+    writeln!(output, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+    writeln!(
+        output,
+        r#"<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">"#
+    )
+    .unwrap();
+    writeln!(output, r#"<plist version="1.0">"#).unwrap();
+    writeln!(output, "    <dict>").unwrap();
+    writeln!(output, "        <key>frames</key>").unwrap();
+    writeln!(output, "        <dict>").unwrap();
+
+    for (name, packed) in &page.fragments {
+        let (source, (source_width, source_height)) = super::source(&packed.fragment);
+        let placement = super::placement(packed, sheet.height, ORIGIN, ROTATION);
+
+        // Offsets go from the untrimmed center to the trimmed one, with y pointing up
+        let offset_x = source.x as f32 + source.width as f32 / 2.0 - source_width as f32 / 2.0;
+        let offset_y = source_height as f32 / 2.0 - source.y as f32 - source.height as f32 / 2.0;
+
+        writeln!(output, "            <key>{}</key>", escape(name)).unwrap();
+        writeln!(output, "            <dict>").unwrap();
+        writeln!(output, "                <key>aliases</key>").unwrap();
+        writeln!(output, "                <array/>").unwrap();
+        writeln!(output, "                <key>spriteOffset</key>").unwrap();
+        writeln!(
+            output,
+            "                <string>{{{offset_x},{offset_y}}}</string>"
+        )
+        .unwrap();
+        writeln!(output, "                <key>spriteSize</key>").unwrap();
+        writeln!(
+            output,
+            "                <string>{{{},{}}}</string>",
+            source.width, source.height
+        )
+        .unwrap();
+        writeln!(output, "                <key>spriteSourceSize</key>").unwrap();
+        writeln!(
+            output,
+            "                <string>{{{source_width},{source_height}}}</string>"
+        )
+        .unwrap();
+        // Like TexturePacker, the rectangle keeps the unrotated size of rotated sprites
+        writeln!(output, "                <key>textureRect</key>").unwrap();
+        writeln!(
+            output,
+            "                <string>{{{{{},{}}},{{{},{}}}}}</string>",
+            placement.x, placement.y, placement.width, placement.height
+        )
+        .unwrap();
+        writeln!(output, "                <key>textureRotated</key>").unwrap();
+        writeln!(output, "                <{}/>", placement.rotated).unwrap();
+        writeln!(output, "            </dict>").unwrap();
+    }
+
+    writeln!(output, "        </dict>").unwrap();
+    writeln!(output, "        <key>metadata</key>").unwrap();
+    writeln!(output, "        <dict>").unwrap();
+    writeln!(output, "            <key>format</key>").unwrap();
+    writeln!(output, "            <integer>3</integer>").unwrap();
+    writeln!(output, "            <key>pixelFormat</key>").unwrap();
+    writeln!(output, "            <string>RGBA8888</string>").unwrap();
+    writeln!(output, "            <key>premultiplyAlpha</key>").unwrap();
+    writeln!(output, "            <false/>").unwrap();
+    writeln!(output, "            <key>realTextureFileName</key>").unwrap();
+    writeln!(
+        output,
+        "            <string>{}</string>",
+        escape(page.image)
+    )
+    .unwrap();
+    writeln!(output, "            <key>size</key>").unwrap();
+    writeln!(
+        output,
+        "            <string>{{{},{}}}</string>",
+        sheet.width, sheet.height
+    )
+    .unwrap();
+    writeln!(output, "            <key>textureFileName</key>").unwrap();
+    writeln!(
+        output,
+        "            <string>{}</string>",
+        escape(page.image)
+    )
+    .unwrap();
+    writeln!(output, "        </dict>").unwrap();
+    writeln!(output, "    </dict>").unwrap();
+    writeln!(output, "</plist>").unwrap();
+
+    output.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::write;
+    use crate::export::{packed, Page, Sheet};
+
+    #[test]
+    fn rotated_sprites_keep_the_unrotated_rect() {
+        // An 8x3 sprite turned clockwise into the 3x8 pixels at 2,1
+        let sprite = packed(2, 1, 8, 3, true);
+        let sheet = Sheet {
+            width: 16,
+            height: 16,
+            images: vec!["atlas.png".to_string()],
+        };
+        let page = Page {
+            image: "atlas.png",
+            fragments: vec![("sprite.png".to_string(), &sprite)],
+        };
+
+        let output = String::from_utf8(write(&page, &sheet)).unwrap();
+
+        assert!(
+            output.contains(
+                "<key>textureRect</key>\n                <string>{{2,1},{8,3}}</string>\n                <key>textureRotated</key>\n                <true/>\n"
+            ),
+            "{output}"
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use std::{
+    fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{animation, html};
+
+use super::{Origin, Page, Rotation};
+
+// AtlasTexture regions can't be rotated
+pub const ROTATION: Rotation = Rotation::Unsupported;
+
+// Regions are measured from the top left of the atlas texture
+pub const ORIGIN: Origin = Origin::TopLeft;
+
+// One AtlasTexture per fragment, mirroring the key's directories, pages carry the path of their
+// atlas image so every resource can point at it relative to itself
+pub fn write(directory: &Path, pages: &[Page]) -> io::Result<Vec<PathBuf>> {
+    let mut outputs = Vec::new();
+
+    for page in pages {
+        for (name, packed) in &page.fragments {
+            let path = resource_path(directory, name);
+            fs::create_dir_all(path.parent().unwrap_or(directory))?;
+
+            let image = html::relative_href(&path, Path::new(page.image))?;
+            let fragment = &packed.fragment;
+            let (source, (source_width, source_height)) = super::source(fragment);
+            // Regions are measured from the top left corner, so the page height doesn't matter
+            let placement = super::placement(packed, 0, ORIGIN, ROTATION);
+
+            let mut resource = format!(
+                r#"[gd_resource type="AtlasTexture" load_steps=2 format=3]
+
+[ext_resource type="Texture2D" path="{image}" id="1"]
+
+[resource]
+atlas = ExtResource("1")
+region = Rect2({}, {}, {}, {})
+"#,
+                placement.x, placement.y, placement.width, placement.height
+            );
+
+            // The margin puts back the transparent border --trim removed
+            if fragment.trim.is_some() {
+                resource.push_str(&format!(
+                    "margin = Rect2({}, {}, {}, {})\n",
+                    source.x,
+                    source.y,
+                    source_width - source.width,
+                    source_height - source.height
+                ));
+            }
+
+            fs::write(&path, resource)?;
+            outputs.push(path);
+        }
+    }
+
+    Ok(outputs)
+}
+
+// Keys can be absolute or climb out with `..`, only their plain components end up in the path.
+// Frames of `walk.gif` become `walk#0.tres`, `walk#1.tres`, ... instead of all being `walk.tres`
+fn resource_path(directory: &Path, name: &str) -> PathBuf {
+    let (name, frame) = animation::split_frame_key(name);
+    let mut path = directory.to_path_buf();
+
+    path.extend(
+        Path::new(name)
+            .components()
+            .filter(|component| matches!(component, Component::Normal(_))),
+    );
+    path.set_extension("tres");
+
+    if let Some(frame) = frame {
+        let mut file_name = path.file_stem().unwrap_or_default().to_os_string();
+        file_name.push(format!("#{frame}.tres"));
+        path.set_file_name(file_name);
+    }
+
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use super::{resource_path, write};
+    use crate::export::{packed, Page};
+
+    #[test]
+    fn animation_frames_get_a_resource_each() {
+        let directory = std::env::temp_dir().join(format!("atlas-godot-{}", std::process::id()));
+        let frames = [packed(0, 0, 8, 9, false), packed(8, 0, 8, 9, false)];
+        let image = directory.join("atlas.png");
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(&image, []).unwrap();
+
+        let page = Page {
+            image: image.to_str().unwrap(),
+            fragments: vec![
+                ("hero/walk.gif#0".to_string(), &frames[0]),
+                ("hero/walk.gif#1".to_string(), &frames[1]),
+            ],
+        };
+
+        let outputs = write(&directory, &[page]).unwrap();
+
+        assert_eq!(
+            outputs,
+            [
+                directory.join("hero/walk#0.tres"),
+                directory.join("hero/walk#1.tres")
+            ]
+        );
+        assert!(fs::read_to_string(&outputs[1])
+            .unwrap()
+            .contains("[ext_resource type=\"Texture2D\" path=\"../atlas.png\" id=\"1\"]\n"));
+        assert!(fs::read_to_string(&outputs[1])
+            .unwrap()
+            .contains("region = Rect2(8, 0, 8, 9)"));
+
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(
+            resource_path(Path::new("out"), "hero/idle.png"),
+            Path::new("out/hero/idle.tres")
+        );
+    }
+}
@@ -0,0 +1,108 @@
+use std::fmt::Write;
+
+use crate::animation;
+
+use super::{Origin, Page, Rotation, Sheet};
+
+// libGDX's `rotate: true` turns regions 90 degrees counter-clockwise, the opposite of how atlas
+// packs them
+pub const ROTATION: Rotation = Rotation::CounterClockwise;
+
+// Regions are measured from the top left of the page, y pointing down
+pub const ORIGIN: Origin = Origin::TopLeft;
+
+// Every page gets a header section followed by its regions, pages are separated by blank lines
+pub fn write(pages: &[Page], sheet: &Sheet) -> Vec<u8> {
+    let mut output = String::new();
+
+    for page in pages {
+        writeln!(output).unwrap();
+        writeln!(output, "{}", page.image).unwrap();
+        writeln!(output, "size: {},{}", sheet.width, sheet.height).unwrap();
+        writeln!(output, "format: RGBA8888").unwrap();
+        writeln!(output, "filter: Nearest,Nearest").unwrap();
+        writeln!(output, "repeat: none").unwrap();
+
+        for (name, packed) in &page.fragments {
+            let (source, (source_width, source_height)) = super::source(&packed.fragment);
+            let placement = super::placement(packed, sheet.height, ORIGIN, ROTATION);
+            let (name, index) = indexed(name);
+
+            // libGDX measures the trim offset from the bottom left corner
+            let offset_y = source_height - source.height - source.y;
+
+            writeln!(output, "{name}").unwrap();
+            writeln!(output, "  rotate: {}", placement.rotated).unwrap();
+            writeln!(output, "  xy: {}, {}", placement.x, placement.y).unwrap();
+            writeln!(output, "  size: {}, {}", placement.width, placement.height).unwrap();
+            writeln!(output, "  orig: {source_width}, {source_height}").unwrap();
+            writeln!(output, "  offset: {}, {offset_y}", source.x).unwrap();
+            writeln!(output, "  index: {index}").unwrap();
+        }
+    }
+
+    output.into_bytes()
+}
+
+// `hero/walk_3.png` becomes region `hero/walk` with index 3, like gdx-texturepacker names frames,
+// and so does frame 3 of an animated `hero/walk.gif`
+fn indexed(name: &str) -> (&str, i64) {
+    let (name, frame) = animation::split_frame_key(name);
+
+    let name = match name.rsplit_once('.') {
+        Some((stem, extension)) if !extension.contains('/') => stem,
+        _ => name,
+    };
+
+    if let Some(frame) = frame {
+        return (name, frame as i64);
+    }
+
+    match name.rsplit_once('_') {
+        Some((base, index))
+            if !base.is_empty()
+                && !index.is_empty()
+                && index.bytes().all(|byte| byte.is_ascii_digit()) =>
+        {
+            (base, index.parse().unwrap_or(-1))
+        }
+        _ => (name, -1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{indexed, write};
+    use crate::export::{packed, Page, Sheet};
+
+    #[test]
+    fn animation_frames_become_indexed_regions() {
+        assert_eq!(indexed("hero/walk.gif#3"), ("hero/walk", 3));
+        assert_eq!(indexed("hero/walk_3.png"), ("hero/walk", 3));
+        assert_eq!(indexed("hero/idle.png"), ("hero/idle", -1));
+
+        let frames = [packed(0, 0, 8, 9, false), packed(8, 0, 8, 9, false)];
+        let sheet = Sheet {
+            width: 16,
+            height: 16,
+            images: vec!["atlas.png".to_string()],
+        };
+        let page = Page {
+            image: "atlas.png",
+            fragments: vec![
+                ("walk.gif#0".to_string(), &frames[0]),
+                ("walk.gif#1".to_string(), &frames[1]),
+            ],
+        };
+
+        let output = String::from_utf8(write(&[page], &sheet)).unwrap();
+
+        assert_eq!(output.matches("\nwalk\n").count(), 2, "{output}");
+        assert!(
+            output.contains("  xy: 0, 0\n  size: 8, 9\n  orig: 8, 9\n  offset: 0, 0\n  index: 0\n")
+        );
+        assert!(
+            output.contains("  xy: 8, 0\n  size: 8, 9\n  orig: 8, 9\n  offset: 0, 0\n  index: 1\n")
+        );
+    }
+}
@@ -0,0 +1,95 @@
+use serde::Serialize;
+
+use super::{
+    texture_packer::{self, Frame, Size},
+    Origin, Page, Rotation, Sheet,
+};
+
+// Phaser reads TexturePacker's frames and its rotated flag with them
+pub const ROTATION: Rotation = texture_packer::ROTATION;
+pub const ORIGIN: Origin = texture_packer::ORIGIN;
+
+#[derive(Serialize)]
+struct Texture<'a> {
+    image: &'a str,
+    format: &'static str,
+    size: Size,
+    scale: u32,
+    frames: Vec<Frame>,
+}
+
+#[derive(Serialize)]
+struct Meta {
+    app: &'static str,
+    version: &'static str,
+}
+
+#[derive(Serialize)]
+struct Document<'a> {
+    textures: Vec<Texture<'a>>,
+    meta: Meta,
+}
+
+// `load.multiatlas` takes every page in one file, each texture lists its frames in the array layout
+pub fn write(pages: &[Page], sheet: &Sheet) -> Vec<u8> {
+    let document = Document {
+        textures: pages
+            .iter()
+            .map(|page| Texture {
+                image: page.image,
+                format: "RGBA8888",
+                size: Size {
+                    w: sheet.width,
+                    h: sheet.height,
+                },
+                scale: 1,
+                frames: page
+                    .fragments
+                    .iter()
+                    .map(|(name, packed)| texture_packer::frame(Some(name.clone()), packed, sheet))
+                    .collect(),
+            })
+            .collect(),
+        meta: Meta {
+            app: "atlas",
+            version: env!("CARGO_PKG_VERSION"),
+        },
+    };
+
+    serde_json::to_vec_pretty(&document).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::write;
+    use crate::export::{packed, Page, Sheet};
+
+    #[test]
+    fn rotated_frames_are_flagged_in_every_texture() {
+        let sprites = [packed(0, 0, 4, 4, false), packed(4, 0, 8, 3, true)];
+        let sheet = Sheet {
+            width: 16,
+            height: 16,
+            images: vec!["atlas.png".to_string()],
+        };
+        let page = Page {
+            image: "atlas.png",
+            fragments: vec![
+                ("plain.png".to_string(), &sprites[0]),
+                ("turned.png".to_string(), &sprites[1]),
+            ],
+        };
+
+        let document =
+            serde_json::from_slice::<serde_json::Value>(&write(&[page], &sheet)).unwrap();
+        let frames = &document["textures"][0]["frames"];
+
+        assert_eq!(frames[0]["rotated"], false);
+        assert_eq!(frames[1]["filename"], "turned.png");
+        assert_eq!(frames[1]["rotated"], true);
+        assert_eq!(
+            frames[1]["frame"],
+            serde_json::json!({"x": 4, "y": 0, "w": 8, "h": 3})
+        );
+    }
+}
@@ -0,0 +1,58 @@
+use std::fmt::Write;
+
+use super::{escape, Origin, Page, Rotation};
+
+// Starling's `rotated` turns a sub-texture 90 degrees counter-clockwise, the opposite of how
+// atlas packs them
+pub const ROTATION: Rotation = Rotation::CounterClockwise;
+
+// Subtextures are measured from the top left of the texture
+pub const ORIGIN: Origin = Origin::TopLeft;
+
+// Starling's TextureAtlas, frame attributes are only written for trimmed sprites since loaders
+// treat their absence as an untrimmed frame
+pub fn write(page: &Page) -> Vec<u8> {
+    let mut output = String::new();
+
+    writeln!(output, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+    writeln!(
+        output,
+        r#"<TextureAtlas imagePath="{}">"#,
+        escape(page.image)
+    )
+    .unwrap();
+
+    for (name, packed) in &page.fragments {
+        let fragment = &packed.fragment;
+        let (source, (source_width, source_height)) = super::source(fragment);
+        // Rectangles are measured from the top left corner, so the page height doesn't matter
+        let placement = super::placement(packed, 0, ORIGIN, ROTATION);
+
+        write!(
+            output,
+            r#"    <SubTexture name="{}" x="{}" y="{}" width="{}" height="{}""#,
+            escape(name),
+            placement.x,
+            placement.y,
+            placement.width,
+            placement.height
+        )
+        .unwrap();
+
+        if fragment.trim.is_some() {
+            write!(
+                output,
+                r#" frameX="{}" frameY="{}" frameWidth="{source_width}" frameHeight="{source_height}""#,
+                -(source.x as i64),
+                -(source.y as i64)
+            )
+            .unwrap();
+        }
+
+        writeln!(output, "/>").unwrap();
+    }
+
+    writeln!(output, "</TextureAtlas>").unwrap();
+
+    output.into_bytes()
+}
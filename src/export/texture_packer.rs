@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Origin, PackedFragment, Page, Rotation, Sheet};
+
+#[derive(Serialize, Deserialize)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Size {
+    pub w: u32,
+    pub h: u32,
+}
+
+#[derive(Serialize)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+}
+
+// Shared by the hash and array layouts and by Phaser, which reads the same frame objects
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Frame {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+    pub frame: Rect,
+    pub rotated: bool,
+    pub trimmed: bool,
+    pub sprite_source_size: Rect,
+    pub source_size: Size,
+    pub pivot: Point,
+}
+
+#[derive(Serialize)]
+struct Meta<'a> {
+    app: &'static str,
+    version: &'static str,
+    image: &'a str,
+    format: &'static str,
+    size: Size,
+    scale: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Frames {
+    Hash(BTreeMap<String, Frame>),
+    Array(Vec<Frame>),
+}
+
+#[derive(Serialize)]
+struct Document<'a> {
+    frames: Frames,
+    meta: Meta<'a>,
+}
+
+// Rotated sprites cover their frame turned 90 degrees clockwise on the page
+pub const ROTATION: Rotation = Rotation::Clockwise;
+
+// Frames are measured from the top left of the page
+pub const ORIGIN: Origin = Origin::TopLeft;
+
+// TexturePacker's frame is the unrotated size even for rotated sprites
+pub fn frame(name: Option<String>, packed: &PackedFragment, sheet: &Sheet) -> Frame {
+    let fragment = &packed.fragment;
+    let (source, (source_width, source_height)) = super::source(fragment);
+    let placement = super::placement(packed, sheet.height, ORIGIN, ROTATION);
+
+    Frame {
+        filename: name,
+        frame: Rect {
+            x: placement.x,
+            y: placement.y,
+            w: placement.width,
+            h: placement.height,
+        },
+        rotated: placement.rotated,
+        trimmed: fragment.trim.is_some(),
+        sprite_source_size: Rect {
+            x: source.x,
+            y: source.y,
+            w: source.width,
+            h: source.height,
+        },
+        source_size: Size {
+            w: source_width,
+            h: source_height,
+        },
+        pivot: Point { x: 0.5, y: 0.5 },
+    }
+}
+
+pub fn write(page: &Page, sheet: &Sheet, array: bool) -> Vec<u8> {
+    let frames = if array {
+        Frames::Array(
+            page.fragments
+                .iter()
+                .map(|(name, packed)| frame(Some(name.clone()), packed, sheet))
+                .collect(),
+        )
+    } else {
+        Frames::Hash(
+            page.fragments
+                .iter()
+                .map(|(name, packed)| (name.clone(), frame(None, packed, sheet)))
+                .collect(),
+        )
+    };
+
+    let document = Document {
+        frames,
+        meta: Meta {
+            app: "atlas",
+            version: env!("CARGO_PKG_VERSION"),
+            image: page.image,
+            format: "RGBA8888",
+            size: Size {
+                w: sheet.width,
+                h: sheet.height,
+            },
+            scale: "1",
+        },
+    };
+
+    serde_json::to_vec_pretty(&document).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::write;
+    use crate::export::{packed, Page, Sheet};
+
+    #[test]
+    fn rotated_frames_keep_the_unrotated_size() {
+        // An 8x3 sprite turned clockwise into the 3x8 pixels at 2,1
+        let sprite = packed(2, 1, 8, 3, true);
+        let sheet = Sheet {
+            width: 16,
+            height: 16,
+            images: vec!["atlas.png".to_string()],
+        };
+        let page = Page {
+            image: "atlas.png",
+            fragments: vec![("sprite.png".to_string(), &sprite)],
+        };
+
+        let document =
+            serde_json::from_slice::<serde_json::Value>(&write(&page, &sheet, false)).unwrap();
+        let frame = &document["frames"]["sprite.png"];
+
+        assert_eq!(
+            frame["frame"],
+            serde_json::json!({"x": 2, "y": 1, "w": 8, "h": 3})
+        );
+        assert_eq!(frame["rotated"], true);
+        assert_eq!(frame["sourceSize"], serde_json::json!({"w": 8, "h": 3}));
+    }
+}
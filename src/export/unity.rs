@@ -0,0 +1,177 @@
+use std::fmt::Write;
+
+use crate::animation;
+
+use super::{Origin, Page, Rotation, Sheet};
+
+// Unity sprites can't be rotated on their texture
+pub const ROTATION: Rotation = Rotation::Unsupported;
+
+// Rectangles are measured from the bottom left like Unity does
+pub const ORIGIN: Origin = Origin::BottomLeft;
+
+// TexturePacker's Unity importer reads one sprite per line, with y and the pivot measured from the
+// bottom left corner of the texture like Unity does
+pub fn write(page: &Page, sheet: &Sheet) -> Vec<u8> {
+    let mut output = String::new();
+
+    writeln!(output, "#").unwrap();
+    writeln!(output, "# Sprite sheet data for Unity, written by atlas").unwrap();
+    writeln!(output, "#").unwrap();
+    writeln!(output, ":format=40300").unwrap();
+    writeln!(output, ":texture={}", page.image).unwrap();
+    writeln!(output, ":size={}x{}", sheet.width, sheet.height).unwrap();
+    writeln!(output, ":pivotpoints=enabled").unwrap();
+    writeln!(
+        output,
+        ":borders={}",
+        if page
+            .fragments
+            .iter()
+            .any(|(_, packed)| packed.nine_slice.is_some())
+        {
+            "enabled"
+        } else {
+            "disabled"
+        }
+    )
+    .unwrap();
+    writeln!(output).unwrap();
+
+    for (name, packed) in &page.fragments {
+        let (source, (source_width, source_height)) = super::source(&packed.fragment);
+        let placement = super::placement(packed, sheet.height, ORIGIN, ROTATION);
+
+        // The pivot stays at the center of the untrimmed sprite, so it moves with the trim. Empty
+        // sprites have nothing to measure it against and keep the middle
+        let bottom = source_height as f32 - source.height as f32 - source.y as f32;
+        let pivot = |half: f32, offset: f32, length: u32| match length {
+            0 => 0.5,
+            length => (half - offset) / length as f32,
+        };
+        let pivot_x = pivot(source_width as f32 / 2.0, source.x as f32, source.width);
+        let pivot_y = pivot(source_height as f32 / 2.0, bottom, source.height);
+
+        // Borders are left, right, top and bottom insets into the packed sprite
+        let borders = packed.nine_slice.map_or([0; 4], |slice| {
+            [slice.left, slice.right, slice.top, slice.bottom]
+        });
+
+        writeln!(
+            output,
+            "{};{};{};{};{}; {pivot_x};{pivot_y}; {};{};{};{}",
+            sprite_name(name),
+            placement.x,
+            placement.y,
+            placement.width,
+            placement.height,
+            borders[0],
+            borders[1],
+            borders[2],
+            borders[3]
+        )
+        .unwrap();
+    }
+
+    output.into_bytes()
+}
+
+// Unity sprites are named without an extension, `;` would end the field early. Frames of an
+// animated input keep their `#index` after the extension is gone
+fn sprite_name(name: &str) -> String {
+    let (name, frame) = animation::split_frame_key(name);
+
+    let name = match name.rsplit_once('.') {
+        Some((stem, extension)) if !extension.contains('/') => stem,
+        _ => name,
+    };
+
+    match frame {
+        Some(frame) => format!("{name}#{frame}"),
+        None => name.to_string(),
+    }
+    .replace(';', "_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::write;
+    use crate::{
+        export::{packed, Page, Sheet},
+        nine_slice::NineSlice,
+    };
+
+    fn sheet() -> Sheet {
+        Sheet {
+            width: 16,
+            height: 16,
+            images: vec!["atlas.png".to_string()],
+        }
+    }
+
+    #[test]
+    fn animation_frames_keep_their_index() {
+        let frames = [packed(0, 0, 8, 9, false), packed(8, 0, 8, 9, false)];
+        let page = Page {
+            image: "atlas.png",
+            fragments: vec![
+                ("walk.gif#0".to_string(), &frames[0]),
+                ("walk.gif#1".to_string(), &frames[1]),
+            ],
+        };
+
+        let output = String::from_utf8(write(&page, &sheet())).unwrap();
+
+        assert!(output.contains(":borders=disabled\n"), "{output}");
+        assert!(output.contains("\nwalk#0;0;7;8;9;"), "{output}");
+        assert!(output.contains("\nwalk#1;8;7;8;9;"), "{output}");
+    }
+
+    #[test]
+    fn nine_slices_become_borders() {
+        let mut panel = packed(0, 0, 12, 10, false);
+        panel.nine_slice = Some(NineSlice {
+            left: 1,
+            right: 2,
+            top: 3,
+            bottom: 4,
+        });
+        let plain = packed(12, 0, 4, 4, false);
+
+        let page = Page {
+            image: "atlas.png",
+            fragments: vec![
+                ("panel.png".to_string(), &panel),
+                ("plain.png".to_string(), &plain),
+            ],
+        };
+
+        let output = String::from_utf8(write(&page, &sheet())).unwrap();
+
+        assert!(output.contains(":borders=enabled\n"), "{output}");
+        assert!(
+            output.contains("\npanel;0;6;12;10; 0.5;0.5; 1;2;3;4\n"),
+            "{output}"
+        );
+        assert!(
+            output.contains("\nplain;12;12;4;4; 0.5;0.5; 0;0;0;0\n"),
+            "{output}"
+        );
+    }
+
+    #[test]
+    fn empty_sprites_keep_a_centered_pivot() {
+        let empty = packed(0, 0, 0, 0, false);
+        let page = Page {
+            image: "atlas.png",
+            fragments: vec![("empty.png".to_string(), &empty)],
+        };
+
+        let output = String::from_utf8(write(&page, &sheet())).unwrap();
+
+        assert!(
+            output.contains("\nempty;0;16;0;0; 0.5;0.5; 0;0;0;0\n"),
+            "{output}"
+        );
+    }
+}
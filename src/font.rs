@@ -0,0 +1,331 @@
+use std::{
+    collections::{hash_map::Entry, BTreeMap, HashMap},
+    fs,
+    path::Path,
+    str::FromStr,
+};
+
+use atlas::{
+    allocator::{Allocator, AllocatorOptions},
+    Algorithm,
+};
+use image::RgbaImage;
+use serde::Serialize;
+
+use crate::{
+    error::{Context, Error},
+    msdf,
+    sdf::{self, Sdf},
+    truetype::{self, Font},
+};
+
+#[derive(Copy, Clone)]
+pub struct CodepointRange {
+    start: u32,
+    end: u32,
+}
+
+pub struct FontOptions {
+    pub size: f32,
+    pub ranges: Vec<CodepointRange>,
+    pub algorithm: Algorithm,
+    pub allocator: AllocatorOptions,
+    pub width: u32,
+    pub height: u32,
+    pub padding: u32,
+    pub sdf: Option<Sdf>,
+    // The spread of a multi-channel field, which replaces the coverage bitmap like --sdf does
+    pub msdf: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct FontMetadata {
+    size: f32,
+    line_height: f32,
+    ascender: f32,
+    descender: f32,
+    width: u32,
+    height: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sdf_spread: Option<u32>,
+    glyphs: BTreeMap<u32, Glyph>,
+    kerning: Vec<Kerning>,
+}
+
+#[derive(Serialize)]
+struct Glyph {
+    character: char,
+    advance: f32,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    bitmap: Option<GlyphBitmap>,
+}
+
+// Whitespace has no bitmap, only an advance
+#[derive(Serialize)]
+struct GlyphBitmap {
+    bearing: Bearing,
+    rect: Rect,
+    uv: Uv,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    msdf: Option<Msdf>,
+}
+
+// What a shader needs to turn the field into screen pixels, distance_range * font size /
+// em_scale of them cover the distance between channel values 0 and 255
+#[derive(Copy, Clone, Serialize)]
+struct Msdf {
+    distance_range: u32,
+    em_scale: f32,
+}
+
+#[derive(Serialize)]
+struct Bearing {
+    x: i32,
+    y: i32,
+}
+
+#[derive(Copy, Clone, Serialize)]
+struct Rect {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+#[derive(Serialize)]
+struct Uv {
+    u0: f32,
+    v0: f32,
+    u1: f32,
+    v1: f32,
+}
+
+#[derive(Serialize)]
+struct Kerning {
+    left: u32,
+    right: u32,
+    amount: f32,
+}
+
+// Characters the font doesn't have are skipped instead of baking .notdef for each of them
+pub fn generate(
+    font_path: &Path,
+    options: &FontOptions,
+    atlas_output: &Path,
+    metadata_output: &Path,
+) -> Result<(), Error> {
+    let data = fs::read(font_path).input_context(font_path)?;
+    let font = Font::parse(&data).input_context(font_path)?;
+    let scale = options.size / font.units_per_em as f32;
+
+    let mut characters = BTreeMap::new();
+
+    for range in &options.ranges {
+        for codepoint in range.start..=range.end {
+            let Some(character) = char::from_u32(codepoint) else {
+                continue;
+            };
+
+            match font.glyph_index(codepoint) {
+                0 => {}
+                glyph => {
+                    characters.insert(codepoint, (character, glyph));
+                }
+            }
+        }
+    }
+
+    let mut bitmaps = HashMap::new();
+
+    for &(_, glyph) in characters.values() {
+        if let Entry::Vacant(entry) = bitmaps.entry(glyph) {
+            let outline = font
+                .outline(glyph)
+                .map_err(|message| Error::input(font_path, format!("glyph {glyph}: {message}")))?;
+
+            let mut bitmap = match options.msdf {
+                Some(spread) => msdf::rasterize(&outline, scale, spread),
+                None => truetype::rasterize(&outline, scale),
+            };
+
+            // The field grows the bitmap on every side, the bearing follows its corner
+            if let (Some(bitmap), Some(sdf)) = (&mut bitmap, options.sdf) {
+                bitmap.image = sdf::generate(&bitmap.image, sdf.spread);
+                bitmap.left -= sdf.spread as i32;
+                bitmap.top += sdf.spread as i32;
+            }
+
+            entry.insert(bitmap);
+        }
+    }
+
+    // Tallest glyphs first, like --sort area-desc does for sprites, glyph ids keep it stable
+    let mut order = bitmaps
+        .iter()
+        .filter_map(|(&glyph, bitmap)| bitmap.as_ref().map(|bitmap| (glyph, bitmap)))
+        .collect::<Vec<_>>();
+    order.sort_by_key(|(glyph, bitmap)| (std::cmp::Reverse(bitmap.image.height()), *glyph));
+
+    let padding = options.padding;
+    let mut allocator = Allocator::with_options(
+        options.algorithm,
+        options.width + padding,
+        options.height + padding,
+        options.allocator,
+    );
+    let mut image = RgbaImage::new(options.width, options.height);
+    let mut rects = HashMap::new();
+
+    for (glyph, bitmap) in order {
+        let (width, height) = bitmap.image.dimensions();
+        let allocation = allocator
+            .allocate(width + padding, height + padding)
+            .ok_or_else(|| {
+                let (character, _) = characters.values().find(|(_, other)| *other == glyph).unwrap();
+
+                Error::Packing(format!(
+                    "glyph '{character}' ({width}x{height}) does not fit in the remaining atlas space, try a larger --width and --height"
+                ))
+            })?;
+
+        image::imageops::replace(
+            &mut image,
+            &bitmap.image,
+            allocation.x as i64,
+            allocation.y as i64,
+        );
+
+        rects.insert(
+            glyph,
+            Rect {
+                x: allocation.x as u32,
+                y: allocation.y as u32,
+                width,
+                height,
+            },
+        );
+    }
+
+    match options.sdf {
+        Some(sdf) => sdf.save(&image, atlas_output),
+        None => image.save(atlas_output),
+    }
+    .output_context(atlas_output)?;
+
+    let (atlas_width, atlas_height) = (options.width as f32, options.height as f32);
+
+    let mut glyphs = BTreeMap::new();
+    let mut codepoints = HashMap::<u16, Vec<u32>>::new();
+
+    for (&codepoint, &(character, glyph)) in &characters {
+        codepoints.entry(glyph).or_default().push(codepoint);
+
+        // Characters sharing a glyph share its rectangle too
+        let bitmap = bitmaps[&glyph].as_ref().and_then(|bitmap| {
+            rects.get(&glyph).copied().map(|rect: Rect| GlyphBitmap {
+                bearing: Bearing {
+                    x: bitmap.left,
+                    y: bitmap.top,
+                },
+                uv: Uv {
+                    u0: rect.x as f32 / atlas_width,
+                    v0: rect.y as f32 / atlas_height,
+                    u1: (rect.x + rect.width) as f32 / atlas_width,
+                    v1: (rect.y + rect.height) as f32 / atlas_height,
+                },
+                rect,
+                msdf: options.msdf.map(|spread| Msdf {
+                    distance_range: spread * 2,
+                    em_scale: options.size,
+                }),
+            })
+        });
+
+        glyphs.insert(
+            codepoint,
+            Glyph {
+                character,
+                advance: font.advance(glyph) as f32 * scale,
+                bitmap,
+            },
+        );
+    }
+
+    let mut kerning = Vec::new();
+
+    for (left, right, amount) in font.kerning() {
+        let (Some(lefts), Some(rights)) = (codepoints.get(&left), codepoints.get(&right)) else {
+            continue;
+        };
+
+        for &left in lefts {
+            for &right in rights {
+                kerning.push(Kerning {
+                    left,
+                    right,
+                    amount: amount as f32 * scale,
+                });
+            }
+        }
+    }
+
+    kerning.sort_by_key(|pair| (pair.left, pair.right));
+
+    let metadata = FontMetadata {
+        size: options.size,
+        line_height: (font.ascender - font.descender + font.line_gap) as f32 * scale,
+        ascender: font.ascender as f32 * scale,
+        descender: font.descender as f32 * scale,
+        width: options.width,
+        height: options.height,
+        sdf_spread: options.sdf.map(|sdf| sdf.spread),
+        glyphs,
+        kerning,
+    };
+
+    fs::write(
+        metadata_output,
+        serde_json::to_string_pretty(&metadata).unwrap(),
+    )
+    .output_context(metadata_output)
+}
+
+// `65`, `0x41` or `U+0041`, ranges are inclusive like `32-126`
+impl FromStr for CodepointRange {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let codepoint = |text: &str| {
+            let text = text.trim();
+
+            let parsed = if let Some(hex) = text
+                .strip_prefix("0x")
+                .or_else(|| text.strip_prefix("U+"))
+                .or_else(|| text.strip_prefix("u+"))
+            {
+                u32::from_str_radix(hex, 16)
+            } else {
+                text.parse()
+            };
+
+            parsed
+                .ok()
+                .filter(|&codepoint| codepoint <= char::MAX as u32)
+                .ok_or_else(|| format!("'{text}' is not a Unicode codepoint"))
+        };
+
+        let (start, end) = match text.split_once('-') {
+            Some((start, end)) => (codepoint(start)?, codepoint(end)?),
+            None => {
+                let codepoint = codepoint(text)?;
+                (codepoint, codepoint)
+            }
+        };
+
+        if start > end {
+            return Err(format!("range {text} ends before it starts"));
+        }
+
+        Ok(Self { start, end })
+    }
+}
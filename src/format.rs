@@ -0,0 +1,831 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use atlas::{runtime::AtlasMetadata, Fragment};
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::{
+    error::{Context, Error},
+    export::{
+        self, cocos2d, libgdx, phaser, sparrow, texture_packer, unity, Origin, PackedFragment,
+        Rotation, Sheet,
+    },
+    metadata,
+};
+
+use node::Node;
+
+mod node;
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum MetadataFormat {
+    Json,
+    JsonCompact,
+    Toml,
+    Yaml,
+    Ron,
+    Msgpack,
+    Cbor,
+    Binary,
+    TexturePackerHash,
+    TexturePackerArray,
+    Libgdx,
+    Unity,
+    Cocos2d,
+    Sparrow,
+    Phaser,
+}
+
+impl MetadataFormat {
+    // Engine formats describe the atlas images as well, instead of just keying fragments
+    pub fn is_export(self) -> bool {
+        matches!(
+            self,
+            MetadataFormat::TexturePackerHash
+                | MetadataFormat::TexturePackerArray
+                | MetadataFormat::Libgdx
+                | MetadataFormat::Unity
+                | MetadataFormat::Cocos2d
+                | MetadataFormat::Sparrow
+                | MetadataFormat::Phaser
+        )
+    }
+
+    // Which way the format turns rotated sprites, the native formats describe atlas's own way
+    pub fn rotation(self) -> Rotation {
+        match self {
+            MetadataFormat::TexturePackerHash | MetadataFormat::TexturePackerArray => {
+                texture_packer::ROTATION
+            }
+            MetadataFormat::Libgdx => libgdx::ROTATION,
+            MetadataFormat::Unity => unity::ROTATION,
+            MetadataFormat::Cocos2d => cocos2d::ROTATION,
+            MetadataFormat::Sparrow => sparrow::ROTATION,
+            MetadataFormat::Phaser => phaser::ROTATION,
+            _ => Rotation::Clockwise,
+        }
+    }
+
+    // The page corner the format measures rectangles from, the native formats have $meta.origin
+    pub fn origin(self) -> Origin {
+        match self {
+            MetadataFormat::TexturePackerHash | MetadataFormat::TexturePackerArray => {
+                texture_packer::ORIGIN
+            }
+            MetadataFormat::Libgdx => libgdx::ORIGIN,
+            MetadataFormat::Unity => unity::ORIGIN,
+            MetadataFormat::Cocos2d => cocos2d::ORIGIN,
+            MetadataFormat::Sparrow => sparrow::ORIGIN,
+            MetadataFormat::Phaser => phaser::ORIGIN,
+            _ => Origin::TopLeft,
+        }
+    }
+
+    pub fn supports_rotation(self) -> bool {
+        self.rotation() == Rotation::Clockwise
+    }
+
+    fn per_page(self) -> bool {
+        !matches!(self, MetadataFormat::Libgdx | MetadataFormat::Phaser)
+    }
+}
+
+// The native formats stay a map keyed by sprite, the meta section is one more key in it
+#[derive(Serialize)]
+struct Document<'a, M, F> {
+    #[serde(rename = "$meta")]
+    meta: &'a M,
+    #[serde(flatten)]
+    fragments: &'a F,
+}
+
+// Returns every file written, formats that only know a single texture get one file per page.
+// Engine formats have a meta section of their own and the binary one has none, so `meta` only
+// goes into the native formats
+pub fn write(
+    path: &Path,
+    fragments: &HashMap<&PathBuf, &crate::Fragment>,
+    meta: &impl Serialize,
+    format: MetadataFormat,
+    sheet: &Sheet,
+) -> Result<Vec<PathBuf>, Error> {
+    if !format.is_export() {
+        let encoded = match format {
+            MetadataFormat::Binary => encode(fragments, format),
+            _ => encode(&Document { meta, fragments }, format),
+        };
+
+        fs::write(path, encoded.output_context(path)?).output_context(path)?;
+
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    write_export(path, &export::fragments(fragments), format, sheet)
+}
+
+// Engine formats only need the packed frames, so `atlas convert` writes them from metadata it
+// read back instead of from a packing run
+pub fn write_export(
+    path: &Path,
+    fragments: &BTreeMap<PathBuf, PackedFragment>,
+    format: MetadataFormat,
+    sheet: &Sheet,
+) -> Result<Vec<PathBuf>, Error> {
+    let pages = export::pages(fragments, sheet);
+
+    let documents = if format.per_page() {
+        pages
+            .iter()
+            .map(|page| match format {
+                MetadataFormat::TexturePackerHash => texture_packer::write(page, sheet, false),
+                MetadataFormat::TexturePackerArray => texture_packer::write(page, sheet, true),
+                MetadataFormat::Unity => unity::write(page, sheet),
+                MetadataFormat::Cocos2d => cocos2d::write(page, sheet),
+                MetadataFormat::Sparrow => sparrow::write(page),
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>()
+    } else {
+        vec![match format {
+            MetadataFormat::Libgdx => libgdx::write(&pages, sheet),
+            MetadataFormat::Phaser => phaser::write(&pages, sheet),
+            _ => unreachable!(),
+        }]
+    };
+
+    let paths = if documents.len() == 1 {
+        vec![path.to_path_buf()]
+    } else {
+        (0..documents.len())
+            .map(|page| metadata::page_path(path, page as u32))
+            .collect()
+    };
+
+    for (path, document) in paths.iter().zip(documents) {
+        fs::write(path, document).output_context(path)?;
+    }
+
+    Ok(paths)
+}
+
+// JSON is written straight from the typed metadata. The other formats go through a tree that
+// still tells f32s from f64s and absent values from nulls, so a UV stays 0.1 everywhere
+pub fn encode(value: &impl Serialize, format: MetadataFormat) -> Result<Vec<u8>, String> {
+    let node = match format {
+        MetadataFormat::Json => {
+            return serde_json::to_vec_pretty(value).map_err(|error| error.to_string())
+        }
+        MetadataFormat::JsonCompact => {
+            return serde_json::to_vec(value).map_err(|error| error.to_string())
+        }
+        // Only the fields the runtime loader knows about survive, the rest is editor tooling
+        MetadataFormat::Binary => {
+            let value = serde_json::to_value(value).map_err(|error| error.to_string())?;
+            let fragments = serde_json::from_value::<BTreeMap<PathBuf, Fragment>>(value)
+                .map_err(|error| error.to_string())?;
+
+            return Ok(AtlasMetadata::from(fragments).to_bytes());
+        }
+        _ => node::to_node(value).map_err(|error| error.to_string())?,
+    };
+
+    Ok(match format {
+        MetadataFormat::Toml => toml(&node)?.into_bytes(),
+        MetadataFormat::Yaml => {
+            let mut output = String::new();
+            yaml(&node, 0, &mut output);
+
+            output.into_bytes()
+        }
+        MetadataFormat::Ron => {
+            let mut output = String::new();
+            ron(&node, 0, &mut output);
+            output.push('\n');
+
+            output.into_bytes()
+        }
+        MetadataFormat::Msgpack => {
+            let mut output = Vec::new();
+            msgpack(&node, &mut output);
+
+            output
+        }
+        MetadataFormat::Cbor => {
+            let mut output = Vec::new();
+            cbor(&node, &mut output);
+
+            output
+        }
+        _ => unreachable!("engine formats are written by write"),
+    })
+}
+
+// Only RON spells out Some, everywhere else a present option is just its value
+fn unwrap(node: &Node) -> &Node {
+    match node {
+        Node::Some(value) => unwrap(value),
+        node => node,
+    }
+}
+
+fn is_null(node: &Node) -> bool {
+    matches!(unwrap(node), Node::None | Node::Unit)
+}
+
+// Maps and structs alike, an enum variant holding data is a map with its name as the only key
+fn entries(node: &Node) -> Option<Vec<(&str, &Node)>> {
+    match unwrap(node) {
+        Node::Map(entries) | Node::Struct(entries) => Some(
+            entries
+                .iter()
+                .map(|(key, value)| (key.as_str(), value))
+                .collect(),
+        ),
+        Node::Tagged(variant, value) => Some(vec![(variant.as_str(), &**value)]),
+        _ => None,
+    }
+}
+
+fn items(node: &Node) -> Option<&[Node]> {
+    match unwrap(node) {
+        Node::Seq(items) | Node::Tuple(items) => Some(items),
+        _ => None,
+    }
+}
+
+// Debug formatting is the shortest text that reads back as the same float, and keeps the
+// fraction on whole numbers
+fn float(node: &Node, nan: &str, infinity: &str) -> Option<String> {
+    let (text, value) = match *unwrap(node) {
+        Node::F32(value) => (format!("{value:?}"), value as f64),
+        Node::F64(value) => (format!("{value:?}"), value),
+        _ => return None,
+    };
+
+    Some(if value.is_nan() {
+        nan.to_string()
+    } else if value.is_infinite() {
+        format!("{}{infinity}", if value < 0.0 { "-" } else { "" })
+    } else {
+        text
+    })
+}
+
+// Booleans and numbers are spelled the same in every text format, apart from non-finite floats
+fn scalar(node: &Node, nan: &str, infinity: &str) -> Option<String> {
+    match unwrap(node) {
+        Node::Bool(value) => Some(value.to_string()),
+        Node::Signed(value) => Some(value.to_string()),
+        Node::Unsigned(value) => Some(value.to_string()),
+        _ => float(node, nan, infinity),
+    }
+}
+
+// JSON escapes are a subset of what TOML basic strings and YAML double quoted strings accept
+fn quoted(text: &str) -> String {
+    serde_json::to_string(text).unwrap()
+}
+
+fn toml(node: &Node) -> Result<String, String> {
+    let mut output = String::new();
+
+    // Metadata is always keyed by sprite, anything else gets a table of its own
+    let Some(entries) = entries(node) else {
+        return Ok(format!("items = {}\n", toml_inline(node)?));
+    };
+
+    // Plain values have to come before the first table header
+    for (key, value) in entries
+        .iter()
+        .filter(|(_, value)| !is_null(value) && self::entries(value).is_none())
+    {
+        output.push_str(&format!("{} = {}\n", toml_key(key), toml_inline(value)?));
+    }
+
+    for (key, value) in &entries {
+        let Some(table) = self::entries(value) else {
+            continue;
+        };
+
+        output.push_str(&format!("\n[{}]\n", toml_key(key)));
+
+        for (field, value) in table.into_iter().filter(|(_, value)| !is_null(value)) {
+            output.push_str(&format!("{} = {}\n", toml_key(field), toml_inline(value)?));
+        }
+    }
+
+    Ok(output)
+}
+
+fn toml_key(key: &str) -> String {
+    let bare = !key.is_empty()
+        && key
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'-');
+
+    if bare {
+        key.to_string()
+    } else {
+        quoted(key)
+    }
+}
+
+// TOML has no null, absent values are simply left out of tables. Arrays can't leave one out
+// without shifting everything after it, so a null in one can't be written at all
+fn toml_inline(node: &Node) -> Result<String, String> {
+    if let Some(entries) = entries(node) {
+        let fields = entries
+            .into_iter()
+            .filter(|(_, value)| !is_null(value))
+            .map(|(key, value)| Ok(format!("{} = {}", toml_key(key), toml_inline(value)?)))
+            .collect::<Result<Vec<_>, String>>()?;
+
+        return Ok(format!("{{ {} }}", fields.join(", ")));
+    }
+
+    if let Some(items) = items(node) {
+        let items = items
+            .iter()
+            .map(toml_inline)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        return Ok(format!("[{}]", items.join(", ")));
+    }
+
+    match unwrap(node) {
+        Node::None | Node::Unit => {
+            Err("TOML has no null to write an absent value with".to_string())
+        }
+        Node::String(text) | Node::Variant(text) => Ok(quoted(text)),
+        node => Ok(scalar(node, "nan", "inf").unwrap()),
+    }
+}
+
+fn yaml(node: &Node, indent: usize, output: &mut String) {
+    let padding = " ".repeat(indent);
+
+    if let Some(entries) = entries(node).filter(|entries| !entries.is_empty()) {
+        for (key, value) in entries {
+            if yaml_nested(value) {
+                output.push_str(&format!("{padding}{}:\n", quoted(key)));
+                yaml(value, indent + 2, output);
+            } else {
+                output.push_str(&format!(
+                    "{padding}{}: {}\n",
+                    quoted(key),
+                    yaml_scalar(value)
+                ));
+            }
+        }
+    } else if let Some(items) = items(node).filter(|items| !items.is_empty()) {
+        for item in items {
+            // Nested collections start on the dash line, `- x: 1` rather than a bare dash
+            if yaml_nested(item) {
+                let mut nested = String::new();
+                yaml(item, indent + 2, &mut nested);
+
+                output.push_str(&format!("{padding}- {}", &nested[indent + 2..]));
+            } else {
+                output.push_str(&format!("{padding}- {}\n", yaml_scalar(item)));
+            }
+        }
+    } else {
+        output.push_str(&format!("{padding}{}\n", yaml_scalar(node)));
+    }
+}
+
+// Empty collections are written inline as {} and [], like scalars
+fn yaml_nested(node: &Node) -> bool {
+    entries(node).is_some_and(|entries| !entries.is_empty())
+        || items(node).is_some_and(|items| !items.is_empty())
+}
+
+fn yaml_scalar(node: &Node) -> String {
+    if entries(node).is_some() {
+        return "{}".to_string();
+    }
+
+    if items(node).is_some() {
+        return "[]".to_string();
+    }
+
+    match unwrap(node) {
+        Node::None | Node::Unit => "null".to_string(),
+        Node::String(text) | Node::Variant(text) => quoted(text),
+        node => scalar(node, ".nan", ".inf").unwrap(),
+    }
+}
+
+// The outermost map is keyed by sprite and gets a line per sprite, everything inside it is
+// written the way RON's own serializer would, options included
+fn ron(node: &Node, depth: usize, output: &mut String) {
+    match node {
+        Node::Map(entries) if depth == 0 => {
+            output.push_str("{\n");
+
+            for (key, value) in entries {
+                output.push_str(&format!("    {}: ", ron_string(key)));
+                ron(value, depth + 1, output);
+                output.push_str(",\n");
+            }
+
+            output.push('}');
+        }
+        Node::Map(entries) => {
+            output.push('{');
+            ron_fields(entries, depth, true, output);
+            output.push('}');
+        }
+        Node::Struct(entries) => {
+            output.push('(');
+            ron_fields(entries, depth, false, output);
+            output.push(')');
+        }
+        Node::Seq(items) => {
+            output.push('[');
+            ron_items(items, depth, output);
+            output.push(']');
+        }
+        Node::Tuple(items) => {
+            output.push('(');
+            ron_items(items, depth, output);
+            output.push(')');
+        }
+        Node::Variant(variant) => output.push_str(&ron_identifier(variant)),
+        Node::Tagged(variant, value) => {
+            output.push_str(&ron_identifier(variant));
+            output.push('(');
+
+            match &**value {
+                Node::Tuple(items) => ron_items(items, depth, output),
+                Node::Struct(entries) => ron_fields(entries, depth, false, output),
+                value => ron(value, depth + 1, output),
+            }
+
+            output.push(')');
+        }
+        Node::Some(value) => {
+            output.push_str("Some(");
+            ron(value, depth + 1, output);
+            output.push(')');
+        }
+        Node::None => output.push_str("None"),
+        Node::Unit => output.push_str("()"),
+        Node::String(text) => output.push_str(&ron_string(text)),
+        node => output.push_str(&scalar(node, "NaN", "inf").unwrap()),
+    }
+}
+
+// Map keys are quoted strings, struct fields bare identifiers
+fn ron_fields(entries: &[(String, Node)], depth: usize, quote: bool, output: &mut String) {
+    for (index, (key, value)) in entries.iter().enumerate() {
+        if index > 0 {
+            output.push_str(", ");
+        }
+
+        let key = if quote {
+            ron_string(key)
+        } else {
+            ron_identifier(key)
+        };
+
+        output.push_str(&format!("{key}: "));
+        ron(value, depth + 1, output);
+    }
+}
+
+fn ron_items(items: &[Node], depth: usize, output: &mut String) {
+    for (index, item) in items.iter().enumerate() {
+        if index > 0 {
+            output.push_str(", ");
+        }
+
+        ron(item, depth + 1, output);
+    }
+}
+
+// Renamed variants like `area-desc` aren't Rust identifiers, RON reads them as raw ones
+fn ron_identifier(name: &str) -> String {
+    let plain = name
+        .chars()
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && name
+            .chars()
+            .all(|character| character.is_ascii_alphanumeric() || character == '_');
+
+    if plain {
+        name.to_string()
+    } else {
+        format!("r#{name}")
+    }
+}
+
+// RON follows Rust escapes, which spell unicode escapes differently from JSON
+fn ron_string(text: &str) -> String {
+    let mut output = String::from('"');
+
+    for character in text.chars() {
+        match character {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            character if character.is_control() => {
+                output.push_str(&format!("\\u{{{:x}}}", character as u32))
+            }
+            character => output.push(character),
+        }
+    }
+
+    output.push('"');
+    output
+}
+
+fn msgpack(node: &Node, output: &mut Vec<u8>) {
+    if let Some(entries) = entries(node) {
+        msgpack_container(entries.len(), 0x80, 0xde, output);
+
+        for (key, value) in entries {
+            msgpack_string(key, output);
+            msgpack(value, output);
+        }
+
+        return;
+    }
+
+    if let Some(items) = items(node) {
+        msgpack_container(items.len(), 0x90, 0xdc, output);
+        items.iter().for_each(|item| msgpack(item, output));
+
+        return;
+    }
+
+    match *unwrap(node) {
+        Node::None | Node::Unit => output.push(0xc0),
+        Node::Bool(value) => output.push(if value { 0xc3 } else { 0xc2 }),
+        Node::Signed(value) if value >= 0 => msgpack_unsigned(value as u64, output),
+        Node::Signed(value) => msgpack_negative(value, output),
+        Node::Unsigned(value) => msgpack_unsigned(value, output),
+        Node::F32(value) => {
+            output.push(0xca);
+            output.extend(value.to_be_bytes());
+        }
+        Node::F64(value) => {
+            output.push(0xcb);
+            output.extend(value.to_be_bytes());
+        }
+        Node::String(ref text) | Node::Variant(ref text) => msgpack_string(text, output),
+        _ => unreachable!("collections are written above"),
+    }
+}
+
+fn msgpack_string(text: &str, output: &mut Vec<u8>) {
+    let length = text.len();
+
+    if length < 32 {
+        output.push(0xa0 | length as u8);
+    } else if length <= u8::MAX as usize {
+        output.extend([0xd9, length as u8]);
+    } else if length <= u16::MAX as usize {
+        output.push(0xda);
+        output.extend((length as u16).to_be_bytes());
+    } else {
+        output.push(0xdb);
+        output.extend((length as u32).to_be_bytes());
+    }
+
+    output.extend(text.as_bytes());
+}
+
+// Arrays and maps share a layout: a 4 bit fix form, then 16 and 32 bit lengths
+fn msgpack_container(length: usize, fix: u8, marker: u8, output: &mut Vec<u8>) {
+    if length < 16 {
+        output.push(fix | length as u8);
+    } else if length <= u16::MAX as usize {
+        output.push(marker);
+        output.extend((length as u16).to_be_bytes());
+    } else {
+        output.push(marker + 1);
+        output.extend((length as u32).to_be_bytes());
+    }
+}
+
+fn msgpack_unsigned(value: u64, output: &mut Vec<u8>) {
+    if value < 0x80 {
+        output.push(value as u8);
+    } else if value <= u8::MAX as u64 {
+        output.extend([0xcc, value as u8]);
+    } else if value <= u16::MAX as u64 {
+        output.push(0xcd);
+        output.extend((value as u16).to_be_bytes());
+    } else if value <= u32::MAX as u64 {
+        output.push(0xce);
+        output.extend((value as u32).to_be_bytes());
+    } else {
+        output.push(0xcf);
+        output.extend(value.to_be_bytes());
+    }
+}
+
+fn msgpack_negative(value: i64, output: &mut Vec<u8>) {
+    if value >= -32 {
+        output.push(value as i8 as u8);
+    } else if value >= i8::MIN as i64 {
+        output.extend([0xd0, value as i8 as u8]);
+    } else if value >= i16::MIN as i64 {
+        output.push(0xd1);
+        output.extend((value as i16).to_be_bytes());
+    } else if value >= i32::MIN as i64 {
+        output.push(0xd2);
+        output.extend((value as i32).to_be_bytes());
+    } else {
+        output.push(0xd3);
+        output.extend(value.to_be_bytes());
+    }
+}
+
+fn cbor(node: &Node, output: &mut Vec<u8>) {
+    if let Some(entries) = entries(node) {
+        cbor_head(5, entries.len() as u64, output);
+
+        for (key, value) in entries {
+            cbor_head(3, key.len() as u64, output);
+            output.extend(key.as_bytes());
+            cbor(value, output);
+        }
+
+        return;
+    }
+
+    if let Some(items) = items(node) {
+        cbor_head(4, items.len() as u64, output);
+        items.iter().for_each(|item| cbor(item, output));
+
+        return;
+    }
+
+    match *unwrap(node) {
+        Node::None | Node::Unit => output.push(0xf6),
+        Node::Bool(value) => output.push(if value { 0xf5 } else { 0xf4 }),
+        Node::Signed(value) if value >= 0 => cbor_head(0, value as u64, output),
+        Node::Signed(value) => cbor_head(1, (-1 - value) as u64, output),
+        Node::Unsigned(value) => cbor_head(0, value, output),
+        Node::F32(value) => {
+            output.push(0xfa);
+            output.extend(value.to_be_bytes());
+        }
+        Node::F64(value) => {
+            output.push(0xfb);
+            output.extend(value.to_be_bytes());
+        }
+        Node::String(ref text) | Node::Variant(ref text) => {
+            cbor_head(3, text.len() as u64, output);
+            output.extend(text.as_bytes());
+        }
+        _ => unreachable!("collections are written above"),
+    }
+}
+
+fn cbor_head(major: u8, argument: u64, output: &mut Vec<u8>) {
+    let major = major << 5;
+
+    if argument < 24 {
+        output.push(major | argument as u8);
+    } else if argument <= u8::MAX as u64 {
+        output.extend([major | 24, argument as u8]);
+    } else if argument <= u16::MAX as u64 {
+        output.push(major | 25);
+        output.extend((argument as u16).to_be_bytes());
+    } else if argument <= u32::MAX as u64 {
+        output.push(major | 26);
+        output.extend((argument as u32).to_be_bytes());
+    } else {
+        output.push(major | 27);
+        output.extend(argument.to_be_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Serialize;
+
+    use clap::ValueEnum;
+
+    use super::{encode, MetadataFormat};
+
+    #[derive(Serialize)]
+    struct Sprite {
+        u: f32,
+        parent: Option<String>,
+        layer: Option<u32>,
+    }
+
+    fn text(value: &impl Serialize, format: MetadataFormat) -> String {
+        String::from_utf8(encode(value, format).unwrap()).unwrap()
+    }
+
+    fn sprites() -> Vec<(&'static str, Sprite)> {
+        vec![
+            (
+                "b",
+                Sprite {
+                    u: 0.1,
+                    parent: None,
+                    layer: Some(2),
+                },
+            ),
+            (
+                "a",
+                Sprite {
+                    u: 1.0,
+                    parent: Some("sheet".to_string()),
+                    layer: None,
+                },
+            ),
+        ]
+    }
+
+    struct Ordered(Vec<(&'static str, Sprite)>);
+
+    impl Serialize for Ordered {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_map(self.0.iter().map(|(key, value)| (key, value)))
+        }
+    }
+
+    #[test]
+    fn f32_keeps_its_shortest_form() {
+        for format in [
+            MetadataFormat::Json,
+            MetadataFormat::Toml,
+            MetadataFormat::Yaml,
+            MetadataFormat::Ron,
+        ] {
+            let text = text(&Ordered(sprites()), format);
+
+            assert!(text.contains("0.1"), "{text}");
+            assert!(!text.contains("0.10000000149011612"), "{text}");
+            assert!(text.contains("1.0"), "{text}");
+        }
+    }
+
+    #[test]
+    fn fields_keep_declaration_order() {
+        let text = text(&Ordered(sprites()), MetadataFormat::Json);
+
+        assert!(text.find("\"b\"").unwrap() < text.find("\"a\"").unwrap());
+        assert!(text.find("\"u\"").unwrap() < text.find("\"parent\"").unwrap());
+        assert!(text.find("\"parent\"").unwrap() < text.find("\"layer\"").unwrap());
+    }
+
+    #[test]
+    fn ron_wraps_options_in_some() {
+        assert_eq!(
+            text(&Ordered(sprites()), MetadataFormat::Ron),
+            "{\n    \"b\": (u: 0.1, parent: None, layer: Some(2)),\n    \"a\": (u: 1.0, parent: Some(\"sheet\"), layer: None),\n}\n"
+        );
+    }
+
+    #[test]
+    fn toml_leaves_nulls_out() {
+        let text = text(&Ordered(sprites()), MetadataFormat::Toml);
+
+        assert_eq!(
+            text,
+            "\n[b]\nu = 0.1\nlayer = 2\n\n[a]\nu = 1.0\nparent = \"sheet\"\n"
+        );
+    }
+
+    #[test]
+    fn toml_refuses_nulls_in_arrays() {
+        assert!(encode(&vec![Some(1), None], MetadataFormat::Toml).is_err());
+    }
+
+    #[test]
+    fn binary_formats_keep_f32() {
+        let msgpack = encode(&0.1f32, MetadataFormat::Msgpack).unwrap();
+        let cbor = encode(&0.1f32, MetadataFormat::Cbor).unwrap();
+
+        assert_eq!(msgpack[0], 0xca);
+        assert_eq!(f32::from_be_bytes(msgpack[1..].try_into().unwrap()), 0.1);
+        assert_eq!(cbor[0], 0xfa);
+        assert_eq!(f32::from_be_bytes(cbor[1..].try_into().unwrap()), 0.1);
+    }
+
+    #[test]
+    fn only_formats_turning_sprites_clockwise_allow_rotation() {
+        let refusing = MetadataFormat::value_variants()
+            .iter()
+            .filter(|format| !format.supports_rotation())
+            .map(|format| format.to_possible_value().unwrap().get_name().to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(refusing, ["libgdx", "unity", "sparrow"]);
+    }
+}
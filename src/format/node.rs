@@ -0,0 +1,416 @@
+use std::fmt;
+
+use serde::{ser, Serialize};
+
+// What the text and binary writers work from. Unlike a JSON value it keeps f32s apart from f64s,
+// options apart from plain values, and fields in the order they were serialized
+pub enum Node {
+    Unit,
+    None,
+    Some(Box<Node>),
+    Bool(bool),
+    Signed(i64),
+    Unsigned(u64),
+    F32(f32),
+    F64(f64),
+    String(String),
+    Seq(Vec<Node>),
+    // Fixed length arrays, tuples and tuple structs, sequences to everything but RON
+    Tuple(Vec<Node>),
+    Map(Vec<(String, Node)>),
+    Struct(Vec<(String, Node)>),
+    // Unit variants are just their name, the others wrap what they hold
+    Variant(String),
+    Tagged(String, Box<Node>),
+}
+
+#[derive(Debug)]
+pub struct NodeError(String);
+
+impl fmt::Display for NodeError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for NodeError {}
+
+impl ser::Error for NodeError {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        NodeError(message.to_string())
+    }
+}
+
+pub fn to_node(value: &impl Serialize) -> Result<Node, NodeError> {
+    value.serialize(NodeSerializer)
+}
+
+struct NodeSerializer;
+
+pub struct SeqSerializer {
+    items: Vec<Node>,
+    is_tuple: bool,
+    variant: Option<&'static str>,
+}
+
+pub struct MapSerializer {
+    entries: Vec<(String, Node)>,
+    key: Option<String>,
+    is_struct: bool,
+    variant: Option<&'static str>,
+}
+
+impl ser::Serializer for NodeSerializer {
+    type Ok = Node;
+    type Error = NodeError;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = SeqSerializer;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = MapSerializer;
+    type SerializeStructVariant = MapSerializer;
+
+    fn serialize_bool(self, value: bool) -> Result<Node, NodeError> {
+        Ok(Node::Bool(value))
+    }
+
+    fn serialize_i8(self, value: i8) -> Result<Node, NodeError> {
+        Ok(Node::Signed(value as i64))
+    }
+
+    fn serialize_i16(self, value: i16) -> Result<Node, NodeError> {
+        Ok(Node::Signed(value as i64))
+    }
+
+    fn serialize_i32(self, value: i32) -> Result<Node, NodeError> {
+        Ok(Node::Signed(value as i64))
+    }
+
+    fn serialize_i64(self, value: i64) -> Result<Node, NodeError> {
+        Ok(Node::Signed(value))
+    }
+
+    fn serialize_u8(self, value: u8) -> Result<Node, NodeError> {
+        Ok(Node::Unsigned(value as u64))
+    }
+
+    fn serialize_u16(self, value: u16) -> Result<Node, NodeError> {
+        Ok(Node::Unsigned(value as u64))
+    }
+
+    fn serialize_u32(self, value: u32) -> Result<Node, NodeError> {
+        Ok(Node::Unsigned(value as u64))
+    }
+
+    fn serialize_u64(self, value: u64) -> Result<Node, NodeError> {
+        Ok(Node::Unsigned(value))
+    }
+
+    fn serialize_f32(self, value: f32) -> Result<Node, NodeError> {
+        Ok(Node::F32(value))
+    }
+
+    fn serialize_f64(self, value: f64) -> Result<Node, NodeError> {
+        Ok(Node::F64(value))
+    }
+
+    fn serialize_char(self, value: char) -> Result<Node, NodeError> {
+        Ok(Node::String(value.to_string()))
+    }
+
+    fn serialize_str(self, value: &str) -> Result<Node, NodeError> {
+        Ok(Node::String(value.to_string()))
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<Node, NodeError> {
+        Ok(Node::Seq(
+            value
+                .iter()
+                .map(|&byte| Node::Unsigned(byte as u64))
+                .collect(),
+        ))
+    }
+
+    fn serialize_none(self) -> Result<Node, NodeError> {
+        Ok(Node::None)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Node, NodeError> {
+        Ok(Node::Some(Box::new(value.serialize(self)?)))
+    }
+
+    fn serialize_unit(self) -> Result<Node, NodeError> {
+        Ok(Node::Unit)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Node, NodeError> {
+        Ok(Node::Unit)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Node, NodeError> {
+        Ok(Node::Variant(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Node, NodeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Node, NodeError> {
+        Ok(Node::Tagged(
+            variant.to_string(),
+            Box::new(value.serialize(self)?),
+        ))
+    }
+
+    fn serialize_seq(self, length: Option<usize>) -> Result<SeqSerializer, NodeError> {
+        Ok(SeqSerializer {
+            items: Vec::with_capacity(length.unwrap_or(0)),
+            is_tuple: false,
+            variant: None,
+        })
+    }
+
+    fn serialize_tuple(self, length: usize) -> Result<SeqSerializer, NodeError> {
+        Ok(SeqSerializer {
+            items: Vec::with_capacity(length),
+            is_tuple: true,
+            variant: None,
+        })
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        length: usize,
+    ) -> Result<SeqSerializer, NodeError> {
+        self.serialize_tuple(length)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        length: usize,
+    ) -> Result<SeqSerializer, NodeError> {
+        Ok(SeqSerializer {
+            items: Vec::with_capacity(length),
+            is_tuple: true,
+            variant: Some(variant),
+        })
+    }
+
+    fn serialize_map(self, _length: Option<usize>) -> Result<MapSerializer, NodeError> {
+        Ok(MapSerializer {
+            entries: Vec::new(),
+            key: None,
+            is_struct: false,
+            variant: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _length: usize,
+    ) -> Result<MapSerializer, NodeError> {
+        Ok(MapSerializer {
+            entries: Vec::new(),
+            key: None,
+            is_struct: true,
+            variant: None,
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _length: usize,
+    ) -> Result<MapSerializer, NodeError> {
+        Ok(MapSerializer {
+            entries: Vec::new(),
+            key: None,
+            is_struct: true,
+            variant: Some(variant),
+        })
+    }
+}
+
+impl SeqSerializer {
+    fn push<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), NodeError> {
+        self.items.push(value.serialize(NodeSerializer)?);
+
+        Ok(())
+    }
+
+    fn finish(self) -> Node {
+        let node = if self.is_tuple {
+            Node::Tuple(self.items)
+        } else {
+            Node::Seq(self.items)
+        };
+
+        match self.variant {
+            Some(variant) => Node::Tagged(variant.to_string(), Box::new(node)),
+            None => node,
+        }
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Node;
+    type Error = NodeError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), NodeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Node, NodeError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Node;
+    type Error = NodeError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), NodeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Node, NodeError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Node;
+    type Error = NodeError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), NodeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Node, NodeError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleVariant for SeqSerializer {
+    type Ok = Node;
+    type Error = NodeError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), NodeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Node, NodeError> {
+        Ok(self.finish())
+    }
+}
+
+impl MapSerializer {
+    fn finish(self) -> Node {
+        let node = if self.is_struct {
+            Node::Struct(self.entries)
+        } else {
+            Node::Map(self.entries)
+        };
+
+        match self.variant {
+            Some(variant) => Node::Tagged(variant.to_string(), Box::new(node)),
+            None => node,
+        }
+    }
+}
+
+impl ser::SerializeMap for MapSerializer {
+    type Ok = Node;
+    type Error = NodeError;
+
+    // Keys are written as strings by every format, like JSON does with numbers and paths
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), NodeError> {
+        self.key = Some(match key.serialize(NodeSerializer)? {
+            Node::String(key) | Node::Variant(key) => key,
+            Node::Signed(key) => key.to_string(),
+            Node::Unsigned(key) => key.to_string(),
+            Node::Bool(key) => key.to_string(),
+            _ => return Err(NodeError("map keys must be strings or numbers".to_string())),
+        });
+
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), NodeError> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| NodeError("map value without a key".to_string()))?;
+
+        self.entries.push((key, value.serialize(NodeSerializer)?));
+
+        Ok(())
+    }
+
+    fn end(self) -> Result<Node, NodeError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeStruct for MapSerializer {
+    type Ok = Node;
+    type Error = NodeError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), NodeError> {
+        self.entries
+            .push((key.to_string(), value.serialize(NodeSerializer)?));
+
+        Ok(())
+    }
+
+    fn end(self) -> Result<Node, NodeError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeStructVariant for MapSerializer {
+    type Ok = Node;
+    type Error = NodeError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), NodeError> {
+        self.entries
+            .push((key.to_string(), value.serialize(NodeSerializer)?));
+
+        Ok(())
+    }
+
+    fn end(self) -> Result<Node, NodeError> {
+        Ok(self.finish())
+    }
+}
@@ -0,0 +1,124 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+
+// Block formats store a fixed number of bytes per block, regardless of content
+const FORMATS: [(&str, u32, u32, u64); 4] = [
+    ("rgba8", 1, 1, 4),
+    ("bc7", 4, 4, 16),
+    ("astc_6x6", 6, 6, 16),
+    ("etc2_rgba8", 4, 4, 16),
+];
+
+#[derive(Serialize)]
+struct Memory {
+    bytes: u64,
+    with_mipmaps: u64,
+}
+
+#[derive(Serialize)]
+struct PageMemory {
+    page: usize,
+    width: u32,
+    height: u32,
+    formats: BTreeMap<&'static str, Memory>,
+}
+
+#[derive(Serialize)]
+struct OutputFile {
+    path: PathBuf,
+    bytes: u64,
+}
+
+#[derive(Serialize)]
+struct GpuReport {
+    pages: Vec<PageMemory>,
+    totals: BTreeMap<&'static str, Memory>,
+    files: Vec<OutputFile>,
+}
+
+pub fn write_report(
+    path: &Path,
+    width: u32,
+    height: u32,
+    pages: usize,
+    files: &[(PathBuf, u64)],
+) -> io::Result<()> {
+    let formats = || {
+        FORMATS
+            .into_iter()
+            .map(|(name, block_width, block_height, block_bytes)| {
+                (
+                    name,
+                    Memory {
+                        bytes: level_size(width, height, block_width, block_height, block_bytes),
+                        with_mipmaps: (0..mip_levels(width, height))
+                            .map(|level| {
+                                level_size(
+                                    (width >> level).max(1),
+                                    (height >> level).max(1),
+                                    block_width,
+                                    block_height,
+                                    block_bytes,
+                                )
+                            })
+                            .sum(),
+                    },
+                )
+            })
+            .collect::<BTreeMap<_, _>>()
+    };
+
+    // Every page shares the canvas size, so the totals are a straight multiple of one page
+    let totals = formats()
+        .into_iter()
+        .map(|(name, memory)| {
+            (
+                name,
+                Memory {
+                    bytes: memory.bytes * pages as u64,
+                    with_mipmaps: memory.with_mipmaps * pages as u64,
+                },
+            )
+        })
+        .collect();
+
+    let report = GpuReport {
+        pages: (0..pages)
+            .map(|page| PageMemory {
+                page,
+                width,
+                height,
+                formats: formats(),
+            })
+            .collect(),
+        totals,
+        files: files
+            .iter()
+            .map(|(path, bytes)| OutputFile {
+                path: path.clone(),
+                bytes: *bytes,
+            })
+            .collect(),
+    };
+
+    fs::write(path, serde_json::to_string_pretty(&report).unwrap())
+}
+
+fn level_size(
+    width: u32,
+    height: u32,
+    block_width: u32,
+    block_height: u32,
+    block_bytes: u64,
+) -> u64 {
+    width.div_ceil(block_width) as u64 * height.div_ceil(block_height) as u64 * block_bytes
+}
+
+fn mip_levels(width: u32, height: u32) -> u32 {
+    32 - width.max(height).max(1).leading_zeros()
+}
@@ -0,0 +1,162 @@
+use std::{
+    fmt::Write,
+    fs, io,
+    path::{Component, Path},
+};
+
+use crate::Placement;
+
+pub fn write_contact_sheet(
+    path: &Path,
+    page_hrefs: &[String],
+    placements: &[Placement],
+) -> io::Result<()> {
+    let mut html = String::new();
+
+    html.push_str(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Atlas contact sheet</title>
+<style>
+body { font-family: sans-serif; margin: 16px; background: #fafafa; }
+#search { width: 100%; padding: 8px; font-size: 16px; box-sizing: border-box; margin-bottom: 16px; }
+.fragments { display: flex; flex-wrap: wrap; gap: 12px; }
+.fragment { background: #fff; border: 1px solid #ddd; padding: 8px; text-align: center; }
+.sprite { margin: 0 auto 8px; image-rendering: pixelated; background-repeat: no-repeat; background-color: #eee; }
+.key { font-family: monospace; word-break: break-all; max-width: 256px; }
+.size { color: #777; font-size: 12px; }
+</style>
+</head>
+<body>
+<input id="search" type="search" placeholder="Filter fragments by key" autofocus>
+<div class="fragments">
+"#,
+    );
+
+    let page_hrefs = page_hrefs
+        .iter()
+        .map(|href| escape(href))
+        .collect::<Vec<_>>();
+
+    for placement in placements {
+        let key = escape(&placement.key.display().to_string());
+
+        writeln!(
+            html,
+            r#"<div class="fragment" data-key="{key}"><div class="sprite" style="width: {width}px; height: {height}px; background-image: url('{atlas_href}'); background-position: -{x}px -{y}px;"></div><div class="key">{key}</div><div class="size">{width}&times;{height} at {x}, {y}</div></div>"#,
+            width = placement.width,
+            height = placement.height,
+            atlas_href = page_hrefs[placement.page],
+            x = placement.x,
+            y = placement.y,
+        )
+        .unwrap();
+    }
+
+    html.push_str(
+        r#"</div>
+<script>
+document.getElementById("search").addEventListener("input", (event) => {
+  const query = event.target.value.toLowerCase();
+  for (const fragment of document.querySelectorAll(".fragment")) {
+    fragment.style.display = fragment.dataset.key.toLowerCase().includes(query) ? "" : "none";
+  }
+});
+</script>
+</body>
+</html>
+"#,
+    );
+
+    fs::write(path, html)
+}
+
+// Every sprite drawn through its class from the stylesheet, so the page shows exactly what the CSS
+// produces
+pub fn write_preview(
+    path: &Path,
+    stylesheet_href: &str,
+    placements: &[Placement],
+    class_names: &[String],
+) -> io::Result<()> {
+    let mut html = String::new();
+
+    writeln!(
+        html,
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Atlas preview</title>
+<link rel="stylesheet" href="{}">
+<style>
+body {{ font-family: sans-serif; margin: 16px; background: #fafafa; }}
+.fragments {{ display: flex; flex-wrap: wrap; gap: 12px; }}
+.fragment {{ background: #fff; border: 1px solid #ddd; padding: 8px; text-align: center; }}
+.fragment > span {{ image-rendering: pixelated; }}
+.key {{ font-family: monospace; word-break: break-all; max-width: 256px; margin-top: 8px; }}
+.class {{ color: #777; font-family: monospace; font-size: 12px; }}
+</style>
+</head>
+<body>
+<div class="fragments">"#,
+        escape(stylesheet_href)
+    )
+    .unwrap();
+
+    for (placement, class_name) in placements.iter().zip(class_names) {
+        writeln!(
+            html,
+            r#"<div class="fragment"><span class="{class_name}"></span><div class="key">{}</div><div class="class">.{class_name}</div></div>"#,
+            escape(&placement.key.display().to_string())
+        )
+        .unwrap();
+    }
+
+    html.push_str("</div>\n</body>\n</html>\n");
+
+    fs::write(path, html)
+}
+
+// Both paths must exist except for the final component of `from`
+pub fn relative_href(from: &Path, to: &Path) -> io::Result<String> {
+    let from_directory = match from.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    let from_directory = fs::canonicalize(from_directory)?;
+    let to = fs::canonicalize(to)?;
+
+    let from_components = from_directory.components().collect::<Vec<_>>();
+    let to_components = to.components().collect::<Vec<_>>();
+
+    let common = from_components
+        .iter()
+        .zip(&to_components)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut segments = vec!["..".to_string(); from_components.len() - common];
+
+    segments.extend(
+        to_components[common..]
+            .iter()
+            .filter_map(|component| match component {
+                Component::Normal(segment) => Some(segment.to_string_lossy().into_owned()),
+                _ => None,
+            }),
+    );
+
+    Ok(segments.join("/"))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
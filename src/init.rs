@@ -0,0 +1,237 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use atlas::{allocator::AllocatorOptions, Algorithm};
+
+use crate::{
+    autosize,
+    error::{self, Context, Error},
+    inputs,
+};
+
+// The page size init estimates for and the spacing it starts from, the same as the examples
+const MAX_SIZE: u32 = 4096;
+const PADDING: u32 = 1;
+const EXTRUDE: u32 = 1;
+
+// A shell script running generate over the directory, each inferred setting explained in the
+// comment above the command. atlas has no config file, the script is what gets edited
+pub fn init(directory: &Path) -> Result<String, Error> {
+    let files = inputs::expand(&[directory.to_path_buf()], true, &[])?;
+
+    if files.is_empty() {
+        return Err(Error::input(directory, "there are no images to pack"));
+    }
+
+    let dimensions = error::collect(
+        files
+            .iter()
+            .map(|file| image::image_dimensions(file).input_context(file)),
+    )?;
+
+    let mut comments = vec![format!(
+        "{} images found under {}",
+        files.len(),
+        directory.display()
+    )];
+    let mut arguments = vec![
+        "atlas generate".to_string(),
+        format!("--files {}", quote(directory)),
+        "--recursive".to_string(),
+    ];
+
+    // Sprites of one size already tile the page, trimming would only break the grid up
+    if dimensions.len() > 1 && dimensions.iter().all(|&size| size == dimensions[0]) {
+        let (width, height) = dimensions[0];
+
+        comments.push(format!(
+            "all of them are {width}x{height}, so they're packed as a grid without --trim"
+        ));
+    } else {
+        comments.push("--trim drops the transparent borders of differently sized sprites".into());
+        arguments.push("--trim".to_string());
+    }
+
+    let groups = animation_groups(&files);
+
+    if !groups.is_empty() {
+        comments.push(format!(
+            "numbered files look like animations ({}), each gets its own --view and --dedupe \
+             packs repeated frames once",
+            groups.keys().cloned().collect::<Vec<_>>().join(", ")
+        ));
+        arguments.push("--dedupe".to_string());
+
+        for (name, pattern) in &groups {
+            arguments.push(format!("--view {}", quote(format!("{name}={pattern}"))));
+        }
+    }
+
+    arguments.push(format!("--padding {PADDING} --extrude {EXTRUDE}"));
+
+    // The same search --auto-size does, before trimming so it's an upper bound
+    let sizes = dimensions
+        .iter()
+        .map(|&(width, height)| {
+            (
+                width + EXTRUDE * 2 + PADDING,
+                height + EXTRUDE * 2 + PADDING,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    match autosize::smallest(
+        &sizes,
+        Algorithm::Etagere,
+        AllocatorOptions::default(),
+        MAX_SIZE + PADDING,
+        MAX_SIZE + PADDING,
+        None,
+    ) {
+        Some((width, height)) => {
+            let (width, height) = (width - PADDING, height - PADDING);
+
+            comments.push(format!(
+                "everything fits a {width}x{height} page, --auto-size keeps finding the smallest \
+                 one as sprites change"
+            ));
+            arguments.push(format!(
+                "--auto-size --max-width {MAX_SIZE} --max-height {MAX_SIZE}"
+            ));
+        }
+        None => {
+            comments.push(format!(
+                "a single {MAX_SIZE}x{MAX_SIZE} page is too small, more pages are opened as needed"
+            ));
+            arguments.push(format!("--width {MAX_SIZE} --height {MAX_SIZE}"));
+        }
+    }
+
+    arguments.push("--atlas-output atlas.png --metadata-output atlas.json".to_string());
+
+    let mut script = "#!/bin/sh\n".to_string();
+
+    for comment in comments {
+        script.push_str(&format!("# {comment}\n"));
+    }
+
+    script.push_str(&arguments.join(" \\\n    "));
+    script.push('\n');
+
+    Ok(script)
+}
+
+// Files in one directory that only differ by a trailing frame number, `walk_0.png`,
+// `walk_1.png`, ..., named after what comes before it
+fn animation_groups(files: &[PathBuf]) -> BTreeMap<String, String> {
+    let mut groups = BTreeMap::<(String, String), usize>::new();
+
+    for file in files {
+        let Some(stem) = file.file_stem().map(|stem| stem.to_string_lossy()) else {
+            continue;
+        };
+
+        let prefix = stem.trim_end_matches(|character: char| character.is_ascii_digit());
+
+        if prefix.len() == stem.len() {
+            continue;
+        }
+
+        let directory = file
+            .parent()
+            .map(|parent| parent.to_string_lossy().replace('\\', "/"))
+            .unwrap_or_default();
+
+        *groups.entry((directory, prefix.to_string())).or_default() += 1;
+    }
+
+    let mut views = BTreeMap::new();
+
+    for ((directory, prefix), count) in groups {
+        let name = prefix.trim_end_matches(['_', '-', '.', ' ']);
+
+        if count < 2 || name.is_empty() {
+            continue;
+        }
+
+        // Two directories with a `walk` animation would share a view name otherwise
+        let view = if views.contains_key(name) {
+            format!("{}_{name}", directory.replace('/', "_"))
+        } else {
+            name.to_string()
+        };
+
+        views.insert(view, format!("{directory}/{prefix}*"));
+    }
+
+    views
+}
+
+// Single quotes for anything the shell would split or expand
+fn quote(text: impl AsRef<std::ffi::OsStr>) -> String {
+    let text = text.as_ref().to_string_lossy();
+
+    if text
+        .chars()
+        .all(|character| character.is_ascii_alphanumeric() || "-_./=".contains(character))
+    {
+        text.into_owned()
+    } else {
+        format!("'{}'", text.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use image::RgbaImage;
+
+    use super::{animation_groups, init};
+
+    #[test]
+    fn numbered_files_become_views() {
+        let files = [
+            "art/hero/walk_0.png",
+            "art/hero/walk_1.png",
+            "art/hero/idle.png",
+            "art/enemy/walk01.png",
+            "art/enemy/walk02.png",
+            "art/logo2.png",
+        ]
+        .map(PathBuf::from);
+
+        let groups = animation_groups(&files).into_iter().collect::<Vec<_>>();
+
+        assert_eq!(
+            groups,
+            [
+                ("art_hero_walk".to_string(), "art/hero/walk_*".to_string()),
+                ("walk".to_string(), "art/enemy/walk*".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn same_sized_sprites_are_packed_as_a_grid() {
+        let directory = std::env::temp_dir().join(format!("atlas-init-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+
+        for frame in 0..4 {
+            RgbaImage::new(16, 16)
+                .save(directory.join(format!("run_{frame}.png")))
+                .unwrap();
+        }
+
+        let script = init(&directory).ok().unwrap();
+
+        assert!(script.contains("packed as a grid"), "{script}");
+        assert!(!script.contains("    --trim"), "{script}");
+        assert!(script.contains("--view 'run="), "{script}");
+        assert!(script.contains("--auto-size"), "{script}");
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
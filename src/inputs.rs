@@ -0,0 +1,118 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use image::ImageFormat;
+
+use crate::{
+    aseprite,
+    error::{self, Context, Error},
+    pattern,
+};
+
+// Explicit files are kept as given, directory and glob matches are limited to decodable images
+pub fn expand(
+    inputs: &[PathBuf],
+    recursive: bool,
+    exclude: &[String],
+) -> Result<Vec<PathBuf>, Error> {
+    let expanded = error::collect(inputs.iter().map(|input| {
+        let text = input.to_string_lossy().replace('\\', "/");
+
+        if text.contains(['*', '?']) {
+            glob(&text)
+        } else if input.is_dir() {
+            let mut files = Vec::new();
+            walk(input, recursive, &mut files)?;
+
+            Ok(files.into_iter().filter(|file| is_image(file)).collect())
+        } else {
+            Ok(vec![input.clone()])
+        }
+    }))?;
+
+    Ok(expanded
+        .into_iter()
+        .flatten()
+        .filter(|file| {
+            !exclude
+                .iter()
+                .any(|pattern| pattern::matches_key(pattern, file))
+        })
+        .collect())
+}
+
+fn glob(pattern: &str) -> Result<Vec<PathBuf>, Error> {
+    // Walk from the deepest directory that has no wildcards in it
+    let literal = pattern
+        .split('/')
+        .take_while(|segment| !segment.contains(['*', '?']))
+        .collect::<Vec<_>>();
+
+    let base = if literal.is_empty() {
+        PathBuf::from(".")
+    } else {
+        PathBuf::from(literal.join("/"))
+    };
+
+    let mut files = Vec::new();
+
+    if base.is_dir() {
+        walk(&base, true, &mut files)?;
+    }
+
+    // Relative patterns shouldn't pick up a ./ prefix in their keys
+    let matched = files
+        .into_iter()
+        .map(|file| {
+            if literal.is_empty() {
+                file.strip_prefix(".")
+                    .map(Path::to_path_buf)
+                    .unwrap_or(file)
+            } else {
+                file
+            }
+        })
+        .filter(|file| {
+            is_image(file) && pattern::matches(pattern, &file.to_string_lossy().replace('\\', "/"))
+        })
+        .collect::<Vec<_>>();
+
+    if matched.is_empty() {
+        return Err(Error::input(pattern, "pattern did not match any images"));
+    }
+
+    Ok(matched)
+}
+
+// Sorted so packing order, and with it the atlas, doesn't depend on directory iteration order
+fn walk(directory: &Path, recursive: bool, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+    let mut entries = fs::read_dir(directory)
+        .input_context(directory)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .input_context(directory)?;
+
+    entries.sort();
+
+    for entry in entries {
+        if entry.is_dir() {
+            if recursive {
+                walk(&entry, recursive, files)?;
+            }
+        } else {
+            files.push(entry);
+        }
+    }
+
+    Ok(())
+}
+
+fn is_image(path: &Path) -> bool {
+    aseprite::is_aseprite(path)
+        || path
+            .extension()
+            .and_then(ImageFormat::from_extension)
+            .is_some_and(|format| format.can_read())
+}
@@ -0,0 +1,292 @@
+use std::{
+    collections::HashMap,
+    env,
+    io::Write,
+    path::{Component, Path, PathBuf},
+    process::{Command, Stdio},
+    str::FromStr,
+    thread,
+};
+
+use atlas::META_KEY;
+use clap::ValueEnum;
+
+use crate::error::{Error, InputError};
+
+// What --key-command tagged, by key
+pub type Tags = HashMap<PathBuf, Vec<String>>;
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum KeyFormat {
+    Path,
+    Relative,
+    Stem,
+    Template,
+}
+
+#[derive(Clone)]
+pub struct KeyTemplate(Vec<TemplatePart>);
+
+#[derive(Clone)]
+enum TemplatePart {
+    Literal(String),
+    Placeholder(&'static str),
+}
+
+pub struct KeyNaming<'a> {
+    pub format: KeyFormat,
+    pub strip_prefix: Option<&'a Path>,
+    pub template: Option<&'a KeyTemplate>,
+    pub command: Option<&'a Path>,
+}
+
+const PLACEHOLDERS: [&str; 5] = ["path", "dir", "name", "stem", "ext"];
+
+impl KeyNaming<'_> {
+    // Keys always use forward slashes, so metadata written on Windows reads the same everywhere
+    pub fn key(&self, file: &Path) -> PathBuf {
+        let path = match self.format {
+            KeyFormat::Relative => relative(file),
+            _ => file.to_path_buf(),
+        };
+
+        // `./sprites` and `sprites` name the same prefix
+        let stripped = self.strip_prefix.and_then(|prefix| {
+            without_current_dir(&path)
+                .strip_prefix(without_current_dir(prefix))
+                .map(Path::to_path_buf)
+                .ok()
+        });
+        let path = stripped.as_deref().unwrap_or(&path);
+
+        let key = match (self.format, self.template) {
+            (KeyFormat::Stem, _) => lossy(path.file_stem()),
+            (KeyFormat::Template, Some(KeyTemplate(template))) => {
+                let normalized = normalize(path);
+                let (dir, name) = normalized.rsplit_once('/').unwrap_or(("", &normalized));
+
+                let key = template
+                    .iter()
+                    .map(|part| match part {
+                        TemplatePart::Literal(literal) => literal.clone(),
+                        TemplatePart::Placeholder("path") => normalized.clone(),
+                        TemplatePart::Placeholder("dir") => dir.to_string(),
+                        TemplatePart::Placeholder("name") => name.to_string(),
+                        TemplatePart::Placeholder("stem") => lossy(path.file_stem()),
+                        TemplatePart::Placeholder(_) => lossy(path.extension()),
+                    })
+                    .collect::<String>();
+
+                // An empty {dir} shouldn't leave a leading or doubled slash behind
+                key.split('/')
+                    .filter(|part| !part.is_empty() && *part != ".")
+                    .collect::<Vec<_>>()
+                    .join("/")
+            }
+            _ => normalize(path),
+        };
+
+        PathBuf::from(key)
+    }
+
+    // Two different files ending up with the same key would silently overwrite each other. Tags
+    // only come from --key-command and are returned by key, for the inputs that got any
+    pub fn assign(&self, files: &[PathBuf]) -> Result<(Vec<PathBuf>, Tags), Error> {
+        let keys = files.iter().map(|file| self.key(file)).collect::<Vec<_>>();
+        let (keys, tags) = match self.command {
+            Some(command) => run_command(command, files, keys)?,
+            None => (keys, HashMap::new()),
+        };
+
+        let mut owners: HashMap<PathBuf, &PathBuf> = HashMap::new();
+        let mut errors = Vec::new();
+
+        let keys = files
+            .iter()
+            .zip(keys)
+            .map(|(file, key)| {
+                let owner = *owners.entry(key.clone()).or_insert(file);
+
+                if key == Path::new(META_KEY) {
+                    errors.push(InputError {
+                        path: file.clone(),
+                        message: format!(
+                            "key '{META_KEY}' is where the metadata keeps its meta section"
+                        ),
+                    });
+                } else if owner != file {
+                    errors.push(InputError {
+                        path: file.clone(),
+                        message: format!(
+                            "key '{}' is already used by {}",
+                            key.display(),
+                            owner.display()
+                        ),
+                    });
+                }
+
+                key
+            })
+            .collect();
+
+        if errors.is_empty() {
+            Ok((keys, tags))
+        } else {
+            Err(Error::Input(errors))
+        }
+    }
+}
+
+// The command reads a `PATH<tab>KEY` line per input, KEY being what the other options made of
+// it, and answers with a line per input in the same order: the key to use, optionally followed
+// by a tab and comma separated tags. Only the answers have to be valid UTF-8
+fn run_command(
+    command: &Path,
+    files: &[PathBuf],
+    keys: Vec<PathBuf>,
+) -> Result<(Vec<PathBuf>, Tags), Error> {
+    let request = files
+        .iter()
+        .zip(&keys)
+        .map(|(file, key)| format!("{}\t{}\n", file.display(), key.display()))
+        .collect::<String>();
+
+    let mut child = Command::new(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(|error| Error::input(command, format!("couldn't run the key command: {error}")))?;
+
+    // Written from another thread, a command answering as it reads would otherwise block on a
+    // full stdout while atlas blocks on a full stdin
+    let mut stdin = child.stdin.take().unwrap();
+    let writer = thread::spawn(move || stdin.write_all(request.as_bytes()));
+
+    let output = child
+        .wait_with_output()
+        .map_err(|error| Error::input(command, error))?;
+    let _ = writer.join();
+
+    if !output.status.success() {
+        return Err(Error::input(
+            command,
+            format!("the key command failed with {}", output.status),
+        ));
+    }
+
+    let answer = String::from_utf8(output.stdout).map_err(|error| Error::input(command, error))?;
+    let lines = answer.lines().collect::<Vec<_>>();
+
+    if lines.len() != files.len() {
+        return Err(Error::input(
+            command,
+            format!(
+                "the key command answered {} lines for {} inputs",
+                lines.len(),
+                files.len()
+            ),
+        ));
+    }
+
+    let mut tags = HashMap::new();
+    let mut errors = Vec::new();
+
+    let keys = files
+        .iter()
+        .zip(lines)
+        .map(|(file, line)| {
+            let (key, key_tags) = line.split_once('\t').unwrap_or((line, ""));
+            let key = PathBuf::from(key.trim());
+
+            if key.as_os_str().is_empty() {
+                errors.push(InputError {
+                    path: file.clone(),
+                    message: "the key command returned an empty key".to_string(),
+                });
+            }
+
+            let key_tags = key_tags
+                .split(',')
+                .map(str::trim)
+                .filter(|tag| !tag.is_empty())
+                .map(str::to_string)
+                .collect::<Vec<_>>();
+
+            if !key_tags.is_empty() {
+                tags.insert(key.clone(), key_tags);
+            }
+
+            key
+        })
+        .collect();
+
+    if errors.is_empty() {
+        Ok((keys, tags))
+    } else {
+        Err(Error::Input(errors))
+    }
+}
+
+impl FromStr for KeyTemplate {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut parts = Vec::new();
+        let mut rest = value;
+
+        while let Some(start) = rest.find('{') {
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| format!("unclosed placeholder in '{value}'"))?;
+            let name = &rest[start + 1..start + end];
+
+            let placeholder = PLACEHOLDERS
+                .into_iter()
+                .find(|placeholder| *placeholder == name)
+                .ok_or_else(|| {
+                    format!(
+                        "unknown placeholder '{{{name}}}', expected one of {}",
+                        PLACEHOLDERS.map(|name| format!("{{{name}}}")).join(", ")
+                    )
+                })?;
+
+            parts.push(TemplatePart::Literal(rest[..start].to_string()));
+            parts.push(TemplatePart::Placeholder(placeholder));
+
+            rest = &rest[start + end + 1..];
+        }
+
+        parts.push(TemplatePart::Literal(rest.to_string()));
+
+        Ok(Self(parts))
+    }
+}
+
+// Absolute paths inside the current directory lose that prefix, anything else is kept as is
+fn relative(file: &Path) -> PathBuf {
+    let current = env::current_dir().unwrap_or_default();
+
+    without_current_dir(file.strip_prefix(&current).unwrap_or(file))
+}
+
+fn without_current_dir(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| *component != Component::CurDir)
+        .collect()
+}
+
+fn normalize(path: &Path) -> String {
+    path.components()
+        .map(|component| match component {
+            Component::RootDir => String::new(),
+            component => component.as_os_str().to_string_lossy().into_owned(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn lossy(part: Option<&std::ffi::OsStr>) -> String {
+    part.map(|part| part.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
@@ -0,0 +1,95 @@
+use std::{fs, io, path::Path};
+
+use image::RgbaImage;
+
+const IDENTIFIER: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+
+const VK_FORMAT_R8G8B8A8_SRGB: u32 = 43;
+
+const HEADER_LENGTH: usize = 12 + 9 * 4;
+const INDEX_LENGTH: usize = 4 * 4 + 2 * 8;
+const LEVEL_INDEX_LENGTH: usize = 3 * 8;
+
+// All layers must share the same dimensions; they are written as a single
+// uncompressed mip level of an sRGB RGBA8 2D texture array
+pub fn write(path: &Path, layers: &[RgbaImage]) -> io::Result<()> {
+    let (width, height) = layers
+        .first()
+        .map(|layer| layer.dimensions())
+        .unwrap_or((0, 0));
+
+    let dfd = data_format_descriptor();
+    let dfd_offset = HEADER_LENGTH + INDEX_LENGTH + LEVEL_INDEX_LENGTH;
+    let level_offset = dfd_offset + dfd.len();
+    let level_length = layers.iter().map(|layer| layer.len()).sum::<usize>();
+
+    let mut bytes = Vec::with_capacity(level_offset + level_length);
+
+    bytes.extend_from_slice(&IDENTIFIER);
+
+    for value in [
+        VK_FORMAT_R8G8B8A8_SRGB,
+        1,
+        width,
+        height,
+        0,
+        layers.len() as u32,
+        1,
+        1,
+        0,
+    ] {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    for value in [dfd_offset as u32, dfd.len() as u32, 0, 0] {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    // No supercompression global data
+    bytes.extend_from_slice(&[0; 16]);
+
+    for value in [level_offset, level_length, level_length] {
+        bytes.extend_from_slice(&(value as u64).to_le_bytes());
+    }
+
+    bytes.extend_from_slice(&dfd);
+
+    for layer in layers {
+        bytes.extend_from_slice(layer.as_raw());
+    }
+
+    fs::write(path, bytes)
+}
+
+fn data_format_descriptor() -> Vec<u8> {
+    const SAMPLE_COUNT: u32 = 4;
+    const BLOCK_LENGTH: u32 = 24 + 16 * SAMPLE_COUNT;
+
+    let mut words = vec![
+        4 + BLOCK_LENGTH,
+        // Khronos vendor, basic descriptor type
+        0,
+        2 | (BLOCK_LENGTH << 16),
+        // RGBSDA color model, BT.709 primaries, sRGB transfer, straight alpha
+        1 | (1 << 8) | (2 << 16),
+        0,
+        4,
+        0,
+    ];
+
+    for (index, channel) in [0u32, 1, 2, 15].into_iter().enumerate() {
+        // Alpha is always stored linearly, even in sRGB formats
+        let qualifiers = if channel == 15 { 1 << 4 } else { 0 };
+
+        words.extend_from_slice(&[
+            (index as u32 * 8) | (7 << 16) | ((channel | qualifiers) << 24),
+            0,
+            0,
+            255,
+        ]);
+    }
+
+    words.into_iter().flat_map(u32::to_le_bytes).collect()
+}
@@ -0,0 +1,269 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use allocator::AllocatorOptions;
+use image::{DynamicImage, ImageResult, RgbaImage};
+use pack::Packer;
+use serde::{Deserialize, Serialize};
+
+pub mod allocator;
+pub mod dynamic;
+pub mod pack;
+pub mod runtime;
+
+/// Key of the section in JSON metadata that describes how the fragments were written, such as
+/// the UV mode and rounding, rather than a sprite.
+pub const META_KEY: &str = "$meta";
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Algorithm {
+    Etagere,
+    Guillotiere,
+    MaxRects,
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 3] = [
+        Algorithm::Etagere,
+        Algorithm::Guillotiere,
+        Algorithm::MaxRects,
+    ];
+
+    /// The name the command line and reports use for it.
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Etagere => "etagere",
+            Algorithm::Guillotiere => "guillotiere",
+            Algorithm::MaxRects => "max-rects",
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Vector2 {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Vector2 {
+    pub fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+}
+
+/// Where a sprite ended up in the packed image, in pixels.
+///
+/// `page` is the page or array layer for atlases spread over several, rotated sprites are
+/// stored turned 90 degrees clockwise with `size` still the unrotated size.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Fragment {
+    pub center: Vector2,
+    pub size: Vector2,
+    #[serde(default, alias = "layer", skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub rotated: bool,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub trim: Option<Trim>,
+}
+
+/// The size of the sprite before transparent borders were trimmed, and where the trimmed
+/// part sits within it.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Trim {
+    pub source_size: Vector2,
+    pub offset: Vector2,
+}
+
+/// Index of a sprite in its atlas.
+///
+/// Ids follow the sprites' names in sorted order, whether the atlas came from [`AtlasBuilder`] or
+/// from loaded metadata, so the same set of names always gets the same ids.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+pub struct SpriteId(pub u32);
+
+/// A packed atlas image and the fragment of every sprite in it.
+///
+/// `fragments` and `names` are both indexed by [`SpriteId`], so a name only has to be hashed
+/// once to look up its id and every lookup after that is a plain index.
+pub struct Atlas {
+    pub image: RgbaImage,
+    pub fragments: Vec<Fragment>,
+    pub names: Vec<PathBuf>,
+    ids: HashMap<PathBuf, SpriteId>,
+}
+
+impl Atlas {
+    pub fn id(&self, name: impl AsRef<Path>) -> Option<SpriteId> {
+        self.ids.get(name.as_ref()).copied()
+    }
+
+    pub fn fragment(&self, id: SpriteId) -> &Fragment {
+        &self.fragments[id.0 as usize]
+    }
+
+    pub fn name(&self, id: SpriteId) -> &Path {
+        &self.names[id.0 as usize]
+    }
+}
+
+#[derive(Debug)]
+pub enum PackError {
+    DoesNotFit {
+        key: PathBuf,
+        width: u32,
+        height: u32,
+    },
+    DuplicateKey(PathBuf),
+    UnknownRegion(String),
+}
+
+impl fmt::Display for PackError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PackError::DoesNotFit { key, width, height } => write!(
+                formatter,
+                "{} ({width}x{height}) does not fit in the remaining atlas space",
+                key.display()
+            ),
+            PackError::DuplicateKey(key) => {
+                write!(formatter, "{} was added more than once", key.display())
+            }
+            PackError::UnknownRegion(name) => {
+                write!(formatter, "no region named {name} was reserved")
+            }
+        }
+    }
+}
+
+impl Error for PackError {}
+
+/// Packs sprites into a single atlas page with the [`Packer`] `atlas generate` uses.
+///
+/// Sprites are placed exactly as given, one page and no margins. `generate`'s sorting and
+/// trimming aren't applied, and its padding, borders, extrusion, rotation and paging are only
+/// available from [`Packer`] directly, so the same inputs can land in different places than
+/// `generate` puts them.
+///
+/// ```no_run
+/// let atlas = atlas::AtlasBuilder::new(1024, 1024)
+///     .algorithm(atlas::Algorithm::Guillotiere)
+///     .add_path("sprites/player.png")?
+///     .add_image("generated/white", image::DynamicImage::new_rgba8(4, 4))
+///     .build()?;
+///
+/// let player = atlas.id("sprites/player.png").unwrap();
+/// println!("player is centered at {:?}", atlas.fragment(player).center);
+///
+/// atlas.image.save("atlas.png")?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct AtlasBuilder {
+    width: u32,
+    height: u32,
+    algorithm: Algorithm,
+    options: AllocatorOptions,
+    images: Vec<(PathBuf, DynamicImage)>,
+}
+
+impl AtlasBuilder {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            algorithm: Algorithm::Etagere,
+            options: AllocatorOptions::default(),
+            images: Vec::new(),
+        }
+    }
+
+    pub fn algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Tuning for the chosen algorithm, options meant for other algorithms are ignored.
+    pub fn allocator_options(mut self, options: AllocatorOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Decodes the image at `path` and keys its fragment by that path.
+    pub fn add_path(self, path: impl AsRef<Path>) -> ImageResult<Self> {
+        let image = image::open(path.as_ref())?;
+
+        Ok(self.add_image(path.as_ref(), image))
+    }
+
+    pub fn add_image(mut self, key: impl Into<PathBuf>, image: DynamicImage) -> Self {
+        self.images.push((key.into(), image));
+        self
+    }
+
+    /// Sprites are packed in the order they were added, their ids are still in name order.
+    pub fn build(self) -> Result<Atlas, PackError> {
+        let mut packer = Packer::new(self.algorithm, self.width, self.height)
+            .allocator_options(self.options)
+            .max_pages(1);
+        let mut fragments = BTreeMap::new();
+
+        for (key, sprite) in self.images {
+            if fragments.contains_key(&key) {
+                return Err(PackError::DuplicateKey(key));
+            }
+
+            let placement = packer
+                .place(sprite.width(), sprite.height(), ())
+                .ok_or_else(|| PackError::DoesNotFit {
+                    key: key.clone(),
+                    width: sprite.width(),
+                    height: sprite.height(),
+                })?;
+
+            packer.draw(&placement, &sprite, false);
+
+            fragments.insert(
+                key,
+                Fragment {
+                    center: placement.allocation.center(sprite.width(), sprite.height()),
+                    size: Vector2::new(sprite.width() as f32, sprite.height() as f32),
+                    page: None,
+                    rotated: false,
+                    trim: None,
+                },
+            );
+        }
+
+        let (fragments, names, ids) = index(fragments);
+        let image = packer.into_pages().remove(0).image;
+
+        Ok(Atlas {
+            image,
+            fragments,
+            names,
+            ids,
+        })
+    }
+}
+
+// Splits fragments into the id indexed lists, the map's name order is the id order
+fn index(
+    fragments: BTreeMap<PathBuf, Fragment>,
+) -> (Vec<Fragment>, Vec<PathBuf>, HashMap<PathBuf, SpriteId>) {
+    let (names, fragments): (Vec<_>, Vec<_>) = fragments.into_iter().unzip();
+    let ids = names
+        .iter()
+        .enumerate()
+        .map(|(index, name)| (name.clone(), SpriteId(index as u32)))
+        .collect();
+
+    (fragments, names, ids)
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
@@ -0,0 +1,120 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use crate::animation;
+
+// `ui/button_ja.png` is the `ja` variant of `ui/button.png`, frames of an animated variant keep
+// their `#index` after the suffix is gone
+pub fn split<'a>(key: &Path, locales: &'a [String]) -> Option<(PathBuf, &'a str)> {
+    let key = key.to_string_lossy();
+    let (name, frame) = animation::split_frame_key(&key);
+
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !extension.contains('/') => (stem, Some(extension)),
+        _ => (name, None),
+    };
+
+    locales.iter().find_map(|locale| {
+        let logical = stem.strip_suffix(locale.as_str())?.strip_suffix('_')?;
+
+        if logical.is_empty() || logical.ends_with('/') {
+            return None;
+        }
+
+        let mut logical = logical.to_string();
+
+        if let Some(extension) = extension {
+            logical.push('.');
+            logical.push_str(extension);
+        }
+
+        let logical = PathBuf::from(logical);
+
+        Some((
+            match frame {
+                Some(index) => animation::frame_key(&logical, index),
+                None => logical,
+            },
+            locale.as_str(),
+        ))
+    })
+}
+
+// What a runtime loading one locale sees: every shared fragment, with the locale's variants
+// replacing the shared one of the same logical key. Variants of other locales are left out
+pub fn view<'a, T>(
+    fragments: &'a HashMap<PathBuf, T>,
+    locales: &[String],
+    locale: &str,
+) -> HashMap<PathBuf, &'a T> {
+    let mut view = HashMap::new();
+    let mut variants = Vec::new();
+
+    for (key, fragment) in fragments {
+        match split(key, locales) {
+            Some((logical, variant)) if variant == locale => variants.push((logical, fragment)),
+            Some(_) => {}
+            None => {
+                view.insert(key.clone(), fragment);
+            }
+        }
+    }
+
+    view.extend(variants);
+
+    view
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, path::PathBuf};
+
+    use super::{split, view};
+
+    fn locales() -> Vec<String> {
+        vec!["en".to_string(), "ja".to_string()]
+    }
+
+    #[test]
+    fn suffixes_name_the_logical_key() {
+        let locales = locales();
+
+        assert_eq!(
+            split("ui/button_ja.png".as_ref(), &locales),
+            Some((PathBuf::from("ui/button.png"), "ja"))
+        );
+        assert_eq!(
+            split("intro_en.gif#2".as_ref(), &locales),
+            Some((PathBuf::from("intro.gif#2"), "en"))
+        );
+        assert_eq!(split("ui/button.png".as_ref(), &locales), None);
+        assert_eq!(split("ui/ja.png".as_ref(), &locales), None);
+        assert_eq!(split("ui/_ja.png".as_ref(), &locales), None);
+        assert_eq!(split("ui/banja.png".as_ref(), &locales), None);
+    }
+
+    #[test]
+    fn variants_replace_the_shared_fragment() {
+        let fragments = HashMap::from([
+            (PathBuf::from("button.png"), 0),
+            (PathBuf::from("button_ja.png"), 1),
+            (PathBuf::from("title_en.png"), 2),
+            (PathBuf::from("title_ja.png"), 3),
+            (PathBuf::from("logo.png"), 4),
+        ]);
+
+        let japanese = view(&fragments, &locales(), "ja");
+
+        assert_eq!(japanese.len(), 3);
+        assert_eq!(japanese[&PathBuf::from("button.png")], &1);
+        assert_eq!(japanese[&PathBuf::from("title.png")], &3);
+        assert_eq!(japanese[&PathBuf::from("logo.png")], &4);
+
+        let english = view(&fragments, &locales(), "en");
+
+        assert_eq!(english[&PathBuf::from("button.png")], &0);
+        assert_eq!(english[&PathBuf::from("title.png")], &2);
+    }
+}
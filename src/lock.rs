@@ -0,0 +1,93 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    process, thread,
+    time::{Duration, Instant},
+};
+
+use crate::{error::Error, interrupt};
+
+const LOCK_FILE_NAME: &str = ".atlas.lock";
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+pub struct OutputLock {
+    paths: Vec<PathBuf>,
+}
+
+impl OutputLock {
+    pub fn acquire<'a>(
+        outputs: impl IntoIterator<Item = &'a Path>,
+        timeout: Duration,
+    ) -> Result<Self, Error> {
+        let mut directories = outputs
+            .into_iter()
+            .map(|output| match output.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+                _ => PathBuf::from("."),
+            })
+            .collect::<Vec<_>>();
+
+        // Always lock in the same order so overlapping runs can't deadlock on each other
+        directories.sort();
+        directories.dedup();
+
+        let mut lock = Self { paths: Vec::new() };
+
+        for directory in directories {
+            let path = directory.join(LOCK_FILE_NAME);
+
+            wait_for(&path, timeout)?;
+
+            lock.paths.push(path);
+        }
+
+        Ok(lock)
+    }
+}
+
+impl Drop for OutputLock {
+    fn drop(&mut self) {
+        for path in &self.paths {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+fn wait_for(path: &Path, timeout: Duration) -> Result<(), Error> {
+    let start = Instant::now();
+    let mut announced = false;
+
+    loop {
+        match OpenOptions::new().write(true).create_new(true).open(path) {
+            Ok(mut file) => {
+                let _ = writeln!(file, "{}", process::id());
+
+                return Ok(());
+            }
+            Err(error) if error.kind() == ErrorKind::AlreadyExists => {
+                if start.elapsed() >= timeout {
+                    return Err(Error::output(
+                        path,
+                        "timed out waiting for the output lock, remove it if no other atlas run is active",
+                    ));
+                }
+
+                if !announced {
+                    eprintln!(
+                        "Waiting for another atlas run to release {}",
+                        path.display()
+                    );
+
+                    announced = true;
+                }
+
+                thread::sleep(POLL_INTERVAL);
+
+                // The locks taken so far are dropped with the partial OutputLock
+                interrupt::check()?;
+            }
+            Err(error) => return Err(Error::output(path, error)),
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+// Splits `hero_lod2.png` into (`hero.png`, 2)
+pub fn parse(key: &Path) -> Option<(PathBuf, u32)> {
+    let stem = key.file_stem()?.to_str()?;
+    let (base, level) = stem.rsplit_once("_lod")?;
+
+    if base.is_empty() || level.is_empty() || !level.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+
+    let mut file_name = base.to_string();
+
+    if let Some(extension) = key.extension().and_then(|extension| extension.to_str()) {
+        file_name.push('.');
+        file_name.push_str(extension);
+    }
+
+    Some((key.with_file_name(file_name), level.parse().ok()?))
+}
+
+pub fn chains<'a>(keys: impl IntoIterator<Item = &'a PathBuf>) -> HashMap<PathBuf, Vec<PathBuf>> {
+    let mut levels: HashMap<PathBuf, Vec<(u32, PathBuf)>> = HashMap::new();
+
+    for key in keys {
+        if let Some((base, level)) = parse(key) {
+            levels.entry(base).or_default().push((level, key.clone()));
+        }
+    }
+
+    levels
+        .into_iter()
+        .map(|(base, mut levels)| {
+            levels.sort();
+
+            (base, levels.into_iter().map(|(_, key)| key).collect())
+        })
+        .collect()
+}
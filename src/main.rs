@@ -1,125 +1,2012 @@
-use std::{collections::HashMap, fs, path::PathBuf};
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
-use clap::{Parser, Subcommand, ValueEnum};
-use image::GenericImageView;
+use alpha::AlphaThreshold;
+use animation::Decoded;
+use aspect::AspectRatio;
+use atlas::{
+    allocator::{Allocation, AllocatorOptions, MaxRectsHeuristic},
+    pack::{self, Packer, Page, Spacing},
+    Algorithm, Trim, Vector2,
+};
+use blend::Blend;
+use clap::{error::ErrorKind, ArgGroup, Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use codegen::Codegen;
+use collision::CollisionShape;
+use compose::Composition;
+use convert::{Source, Target};
+use decode::DecodeLimits;
+use dither::DitherPattern;
+use error::{Context, Error};
+use font::{CodepointRange, FontOptions};
+use format::MetadataFormat;
+use image::{DynamicImage, RgbaImage};
+use keys::{KeyFormat, KeyNaming, KeyTemplate};
+use lock::OutputLock;
+use mask::{Channel, ChannelArg, MaskArg};
+use nine_slice::{NineSlice, NineSliceArg};
+use overlay::OverlayStyle;
+use palette::Palette;
+use placeholder::Placeholder;
+use priority::Priority;
+use provenance::Provenance;
+use quality::QualityArg;
+use region::Region;
+use remote::Remote;
+use sampling::{Sampling, SamplingArg};
+use sdf::{Sdf, SdfChannels};
 use serde::Serialize;
+use shard::Shard;
+use sort::SortOrder;
+use stats::RunStats;
+use version::VersionReq;
+use view::View;
+use warnings::{Lint, Warning, Warnings};
+
+mod alpha;
+mod animation;
+mod anonymous;
+mod aseprite;
+mod aspect;
+mod autosize;
+mod blend;
+mod codegen;
+mod collision;
+mod compose;
+mod compression;
+mod convert;
+mod css;
+#[cfg(unix)]
+mod daemon;
+mod decode;
+mod diff;
+mod dither;
+mod error;
+mod examples;
+mod export;
+mod font;
+mod format;
+mod gpu;
+mod html;
+mod init;
+mod inputs;
+mod interrupt;
+mod keys;
+mod ktx2;
+mod locale;
+mod lock;
+mod lod;
+mod mask;
+mod metadata;
+mod msdf;
+mod nine_slice;
+mod overlap;
+mod overlay;
+mod palette;
+mod pattern;
+mod placeholder;
+mod plan;
+mod priority;
+mod provenance;
+mod quality;
+mod region;
+mod remote;
+mod rename;
+mod sampling;
+mod sdf;
+mod selection;
+mod sha256;
+mod shard;
+mod shuffle;
+mod sort;
+mod stats;
+mod stress;
+mod subatlas;
+mod summary;
+mod svg;
+mod tiles;
+mod tps;
+mod trim;
+mod truetype;
+mod usage;
+mod version;
+mod view;
+mod warnings;
+
+const LOW_OCCUPANCY_THRESHOLD: f32 = 50.0;
 
 fn main() {
     let cli = Cli::parse();
 
-    if let Some(command) = cli.command {
-        match command {
-            Command::Generate {
-                files,
-                atlas_output,
-                metadata_output,
-                width,
-                height,
-                algorithm,
-            } => {
-                let images = files
-                    .into_iter()
-                    .map(|file| (file.clone(), image::open(file).unwrap()))
-                    .collect::<Vec<_>>();
-
-                let mut atlas = image::RgbaImage::new(width, height);
-                let mut fragments = HashMap::new();
-
-                match algorithm {
-                    Algorithm::Etagere => {
-                        let mut allocator = etagere::AtlasAllocator::new(etagere::size2(
-                            width as i32,
-                            height as i32,
-                        ));
-
-                        for (file_path, image) in images {
-                            let allocation = allocator
-                                .allocate(etagere::size2(
-                                    image.width() as i32,
-                                    image.height() as i32,
-                                ))
-                                .expect("Failed to allocate atlas space");
-
-                            let rectangle = allocation.rectangle;
-
-                            image.pixels().for_each(|(x, y, pixel)| {
-                                atlas.put_pixel(
-                                    rectangle.min.x as u32 + x,
-                                    rectangle.min.y as u32 + y,
-                                    pixel,
-                                );
-                            });
-
-                            fragments.insert(
-                                file_path.clone(),
-                                Fragment {
-                                    center: Vector2::new(
-                                        (rectangle.center().x
-                                            - (rectangle.width() - image.width() as i32) / 2)
-                                            as f32,
-                                        (rectangle.center().y
-                                            - (rectangle.height() - image.height() as i32) / 2)
-                                            as f32,
-                                    ),
-                                    size: Vector2::new(image.width() as f32, image.height() as f32),
-                                },
-                            );
-                        }
-                    }
-                    Algorithm::Guillotiere => {
-                        let mut allocator = guillotiere::AtlasAllocator::new(guillotiere::size2(
-                            width as i32,
-                            height as i32,
-                        ));
-
-                        for (file_path, image) in images {
-                            let allocation = allocator
-                                .allocate(guillotiere::size2(
-                                    image.width() as i32,
-                                    image.height() as i32,
-                                ))
-                                .expect("Failed to allocate atlas space");
-
-                            let rectangle = allocation.rectangle;
-
-                            image.pixels().for_each(|(x, y, pixel)| {
-                                atlas.put_pixel(
-                                    rectangle.min.x as u32 + x,
-                                    rectangle.min.y as u32 + y,
-                                    pixel,
-                                );
-                            });
-
-                            fragments.insert(
-                                file_path.clone(),
-                                Fragment {
-                                    center: Vector2::new(
-                                        (rectangle.center().x
-                                            - (rectangle.width() - image.width() as i32) / 2)
-                                            as f32,
-                                        (rectangle.center().y
-                                            - (rectangle.height() - image.height() as i32) / 2)
-                                            as f32,
-                                    ),
-                                    size: Vector2::new(image.width() as f32, image.height() as f32),
-                                },
-                            );
-                        }
-                    }
+    if let Some(required_version) = &cli.required_version {
+        if !required_version.matches(env!("CARGO_PKG_VERSION")) {
+            let error = Error::Check(format!(
+                "atlas {} doesn't satisfy --required-version {required_version}",
+                env!("CARGO_PKG_VERSION")
+            ));
+
+            eprintln!("error: {error}");
+            std::process::exit(error.exit_code());
+        }
+    }
+
+    let Some(command) = cli.command else {
+        println!("No command specified");
+        return;
+    };
+
+    interrupt::install();
+
+    if let Err(error) = run(command) {
+        // clap prints usage errors with the usage line and the --help hint
+        if let Error::Usage(error) = error {
+            error.exit();
+        }
+
+        eprintln!("error: {error}");
+
+        std::process::exit(error.exit_code());
+    }
+}
+
+fn usage(kind: ErrorKind, message: impl std::fmt::Display) -> Error {
+    Error::Usage(Box::new(Cli::command().error(kind, message)))
+}
+
+fn run(command: Command) -> Result<(), Error> {
+    match command {
+        Command::Generate(args) => generate(args),
+        Command::Merge { shards, mut args } => {
+            if shards.len() % 2 != 0 {
+                return Err(usage(
+                    ErrorKind::WrongNumberOfValues,
+                    "shards must be given as ATLAS METADATA pairs",
+                ));
+            }
+
+            args.sub_atlas.splice(0..0, shards);
+
+            generate(args)
+        }
+        Command::Overlap { atlases } => {
+            if atlases.len() % 2 != 0 {
+                return Err(usage(
+                    ErrorKind::WrongNumberOfValues,
+                    "atlases must be given as ATLAS METADATA pairs",
+                ));
+            }
+
+            overlap::overlap(&atlases)
+        }
+        Command::Stats { history, last } => stats::print_trends(&history, last),
+        Command::Rename {
+            map,
+            metadata,
+            output,
+            keep_aliases,
+        } => rename::rename(
+            &map,
+            &metadata,
+            output.as_ref().unwrap_or(&metadata),
+            keep_aliases,
+        ),
+        Command::Plan {
+            files,
+            algorithm,
+            max_texture_size,
+            allocator,
+            max_pages,
+            aspect_ratio,
+        } => plan::plan(
+            &files,
+            algorithm.0,
+            allocator.options()?,
+            max_texture_size,
+            max_pages,
+            aspect_ratio,
+        ),
+        Command::Stress {
+            files,
+            algorithm,
+            allocator,
+            width,
+            height,
+            seeds,
+        } => stress::stress(
+            &files,
+            algorithm.0,
+            allocator.options()?,
+            width,
+            height,
+            seeds,
+        ),
+        Command::Examples { directory, seed } => examples::write(&directory, seed).and_then(|()| {
+            let arguments = examples::pipeline(&directory);
+
+            println!(
+                "{}",
+                arguments
+                    .iter()
+                    .map(|argument| argument.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            );
+
+            let Some(Command::Generate(args)) = Cli::parse_from(arguments).command else {
+                unreachable!();
+            };
+
+            generate(args)
+        }),
+        Command::Init { directory } => init::init(&directory).map(|script| print!("{script}")),
+        #[cfg(unix)]
+        Command::Daemon { socket } => daemon::serve(&socket, |arguments| {
+            let invocation = std::iter::once("generate".to_string())
+                .chain(arguments)
+                .collect::<Vec<_>>();
+            let cli = Cli::try_parse_from(
+                std::iter::once("atlas").chain(invocation.iter().map(String::as_str)),
+            )
+            .map_err(|error| Error::Usage(Box::new(error)))?;
+
+            let Some(Command::Generate(mut args)) = cli.command else {
+                unreachable!();
+            };
+
+            args.invocation = Some(invocation);
+
+            generate(args)
+        }),
+        Command::ImportTps { project } => tps::import(&project).map(|(arguments, notes)| {
+            for note in notes {
+                eprintln!("note: {note}");
+            }
+
+            println!(
+                "{}",
+                arguments
+                    .iter()
+                    .map(|argument| argument.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            );
+        }),
+        Command::Convert {
+            input,
+            output,
+            from,
+            to,
+            atlas,
+        } => {
+            if from == Source::Atlas && to.needs_images() && atlas.is_none() {
+                return Err(usage(
+                    ErrorKind::MissingRequiredArgument,
+                    "--atlas is needed to refer to the page images from atlas's own metadata",
+                ));
+            }
+
+            convert::convert(&input, &output, from, to, atlas.as_deref())
+        }
+        Command::Diff {
+            old_atlas,
+            old_metadata,
+            new_atlas,
+            new_metadata,
+            pixels,
+            threshold,
+            overlay,
+            overlay_style,
+            events,
+        } => diff::diff(
+            &old_atlas,
+            &old_metadata,
+            &new_atlas,
+            &new_metadata,
+            &diff::DiffOptions {
+                pixels,
+                threshold,
+                overlay,
+                overlay_style,
+                events,
+            },
+        )
+        .and_then(|changed| {
+            if changed {
+                Err(Error::Check("the atlases differ".to_string()))
+            } else {
+                Ok(())
+            }
+        }),
+        Command::GenerateFont {
+            font,
+            size,
+            ranges,
+            algorithm,
+            allocator,
+            width,
+            height,
+            padding,
+            sdf,
+            msdf,
+            sdf_spread,
+            sdf_channels,
+            atlas_output,
+            metadata_output,
+        } => {
+            if !(size > 0.0 && size.is_finite()) {
+                return Err(usage(
+                    ErrorKind::ValueValidation,
+                    "--size must be a positive number of pixels",
+                ));
+            }
+
+            font::generate(
+                &font,
+                &FontOptions {
+                    size,
+                    ranges,
+                    algorithm: algorithm.0,
+                    allocator: allocator.options()?,
+                    width,
+                    height,
+                    padding,
+                    sdf: sdf.then_some(Sdf {
+                        spread: sdf_spread,
+                        channels: sdf_channels,
+                    }),
+                    msdf: msdf.then_some(sdf_spread),
+                },
+                &atlas_output,
+                &metadata_output,
+            )
+        }
+    }
+}
+
+fn generate(mut args: Generate) -> Result<(), Error> {
+    let allocator_options = check_arguments(&args)?;
+
+    // Held until generate returns, including when it returns an error
+    let _lock = lock_outputs(&args)?;
+
+    let start = Instant::now();
+
+    args.files = inputs::expand(&args.files, args.recursive, &args.exclude)?;
+
+    // Fetched up front so the provenance hashes what gets packed
+    let remote_files = error::collect(
+        args.remote
+            .iter()
+            .map(|remote| Ok((remote.key(), remote.fetch(&args.remote_cache)?))),
+    )?;
+
+    // Read up front so a missing key fails before anything is packed
+    let mac_key = match &args.mac_key {
+        Some(mac_key) => Some(fs::read(mac_key).input_context(mac_key)?),
+        None => None,
+    };
+
+    let provenance = match args.provenance {
+        Some(_) => Some(Provenance::new(
+            args.invocation.clone().unwrap_or_else(|| {
+                std::env::args_os()
+                    .skip(1)
+                    .map(|argument| argument.to_string_lossy().into_owned())
+                    .collect()
+            }),
+            error::collect(
+                args.files
+                    .iter()
+                    .chain(&args.sub_atlas)
+                    .chain(remote_files.iter().map(|(_, file)| file))
+                    .chain(args.compose.iter().flat_map(|composition| {
+                        std::iter::once(&composition.base)
+                            .chain(composition.layers.iter().map(|layer| &layer.path))
+                    }))
+                    .map(|file| Ok((file.clone(), Provenance::hash_file(file)?))),
+            )?
+            .into_iter()
+            .collect(),
+        )),
+        None => None,
+    };
+
+    let limits = args.untrusted.then(|| DecodeLimits {
+        max_dimension: args.max_input_dimension,
+        max_decoded_bytes: args.max_decoded_bytes,
+        timeout: Duration::from_secs(args.decode_timeout),
+    });
+
+    let Inputs {
+        mut images,
+        timings,
+        mut nine_slices,
+        key_tags,
+    } = load_inputs(&mut args, &remote_files, limits.as_ref())?;
+
+    interrupt::check()?;
+
+    let mut warnings = Warnings::new(args.allow.clone(), args.deny.clone());
+
+    if let Some(shard) = args.shard {
+        images.retain(|(file_path, _)| shard.includes(file_path));
+    }
+
+    if let Some(seed) = args.shuffle {
+        shuffle::shuffle(&mut images, seed);
+    }
+
+    let mut seen = HashSet::new();
+
+    images.retain(|(file_path, _)| {
+        let unique = seen.insert(file_path.clone());
+
+        if !unique {
+            warnings.emit(
+                Warning::DuplicateInput,
+                format!(
+                    "{} was given more than once, ignoring duplicate",
+                    file_path.display()
+                ),
+            );
+        }
+
+        unique
+    });
+
+    let Processed {
+        channels,
+        alpha_thresholds,
+    } = process_images(
+        &args,
+        &mut images,
+        &mut nine_slices,
+        &mut warnings,
+        limits.as_ref(),
+    )?;
+
+    let sdf = args.sdf.then_some(Sdf {
+        spread: args.sdf_spread,
+        channels: args.sdf_channels,
+    });
+
+    if let Some(sdf) = sdf {
+        for (_, image) in &mut images {
+            *image = DynamicImage::ImageRgba8(sdf::generate(&image.to_rgba8(), sdf.spread));
+        }
+    }
+
+    let mut trims = HashMap::new();
+
+    let tileable = |key: &Path| {
+        args.tileable
+            .iter()
+            .any(|pattern| pattern::matches_key(pattern, key))
+    };
+
+    if args.trim {
+        // Trimming a tile would change its period, so tileable sprites keep their borders
+        for (file_path, image) in images.iter_mut().filter(|(key, _)| !tileable(key)) {
+            let (trimmed, trim) = trim::trim(image);
+
+            // Insets were checked against the untrimmed image, the metadata describes the
+            // trimmed one
+            if let Some(slice) = nine_slices.get_mut(file_path) {
+                *slice = slice.trim(&trim, trimmed.width(), trimmed.height());
+            }
+
+            *image = trimmed;
+            trims.insert(file_path.clone(), trim);
+        }
+    }
+
+    // With --locale-pages each locale's variants go on pages of their own, a runtime then loads the
+    // shared pages and its own locale's
+    let page_locale = |key: &Path| {
+        args.locale_pages
+            .then(|| locale::split(key, &args.locale))
+            .flatten()
+            .and_then(|(_, locale)| args.locale.iter().position(|other| other == locale))
+    };
+
+    // Identical pixels are packed once, later copies become aliases of the first. Aliases never
+    // cross locale pages, or a locale would need another one's pages
+    let mut aliases = HashMap::new();
+
+    if args.dedupe {
+        let mut originals = HashMap::<_, PathBuf>::new();
+
+        images.retain(|(file_path, image)| {
+            match originals.entry((anonymous::content_hash(image), page_locale(file_path))) {
+                Entry::Occupied(original) => {
+                    aliases.insert(file_path.clone(), original.get().clone());
+                    false
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(file_path.clone());
+                    true
+                }
+            }
+        });
+    }
+
+    // A shuffle or compression order replaces the sort rather than being sorted away again
+    if args.shuffle.is_none() && !args.compression_order {
+        sort::sort(&mut images, args.sort);
+    }
+
+    let spacing = Spacing {
+        padding: args.padding,
+        border: args.border,
+        extrude: args.extrude,
+    };
+
+    let sizes = images
+        .iter()
+        .map(|(_, image)| spacing.padded(image.width(), image.height()))
+        .collect::<Vec<_>>();
+
+    // Auto-size searches the allocator's area, which padding grows and the border shrinks
+    let margin = |size: u32| size.saturating_sub(spacing.border * 2) + spacing.padding;
+
+    let smallest_page = |algorithm, max_width, max_height, aspect_ratio| {
+        autosize::smallest(
+            &sizes,
+            algorithm,
+            allocator_options,
+            margin(max_width),
+            margin(max_height),
+            aspect_ratio,
+        )
+        .map(|(width, height)| {
+            (
+                width + spacing.border * 2 - spacing.padding,
+                height + spacing.border * 2 - spacing.padding,
+            )
+        })
+    };
+
+    let auto_size = |algorithm, max_width, max_height| {
+        if args.power_of_two {
+            return autosize::power_of_two_pages(max_width, max_height, args.square)
+                .into_iter()
+                .find(|&(width, height)| {
+                    width > spacing.border * 2
+                        && height > spacing.border * 2
+                        && autosize::fits(
+                            &sizes,
+                            algorithm,
+                            allocator_options,
+                            (margin(width), margin(height)),
+                        )
+                });
+        }
+
+        let aspect_ratio = if args.square {
+            Some(AspectRatio {
+                width: 1,
+                height: 1,
+            })
+        } else {
+            args.aspect_ratio
+        };
+
+        smallest_page(algorithm, max_width, max_height, aspect_ratio)
+    };
+
+    let (algorithm, (width, height)) = if args.auto_size {
+        let algorithm = match args.algorithm {
+            AlgorithmSelection::Fixed(algorithm) => algorithm,
+            AlgorithmSelection::Auto => selection::best(|algorithm| {
+                auto_size(algorithm, args.max_width, args.max_height)
+                    .map(|(width, height)| (1, width as u64 * height as u64))
+            }),
+        };
+
+        let size = auto_size(algorithm, args.max_width, args.max_height).ok_or_else(|| {
+            Error::Packing(format!(
+                "inputs don't fit on a single page of at most {}x{}",
+                args.max_width, args.max_height
+            ))
+        })?;
+
+        (algorithm, size)
+    } else {
+        let (mut width, mut height) = (args.width.unwrap(), args.height.unwrap());
+
+        if args.square && width != height {
+            warnings.emit(
+                Warning::AdjustedSize,
+                format!(
+                    "{width}x{height} rounded up to {0}x{0} for --square",
+                    width.max(height)
+                ),
+            );
+
+            (width, height) = (width.max(height), width.max(height));
+        }
+
+        if args.power_of_two && (!width.is_power_of_two() || !height.is_power_of_two()) {
+            warnings.emit(
+                Warning::AdjustedSize,
+                format!(
+                    "{width}x{height} rounded up to {}x{} for --power-of-two",
+                    width.next_power_of_two(),
+                    height.next_power_of_two()
+                ),
+            );
+
+            (width, height) = (width.next_power_of_two(), height.next_power_of_two());
+        }
+
+        let algorithm = match args.algorithm {
+            AlgorithmSelection::Fixed(algorithm) => algorithm,
+            AlgorithmSelection::Auto => selection::best(|algorithm| {
+                selection::score(
+                    &sizes,
+                    algorithm,
+                    allocator_options,
+                    (margin(width), margin(height)),
+                )
+            }),
+        };
+
+        (algorithm, (width, height))
+    };
+
+    if spacing.border * 2 >= width || spacing.border * 2 >= height {
+        return Err(Error::Packing(format!(
+            "a border of {} leaves no room on a {width}x{height} page",
+            spacing.border
+        )));
+    }
+
+    if args.compression_order {
+        let default_size =
+            compression::encoded_size(&images, algorithm, allocator_options, width, height);
+
+        compression::order(&mut images);
+
+        let ordered_size =
+            compression::encoded_size(&images, algorithm, allocator_options, width, height);
+
+        if let (Some(default_size), Some(ordered_size)) = (default_size, ordered_size) {
+            println!(
+                "Compression order: {ordered_size} bytes as PNG, {default_size} bytes in default order ({:+.2}%)",
+                (ordered_size as f64 - default_size as f64) / default_size as f64 * 100.0
+            );
+        }
+    }
+
+    let (canvas_width, canvas_height) = if args.snap_pot_up {
+        (width.next_power_of_two(), height.next_power_of_two())
+    } else {
+        (width, height)
+    };
+
+    let sprite_area = images
+        .iter()
+        .map(|(_, image)| image.width() as u64 * image.height() as u64)
+        .sum::<u64>();
+    let transparent_area = images
+        .iter()
+        .map(|(_, image)| summary::transparent_area(image))
+        .sum::<u64>();
+
+    // Later --sampling arguments override earlier ones, regions match by their own key
+    let sampling_of = |key: &Path| {
+        args.sampling
+            .iter()
+            .rev()
+            .find(|sampling| sampling.includes(key))
+            .map(|sampling| sampling.sampling)
+    };
+
+    // Animation tags first, then the ones --key-command gave the input
+    let tags_of = |key: &Path| {
+        let tags = timings
+            .get(key)
+            .into_iter()
+            .flat_map(|timing| &timing.tags)
+            .chain(key_tags.get(key).into_iter().flatten())
+            .cloned()
+            .collect::<Vec<_>>();
+
+        (!tags.is_empty()).then_some(tags)
+    };
+
+    // Higher priorities are packed first, and lower ones never go on a page before the last one
+    // a higher priority used, so the first pages fetched hold the important sprites
+    let priority_of = |key: &Path| {
+        args.priority
+            .iter()
+            .rev()
+            .find(|priority| priority.includes(key))
+            .map(|priority| priority.priority)
+    };
+
+    if !args.priority.is_empty() {
+        images.sort_by_key(|(key, _)| std::cmp::Reverse(priority_of(key).unwrap_or(0)));
+    }
+
+    let loaded = Instant::now();
+    let packer = Packer::new(algorithm, width, height)
+        .allocator_options(allocator_options)
+        .canvas(canvas_width, canvas_height)
+        .spacing(spacing)
+        .allow_rotation(args.allow_rotation);
+    let class_of = |key: &Path, image: &DynamicImage| PageClass {
+        blend: args.split_opaque.then(|| Blend::of(image)),
+        volatile: args
+            .volatile
+            .iter()
+            .any(|pattern| pattern::matches_key(pattern, key)),
+        quality: args
+            .quality
+            .iter()
+            .rev()
+            .find(|quality| quality.includes(key))
+            .map(|quality| quality.quality),
+        locale: page_locale(key),
+    };
+    let (pages, packed) = pack_sprites(
+        packer,
+        images,
+        class_of,
+        priority_of,
+        args.atlas_output.is_some().then_some(&tileable),
+        (width, height),
+    )?;
+    let mut fragments = HashMap::new();
+    let mut placements = Vec::new();
+
+    for Packed {
+        key: file_path,
+        image,
+        placement,
+    } in packed
+    {
+        let (index, allocation, rotated) =
+            (placement.page, placement.allocation, placement.rotated);
+        let (packed_width, packed_height) = if rotated {
+            (image.height(), image.width())
+        } else {
+            (image.width(), image.height())
+        };
+        let priority = priority_of(&file_path);
+
+        placements.push(Placement {
+            key: file_path.clone(),
+            page: index,
+            x: allocation.x as u32,
+            y: allocation.y as u32,
+            width: packed_width,
+            height: packed_height,
+        });
+
+        fragments.insert(
+            file_path.clone(),
+            Fragment {
+                center: args
+                    .rounding
+                    .center(&allocation, packed_width, packed_height),
+                frame: metadata::Rectangle {
+                    x: allocation.x as u32,
+                    y: allocation.y as u32,
+                    width: packed_width,
+                    height: packed_height,
+                },
+                size: Vector2::new(image.width() as f32, image.height() as f32),
+                uv: args.uv_mode.map(|uv_mode| {
+                    Uv::new(
+                        uv_mode,
+                        allocation.x as u32,
+                        allocation.y as u32,
+                        packed_width,
+                        packed_height,
+                        canvas_width,
+                        canvas_height,
+                    )
+                }),
+                rotated: args.allow_rotation.then_some(rotated),
+                layer: (args.layout == Layout::Array).then_some(index as u32),
+                page: None,
+                parent: None,
+                alias_of: None,
+                lods: None,
+                channel: channels.get(&file_path).copied(),
+                alpha_threshold: alpha_thresholds.get(&file_path).copied(),
+                dither: args.dither_alpha,
+                sdf_spread: sdf.map(|sdf| sdf.spread),
+                nine_slice: nine_slices
+                    .get(&file_path)
+                    .map(|slice| slice.grow(sdf.map_or(0, |sdf| sdf.spread))),
+                trim: trims.remove(&file_path),
+                tileable: tileable(&file_path),
+                sampling: sampling_of(&file_path),
+                priority,
+                collision: args
+                    .collision
+                    .map(|shape| collision::extract(&image, shape, args.collision_tolerance)),
+                tiles: None,
+                duration: timings.get(&file_path).map(|timing| timing.duration),
+                tags: tags_of(&file_path),
+                locale: locale::split(&file_path, &args.locale)
+                    .map(|(_, locale)| locale.to_string()),
+            },
+        );
+    }
+
+    // Only the trim can differ, pixels that were identical before trimming stay identical after
+    for (alias, original) in &aliases {
+        let fragment = Fragment {
+            alias_of: Some(original.clone()),
+            channel: channels.get(alias).copied(),
+            alpha_threshold: alpha_thresholds.get(alias).copied(),
+            nine_slice: nine_slices
+                .get(alias)
+                .map(|slice| slice.grow(sdf.map_or(0, |sdf| sdf.spread))),
+            trim: trims.remove(alias),
+            tileable: tileable(alias),
+            sampling: sampling_of(alias),
+            priority: priority_of(alias),
+            duration: timings.get(alias).map(|timing| timing.duration),
+            tags: tags_of(alias),
+            locale: locale::split(alias, &args.locale).map(|(_, locale)| locale.to_string()),
+            ..fragments[original].clone()
+        };
+
+        fragments.insert(alias.clone(), fragment);
+    }
+
+    // Regions reuse their parent's packed pixels, so they only add metadata
+    let regions = error::collect(args.region.iter().map(|region| {
+        let source = aliases.get(&region.parent).unwrap_or(&region.parent);
+        let parent = placements
+            .iter()
+            .find(|placement| &placement.key == source)
+            .ok_or_else(|| Error::input(&region.parent, "region parent is not an input"))?;
+
+        if fragments.contains_key(&region.key) {
+            return Err(Error::input(
+                &region.parent,
+                format!(
+                    "region {} has the same key as an input",
+                    region.key.display()
+                ),
+            ));
+        }
+
+        let parent_fragment = &fragments[&region.parent];
+        let rotated = parent_fragment.rotated == Some(true);
+
+        // Regions are given in the original image, a trimmed parent moved its pixels up and left
+        // and a distance field moved them down and right by its spread
+        let (offset_x, offset_y) = parent_fragment
+            .trim
+            .as_ref()
+            .map_or((0, 0), |trim| (trim.offset.x as u32, trim.offset.y as u32));
+        let spread = parent_fragment.sdf_spread.unwrap_or(0);
+        let (region_x, region_y) = (region.x + spread, region.y + spread);
+
+        let (parent_width, parent_height) = if rotated {
+            (parent.height, parent.width)
+        } else {
+            (parent.width, parent.height)
+        };
+
+        if region_x < offset_x
+            || region_y < offset_y
+            || region_x - offset_x + region.width > parent_width
+            || region_y - offset_y + region.height > parent_height
+        {
+            return Err(Error::input(
+                &region.parent,
+                format!(
+                    "region {} is outside the {}x{} image",
+                    region.key.display(),
+                    parent_width,
+                    parent_height
+                ),
+            ));
+        }
+
+        let (x, y) = (region_x - offset_x, region_y - offset_y);
+
+        // Follows the parent's clockwise turn, so what was the region's bottom edge faces left
+        let (x, y, packed_width, packed_height) = if rotated {
+            (
+                parent_height - y - region.height,
+                x,
+                region.height,
+                region.width,
+            )
+        } else {
+            (x, y, region.width, region.height)
+        };
+
+        let allocation = Allocation {
+            x: (parent.x + x) as i32,
+            y: (parent.y + y) as i32,
+            width: packed_width as i32,
+            height: packed_height as i32,
+        };
+
+        Ok((
+            region.key.clone(),
+            Fragment {
+                center: args
+                    .rounding
+                    .center(&allocation, packed_width, packed_height),
+                frame: metadata::Rectangle {
+                    x: allocation.x as u32,
+                    y: allocation.y as u32,
+                    width: packed_width,
+                    height: packed_height,
+                },
+                size: Vector2::new(region.width as f32, region.height as f32),
+                uv: args.uv_mode.map(|uv_mode| {
+                    Uv::new(
+                        uv_mode,
+                        allocation.x as u32,
+                        allocation.y as u32,
+                        packed_width,
+                        packed_height,
+                        canvas_width,
+                        canvas_height,
+                    )
+                }),
+                rotated: parent_fragment.rotated,
+                layer: (args.layout == Layout::Array).then_some(parent.page as u32),
+                page: None,
+                parent: Some(region.parent.clone()),
+                alias_of: None,
+                lods: None,
+                channel: None,
+                alpha_threshold: None,
+                dither: None,
+                sdf_spread: parent_fragment.sdf_spread,
+                nine_slice: None,
+                trim: None,
+                tileable: false,
+                sampling: sampling_of(&region.key),
+                priority: None,
+                collision: None,
+                tiles: None,
+                duration: None,
+                tags: None,
+                locale: None,
+            },
+        ))
+    }))?;
+
+    fragments.extend(regions);
+
+    if args.layout == Layout::Atlas && pages.len() > 1 {
+        let page_of = placements
+            .iter()
+            .map(|placement| (&placement.key, placement.page as u32))
+            .collect::<HashMap<_, _>>();
+
+        for (key, fragment) in &mut fragments {
+            let source = fragment.parent.as_ref().unwrap_or(key);
+
+            fragment.page = page_of.get(aliases.get(source).unwrap_or(source)).copied();
+        }
+    }
+
+    if args.lod_chains {
+        for (base, chain) in lod::chains(fragments.keys()) {
+            if let Some(fragment) = fragments.get_mut(&base) {
+                fragment.lods = Some(chain);
+            }
+        }
+    }
+
+    if let Some(tile_size) = args.tile_size {
+        for fragment in fragments.values_mut() {
+            fragment.tiles = Some(tiles::range(&fragment.frame, tile_size));
+        }
+    }
+
+    // The last chance to stop, everything after this writes outputs
+    interrupt::check()?;
+
+    let packed = Instant::now();
+    let page_count = pages.len() as u32;
+    let page_classes = pages
+        .iter()
+        .map(|page| page.class.unwrap_or_default())
+        .collect::<Vec<_>>();
+
+    if !canvas_width.is_power_of_two() || !canvas_height.is_power_of_two() {
+        warnings.emit(
+            Warning::NonPowerOfTwo,
+            format!("atlas size {canvas_width}x{canvas_height} is not a power of two"),
+        );
+    }
+
+    let occupancy = occupancy(&fragments, canvas_width, canvas_height, page_count);
+
+    if occupancy < LOW_OCCUPANCY_THRESHOLD {
+        warnings.emit(
+            Warning::LowOccupancy,
+            format!("atlas occupancy is only {occupancy:.2}%"),
+        );
+    }
+
+    if let Some(usage_report) = &args.usage_report {
+        usage::write_report(
+            usage_report,
+            &fragments,
+            &args.view,
+            canvas_width as u64 * canvas_height as u64 * page_count as u64,
+        )
+        .output_context(usage_report)?;
+    }
+
+    if let Some(layout_svg) = &args.layout_svg {
+        svg::write_layout(
+            layout_svg,
+            canvas_width,
+            canvas_height,
+            pages.len(),
+            &placements,
+        )
+        .output_context(layout_svg)?;
+    }
+
+    let tile_index = match (args.tile_size, &args.atlas_output) {
+        (Some(tile_size), Some(atlas_output)) => Some(
+            tiles::write(
+                atlas_output,
+                &pages.iter().map(|page| &page.image).collect::<Vec<_>>(),
+                tile_size,
+            )
+            .output_context(atlas_output)?,
+        ),
+        _ => None,
+    };
+
+    let mut atlas_outputs = match &args.atlas_output {
+        Some(atlas_output) => write_pages(pages, atlas_output, args.layout, sdf)?,
+        None => Vec::new(),
+    };
+
+    // Everything up to here works in image space, only the written metadata follows the convention
+    if args.origin != Origin::TopLeft || args.flip_y {
+        for fragment in fragments.values_mut() {
+            fragment.convert(args.origin, args.flip_y, canvas_width, canvas_height);
+        }
+    }
+
+    let sheet = export::Sheet {
+        width: canvas_width,
+        height: canvas_height,
+        images: if args.metadata_format.is_export() {
+            atlas_outputs
+                .iter()
+                .map(|atlas_output| html::relative_href(&args.metadata_output, atlas_output))
+                .collect::<Result<_, _>>()
+                .output_context(&args.metadata_output)?
+        } else {
+            Vec::new()
+        },
+    };
+
+    let meta = Meta {
+        uv_mode: args.uv_mode,
+        rounding: args.rounding,
+        origin: args.origin,
+        flip_y: args.flip_y,
+        rotation: args
+            .allow_rotation
+            .then_some(args.metadata_format.rotation()),
+        pages: if args.snap_pot_up
+            || args.split_opaque
+            || !args.volatile.is_empty()
+            || !args.quality.is_empty()
+            || args.locale_pages
+        {
+            page_classes
+                .iter()
+                .enumerate()
+                .map(|(page, class)| PageArea {
+                    page,
+                    width: canvas_width,
+                    height: canvas_height,
+                    used: used_area(&placements, page),
+                    blend: class.blend,
+                    volatile: class.volatile,
+                    quality: class.quality,
+                    locale: class.locale.map(|locale| args.locale[locale].clone()),
+                })
+                .collect()
+        } else {
+            Vec::new()
+        },
+    };
+
+    let all_fragments = fragments.iter().collect::<HashMap<_, _>>();
+
+    check_frames(
+        &all_fragments,
+        args.origin,
+        args.flip_y,
+        args.metadata_format,
+        args.godot_output.is_some(),
+        canvas_width,
+        canvas_height,
+    )?;
+
+    let mut metadata_outputs = format::write(
+        &args.metadata_output,
+        &all_fragments,
+        &meta,
+        args.metadata_format,
+        &sheet,
+    )?;
+
+    if let (Some(codegen), Some(codegen_output)) = (args.codegen, &args.codegen_output) {
+        codegen::write(
+            codegen_output,
+            codegen,
+            &export::fragments(&all_fragments),
+            &fragments,
+            canvas_width,
+            canvas_height,
+        )
+        .output_context(codegen_output)?;
+    }
+
+    if let Some(godot_output) = &args.godot_output {
+        let fragments = export::fragments(&all_fragments);
+        let images = export::Sheet {
+            width: canvas_width,
+            height: canvas_height,
+            images: atlas_outputs
+                .iter()
+                .map(|atlas_output| atlas_output.to_string_lossy().into_owned())
+                .collect(),
+        };
+
+        metadata_outputs.extend(
+            export::godot::write(godot_output, &export::pages(&fragments, &images))
+                .output_context(godot_output)?,
+        );
+    }
+
+    let mut view_outputs = Vec::new();
+
+    if let Some(tile_index) = &tile_index {
+        let tiles_output = view_output_path(&args.metadata_output, "tiles");
+
+        fs::write(
+            &tiles_output,
+            serde_json::to_string_pretty(tile_index).unwrap(),
+        )
+        .output_context(&tiles_output)?;
+
+        view_outputs.push(tiles_output);
+    }
+
+    for view in &args.view {
+        let view_fragments = fragments
+            .iter()
+            .filter(|(key, _)| view.includes(key))
+            .collect::<HashMap<_, _>>();
+
+        if view_fragments.is_empty() {
+            warnings.emit(
+                Warning::EmptyView,
+                format!("view '{}' does not match any fragment", view.name),
+            );
+        }
+
+        let view_output = view_output_path(&args.metadata_output, &view.name);
+
+        view_outputs.extend(format::write(
+            &view_output,
+            &view_fragments,
+            &meta,
+            args.metadata_format,
+            &sheet,
+        )?);
+    }
+
+    for locale in &args.locale {
+        let locale_fragments = locale::view(&fragments, &args.locale, locale);
+
+        if !fragments
+            .values()
+            .any(|fragment| fragment.locale.as_ref() == Some(locale))
+        {
+            warnings.emit(
+                Warning::EmptyView,
+                format!("locale '{locale}' does not have any variant"),
+            );
+        }
+
+        let locale_output = view_output_path(&args.metadata_output, locale);
+
+        view_outputs.extend(format::write(
+            &locale_output,
+            &locale_fragments
+                .iter()
+                .map(|(key, fragment)| (key, *fragment))
+                .collect(),
+            &meta,
+            args.metadata_format,
+            &sheet,
+        )?);
+    }
+
+    let mut outputs = atlas_outputs
+        .iter()
+        .chain(&metadata_outputs)
+        .chain(&args.layout_svg)
+        .cloned()
+        .chain(view_outputs)
+        .collect::<Vec<_>>();
+
+    if args.hash_names {
+        let mut renames = HashMap::new();
+
+        for output in &mut outputs {
+            let hashed = rename_with_hash(output)?;
+
+            if let Some(atlas_output) = atlas_outputs
+                .iter_mut()
+                .find(|atlas_output| atlas_output == &output)
+            {
+                *atlas_output = hashed.clone();
+            }
+
+            renames.insert(std::mem::replace(output, hashed.clone()), hashed);
+        }
+
+        let hash_manifest = args
+            .hash_manifest
+            .unwrap_or_else(|| args.metadata_output.with_file_name("hash-manifest.json"));
+
+        fs::write(
+            &hash_manifest,
+            serde_json::to_string_pretty(&renames).unwrap(),
+        )
+        .output_context(&hash_manifest)?;
+    }
+
+    if let Some(gpu_report) = &args.gpu_report {
+        let files = error::collect(atlas_outputs.iter().map(|output| {
+            Ok((
+                output.clone(),
+                fs::metadata(output).output_context(output)?.len(),
+            ))
+        }))?;
+
+        gpu::write_report(
+            gpu_report,
+            canvas_width,
+            canvas_height,
+            page_count as usize,
+            &files,
+        )
+        .output_context(gpu_report)?;
+    }
+
+    if let Some(contact_sheet) = &args.contact_sheet {
+        let page_hrefs = atlas_outputs
+            .iter()
+            .map(|atlas_output| html::relative_href(contact_sheet, atlas_output))
+            .collect::<Result<Vec<_>, _>>()
+            .output_context(contact_sheet)?;
+
+        html::write_contact_sheet(contact_sheet, &page_hrefs, &placements)
+            .output_context(contact_sheet)?;
+    }
+
+    if let Some(css_output) = &args.css_output {
+        let page_hrefs = atlas_outputs
+            .iter()
+            .map(|atlas_output| html::relative_href(css_output, atlas_output))
+            .collect::<Result<Vec<_>, _>>()
+            .output_context(css_output)?;
+        let class_names = css::class_names(&placements);
+
+        css::write_stylesheet(css_output, &page_hrefs, &placements, &class_names)
+            .output_context(css_output)?;
+
+        if let Some(html_preview) = &args.html_preview {
+            let stylesheet_href =
+                html::relative_href(html_preview, css_output).output_context(html_preview)?;
+
+            html::write_preview(html_preview, &stylesheet_href, &placements, &class_names)
+                .output_context(html_preview)?;
+        }
+    }
+
+    if let (Some(provenance_output), Some(mut provenance)) = (&args.provenance, provenance) {
+        for output in outputs {
+            provenance.add_output(output)?;
+        }
+
+        provenance.write(provenance_output, mac_key.as_deref())?;
+    }
+
+    if let Some(stats_history) = &args.stats_history {
+        let written = Instant::now();
+
+        let output_bytes = output_size(&atlas_outputs)?;
+
+        stats::append(
+            stats_history,
+            &RunStats {
+                timestamp: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap()
+                    .as_secs(),
+                sprites: fragments.len(),
+                pages: page_count,
+                occupancy,
+                output_bytes,
+                load_ms: (loaded - start).as_millis(),
+                pack_ms: (packed - loaded).as_millis(),
+                write_ms: (written - packed).as_millis(),
+            },
+        )?;
+    }
+
+    let budget = Budget {
+        max_output_size: args.max_output_size,
+        min_occupancy: args.min_occupancy,
+        max_pages: args.max_pages,
+    };
+
+    let violations = budget.check(
+        &atlas_outputs,
+        &fragments,
+        canvas_width,
+        canvas_height,
+        page_count,
+    )?;
+
+    let mut suggestions = Vec::new();
+
+    if sprite_area > 0 && transparent_area * 10 >= sprite_area {
+        suggestions.push(format!(
+            "enable --trim: would save ~{:.0}% of sprite area based on the transparent borders detected",
+            transparent_area as f64 / sprite_area as f64 * 100.0
+        ));
+    }
+
+    if !args.auto_size {
+        let (max_width, max_height) = if page_count == 1 {
+            (width, height)
+        } else {
+            (args.max_width, args.max_height)
+        };
+
+        if let Some((smallest_width, smallest_height)) = auto_size(algorithm, max_width, max_height)
+        {
+            let smallest_area = smallest_width as u64 * smallest_height as u64;
+            let current_area = width as u64 * height as u64 * page_count as u64;
+
+            if smallest_area * 4 <= current_area * 3 {
+                suggestions.push(format!(
+                    "enable --auto-size: everything fits on a single {smallest_width}x{smallest_height} page, {:.0}% of the current area",
+                    smallest_area as f64 / current_area as f64 * 100.0
+                ));
+            }
+        }
+    }
+
+    if !args.snap_pot_up && (!canvas_width.is_power_of_two() || !canvas_height.is_power_of_two()) {
+        suggestions.push(format!(
+            "enable --snap-pot-up: pads the pages to {}x{} without repacking",
+            canvas_width.next_power_of_two(),
+            canvas_height.next_power_of_two()
+        ));
+    }
+
+    summary::Summary {
+        pages: page_count,
+        width: canvas_width,
+        height: canvas_height,
+        sprites: fragments.len(),
+        aliases: aliases.len(),
+        auto_algorithm: matches!(args.algorithm, AlgorithmSelection::Auto).then_some(algorithm),
+        occupancy,
+        wasted_area: (canvas_width as u64 * canvas_height as u64 * page_count as u64)
+            .saturating_sub(used_sprite_area(&fragments)),
+        warnings: warnings.reported(),
+        suggestions,
+    }
+    .print();
+
+    for violation in &violations {
+        eprintln!("Budget violation: {violation}");
+    }
+
+    if warnings.denied() > 0 {
+        return Err(Error::Check(format!(
+            "aborting due to {} denied warnings",
+            warnings.denied()
+        )));
+    }
+
+    if !violations.is_empty() {
+        return Err(Error::Check(format!(
+            "aborting due to {} budget violations",
+            violations.len()
+        )));
+    }
+
+    Ok(())
+}
+
+fn output_size(outputs: &[PathBuf]) -> Result<u64, Error> {
+    error::collect(
+        outputs
+            .iter()
+            .map(|output| Ok(fs::metadata(output).output_context(output)?.len())),
+    )
+    .map(|sizes| sizes.into_iter().sum())
+}
+
+// Every usage error the arguments alone show, found before the lock is taken or anything is read
+fn check_arguments(args: &Generate) -> Result<AllocatorOptions, Error> {
+    if args.contact_sheet.is_some() && args.layout == Layout::Array {
+        return Err(usage(
+            ErrorKind::ArgumentConflict,
+            "--contact-sheet can only be used with --layout atlas",
+        ));
+    }
+
+    if args.sdf_channels == SdfChannels::Single && args.layout == Layout::Array {
+        return Err(usage(
+            ErrorKind::ArgumentConflict,
+            "--sdf-channels single can only be used with --layout atlas",
+        ));
+    }
+
+    if args.css_output.is_some() && args.layout == Layout::Array {
+        return Err(usage(
+            ErrorKind::ArgumentConflict,
+            "--css-output can only be used with --layout atlas",
+        ));
+    }
+
+    if args.godot_output.is_some() && args.layout == Layout::Array {
+        return Err(usage(
+            ErrorKind::ArgumentConflict,
+            "--godot-output can only be used with --layout atlas",
+        ));
+    }
+
+    if args.metadata_format.is_export() {
+        let format = args.metadata_format.to_possible_value().unwrap();
+
+        let conflict = if args.atlas_output.is_none() {
+            Some("needs --atlas-output")
+        } else if args.layout == Layout::Array {
+            Some("can only be used with --layout atlas")
+        } else if args.hash_names {
+            Some("cannot be used with --hash-names, it refers to the atlas by name")
+        } else if args.origin != Origin::TopLeft || args.flip_y {
+            Some("has its own coordinate convention, --origin and --flip-y don't apply")
+        } else if args.allow_rotation && !args.metadata_format.supports_rotation() {
+            Some("cannot describe sprites rotated clockwise, drop --allow-rotation")
+        } else {
+            None
+        };
+
+        if let Some(conflict) = conflict {
+            return Err(usage(
+                ErrorKind::ArgumentConflict,
+                format!("--metadata-format {} {conflict}", format.get_name()),
+            ));
+        }
+    }
+
+    // The binary format has no meta section, its loader reads every center as top-left, y down
+    if args.metadata_format == MetadataFormat::Binary
+        && (args.origin != Origin::TopLeft || args.flip_y)
+    {
+        return Err(usage(
+            ErrorKind::ArgumentConflict,
+            "--metadata-format binary always measures from the top left, --origin and --flip-y \
+                 don't apply",
+        ));
+    }
+
+    // Views, locales and the tile index all write next to the metadata, named after themselves
+    let mut named_outputs = HashMap::<PathBuf, String>::new();
+
+    for (name, source) in args
+        .tile_size
+        .map(|_| ("tiles".to_string(), "the tile index".to_string()))
+        .into_iter()
+        .chain(
+            args.view
+                .iter()
+                .map(|view| (view.name.clone(), format!("view '{}'", view.name))),
+        )
+        .chain(
+            args.locale
+                .iter()
+                .map(|locale| (locale.clone(), format!("locale '{locale}'"))),
+        )
+    {
+        let path = view_output_path(&args.metadata_output, &name);
+
+        if let Some(other) = named_outputs.insert(path.clone(), source.clone()) {
+            return Err(Error::input(
+                path,
+                format!("{other} and {source} would both be written here"),
+            ));
+        }
+    }
+
+    args.allocator.options()
+}
+
+fn lock_outputs(args: &Generate) -> Result<Option<OutputLock>, Error> {
+    if args.no_lock {
+        return Ok(None);
+    }
+
+    OutputLock::acquire(
+        [
+            args.atlas_output.as_deref(),
+            Some(args.metadata_output.as_path()),
+            args.layout_svg.as_deref(),
+            args.palette_report.as_deref(),
+            args.usage_report.as_deref(),
+            args.gpu_report.as_deref(),
+            args.hash_manifest.as_deref(),
+            args.provenance.as_deref(),
+            args.contact_sheet.as_deref(),
+            args.css_output.as_deref(),
+            args.html_preview.as_deref(),
+            args.codegen_output.as_deref(),
+        ]
+        .into_iter()
+        .flatten(),
+        Duration::from_secs(args.lock_timeout),
+    )
+    .map(Some)
+}
+
+// What generate packs, decoded and keyed, with what the inputs said about themselves
+struct Inputs {
+    images: Vec<(PathBuf, DynamicImage)>,
+    timings: HashMap<PathBuf, animation::Timing>,
+    nine_slices: HashMap<PathBuf, NineSlice>,
+    key_tags: keys::Tags,
+}
+
+// Animations come apart into their frames and nine-patches lose their border here, every other
+// input is one image
+fn load_inputs(
+    args: &mut Generate,
+    remote_files: &[(PathBuf, PathBuf)],
+    limits: Option<&DecodeLimits>,
+) -> Result<Inputs, Error> {
+    let (keys, mut key_tags) = KeyNaming {
+        format: args.key_format,
+        strip_prefix: args.strip_prefix.as_deref(),
+        template: args.key_template.as_ref(),
+        command: args.key_command.as_deref(),
+    }
+    .assign(&args.files)?;
+
+    let mut timings = HashMap::new();
+    let mut nine_slices = HashMap::new();
+
+    let mut loaded_inputs = std::mem::take(&mut args.files)
+        .into_iter()
+        .zip(keys)
+        .map(|(file, key)| {
+            let image = match animation::open(&file, limits).input_context(&file)? {
+                Some(Decoded::Animation(frames)) => {
+                    return Ok(frames
+                        .into_iter()
+                        .enumerate()
+                        .map(|(index, frame)| {
+                            let frame_key = animation::frame_key(&key, index);
+                            timings.insert(frame_key.clone(), frame.timing);
+
+                            if let Some(tags) = key_tags.get(&key).cloned() {
+                                key_tags.insert(frame_key.clone(), tags);
+                            }
+
+                            (frame_key, frame.image)
+                        })
+                        .collect());
                 }
+                Some(Decoded::Still(image)) => image,
+                None => decode::open(&file, limits).input_context(&file)?,
+            };
 
-                atlas.save(&atlas_output).unwrap();
+            if nine_slice::is_nine_patch(&file) {
+                let (image, slice) = nine_slice::strip(&image).input_context(&file)?;
+                nine_slices.insert(key.clone(), slice);
 
-                fs::write(
-                    metadata_output,
-                    serde_json::to_string_pretty(&fragments).unwrap(),
-                )
-                .unwrap();
+                return Ok(vec![(key, image)]);
+            }
+
+            Ok(vec![(key, image)])
+        })
+        .collect::<Vec<_>>();
+
+    loaded_inputs.extend(
+        args.sub_atlas
+            .chunks_exact(2)
+            .map(|pair| subatlas::load(&pair[0], &pair[1], limits)),
+    );
+
+    if args.stdin {
+        let stdin = Path::new("<stdin>");
+
+        match anonymous::read_png_stream(std::io::stdin().lock()) {
+            Ok(files) => {
+                loaded_inputs.extend(files.into_iter().enumerate().map(|(index, bytes)| {
+                    let image = decode::from_bytes(bytes, limits).map_err(|message| {
+                        Error::input(stdin, format!("image {index}: {message}"))
+                    })?;
+
+                    Ok(vec![(
+                        anonymous::content_key(&args.key_prefix, &image),
+                        image,
+                    )])
+                }));
             }
+            Err(message) => loaded_inputs.push(Err(Error::input(stdin, message))),
         }
-    } else {
-        println!("No command specified");
     }
+
+    loaded_inputs.extend(remote_files.iter().map(|(key, file)| {
+        Ok(vec![(
+            key.clone(),
+            decode::open(file, limits).input_context(file)?,
+        )])
+    }));
+
+    loaded_inputs.extend(args.compose.iter().map(|composition| {
+        let open = |path: &Path| {
+            decode::open(path, limits)
+                .input_context(path)
+                .map(|image| image.to_rgba8())
+        };
+
+        let base = open(&composition.base)?;
+        let layers = error::collect(composition.layers.iter().map(|layer| open(&layer.path)))?;
+
+        Ok(vec![(
+            PathBuf::from(&composition.key),
+            DynamicImage::ImageRgba8(composition.render(&base, &layers)),
+        )])
+    }));
+
+    loaded_inputs.extend(
+        std::mem::take(&mut args.generate)
+            .into_iter()
+            .map(|placeholder| {
+                if let Some(limits) = limits {
+                    limits
+                        .check_dimensions(placeholder.width, placeholder.height)
+                        .map_err(|message| Error::input(&placeholder.name, message))?;
+                }
+
+                Ok(vec![(
+                    PathBuf::from(&placeholder.name),
+                    image::DynamicImage::ImageRgba8(placeholder.render()),
+                )])
+            }),
+    );
+
+    let images = error::collect(loaded_inputs)?
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+
+    Ok(Inputs {
+        images,
+        timings,
+        nine_slices,
+        key_tags,
+    })
+}
+
+// The channel and alpha threshold each processed sprite got, for its metadata
+struct Processed {
+    channels: HashMap<PathBuf, Channel>,
+    alpha_thresholds: HashMap<PathBuf, u8>,
+}
+
+// Palette checks, channel extraction, masks, alpha thresholds and dithering change the pixels in
+// place
+fn process_images(
+    args: &Generate,
+    images: &mut [(PathBuf, DynamicImage)],
+    nine_slices: &mut HashMap<PathBuf, NineSlice>,
+    warnings: &mut Warnings,
+    limits: Option<&DecodeLimits>,
+) -> Result<Processed, Error> {
+    if let Some(palette) = &args.palette {
+        let palette = Palette::from_image(&decode::open(palette, limits).input_context(palette)?);
+        let mut reports = HashMap::new();
+
+        for (file_path, image) in images.iter_mut() {
+            let report = palette.report(image);
+
+            if !report.outside_palette.is_empty() {
+                warnings.emit(
+                    Warning::OutsidePalette,
+                    format!(
+                        "{} uses {} colors outside the palette",
+                        file_path.display(),
+                        report.outside_palette.len()
+                    ),
+                );
+
+                if args.remap_to_palette {
+                    *image = palette.remap(image);
+                }
+            }
+
+            reports.insert(file_path.clone(), report);
+        }
+
+        if let Some(palette_report) = &args.palette_report {
+            fs::write(
+                palette_report,
+                serde_json::to_string_pretty(&reports).unwrap(),
+            )
+            .output_context(palette_report)?;
+        }
+    }
+
+    // A channel is extracted before the mask cuts it out, so a mask applies to the mask it made
+    let mut channels = HashMap::new();
+
+    for (file_path, image) in images.iter_mut() {
+        if let Some(extract) = args
+            .extract_channel
+            .iter()
+            .rev()
+            .find(|extract| extract.includes(file_path))
+        {
+            *image = mask::extract(image, extract.channel);
+            channels.insert(file_path.clone(), extract.channel);
+        }
+
+        if let Some(mask) = args.mask.iter().rev().find(|mask| mask.includes(file_path)) {
+            let mask_image = decode::open(&mask.path, limits).input_context(&mask.path)?;
+
+            *image = mask::apply(image, &mask_image).map_err(|message| {
+                Error::input(&*file_path, format!("{}: {message}", mask.path.display()))
+            })?;
+        }
+    }
+
+    // Later --alpha-threshold arguments override earlier ones, so a global default can come first
+    let mut alpha_thresholds = HashMap::new();
+
+    for (file_path, image) in images.iter_mut() {
+        if let Some(alpha_threshold) = args
+            .alpha_threshold
+            .iter()
+            .rev()
+            .find(|alpha_threshold| alpha_threshold.includes(file_path))
+        {
+            *image = alpha::binarize(image, alpha_threshold.threshold);
+            alpha_thresholds.insert(file_path.clone(), alpha_threshold.threshold);
+        }
+    }
+
+    // Given slices override the ones read from a nine-patch border, later ones the earlier ones
+    for (file_path, image) in images.iter() {
+        if let Some(nine_slice) = args
+            .nine_slice
+            .iter()
+            .rev()
+            .find(|nine_slice| nine_slice.includes(file_path))
+        {
+            nine_slices.insert(file_path.clone(), nine_slice.slice);
+        }
+
+        if let Some(slice) = nine_slices.get(file_path) {
+            if !slice.fits(image.width(), image.height()) {
+                return Err(Error::input(
+                    file_path,
+                    format!(
+                        "nine-slice insets {},{},{},{} don't fit the {}x{} image",
+                        slice.left,
+                        slice.right,
+                        slice.top,
+                        slice.bottom,
+                        image.width(),
+                        image.height()
+                    ),
+                ));
+            }
+        }
+    }
+
+    if let Some(dither_alpha) = args.dither_alpha {
+        for (_, image) in images.iter_mut() {
+            *image = dither::dither(image, dither_alpha);
+        }
+    }
+
+    Ok(Processed {
+        channels,
+        alpha_thresholds,
+    })
+}
+
+// Sprites as they were placed, each with the unrotated image it was packed from
+struct Packed {
+    key: PathBuf,
+    image: DynamicImage,
+    placement: pack::Placement,
+}
+
+// Places every sprite in the order given, a lower priority never goes on a page before the last
+// one a higher priority used. Sprites are only drawn when there's a `tileable` to draw them with
+fn pack_sprites(
+    mut packer: Packer<PageClass>,
+    images: Vec<(PathBuf, DynamicImage)>,
+    class_of: impl Fn(&Path, &DynamicImage) -> PageClass,
+    priority_of: impl Fn(&Path) -> Option<i32>,
+    tileable: Option<impl Fn(&Path) -> bool>,
+    (width, height): (u32, u32),
+) -> Result<(Vec<Page<PageClass>>, Vec<Packed>), Error> {
+    let mut floor = None;
+    let mut packed = Vec::new();
+
+    for (key, image) in images {
+        interrupt::check()?;
+
+        let priority = priority_of(&key);
+
+        if floor != priority {
+            packer.raise_floor();
+            floor = priority;
+        }
+
+        let placement = packer
+            .place(image.width(), image.height(), class_of(&key, &image))
+            .ok_or_else(|| does_not_fit(&key, &image, width, height))?;
+
+        if let Some(tileable) = &tileable {
+            if placement.rotated {
+                packer.draw(&placement, &image.rotate90(), tileable(&key));
+            } else {
+                packer.draw(&placement, &image, tileable(&key));
+            }
+        }
+
+        packed.push(Packed {
+            key,
+            image,
+            placement,
+        });
+    }
+
+    Ok((packer.into_pages(), packed))
+}
+
+// A lone page is written to the atlas output itself, more pages get numbered next to it
+fn write_pages(
+    pages: Vec<Page<PageClass>>,
+    atlas_output: &Path,
+    layout: Layout,
+    sdf: Option<Sdf>,
+) -> Result<Vec<PathBuf>, Error> {
+    match layout {
+        Layout::Atlas if pages.len() == 1 => {
+            save_page(&pages[0].image, atlas_output, sdf).output_context(atlas_output)?;
+
+            Ok(vec![atlas_output.to_path_buf()])
+        }
+        Layout::Atlas => error::collect(pages.iter().enumerate().map(|(index, page)| {
+            let page_output = metadata::page_path(atlas_output, index as u32);
+
+            save_page(&page.image, &page_output, sdf).output_context(&page_output)?;
+
+            Ok(page_output)
+        })),
+        Layout::Array => {
+            let layers = pages.into_iter().map(|page| page.image).collect::<Vec<_>>();
+
+            ktx2::write(atlas_output, &layers).output_context(atlas_output)?;
+
+            Ok(vec![atlas_output.to_path_buf()])
+        }
+    }
+}
+
+fn does_not_fit(key: &Path, image: &DynamicImage, width: u32, height: u32) -> Error {
+    Error::Packing(format!(
+        "{} ({}x{}) does not fit in an empty {width}x{height} page",
+        key.display(),
+        image.width(),
+        image.height()
+    ))
+}
+
+fn used_area(placements: &[Placement], page: usize) -> metadata::Rectangle {
+    let (left, top, right, bottom) = placements
+        .iter()
+        .filter(|placement| placement.page == page)
+        .fold(
+            (u32::MAX, u32::MAX, 0, 0),
+            |(left, top, right, bottom), placement| {
+                (
+                    left.min(placement.x),
+                    top.min(placement.y),
+                    right.max(placement.x + placement.width),
+                    bottom.max(placement.y + placement.height),
+                )
+            },
+        );
+
+    if left > right {
+        return metadata::Rectangle {
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 0,
+        };
+    }
+
+    metadata::Rectangle {
+        x: left,
+        y: top,
+        width: right - left,
+        height: bottom - top,
+    }
+}
+
+fn view_output_path(metadata_output: &Path, view: &str) -> PathBuf {
+    let mut file_name = metadata_output
+        .file_stem()
+        .unwrap_or_default()
+        .to_os_string();
+    file_name.push(".");
+    file_name.push(view);
+
+    if let Some(extension) = metadata_output.extension() {
+        file_name.push(".");
+        file_name.push(extension);
+    }
+
+    metadata_output.with_file_name(file_name)
+}
+
+fn rename_with_hash(path: &Path) -> Result<PathBuf, Error> {
+    let hash = sha256::hex_digest(&fs::read(path).output_context(path)?);
+
+    let mut file_name = path.file_stem().unwrap_or_default().to_os_string();
+    file_name.push(".");
+    file_name.push(&hash[..8]);
+
+    if let Some(extension) = path.extension() {
+        file_name.push(".");
+        file_name.push(extension);
+    }
+
+    let hashed = path.with_file_name(file_name);
+
+    fs::rename(path, &hashed).output_context(&hashed)?;
+
+    Ok(hashed)
 }
 
 #[derive(Parser)]
@@ -127,46 +2014,860 @@ fn main() {
 struct Cli {
     #[command(subcommand)]
     command: Option<Command>,
+    // Checked before any command runs, so a team can pin the version in its build scripts
+    #[arg(long, global = true, value_name = "REQ")]
+    required_version: Option<VersionReq>,
 }
 
 #[derive(Subcommand, Clone)]
+#[allow(clippy::large_enum_variant)]
 enum Command {
-    Generate {
-        #[arg(short, long, num_args = 1..)]
-        files: Vec<PathBuf>,
-        #[arg(short, long)]
-        atlas_output: PathBuf,
+    Generate(Generate),
+    Merge {
+        #[arg(num_args = 2.., value_names = ["ATLAS", "METADATA"], required = true)]
+        shards: Vec<PathBuf>,
+        #[command(flatten)]
+        args: Generate,
+    },
+    Stats {
+        history: PathBuf,
+        #[arg(long, default_value_t = 20)]
+        last: usize,
+    },
+    Overlap {
+        #[arg(num_args = 4.., value_names = ["ATLAS", "METADATA"], required = true)]
+        atlases: Vec<PathBuf>,
+    },
+    Rename {
+        #[arg(long)]
+        map: PathBuf,
+        metadata: PathBuf,
         #[arg(short, long)]
-        metadata_output: PathBuf,
+        output: Option<PathBuf>,
+        #[arg(long)]
+        keep_aliases: bool,
+    },
+    Plan {
+        #[arg(short, long, num_args = 1.., required = true)]
+        files: Vec<PathBuf>,
+        #[arg(long, value_enum, default_value_t = AlgorithmArg(Algorithm::Etagere))]
+        algorithm: AlgorithmArg,
+        #[command(flatten)]
+        allocator: AllocatorArgs,
+        #[arg(long, default_value_t = 2048)]
+        max_texture_size: u32,
+        #[arg(long, default_value_t = 1)]
+        max_pages: u32,
+        #[arg(long, value_name = "W:H", default_value = "1:1")]
+        aspect_ratio: AspectRatio,
+    },
+    Stress {
+        #[arg(short, long, num_args = 1.., required = true)]
+        files: Vec<PathBuf>,
+        #[arg(long, value_enum, default_value_t = AlgorithmArg(Algorithm::Etagere))]
+        algorithm: AlgorithmArg,
+        #[command(flatten)]
+        allocator: AllocatorArgs,
         #[arg(long)]
         width: u32,
         #[arg(long)]
         height: u32,
-        #[arg(long, value_enum, default_value_t = Algorithm::Etagere)]
-        algorithm: Algorithm,
+        #[arg(long, default_value_t = 100)]
+        seeds: u64,
+    },
+    Examples {
+        directory: PathBuf,
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+    },
+    Init {
+        directory: PathBuf,
+    },
+    // Serves pack, append, inspect and unpack as JSON-RPC over a Unix socket
+    #[cfg(unix)]
+    Daemon {
+        #[arg(long, value_name = "PATH", default_value = ".atlas.sock")]
+        socket: PathBuf,
+    },
+    ImportTps {
+        project: PathBuf,
+    },
+    Convert {
+        input: PathBuf,
+        output: PathBuf,
+        #[arg(long, value_enum, default_value_t = Source::Atlas)]
+        from: Source,
+        #[arg(long)]
+        to: Target,
+        // The page images, atlas's own metadata doesn't name them
+        #[arg(long)]
+        atlas: Option<PathBuf>,
+    },
+    Diff {
+        old_atlas: PathBuf,
+        old_metadata: PathBuf,
+        new_atlas: PathBuf,
+        new_metadata: PathBuf,
+        #[arg(long)]
+        pixels: bool,
+        #[arg(long, default_value_t = 0.01)]
+        threshold: f64,
+        #[arg(long)]
+        overlay: Option<PathBuf>,
+        #[arg(long, value_enum, default_value_t = OverlayStyle::Default, requires = "overlay")]
+        overlay_style: OverlayStyle,
+        #[arg(long)]
+        events: Option<PathBuf>,
+    },
+    #[command(group(ArgGroup::new("distance_field").args(["sdf", "msdf"])))]
+    GenerateFont {
+        #[arg(long)]
+        font: PathBuf,
+        #[arg(long, value_name = "PIXELS")]
+        size: f32,
+        #[arg(long, value_delimiter = ',', default_value = "32-126")]
+        ranges: Vec<CodepointRange>,
+        #[arg(long, value_enum, default_value_t = AlgorithmArg(Algorithm::Etagere))]
+        algorithm: AlgorithmArg,
+        #[command(flatten)]
+        allocator: AllocatorArgs,
+        #[arg(long, default_value_t = 512)]
+        width: u32,
+        #[arg(long, default_value_t = 512)]
+        height: u32,
+        #[arg(long, value_name = "PIXELS", default_value_t = 1)]
+        padding: u32,
+        #[arg(long)]
+        sdf: bool,
+        #[arg(long, conflicts_with = "sdf_channels")]
+        msdf: bool,
+        #[arg(
+            long,
+            value_name = "PIXELS",
+            default_value_t = 8,
+            requires = "distance_field",
+            value_parser = clap::value_parser!(u32).range(1..)
+        )]
+        sdf_spread: u32,
+        #[arg(long, value_enum, default_value_t = SdfChannels::Rgba, requires = "sdf")]
+        sdf_channels: SdfChannels,
+        #[arg(short, long)]
+        atlas_output: PathBuf,
+        #[arg(short, long)]
+        metadata_output: PathBuf,
     },
 }
 
+#[derive(Args, Clone)]
+struct Generate {
+    // The arguments the provenance hashes when they're not the process's own, as for the daemon
+    #[arg(skip)]
+    invocation: Option<Vec<String>>,
+    #[arg(short, long, num_args = 1..)]
+    files: Vec<PathBuf>,
+    #[arg(long)]
+    recursive: bool,
+    #[arg(long, value_name = "PATTERN")]
+    exclude: Vec<String>,
+    #[arg(long, num_args = 2, value_names = ["ATLAS", "METADATA"])]
+    sub_atlas: Vec<PathBuf>,
+    #[arg(long)]
+    stdin: bool,
+    #[arg(long, default_value = "", requires = "stdin")]
+    key_prefix: String,
+    #[arg(long, value_enum, default_value_t = KeyFormat::Path)]
+    key_format: KeyFormat,
+    #[arg(long, value_name = "PREFIX")]
+    strip_prefix: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        required_if_eq("key_format", "template")
+    )]
+    key_template: Option<KeyTemplate>,
+    #[arg(long, value_name = "PROGRAM")]
+    key_command: Option<PathBuf>,
+    #[arg(long, value_name = "NAME=WxH[:STYLE]")]
+    generate: Vec<Placeholder>,
+    #[arg(long, value_name = "KEY=BASE+OVERLAY[@X,Y][:MODE]")]
+    compose: Vec<Composition>,
+    #[arg(long, value_name = "URL[#sha256=HEX]", requires = "allow_curl")]
+    remote: Vec<Remote>,
+    /// Lets --remote run the system's curl to download its URLs, atlas has no HTTP client of its
+    /// own and runs no other program for them
+    #[arg(long)]
+    allow_curl: bool,
+    #[arg(long, value_name = "DIRECTORY", default_value = ".atlas-cache")]
+    remote_cache: PathBuf,
+    #[arg(long, value_name = "KEY=PARENT@X,Y,WxH")]
+    region: Vec<Region>,
+    #[arg(long, value_name = "I/N")]
+    shard: Option<Shard>,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = SortOrder::AreaDesc,
+        conflicts_with_all = ["shuffle", "compression_order"]
+    )]
+    sort: SortOrder,
+    #[arg(long, value_name = "SEED")]
+    shuffle: Option<u64>,
+    #[arg(long, conflicts_with = "shuffle")]
+    compression_order: bool,
+    #[arg(long)]
+    dedupe: bool,
+    #[arg(short, long, required_unless_present = "layout_svg")]
+    atlas_output: Option<PathBuf>,
+    #[arg(short, long)]
+    metadata_output: PathBuf,
+    #[arg(long, value_enum, default_value_t = MetadataFormat::Json)]
+    metadata_format: MetadataFormat,
+    #[arg(long)]
+    lod_chains: bool,
+    #[arg(long, value_name = "NAME=PATTERN[,PATTERN...]")]
+    view: Vec<View>,
+    #[arg(long, value_name = "LOCALE", value_parser = view::output_name)]
+    locale: Vec<String>,
+    #[arg(long, requires = "locale")]
+    locale_pages: bool,
+    #[arg(long)]
+    layout_svg: Option<PathBuf>,
+    #[arg(long)]
+    usage_report: Option<PathBuf>,
+    #[arg(long, requires = "atlas_output")]
+    gpu_report: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "PIXELS",
+        requires = "atlas_output",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    tile_size: Option<u32>,
+    #[arg(long, requires = "atlas_output")]
+    contact_sheet: Option<PathBuf>,
+    #[arg(long, requires = "atlas_output", conflicts_with = "allow_rotation")]
+    css_output: Option<PathBuf>,
+    #[arg(long, requires = "css_output")]
+    html_preview: Option<PathBuf>,
+    #[arg(long, requires = "codegen_output")]
+    codegen: Option<Codegen>,
+    #[arg(long, requires = "codegen")]
+    codegen_output: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "DIRECTORY",
+        requires = "atlas_output",
+        conflicts_with_all = ["allow_rotation", "hash_names"]
+    )]
+    godot_output: Option<PathBuf>,
+    #[arg(long)]
+    hash_names: bool,
+    #[arg(long, requires = "hash_names")]
+    hash_manifest: Option<PathBuf>,
+    #[arg(long)]
+    provenance: Option<PathBuf>,
+    /// Writes an HMAC-SHA256 of the provenance document to <PROVENANCE>.mac, keyed with this
+    /// file's bytes as they are. This is a shared-secret MAC, not a public-key signature: whoever
+    /// can check it can also forge it
+    #[arg(long, value_name = "KEY_FILE", requires = "provenance")]
+    mac_key: Option<PathBuf>,
+    #[arg(long)]
+    stats_history: Option<PathBuf>,
+    #[arg(long)]
+    no_lock: bool,
+    #[arg(long, value_name = "WARNING")]
+    allow: Vec<Lint>,
+    #[arg(long, value_name = "WARNING")]
+    deny: Vec<Lint>,
+    #[arg(long, value_name = "SECONDS", default_value_t = 300)]
+    lock_timeout: u64,
+    // Only the given inputs are read, nothing that runs programs or opens other paths
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "stats_history",
+            "key_command",
+            "remote",
+            "allow_curl",
+            "mask",
+            "palette",
+            "compose",
+            "sub_atlas",
+        ]
+    )]
+    untrusted: bool,
+    #[arg(
+        long,
+        value_name = "PIXELS",
+        default_value_t = 8192,
+        requires = "untrusted"
+    )]
+    max_input_dimension: u32,
+    #[arg(long, value_name = "BYTES", default_value_t = 256 * 1024 * 1024, requires = "untrusted")]
+    max_decoded_bytes: u64,
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 10,
+        requires = "untrusted"
+    )]
+    decode_timeout: u64,
+    #[arg(
+        long,
+        required_unless_present = "auto_size",
+        conflicts_with = "auto_size"
+    )]
+    width: Option<u32>,
+    #[arg(
+        long,
+        required_unless_present = "auto_size",
+        conflicts_with = "auto_size"
+    )]
+    height: Option<u32>,
+    #[arg(long)]
+    auto_size: bool,
+    #[arg(long, default_value_t = 4096, requires = "auto_size")]
+    max_width: u32,
+    #[arg(long, default_value_t = 4096, requires = "auto_size")]
+    max_height: u32,
+    #[arg(
+        long,
+        value_name = "W:H",
+        requires = "auto_size",
+        conflicts_with_all = ["square", "power_of_two"]
+    )]
+    aspect_ratio: Option<AspectRatio>,
+    #[arg(long)]
+    power_of_two: bool,
+    #[arg(long)]
+    square: bool,
+    #[arg(long, value_enum, default_value_t = AlgorithmSelection::Fixed(Algorithm::Etagere))]
+    algorithm: AlgorithmSelection,
+    #[command(flatten)]
+    allocator: AllocatorArgs,
+    #[arg(long, value_name = "PIXELS", default_value_t = 0)]
+    padding: u32,
+    #[arg(long, value_name = "PIXELS", default_value_t = 0)]
+    border: u32,
+    #[arg(long, value_name = "PIXELS", default_value_t = 0)]
+    extrude: u32,
+    #[arg(long, value_enum, default_value_t = Layout::Atlas)]
+    layout: Layout,
+    #[arg(long)]
+    snap_pot_up: bool,
+    #[arg(long)]
+    allow_rotation: bool,
+    #[arg(long)]
+    split_opaque: bool,
+    #[arg(long, value_name = "PATTERN")]
+    volatile: Vec<String>,
+    #[arg(long, value_name = "[PATTERN=]QUALITY")]
+    quality: Vec<QualityArg>,
+    #[arg(long, value_name = "[PATTERN=]N", allow_hyphen_values = true)]
+    priority: Vec<Priority>,
+    #[arg(long, value_enum)]
+    uv_mode: Option<UvMode>,
+    #[arg(long, value_enum, default_value_t = Rounding::Floor)]
+    rounding: Rounding,
+    #[arg(long, value_enum, default_value_t = Origin::TopLeft)]
+    origin: Origin,
+    #[arg(long)]
+    flip_y: bool,
+    #[arg(long, value_name = "BYTES", requires = "atlas_output")]
+    max_output_size: Option<u64>,
+    #[arg(long, value_name = "PCT")]
+    min_occupancy: Option<f32>,
+    #[arg(long, value_name = "N")]
+    max_pages: Option<u32>,
+    #[arg(long, value_name = "[PATTERN=]N")]
+    alpha_threshold: Vec<AlphaThreshold>,
+    #[arg(long, value_name = "[PATTERN=]CHANNEL")]
+    extract_channel: Vec<ChannelArg>,
+    #[arg(long, value_name = "PATTERN=MASK")]
+    mask: Vec<MaskArg>,
+    #[arg(long, value_enum, conflicts_with = "alpha_threshold")]
+    dither_alpha: Option<DitherPattern>,
+    #[arg(long, conflicts_with_all = ["dither_alpha", "collision"])]
+    sdf: bool,
+    #[arg(
+        long,
+        value_name = "PIXELS",
+        default_value_t = 8,
+        requires = "sdf",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    sdf_spread: u32,
+    #[arg(long, value_enum, default_value_t = SdfChannels::Rgba, requires = "sdf")]
+    sdf_channels: SdfChannels,
+    #[arg(long)]
+    trim: bool,
+    #[arg(long, value_name = "PATTERN")]
+    tileable: Vec<String>,
+    #[arg(long, value_name = "[PATTERN=]L,R,T,B")]
+    nine_slice: Vec<NineSliceArg>,
+    #[arg(long, value_name = "[PATTERN=]HINT[,HINT...]")]
+    sampling: Vec<SamplingArg>,
+    #[arg(long, value_enum)]
+    collision: Option<CollisionShape>,
+    #[arg(long, default_value_t = 1.0)]
+    collision_tolerance: f32,
+    #[arg(long)]
+    palette: Option<PathBuf>,
+    #[arg(long, requires = "palette")]
+    palette_report: Option<PathBuf>,
+    #[arg(long, requires = "palette")]
+    remap_to_palette: bool,
+}
+
+#[derive(Copy, Clone)]
+enum AlgorithmSelection {
+    Auto,
+    Fixed(Algorithm),
+}
+
+impl ValueEnum for AlgorithmSelection {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[
+            AlgorithmSelection::Auto,
+            AlgorithmSelection::Fixed(Algorithm::Etagere),
+            AlgorithmSelection::Fixed(Algorithm::Guillotiere),
+            AlgorithmSelection::Fixed(Algorithm::MaxRects),
+        ]
+    }
+
+    fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
+        match self {
+            AlgorithmSelection::Auto => Some(clap::builder::PossibleValue::new("auto")),
+            AlgorithmSelection::Fixed(algorithm) => AlgorithmArg(*algorithm).to_possible_value(),
+        }
+    }
+}
+
+// The library doesn't depend on clap, its enums become values through these wrappers
+#[derive(Copy, Clone)]
+struct AlgorithmArg(Algorithm);
+
+impl ValueEnum for AlgorithmArg {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[
+            AlgorithmArg(Algorithm::Etagere),
+            AlgorithmArg(Algorithm::Guillotiere),
+            AlgorithmArg(Algorithm::MaxRects),
+        ]
+    }
+
+    fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
+        Some(clap::builder::PossibleValue::new(self.0.name()))
+    }
+}
+
+#[derive(Copy, Clone)]
+struct HeuristicArg(MaxRectsHeuristic);
+
+impl ValueEnum for HeuristicArg {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[
+            HeuristicArg(MaxRectsHeuristic::BestShortSideFit),
+            HeuristicArg(MaxRectsHeuristic::BestAreaFit),
+        ]
+    }
+
+    fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
+        Some(clap::builder::PossibleValue::new(self.0.name()))
+    }
+}
+
+#[derive(Args, Clone)]
+struct AllocatorArgs {
+    #[arg(long, value_enum, default_value_t = HeuristicArg(MaxRectsHeuristic::BestShortSideFit))]
+    max_rects_heuristic: HeuristicArg,
+    #[arg(long, value_name = "PIXELS", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    alignment: u32,
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    etagere_columns: u32,
+    #[arg(long)]
+    etagere_vertical_shelves: bool,
+    #[arg(long, value_name = "PIXELS", default_value_t = 32)]
+    small_size_threshold: u32,
+    #[arg(long, value_name = "PIXELS", default_value_t = 256)]
+    large_size_threshold: u32,
+}
+
+impl AllocatorArgs {
+    fn options(&self) -> Result<AllocatorOptions, Error> {
+        if self.small_size_threshold > self.large_size_threshold {
+            return Err(usage(
+                ErrorKind::ValueValidation,
+                "--small-size-threshold must not be larger than --large-size-threshold",
+            ));
+        }
+
+        Ok(AllocatorOptions {
+            max_rects_heuristic: self.max_rects_heuristic.0,
+            alignment: self.alignment,
+            etagere_columns: self.etagere_columns,
+            etagere_vertical_shelves: self.etagere_vertical_shelves,
+            small_size_threshold: self.small_size_threshold,
+            large_size_threshold: self.large_size_threshold,
+        })
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum UvMode {
+    Edges,
+    Centers,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum Origin {
+    TopLeft,
+    Center,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum Rounding {
+    Floor,
+    Exact,
+}
+
+impl Rounding {
+    fn center(self, allocation: &Allocation, width: u32, height: u32) -> Vector2 {
+        match self {
+            Rounding::Floor => allocation.center(width, height),
+            Rounding::Exact => Vector2::new(
+                allocation.x as f32 + width as f32 / 2.0,
+                allocation.y as f32 + height as f32 / 2.0,
+            ),
+        }
+    }
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
-enum Algorithm {
-    Etagere,
-    Guillotiere,
+enum Layout {
+    Atlas,
+    Array,
+}
+
+struct Placement {
+    key: PathBuf,
+    page: usize,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+// How the fragments were written, so readers don't have to guess at half-texel offsets
+#[derive(Serialize)]
+struct Meta {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uv_mode: Option<UvMode>,
+    rounding: Rounding,
+    // Where centers are measured from, and whether y grows upwards from there
+    origin: Origin,
+    flip_y: bool,
+    // Which way rotated fragments are turned, only written when rotation was allowed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rotation: Option<export::Rotation>,
+    // With --snap-pot-up the pages are larger than what's packed on them, with --split-opaque
+    // they say whether they need blending, with --volatile which ones change often and with
+    // --quality what to encode them at
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pages: Vec<PageArea>,
 }
 
 #[derive(Serialize)]
+struct PageArea {
+    page: usize,
+    width: u32,
+    height: u32,
+    used: metadata::Rectangle,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    blend: Option<Blend>,
+    #[serde(skip_serializing_if = "is_false")]
+    volatile: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    quality: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    locale: Option<String>,
+}
+
+// What sprites have to share to go on the same page
+#[derive(Copy, Clone, PartialEq, Eq, Default)]
+struct PageClass {
+    blend: Option<Blend>,
+    // Kept apart so updating them only touches their pages in a patch
+    volatile: bool,
+    // Lossy encoders work per page, so sprites wanting different qualities can't share one
+    quality: Option<u8>,
+    // An index into --locale, shared sprites don't have one
+    locale: Option<usize>,
+}
+
+struct Budget {
+    max_output_size: Option<u64>,
+    min_occupancy: Option<f32>,
+    max_pages: Option<u32>,
+}
+
+impl Budget {
+    fn check(
+        &self,
+        atlas_outputs: &[PathBuf],
+        fragments: &HashMap<PathBuf, Fragment>,
+        width: u32,
+        height: u32,
+        pages: u32,
+    ) -> Result<Vec<String>, Error> {
+        let mut violations = Vec::new();
+
+        if let Some(max_output_size) = self.max_output_size {
+            let output_size = output_size(atlas_outputs)?;
+
+            if output_size > max_output_size {
+                violations.push(format!(
+                    "atlas output is {output_size} bytes, exceeding the maximum of {max_output_size} bytes"
+                ));
+            }
+        }
+
+        if let Some(min_occupancy) = self.min_occupancy {
+            let occupancy = occupancy(fragments, width, height, pages);
+
+            if occupancy < min_occupancy {
+                violations.push(format!(
+                    "atlas occupancy is {occupancy:.2}%, below the minimum of {min_occupancy}%"
+                ));
+            }
+        }
+
+        if let Some(max_pages) = self.max_pages {
+            if pages > max_pages {
+                violations.push(format!(
+                    "atlas uses {pages} pages, exceeding the maximum of {max_pages} pages"
+                ));
+            }
+        }
+
+        Ok(violations)
+    }
+}
+
+fn occupancy(fragments: &HashMap<PathBuf, Fragment>, width: u32, height: u32, pages: u32) -> f32 {
+    let total_area = width as f64 * height as f64 * pages as f64;
+
+    (used_sprite_area(fragments) as f64 / total_area * 100.0) as f32
+}
+
+// Whatever a run writes has to describe the allocator's rectangles: the centers once --origin
+// and --flip-y are undone, and every engine format once its own corner and rotation are. A
+// mismatch is a bug in a writer, so it fails the run before any metadata is written
+fn check_frames(
+    fragments: &HashMap<&PathBuf, &Fragment>,
+    origin: Origin,
+    flip_y: bool,
+    metadata_format: MetadataFormat,
+    godot: bool,
+    page_width: u32,
+    page_height: u32,
+) -> Result<(), Error> {
+    let mismatch = |format: &str, key: &Path, read: metadata::Rectangle, frame| {
+        let describe = |rectangle: metadata::Rectangle| {
+            format!(
+                "{}x{} at {},{}",
+                rectangle.width, rectangle.height, rectangle.x, rectangle.y
+            )
+        };
+
+        Err(Error::Packing(format!(
+            "{format} describes {} as {}, but it was packed as {}",
+            key.display(),
+            describe(read),
+            describe(frame)
+        )))
+    };
+
+    for (key, fragment) in fragments {
+        let center =
+            metadata::image_center(fragment.center, origin, flip_y, page_width, page_height);
+        let read =
+            metadata::packed_rectangle(center, fragment.size, fragment.rotated == Some(true));
+
+        if read != fragment.frame {
+            return mismatch("the metadata", key, read, fragment.frame);
+        }
+    }
+
+    let mut formats = Vec::new();
+
+    if metadata_format.is_export() {
+        formats.push((
+            metadata_format
+                .to_possible_value()
+                .unwrap()
+                .get_name()
+                .to_string(),
+            metadata_format.origin(),
+            metadata_format.rotation(),
+        ));
+    }
+
+    if godot {
+        formats.push((
+            "godot".to_string(),
+            export::godot::ORIGIN,
+            export::godot::ROTATION,
+        ));
+    }
+
+    if formats.is_empty() {
+        return Ok(());
+    }
+
+    for (key, packed) in export::fragments(fragments) {
+        for (format, origin, rotation) in &formats {
+            let placement = export::placement(&packed, page_height, *origin, *rotation);
+            let read = export::frame(&placement, page_height, *origin);
+
+            if read != packed.frame {
+                return mismatch(format, &key, read, packed.frame);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn save_page(image: &RgbaImage, path: &Path, sdf: Option<Sdf>) -> image::ImageResult<()> {
+    match sdf {
+        Some(sdf) => sdf.save(image, path),
+        None => image.save(path),
+    }
+}
+
+fn used_sprite_area(fragments: &HashMap<PathBuf, Fragment>) -> u64 {
+    fragments
+        .values()
+        .filter(|fragment| fragment.parent.is_none() && fragment.alias_of.is_none())
+        .map(|fragment| fragment.size.x as u64 * fragment.size.y as u64)
+        .sum()
+}
+
+#[derive(Clone, Serialize)]
 struct Fragment {
     center: Vector2,
     size: Vector2,
+    // Where the allocator put the packed pixels, in image space whatever --origin and --flip-y
+    // do to the center. Engine formats and tile ranges are taken from it
+    #[serde(skip)]
+    frame: metadata::Rectangle,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uv: Option<Uv>,
+    // Rotated sprites are stored turned 90 degrees clockwise, size stays the unrotated size
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rotated: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    layer: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    alias_of: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lods: Option<Vec<PathBuf>>,
+    // Packed as a gray level, see mask::extract
+    #[serde(skip_serializing_if = "Option::is_none")]
+    channel: Option<Channel>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    alpha_threshold: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dither: Option<DitherPattern>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sdf_spread: Option<u32>,
+    // In the untrimmed sprite, so a distance field's spread widens them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nine_slice: Option<NineSlice>,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    trim: Option<Trim>,
+    // Untrimmed and extruded by wrapping, so it can be sampled repeating within its frame
+    #[serde(skip_serializing_if = "is_false")]
+    tileable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sampling: Option<Sampling>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    priority: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    collision: Option<Vec<Vector2>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tiles: Option<tiles::TileRange>,
+    // Milliseconds, only frames of animated inputs have one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tags: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    locale: Option<String>,
 }
 
-#[derive(Serialize)]
-struct Vector2 {
-    x: f32,
-    y: f32,
+impl Fragment {
+    // With --flip-y v0 still belongs to the sprite's top edge, so it ends up above v1
+    fn convert(&mut self, origin: Origin, flip_y: bool, page_width: u32, page_height: u32) {
+        if origin == Origin::Center {
+            self.center.x -= page_width as f32 / 2.0;
+            self.center.y -= page_height as f32 / 2.0;
+        }
+
+        if flip_y {
+            self.center.y = match origin {
+                Origin::TopLeft => page_height as f32 - self.center.y,
+                Origin::Center => -self.center.y,
+            };
+
+            if let Some(uv) = &mut self.uv {
+                uv.v0 = 1.0 - uv.v0;
+                uv.v1 = 1.0 - uv.v1;
+            }
+        }
+    }
 }
 
-impl Vector2 {
-    fn new(x: f32, y: f32) -> Self {
-        Self { x, y }
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+#[derive(Clone, Serialize)]
+struct Uv {
+    u0: f32,
+    v0: f32,
+    u1: f32,
+    v1: f32,
+}
+
+impl Uv {
+    fn new(
+        mode: UvMode,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        page_width: u32,
+        page_height: u32,
+    ) -> Self {
+        let inset = match mode {
+            UvMode::Edges => 0.0,
+            UvMode::Centers => 0.5,
+        };
+
+        Self {
+            u0: (x as f32 + inset) / page_width as f32,
+            v0: (y as f32 + inset) / page_height as f32,
+            u1: ((x + width) as f32 - inset) / page_width as f32,
+            v1: ((y + height) as f32 - inset) / page_height as f32,
+        }
     }
 }
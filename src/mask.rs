@@ -0,0 +1,193 @@
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use image::{DynamicImage, GenericImageView, RgbaImage};
+use serde::Serialize;
+
+use crate::pattern;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Channel {
+    Red,
+    Green,
+    Blue,
+    Alpha,
+}
+
+impl Channel {
+    fn index(self) -> usize {
+        match self {
+            Channel::Red => 0,
+            Channel::Green => 1,
+            Channel::Blue => 2,
+            Channel::Alpha => 3,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ChannelArg {
+    pattern: Option<String>,
+    pub channel: Channel,
+}
+
+impl ChannelArg {
+    pub fn includes(&self, key: &Path) -> bool {
+        self.pattern
+            .as_ref()
+            .is_none_or(|pattern| pattern::matches_key(pattern, key))
+    }
+}
+
+impl FromStr for ChannelArg {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (pattern, channel) = match value.rsplit_once('=') {
+            Some((pattern, channel)) => (Some(pattern.to_string()), channel),
+            None => (None, value),
+        };
+
+        let channel = match channel {
+            "red" => Channel::Red,
+            "green" => Channel::Green,
+            "blue" => Channel::Blue,
+            "alpha" => Channel::Alpha,
+            _ => {
+                return Err(format!(
+                    "invalid channel '{channel}', expected red, green, blue or alpha"
+                ))
+            }
+        };
+
+        Ok(Self { pattern, channel })
+    }
+}
+
+#[derive(Clone)]
+pub struct MaskArg {
+    pattern: String,
+    pub path: PathBuf,
+}
+
+impl MaskArg {
+    pub fn includes(&self, key: &Path) -> bool {
+        pattern::matches_key(&self.pattern, key)
+    }
+}
+
+impl FromStr for MaskArg {
+    type Err = String;
+
+    // Patterns don't contain `=`, paths might
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.split_once('=') {
+            Some((pattern, path)) if !pattern.is_empty() && !path.is_empty() => Ok(Self {
+                pattern: pattern.to_string(),
+                path: PathBuf::from(path),
+            }),
+            _ => Err(format!("expected PATTERN=MASK, got '{value}'")),
+        }
+    }
+}
+
+// The channel becomes a gray level, alpha stays so transparent pixels still trim away
+pub fn extract(image: &DynamicImage, channel: Channel) -> DynamicImage {
+    let mut image: RgbaImage = image.to_rgba8();
+
+    for pixel in image.pixels_mut() {
+        let level = pixel.0[channel.index()];
+
+        pixel.0 = [level, level, level, pixel.0[3]];
+    }
+
+    DynamicImage::ImageRgba8(image)
+}
+
+// Alpha is scaled by the mask's coverage, its gray level times its own alpha, so both a white
+// shape on transparency and an opaque black and white mask work
+pub fn apply(image: &DynamicImage, mask: &DynamicImage) -> Result<DynamicImage, String> {
+    if image.dimensions() != mask.dimensions() {
+        return Err(format!(
+            "the {}x{} mask doesn't match the {}x{} image",
+            mask.width(),
+            mask.height(),
+            image.width(),
+            image.height()
+        ));
+    }
+
+    let mut image: RgbaImage = image.to_rgba8();
+    let mask = mask.to_luma_alpha8();
+
+    for (pixel, coverage) in image.pixels_mut().zip(mask.pixels()) {
+        let coverage = coverage.0[0] as u32 * coverage.0[1] as u32 / 255;
+
+        pixel.0[3] = (pixel.0[3] as u32 * coverage / 255) as u8;
+    }
+
+    Ok(DynamicImage::ImageRgba8(image))
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, LumaA, Rgba, RgbaImage};
+
+    use super::{apply, extract, Channel, ChannelArg, MaskArg};
+
+    fn decal() -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(2, 1, |x, _| match x {
+            0 => Rgba([200, 100, 50, 255]),
+            _ => Rgba([10, 20, 30, 128]),
+        }))
+    }
+
+    #[test]
+    fn channels_become_gray_levels() {
+        let alpha = extract(&decal(), Channel::Alpha).to_rgba8();
+
+        assert_eq!(alpha.get_pixel(0, 0), &Rgba([255, 255, 255, 255]));
+        assert_eq!(alpha.get_pixel(1, 0), &Rgba([128, 128, 128, 128]));
+
+        let green = extract(&decal(), Channel::Green).to_rgba8();
+
+        assert_eq!(green.get_pixel(0, 0), &Rgba([100, 100, 100, 255]));
+    }
+
+    #[test]
+    fn masks_scale_alpha() {
+        let mask = DynamicImage::ImageLumaA8(image::ImageBuffer::from_fn(2, 1, |x, _| match x {
+            0 => LumaA([255u8, 0]),
+            _ => LumaA([255, 255]),
+        }));
+
+        let masked = apply(&decal(), &mask).unwrap().to_rgba8();
+
+        assert_eq!(masked.get_pixel(0, 0), &Rgba([200, 100, 50, 0]));
+        assert_eq!(masked.get_pixel(1, 0), &Rgba([10, 20, 30, 128]));
+
+        assert!(apply(&decal(), &DynamicImage::new_luma8(1, 1)).is_err());
+    }
+
+    #[test]
+    fn arguments_parse() {
+        assert_eq!(
+            "decals/*=alpha".parse::<ChannelArg>().unwrap().channel,
+            Channel::Alpha
+        );
+        assert!("red"
+            .parse::<ChannelArg>()
+            .unwrap()
+            .includes("any.png".as_ref()));
+        assert!("decals/*=luma".parse::<ChannelArg>().is_err());
+
+        let mask = "ui/*=masks/round=1.png".parse::<MaskArg>().unwrap();
+
+        assert!(mask.includes("ui/icon.png".as_ref()));
+        assert_eq!(mask.path, std::path::PathBuf::from("masks/round=1.png"));
+        assert!("masks/round.png".parse::<MaskArg>().is_err());
+    }
+}
@@ -0,0 +1,152 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use atlas::META_KEY;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    error::{Context, Error},
+    Origin, Vector2,
+};
+
+#[derive(Deserialize)]
+pub struct StoredFragment {
+    pub center: Vector2,
+    pub size: Vector2,
+    #[serde(default)]
+    pub rotated: Option<bool>,
+    #[serde(default)]
+    pub layer: Option<u32>,
+    #[serde(default)]
+    pub page: Option<u32>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize)]
+pub struct Rectangle {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl StoredFragment {
+    pub fn rectangle(&self) -> Rectangle {
+        packed_rectangle(self.center, self.size, self.rotated == Some(true))
+    }
+}
+
+// The area covered on the page, which for rotated sprites is turned from their size
+pub fn packed_rectangle(center: Vector2, size: Vector2, rotated: bool) -> Rectangle {
+    let size = if rotated {
+        Vector2::new(size.y, size.x)
+    } else {
+        size
+    };
+
+    // Centers are min + size / 2, either floored or exact depending on --rounding
+    Rectangle {
+        x: (center.x - size.x / 2.0 + 0.5).floor() as u32,
+        y: (center.y - size.y / 2.0 + 0.5).floor() as u32,
+        width: size.x.round() as u32,
+        height: size.y.round() as u32,
+    }
+}
+
+// Undoes --origin and --flip-y, centers from the metadata are back in image space after this
+pub fn image_center(
+    center: Vector2,
+    origin: Origin,
+    flip_y: bool,
+    page_width: u32,
+    page_height: u32,
+) -> Vector2 {
+    let mut center = center;
+
+    if flip_y {
+        center.y = match origin {
+            Origin::TopLeft => page_height as f32 - center.y,
+            Origin::Center => -center.y,
+        };
+    }
+
+    if origin == Origin::Center {
+        center.x += page_width as f32 / 2.0;
+        center.y += page_height as f32 / 2.0;
+    }
+
+    center
+}
+
+// Spilled atlases are written as atlas_0.png, atlas_1.png, ... next to the requested path
+pub fn page_path(atlas: &Path, page: u32) -> PathBuf {
+    let mut file_name = atlas.file_stem().unwrap_or_default().to_os_string();
+    file_name.push(format!("_{page}"));
+
+    if let Some(extension) = atlas.extension() {
+        file_name.push(".");
+        file_name.push(extension);
+    }
+
+    atlas.with_file_name(file_name)
+}
+
+// The meta section describes how the fragments were written, it isn't one of them
+pub fn read(path: &Path) -> Result<HashMap<PathBuf, StoredFragment>, Error> {
+    let mut fragments = serde_json::from_str::<HashMap<PathBuf, Value>>(
+        &fs::read_to_string(path).input_context(path)?,
+    )
+    .input_context(path)?;
+
+    fragments.remove(Path::new(META_KEY));
+
+    fragments
+        .into_iter()
+        .map(|(key, fragment)| Ok((key, serde_json::from_value(fragment)?)))
+        .collect::<Result<_, serde_json::Error>>()
+        .input_context(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use atlas::allocator::Allocation;
+
+    use super::{image_center, packed_rectangle};
+    use crate::{Origin, Vector2};
+
+    #[test]
+    fn floored_centers_read_back_as_the_drawn_rectangle() {
+        for (x, y, width, height, padding) in [(0, 0, 8, 9, 1), (3, 7, 5, 4, 3), (10, 2, 1, 1, 0)] {
+            let allocation = Allocation {
+                x,
+                y,
+                width: width + padding,
+                height: height + padding,
+            };
+            let center = allocation.center(width as u32, height as u32);
+            let rectangle =
+                packed_rectangle(center, Vector2::new(width as f32, height as f32), false);
+
+            assert_eq!(
+                (rectangle.x, rectangle.y, rectangle.width, rectangle.height),
+                (x as u32, y as u32, width as u32, height as u32)
+            );
+        }
+    }
+
+    #[test]
+    fn centers_are_measured_from_the_top_left_again() {
+        // 10,4 on a 32x16 page, written as -6,4 with --origin center --flip-y
+        let written = Vector2::new(-6.0, 4.0);
+        let center = image_center(written, Origin::Center, true, 32, 16);
+
+        assert_eq!((center.x, center.y), (10.0, 4.0));
+
+        let center = image_center(Vector2::new(10.0, 12.0), Origin::TopLeft, true, 32, 16);
+
+        assert_eq!((center.x, center.y), (10.0, 4.0));
+    }
+}
@@ -0,0 +1,606 @@
+use std::f64::consts::PI;
+
+use image::{Rgba, RgbaImage};
+
+use crate::truetype::{self, Bitmap, Point, Segment};
+
+// Channels an edge contributes to, as red, green and blue bits
+const RED: u8 = 1;
+const GREEN: u8 = 2;
+const BLUE: u8 = 4;
+const CYAN: u8 = GREEN | BLUE;
+const MAGENTA: u8 = RED | BLUE;
+const YELLOW: u8 = RED | GREEN;
+const WHITE: u8 = RED | GREEN | BLUE;
+
+// Direction changes sharper than about 3 radians apart keep their corner
+const CORNER_ANGLE: f64 = 3.0;
+
+type Vector = (f64, f64);
+
+struct Edge {
+    segment: Segment,
+    color: u8,
+}
+
+// Distances compare by magnitude, ties at a shared endpoint go to the edge pointing more
+// directly away from the sample
+#[derive(Copy, Clone)]
+struct Distance {
+    distance: f64,
+    dot: f64,
+}
+
+impl Distance {
+    const FAR: Distance = Distance {
+        distance: f64::MAX,
+        dot: 1.0,
+    };
+
+    fn closer_than(&self, other: &Distance) -> bool {
+        self.distance.abs() < other.distance.abs()
+            || (self.distance.abs() == other.distance.abs() && self.dot < other.dot)
+    }
+}
+
+// msdfgen's approach: edges are colored so that every corner sits between two edges that
+// share only one channel, the median of the three channels then keeps the corner sharp.
+// Alpha holds the true distance like MTSDF, so effects like outlines don't need the median.
+// The bitmap grows by the spread on every side, and 128 is the edge in every channel.
+pub fn rasterize(contours: &[Vec<Point>], scale: f32, spread: u32) -> Option<Bitmap> {
+    let mut edges = Vec::new();
+
+    for contour in contours {
+        edges.extend(color_edges(truetype::segments(contour, scale)));
+    }
+
+    let points = edges.iter().flat_map(|edge| match edge.segment {
+        Segment::Line(start, end) => vec![start, end],
+        Segment::Quadratic(start, control, end) => vec![start, control, end],
+    });
+    let (min_x, min_y, max_x, max_y) = points.fold(
+        (f32::MAX, f32::MAX, f32::MIN, f32::MIN),
+        |(min_x, min_y, max_x, max_y), (x, y)| {
+            (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y))
+        },
+    );
+
+    if edges.is_empty() || min_x >= max_x || min_y >= max_y {
+        return None;
+    }
+
+    let left = min_x.floor() - spread as f32;
+    let top = min_y.floor() - spread as f32;
+    let width = (max_x - min_x.floor()).ceil() as u32 + spread * 2;
+    let height = (max_y - min_y.floor()).ceil() as u32 + spread * 2;
+    let range = spread as f64 * 2.0;
+
+    let image = RgbaImage::from_fn(width, height, |x, y| {
+        let sample = (left as f64 + x as f64 + 0.5, top as f64 + y as f64 + 0.5);
+
+        let mut channels = [(Distance::FAR, 0.0, None::<&Edge>); 3];
+        let mut nearest = Distance::FAR;
+
+        for edge in &edges {
+            let (distance, t) = signed_distance(&edge.segment, sample);
+
+            if distance.closer_than(&nearest) {
+                nearest = distance;
+            }
+
+            for (channel, bit) in [RED, GREEN, BLUE].into_iter().enumerate() {
+                if edge.color & bit != 0 && distance.closer_than(&channels[channel].0) {
+                    channels[channel] = (distance, t, Some(edge));
+                }
+            }
+        }
+
+        // TrueType winds outer contours clockwise with y up, with y pointing down the distances
+        // come out negative inside
+        let value =
+            |distance: f64| ((0.5 - distance / range).clamp(0.0, 1.0) * 255.0).round() as u8;
+
+        let [red, green, blue] = channels.map(|(distance, t, edge)| match edge {
+            Some(edge) => value(pseudo_distance(&edge.segment, sample, distance, t)),
+            None => value(range),
+        });
+
+        Rgba([red, green, blue, value(nearest.distance)])
+    });
+
+    Some(Bitmap {
+        image,
+        left: left as i32,
+        top: -top as i32,
+    })
+}
+
+fn color_edges(segments: Vec<Segment>) -> Vec<Edge> {
+    let corners = (0..segments.len())
+        .filter(|&index| {
+            let previous = &segments[(index + segments.len() - 1) % segments.len()];
+
+            is_corner(
+                normalize(direction(previous, 1.0)),
+                normalize(direction(&segments[index], 0.0)),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    match corners.len() {
+        // Smooth contours never need more than one channel
+        0 => segments
+            .into_iter()
+            .map(|segment| Edge {
+                segment,
+                color: WHITE,
+            })
+            .collect(),
+        // A teardrop's single corner needs three differently colored edges around the contour
+        1 => {
+            let mut segments = segments;
+            segments.rotate_left(corners[0]);
+
+            if segments.len() < 3 {
+                segments = segments.into_iter().flat_map(split_in_thirds).collect();
+            }
+
+            let count = segments.len();
+
+            segments
+                .into_iter()
+                .enumerate()
+                .map(|(index, segment)| Edge {
+                    segment,
+                    color: [MAGENTA, WHITE, YELLOW][trichotomy(index, count)],
+                })
+                .collect()
+        }
+        _ => {
+            let mut color = switch_color(WHITE, 0);
+            let initial = color;
+            let mut spline = 0;
+            let mut edges = Vec::with_capacity(segments.len());
+            let count = segments.len();
+
+            for offset in 0..count {
+                let index = (corners[0] + offset) % count;
+
+                if spline + 1 < corners.len() && corners[spline + 1] == index {
+                    spline += 1;
+
+                    // The last spline also has to differ from the first, which it meets again
+                    let banned = if spline == corners.len() - 1 {
+                        initial
+                    } else {
+                        0
+                    };
+
+                    color = switch_color(color, banned);
+                }
+
+                edges.push(Edge {
+                    segment: segments[index],
+                    color,
+                });
+            }
+
+            edges
+        }
+    }
+}
+
+fn is_corner(a: Vector, b: Vector) -> bool {
+    dot(a, b) <= 0.0 || cross(a, b).abs() > CORNER_ANGLE.sin()
+}
+
+// Cyan, magenta and yellow each share one channel with the others, rotating through them
+// never gives two neighbouring splines the same color
+fn switch_color(color: u8, banned: u8) -> u8 {
+    let combined = color & banned;
+
+    if combined == RED || combined == GREEN || combined == BLUE {
+        return combined ^ WHITE;
+    }
+
+    if color == 0 || color == WHITE {
+        return CYAN;
+    }
+
+    let shifted = color << 1;
+    (shifted | shifted >> 3) & WHITE
+}
+
+// Splits the edges of a teardrop into the three colors, symmetrically around its corner
+fn trichotomy(position: usize, count: usize) -> usize {
+    let position = position as f64 / (count - 1).max(1) as f64;
+
+    ((3.0 + 2.875 * position - 1.4375 + 0.5) as i32 - 2).clamp(0, 2) as usize
+}
+
+fn split_in_thirds(segment: Segment) -> Vec<Segment> {
+    let at = |t: f32| point(&segment, t as f64);
+    let (first, second) = (at(1.0 / 3.0), at(2.0 / 3.0));
+    let first = (first.0 as f32, first.1 as f32);
+    let second = (second.0 as f32, second.1 as f32);
+
+    match segment {
+        Segment::Line(start, end) => vec![
+            Segment::Line(start, first),
+            Segment::Line(first, second),
+            Segment::Line(second, end),
+        ],
+        Segment::Quadratic(start, control, end) => {
+            let mix = |a: (f32, f32), b: (f32, f32), t: f32| {
+                (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t)
+            };
+
+            vec![
+                Segment::Quadratic(start, mix(start, control, 1.0 / 3.0), first),
+                Segment::Quadratic(
+                    first,
+                    mix(
+                        mix(start, control, 5.0 / 9.0),
+                        mix(control, end, 4.0 / 9.0),
+                        0.5,
+                    ),
+                    second,
+                ),
+                Segment::Quadratic(second, mix(control, end, 2.0 / 3.0), end),
+            ]
+        }
+    }
+}
+
+fn point(segment: &Segment, t: f64) -> Vector {
+    match *segment {
+        Segment::Line(start, end) => mix(vector(start), vector(end), t),
+        Segment::Quadratic(start, control, end) => mix(
+            mix(vector(start), vector(control), t),
+            mix(vector(control), vector(end), t),
+            t,
+        ),
+    }
+}
+
+fn direction(segment: &Segment, t: f64) -> Vector {
+    match *segment {
+        Segment::Line(start, end) => sub(vector(end), vector(start)),
+        Segment::Quadratic(start, control, end) => {
+            let tangent = mix(
+                sub(vector(control), vector(start)),
+                sub(vector(end), vector(control)),
+                t,
+            );
+
+            // A control point on an endpoint leaves no tangent there, the chord stands in
+            if tangent == (0.0, 0.0) {
+                sub(vector(end), vector(start))
+            } else {
+                tangent
+            }
+        }
+    }
+}
+
+// The distance to the segment and where along it the closest point lies, outside 0 to 1 when
+// the closest point is an endpoint the sample lies beyond
+fn signed_distance(segment: &Segment, sample: Vector) -> (Distance, f64) {
+    match *segment {
+        Segment::Line(start, end) => {
+            let (start, end) = (vector(start), vector(end));
+            let to_sample = sub(sample, start);
+            let along = sub(end, start);
+            let t = dot(to_sample, along) / dot(along, along);
+            let endpoint = sub(if t > 0.5 { end } else { start }, sample);
+            let endpoint_distance = length(endpoint);
+
+            if t > 0.0 && t < 1.0 {
+                let orthogonal = cross(to_sample, along) / length(along);
+
+                if orthogonal.abs() < endpoint_distance {
+                    return (
+                        Distance {
+                            distance: orthogonal,
+                            dot: 0.0,
+                        },
+                        t,
+                    );
+                }
+            }
+
+            (
+                Distance {
+                    distance: sign(cross(to_sample, along)) * endpoint_distance,
+                    dot: dot(normalize(along), normalize(endpoint)).abs(),
+                },
+                t,
+            )
+        }
+        Segment::Quadratic(start, control, end) => {
+            let (start, control, end) = (vector(start), vector(control), vector(end));
+            let from_sample = sub(start, sample);
+            let first = sub(control, start);
+            let second = sub(sub(end, control), first);
+
+            let a = dot(second, second);
+            let b = 3.0 * dot(first, second);
+            let c = 2.0 * dot(first, first) + dot(from_sample, second);
+            let d = dot(from_sample, first);
+
+            let start_direction = direction(segment, 0.0);
+            let mut distance = sign(cross(start_direction, from_sample)) * length(from_sample);
+            let mut t = -dot(from_sample, start_direction) / dot(start_direction, start_direction);
+
+            let end_direction = direction(segment, 1.0);
+            let end_distance = length(sub(end, sample));
+
+            if end_distance < distance.abs() {
+                distance = sign(cross(end_direction, sub(end, sample))) * end_distance;
+                t = dot(sub(sample, control), end_direction) / dot(end_direction, end_direction);
+            }
+
+            for root in solve_cubic(a, b, c, d) {
+                if root > 0.0 && root < 1.0 {
+                    let offset = add(
+                        add(from_sample, scale(first, 2.0 * root)),
+                        scale(second, root * root),
+                    );
+                    let root_distance = length(offset);
+
+                    if root_distance <= distance.abs() {
+                        distance =
+                            sign(cross(add(first, scale(second, root)), offset)) * root_distance;
+                        t = root;
+                    }
+                }
+            }
+
+            let dot_product = if (0.0..=1.0).contains(&t) {
+                0.0
+            } else if t < 0.5 {
+                dot(normalize(start_direction), normalize(from_sample)).abs()
+            } else {
+                dot(normalize(end_direction), normalize(sub(end, sample))).abs()
+            };
+
+            (
+                Distance {
+                    distance,
+                    dot: dot_product,
+                },
+                t,
+            )
+        }
+    }
+}
+
+// Beyond an endpoint the distance is taken to the edge extended along its tangent, which keeps
+// the channels of two edges meeting at a corner from rounding it off
+fn pseudo_distance(segment: &Segment, sample: Vector, distance: Distance, t: f64) -> f64 {
+    let (endpoint, tangent, beyond) = if t < 0.0 {
+        let tangent = normalize(direction(segment, 0.0));
+        let to_sample = sub(sample, point(segment, 0.0));
+
+        (to_sample, tangent, dot(to_sample, tangent) < 0.0)
+    } else if t > 1.0 {
+        let tangent = normalize(direction(segment, 1.0));
+        let to_sample = sub(sample, point(segment, 1.0));
+
+        (to_sample, tangent, dot(to_sample, tangent) > 0.0)
+    } else {
+        return distance.distance;
+    };
+
+    let pseudo = cross(endpoint, tangent);
+
+    if beyond && pseudo.abs() <= distance.distance.abs() {
+        pseudo
+    } else {
+        distance.distance
+    }
+}
+
+fn solve_quadratic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    if a.abs() < 1e-14 {
+        if b.abs() < 1e-14 {
+            return Vec::new();
+        }
+
+        return vec![-c / b];
+    }
+
+    let discriminant = b * b - 4.0 * a * c;
+
+    if discriminant > 0.0 {
+        let root = discriminant.sqrt();
+        vec![(-b + root) / (2.0 * a), (-b - root) / (2.0 * a)]
+    } else if discriminant == 0.0 {
+        vec![-b / (2.0 * a)]
+    } else {
+        Vec::new()
+    }
+}
+
+fn solve_cubic(a: f64, b: f64, c: f64, d: f64) -> Vec<f64> {
+    if a.abs() < 1e-14 {
+        return solve_quadratic(b, c, d);
+    }
+
+    let (a, b, c) = (b / a, c / a, d / a);
+    let q = (a * a - 3.0 * b) / 9.0;
+    let r = (a * (2.0 * a * a - 9.0 * b) + 27.0 * c) / 54.0;
+
+    if r * r < q * q * q {
+        let angle = (r / (q * q * q).sqrt()).clamp(-1.0, 1.0).acos();
+        let (a, q) = (a / 3.0, -2.0 * q.sqrt());
+
+        vec![
+            q * (angle / 3.0).cos() - a,
+            q * ((angle + 2.0 * PI) / 3.0).cos() - a,
+            q * ((angle - 2.0 * PI) / 3.0).cos() - a,
+        ]
+    } else {
+        let u = -r.signum() * (r.abs() + (r * r - q * q * q).sqrt()).cbrt();
+        let v = if u == 0.0 { 0.0 } else { q / u };
+        let a = a / 3.0;
+
+        let mut roots = vec![u + v - a];
+
+        if (0.5 * 3f64.sqrt() * (u - v)).abs() < 1e-14 {
+            roots.push(-0.5 * (u + v) - a);
+        }
+
+        roots
+    }
+}
+
+fn vector((x, y): (f32, f32)) -> Vector {
+    (x as f64, y as f64)
+}
+
+fn add(a: Vector, b: Vector) -> Vector {
+    (a.0 + b.0, a.1 + b.1)
+}
+
+fn sub(a: Vector, b: Vector) -> Vector {
+    (a.0 - b.0, a.1 - b.1)
+}
+
+fn scale(a: Vector, factor: f64) -> Vector {
+    (a.0 * factor, a.1 * factor)
+}
+
+fn mix(a: Vector, b: Vector, t: f64) -> Vector {
+    add(a, scale(sub(b, a), t))
+}
+
+fn dot(a: Vector, b: Vector) -> f64 {
+    a.0 * b.0 + a.1 * b.1
+}
+
+fn cross(a: Vector, b: Vector) -> f64 {
+    a.0 * b.1 - a.1 * b.0
+}
+
+fn length(a: Vector) -> f64 {
+    dot(a, a).sqrt()
+}
+
+fn normalize(a: Vector) -> Vector {
+    match length(a) {
+        0.0 => (0.0, 1.0),
+        length => scale(a, 1.0 / length),
+    }
+}
+
+// Zero counts as positive, so a sample right on an edge's extension still gets a side
+fn sign(value: f64) -> f64 {
+    if value < 0.0 {
+        -1.0
+    } else {
+        1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{color_edges, rasterize, BLUE, GREEN, MAGENTA, RED, WHITE, YELLOW};
+    use crate::truetype::{Point, Segment};
+
+    fn shares_one_channel(a: u8, b: u8) -> bool {
+        [RED, GREEN, BLUE].contains(&(a & b))
+    }
+
+    fn median([red, green, blue, _]: [u8; 4]) -> u8 {
+        red.max(green).min(red.min(green).max(blue))
+    }
+
+    #[test]
+    fn every_corner_sits_between_edges_sharing_one_channel() {
+        let square = vec![
+            Segment::Line((0.0, 0.0), (10.0, 0.0)),
+            Segment::Line((10.0, 0.0), (10.0, 10.0)),
+            Segment::Line((10.0, 10.0), (0.0, 10.0)),
+            Segment::Line((0.0, 10.0), (0.0, 0.0)),
+        ];
+
+        let colors = color_edges(square)
+            .iter()
+            .map(|edge| edge.color)
+            .collect::<Vec<_>>();
+
+        assert_eq!(colors.len(), 4);
+
+        for (index, &color) in colors.iter().enumerate() {
+            assert!(shares_one_channel(
+                color,
+                colors[(index + 1) % colors.len()]
+            ));
+        }
+    }
+
+    #[test]
+    fn smooth_contours_use_every_channel() {
+        // Eight quadratics through a circle, each control point where the tangents meet
+        let point = |angle: f32, radius: f32| (angle.cos() * radius, angle.sin() * radius);
+        let step = std::f32::consts::FRAC_PI_4;
+        let circle = (0..8)
+            .map(|index| {
+                let angle = index as f32 * step;
+
+                Segment::Quadratic(
+                    point(angle, 10.0),
+                    point(angle + step / 2.0, 10.0 / (step / 2.0).cos()),
+                    point(angle + step, 10.0),
+                )
+            })
+            .collect();
+
+        assert!(color_edges(circle).iter().all(|edge| edge.color == WHITE));
+    }
+
+    #[test]
+    fn a_single_corner_is_split_into_three_colors() {
+        // A teardrop, the line runs into the curve smoothly and only the tip is a corner
+        let teardrop = vec![
+            Segment::Line((0.0, 0.0), (10.0, -5.0)),
+            Segment::Quadratic((10.0, -5.0), (20.0, -10.0), (0.0, 0.0)),
+        ];
+
+        let colors = color_edges(teardrop)
+            .iter()
+            .map(|edge| edge.color)
+            .collect::<Vec<_>>();
+
+        assert_eq!(colors.len(), 6);
+        assert_eq!(colors.first(), Some(&MAGENTA));
+        assert!(colors.contains(&WHITE));
+        assert_eq!(colors.last(), Some(&YELLOW));
+        assert!(shares_one_channel(colors[colors.len() - 1], colors[0]));
+    }
+
+    #[test]
+    fn the_median_keeps_square_corners_sharp() {
+        let point = |x, y| Point {
+            x,
+            y,
+            on_curve: true,
+        };
+        let square = [
+            point(0.0, 0.0),
+            point(0.0, 10.0),
+            point(10.0, 10.0),
+            point(10.0, 0.0),
+        ];
+
+        let bitmap = rasterize(&[square.to_vec()], 1.0, 2).unwrap();
+
+        assert_eq!(bitmap.image.dimensions(), (14, 14));
+
+        // Just inside and just outside the top left corner, diagonally
+        assert!(median(bitmap.image.get_pixel(2, 2).0) > 128);
+        assert!(median(bitmap.image.get_pixel(1, 1).0) < 128);
+        assert!(median(bitmap.image.get_pixel(7, 7).0) > 128);
+    }
+}
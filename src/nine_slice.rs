@@ -0,0 +1,202 @@
+use std::{path::Path, str::FromStr};
+
+use atlas::Trim;
+use image::{DynamicImage, GenericImageView, Rgba};
+use serde::{Deserialize, Serialize};
+
+use crate::pattern;
+
+// Pixels from each edge that stay unstretched
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct NineSlice {
+    pub left: u32,
+    pub right: u32,
+    pub top: u32,
+    pub bottom: u32,
+}
+
+impl NineSlice {
+    pub fn grow(self, margin: u32) -> Self {
+        Self {
+            left: self.left + margin,
+            right: self.right + margin,
+            top: self.top + margin,
+            bottom: self.bottom + margin,
+        }
+    }
+
+    // The same slice once the trim cut the width x height sprite out of its source. Trimmed
+    // away pixels no longer count towards an inset, and an inset can't reach past the sprite
+    pub fn trim(self, trim: &Trim, width: u32, height: u32) -> Self {
+        let (left, top) = (trim.offset.x as u32, trim.offset.y as u32);
+        let right = (trim.source_size.x as u32).saturating_sub(left + width);
+        let bottom = (trim.source_size.y as u32).saturating_sub(top + height);
+
+        Self {
+            left: self.left.saturating_sub(left).min(width),
+            right: self.right.saturating_sub(right).min(width),
+            top: self.top.saturating_sub(top).min(height),
+            bottom: self.bottom.saturating_sub(bottom).min(height),
+        }
+    }
+
+    pub fn fits(&self, width: u32, height: u32) -> bool {
+        self.left + self.right <= width && self.top + self.bottom <= height
+    }
+}
+
+#[derive(Clone)]
+pub struct NineSliceArg {
+    pattern: Option<String>,
+    pub slice: NineSlice,
+}
+
+impl NineSliceArg {
+    pub fn includes(&self, key: &Path) -> bool {
+        self.pattern
+            .as_ref()
+            .is_none_or(|pattern| pattern::matches_key(pattern, key))
+    }
+}
+
+impl FromStr for NineSliceArg {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (pattern, insets) = match value.rsplit_once('=') {
+            Some((pattern, insets)) => (Some(pattern.to_string()), insets),
+            None => (None, value),
+        };
+
+        let invalid = || format!("invalid nine-slice '{insets}', expected LEFT,RIGHT,TOP,BOTTOM");
+
+        let insets = insets
+            .split(',')
+            .map(|inset| inset.trim().parse::<u32>().map_err(|_| invalid()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let [left, right, top, bottom] = insets[..] else {
+            return Err(invalid());
+        };
+
+        Ok(Self {
+            pattern,
+            slice: NineSlice {
+                left,
+                right,
+                top,
+                bottom,
+            },
+        })
+    }
+}
+
+// Android's nine-patch convention, `name.9.png`
+pub fn is_nine_patch(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.to_ascii_lowercase().ends_with(".9.png"))
+}
+
+// The 1px border marks the stretchable span in black along the top and left edges. The padding
+// marks along the bottom and right are dropped with it, and several stretchable spans on one
+// edge become the single span from the first to the last
+pub fn strip(image: &DynamicImage) -> Result<(DynamicImage, NineSlice), String> {
+    let (width, height) = image.dimensions();
+
+    if width < 3 || height < 3 {
+        return Err("a nine-patch needs at least one pixel inside its 1px border".to_string());
+    }
+
+    for (x, y) in (0..width)
+        .flat_map(|x| [(x, 0), (x, height - 1)])
+        .chain((0..height).flat_map(|y| [(0, y), (width - 1, y)]))
+    {
+        let pixel = image.get_pixel(x, y);
+
+        if pixel != Rgba([0, 0, 0, 255]) && pixel.0[3] != 0 {
+            return Err(format!(
+                "nine-patch border pixel at {x},{y} is neither transparent nor opaque black"
+            ));
+        }
+    }
+
+    let marked = |x: u32, y: u32| image.get_pixel(x, y).0[3] != 0;
+
+    // The unstretched pixels before and after the marked span, with nothing marked the whole
+    // edge stretches
+    let insets = |length: u32, marked: &dyn Fn(u32) -> bool| {
+        let inner = 1..length - 1;
+
+        match (
+            inner.clone().find(|&index| marked(index)),
+            inner.rev().find(|&index| marked(index)),
+        ) {
+            (Some(first), Some(last)) => (first - 1, length - 2 - last),
+            _ => (0, 0),
+        }
+    };
+
+    let (left, right) = insets(width, &|x| marked(x, 0));
+    let (top, bottom) = insets(height, &|y| marked(0, y));
+
+    Ok((
+        image.crop_imm(1, 1, width - 2, height - 2),
+        NineSlice {
+            left,
+            right,
+            top,
+            bottom,
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use atlas::{Trim, Vector2};
+
+    use super::NineSlice;
+
+    #[test]
+    fn trimming_shifts_insets_into_the_trimmed_sprite() {
+        let slice = NineSlice {
+            left: 6,
+            right: 6,
+            top: 4,
+            bottom: 2,
+        };
+        // A 32x24 source trimmed to the 20x16 pixels at 8,3
+        let trim = Trim {
+            source_size: Vector2::new(32.0, 24.0),
+            offset: Vector2::new(8.0, 3.0),
+        };
+
+        let trimmed = slice.trim(&trim, 20, 16);
+
+        assert_eq!(
+            [trimmed.left, trimmed.right, trimmed.top, trimmed.bottom],
+            [0, 2, 1, 0]
+        );
+        assert!(trimmed.fits(20, 16));
+    }
+
+    #[test]
+    fn insets_stop_at_the_trimmed_sprite() {
+        let slice = NineSlice {
+            left: 2,
+            right: 8,
+            top: 0,
+            bottom: 0,
+        };
+        // Only the right inset's pixels survive the trim
+        let trim = Trim {
+            source_size: Vector2::new(10.0, 4.0),
+            offset: Vector2::new(5.0, 0.0),
+        };
+
+        let trimmed = slice.trim(&trim, 5, 4);
+
+        assert_eq!([trimmed.left, trimmed.right], [0, 5]);
+        assert!(trimmed.fits(5, 4));
+    }
+}
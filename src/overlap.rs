@@ -0,0 +1,139 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+};
+
+use image::DynamicImage;
+
+use crate::{anonymous, error::Error, subatlas};
+
+// Pixels packed by more than one atlas, with every key they go by. The same sprite can be
+// keyed differently from one atlas to the next, only the content has to match
+struct Shared {
+    width: u32,
+    height: u32,
+    copies: Vec<(usize, PathBuf)>,
+}
+
+fn shared(atlases: &[Vec<(PathBuf, DynamicImage)>]) -> Vec<Shared> {
+    let mut by_content = BTreeMap::<String, Shared>::new();
+
+    for (index, sprites) in atlases.iter().enumerate() {
+        for (key, image) in sprites {
+            by_content
+                .entry(anonymous::content_hash(image))
+                .or_insert_with(|| Shared {
+                    width: image.width(),
+                    height: image.height(),
+                    copies: Vec::new(),
+                })
+                .copies
+                .push((index, key.clone()));
+        }
+    }
+
+    let mut shared = by_content
+        .into_values()
+        // Aliases repeat a sprite within its own atlas, that's already packed once
+        .filter(|shared| {
+            shared
+                .copies
+                .iter()
+                .any(|(index, _)| *index != shared.copies[0].0)
+        })
+        .collect::<Vec<_>>();
+
+    shared.sort_by(|a, b| a.copies[0].1.cmp(&b.copies[0].1));
+
+    shared
+}
+
+// Reports the sprites that several atlases pack, and the area a shared atlas holding them once
+// would save the others
+pub fn overlap(pairs: &[PathBuf]) -> Result<(), Error> {
+    let pairs = pairs.chunks_exact(2).collect::<Vec<_>>();
+    let atlases = pairs
+        .iter()
+        .map(|pair| subatlas::load(&pair[0], &pair[1], None))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let shared = shared(&atlases);
+
+    if shared.is_empty() {
+        println!("No sprite is packed in more than one atlas");
+        return Ok(());
+    }
+
+    let saved = shared
+        .iter()
+        .map(|shared| {
+            let atlases = shared
+                .copies
+                .iter()
+                .map(|(index, _)| index)
+                .collect::<BTreeSet<_>>()
+                .len() as u64;
+
+            shared.width as u64 * shared.height as u64 * (atlases - 1)
+        })
+        .sum::<u64>();
+
+    println!(
+        "Sprites packed in more than one atlas: {}, sharing them would save {saved} px",
+        shared.len()
+    );
+
+    for shared in &shared {
+        println!(
+            "  {}x{}: {}",
+            shared.width,
+            shared.height,
+            shared
+                .copies
+                .iter()
+                .map(|(index, key)| format!("{} in {}", key.display(), pairs[*index][1].display()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use image::{DynamicImage, Rgba, RgbaImage};
+
+    use super::shared;
+
+    fn sprite(key: &str, color: u8) -> (PathBuf, DynamicImage) {
+        (
+            PathBuf::from(key),
+            DynamicImage::ImageRgba8(RgbaImage::from_pixel(4, 2, Rgba([color, 0, 0, 255]))),
+        )
+    }
+
+    #[test]
+    fn only_content_in_several_atlases_is_shared() {
+        let hud = vec![sprite("ui/button.png", 1), sprite("hud/health.png", 2)];
+        let menu = vec![
+            sprite("common/button.png", 1),
+            sprite("menu/logo.png", 3),
+            sprite("menu/logo_copy.png", 3),
+        ];
+
+        let shared = shared(&[hud, menu]);
+
+        assert_eq!(shared.len(), 1);
+        assert_eq!((shared[0].width, shared[0].height), (4, 2));
+        assert_eq!(
+            shared[0].copies,
+            [
+                (0, PathBuf::from("ui/button.png")),
+                (1, PathBuf::from("common/button.png"))
+            ]
+        );
+    }
+}
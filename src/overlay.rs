@@ -0,0 +1,249 @@
+use std::path::Path;
+
+use clap::ValueEnum;
+use image::{DynamicImage, GenericImageView, ImageResult, Rgba, RgbaImage};
+
+use crate::metadata::Rectangle;
+
+const ARROW_HEAD: f32 = 6.0;
+const PATTERN_SPACING: u32 = 4;
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum OverlayStyle {
+    Default,
+    ColorBlind,
+    Patterns,
+}
+
+#[derive(Copy, Clone)]
+pub enum Mark {
+    Moved,
+    Changed,
+    Resized,
+    Added,
+    Removed,
+}
+
+impl Mark {
+    const ALL: [Mark; 5] = [
+        Mark::Moved,
+        Mark::Changed,
+        Mark::Resized,
+        Mark::Added,
+        Mark::Removed,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Mark::Moved => "moved",
+            Mark::Changed => "changed",
+            Mark::Resized => "resized",
+            Mark::Added => "added",
+            Mark::Removed => "removed",
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+enum Pattern {
+    Vertical,
+    Diagonal,
+    Cross,
+    Dots,
+    Horizontal,
+}
+
+impl Pattern {
+    fn covers(self, x: u32, y: u32) -> bool {
+        match self {
+            Pattern::Vertical => x.is_multiple_of(PATTERN_SPACING),
+            Pattern::Diagonal => (x + y).is_multiple_of(PATTERN_SPACING),
+            Pattern::Cross => {
+                (x + y).is_multiple_of(PATTERN_SPACING)
+                    || x.abs_diff(y).is_multiple_of(PATTERN_SPACING)
+            }
+            Pattern::Dots => x % PATTERN_SPACING == 1 && y % PATTERN_SPACING == 1,
+            Pattern::Horizontal => y.is_multiple_of(PATTERN_SPACING),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Pattern::Vertical => "vertical stripes",
+            Pattern::Diagonal => "diagonal stripes",
+            Pattern::Cross => "cross hatching",
+            Pattern::Dots => "dots",
+            Pattern::Horizontal => "horizontal stripes",
+        }
+    }
+}
+
+impl OverlayStyle {
+    // The color blind palette is Okabe and Ito's, which stays distinct under the common forms
+    // of color vision deficiency
+    fn color(self, mark: Mark) -> Rgba<u8> {
+        match (self, mark) {
+            (OverlayStyle::Default, Mark::Moved) => Rgba([0, 120, 255, 255]),
+            (OverlayStyle::Default, Mark::Changed) => Rgba([255, 40, 40, 255]),
+            (OverlayStyle::Default, Mark::Resized) => Rgba([255, 160, 0, 255]),
+            (OverlayStyle::Default, Mark::Added) => Rgba([0, 200, 60, 255]),
+            (OverlayStyle::Default, Mark::Removed) => Rgba([160, 160, 160, 255]),
+            (_, Mark::Moved) => Rgba([86, 180, 233, 255]),
+            (_, Mark::Changed) => Rgba([213, 94, 0, 255]),
+            (_, Mark::Resized) => Rgba([240, 228, 66, 255]),
+            (_, Mark::Added) => Rgba([0, 158, 115, 255]),
+            (_, Mark::Removed) => Rgba([204, 121, 167, 255]),
+        }
+    }
+
+    fn pattern(self, mark: Mark) -> Option<Pattern> {
+        if self != OverlayStyle::Patterns {
+            return None;
+        }
+
+        Some(match mark {
+            Mark::Moved => Pattern::Vertical,
+            Mark::Changed => Pattern::Diagonal,
+            Mark::Resized => Pattern::Cross,
+            Mark::Added => Pattern::Dots,
+            Mark::Removed => Pattern::Horizontal,
+        })
+    }
+
+    pub fn legend(self) -> Vec<String> {
+        Mark::ALL
+            .into_iter()
+            .map(|mark| {
+                let [red, green, blue, _] = self.color(mark).0;
+                let mut entry = format!("{}: #{red:02x}{green:02x}{blue:02x}", mark.name());
+
+                if let Some(pattern) = self.pattern(mark) {
+                    entry.push_str(&format!(", {}", pattern.name()));
+                }
+
+                entry
+            })
+            .collect()
+    }
+}
+
+pub struct Overlay {
+    image: RgbaImage,
+    style: OverlayStyle,
+}
+
+impl Overlay {
+    // The new atlas is dimmed so highlights stand out while sprites stay recognizable
+    pub fn new(width: u32, height: u32, background: &DynamicImage, style: OverlayStyle) -> Self {
+        let mut image = RgbaImage::from_pixel(width, height, Rgba([0, 0, 0, 255]));
+
+        for (x, y, pixel) in background.pixels() {
+            let alpha = pixel.0[3] as u32;
+            let dimmed = pixel
+                .0
+                .map(|channel| (channel as u32 * alpha / 255 / 3) as u8);
+
+            image.put_pixel(x, y, Rgba([dimmed[0], dimmed[1], dimmed[2], 255]));
+        }
+
+        Self { image, style }
+    }
+
+    // Pattern fills are relative to the rectangle so neighbouring marks don't run together
+    pub fn outline(&mut self, rectangle: &Rectangle, mark: Mark) {
+        if rectangle.width == 0 || rectangle.height == 0 {
+            return;
+        }
+
+        let color = self.style.color(mark);
+
+        if let Some(pattern) = self.style.pattern(mark) {
+            for y in 0..rectangle.height {
+                for x in 0..rectangle.width {
+                    if pattern.covers(x, y) {
+                        self.put((rectangle.x + x) as i64, (rectangle.y + y) as i64, color);
+                    }
+                }
+            }
+        }
+
+        let right = rectangle.x + rectangle.width - 1;
+        let bottom = rectangle.y + rectangle.height - 1;
+
+        for x in rectangle.x..=right {
+            self.put(x as i64, rectangle.y as i64, color);
+            self.put(x as i64, bottom as i64, color);
+        }
+
+        for y in rectangle.y..=bottom {
+            self.put(rectangle.x as i64, y as i64, color);
+            self.put(right as i64, y as i64, color);
+        }
+    }
+
+    pub fn tint(&mut self, rectangle: &Rectangle, mark: Mark) {
+        let color = self.style.color(mark);
+
+        for y in rectangle.y..rectangle.y + rectangle.height {
+            for x in rectangle.x..rectangle.x + rectangle.width {
+                if let Some(pixel) = self.image.get_pixel_mut_checked(x, y) {
+                    for channel in 0..3 {
+                        pixel.0[channel] =
+                            ((pixel.0[channel] as u32 + color.0[channel] as u32) / 2) as u8;
+                    }
+                }
+            }
+        }
+
+        self.outline(rectangle, mark);
+    }
+
+    pub fn arrow(&mut self, from: (f32, f32), to: (f32, f32), mark: Mark) {
+        let color = self.style.color(mark);
+
+        self.line(from, to, color);
+
+        let angle = (to.1 - from.1).atan2(to.0 - from.0);
+
+        for side in [-1.0, 1.0] {
+            let head = angle + std::f32::consts::PI + side * std::f32::consts::FRAC_PI_6;
+
+            self.line(
+                to,
+                (
+                    to.0 + head.cos() * ARROW_HEAD,
+                    to.1 + head.sin() * ARROW_HEAD,
+                ),
+                color,
+            );
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> ImageResult<()> {
+        self.image.save(path)
+    }
+
+    fn line(&mut self, from: (f32, f32), to: (f32, f32), color: Rgba<u8>) {
+        let steps = (to.0 - from.0)
+            .abs()
+            .max((to.1 - from.1).abs())
+            .ceil()
+            .max(1.0);
+
+        for step in 0..=steps as u32 {
+            let t = step as f32 / steps;
+
+            self.put(
+                (from.0 + (to.0 - from.0) * t).round() as i64,
+                (from.1 + (to.1 - from.1) * t).round() as i64,
+                color,
+            );
+        }
+    }
+
+    fn put(&mut self, x: i64, y: i64, color: Rgba<u8>) {
+        if x >= 0 && y >= 0 && x < self.image.width() as i64 && y < self.image.height() as i64 {
+            self.image.put_pixel(x as u32, y as u32, color);
+        }
+    }
+}
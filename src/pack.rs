@@ -0,0 +1,321 @@
+//! Packing over as many pages as the sprites need, with the spacing, rotation and page classes
+//! `atlas generate` uses.
+
+use image::{DynamicImage, GenericImageView, RgbaImage};
+
+use crate::{
+    allocator::{Allocation, Allocator, AllocatorOptions},
+    Algorithm,
+};
+
+/// Room kept around every sprite, in pixels.
+///
+/// `padding` separates neighbouring sprites, `border` keeps them away from the page edges and
+/// `extrude` repeats each sprite's edge pixels around it.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct Spacing {
+    pub padding: u32,
+    pub border: u32,
+    pub extrude: u32,
+}
+
+impl Spacing {
+    /// The room a `width` by `height` sprite takes up in the allocator.
+    pub fn padded(self, width: u32, height: u32) -> (u32, u32) {
+        (
+            width + self.extrude * 2 + self.padding,
+            height + self.extrude * 2 + self.padding,
+        )
+    }
+}
+
+/// One packed page, `class` is set once a sprite went on it.
+pub struct Page<C> {
+    allocator: Allocator,
+    spacing: Spacing,
+    pub image: RgbaImage,
+    pub class: Option<C>,
+}
+
+impl<C> Page<C> {
+    fn new(packer: &Packer<C>) -> Self {
+        // Every allocation carries its padding on the right and bottom, so the allocator gets that
+        // much extra room to let the last sprite in a row sit flush against the border
+        let spacing = packer.spacing;
+
+        Self {
+            allocator: Allocator::with_options(
+                packer.algorithm,
+                packer.width - spacing.border * 2 + spacing.padding,
+                packer.height - spacing.border * 2 + spacing.padding,
+                packer.options,
+            ),
+            spacing,
+            image: RgbaImage::new(packer.canvas_width, packer.canvas_height),
+            class: None,
+        }
+    }
+
+    fn allocate(&mut self, width: u32, height: u32) -> Option<Allocation> {
+        let (padded_width, padded_height) = self.spacing.padded(width, height);
+        let allocation = self.allocator.allocate(padded_width, padded_height)?;
+
+        let offset = (self.spacing.border + self.spacing.extrude) as i32;
+        let shrink = (self.spacing.extrude * 2 + self.spacing.padding) as i32;
+
+        Some(Allocation {
+            x: allocation.x + offset,
+            y: allocation.y + offset,
+            width: allocation.width - shrink,
+            height: allocation.height - shrink,
+        })
+    }
+
+    // Out of range coordinates clamp to the nearest edge, so extrusion repeats the border pixels.
+    // Tileable sprites wrap around instead, what's next to an edge is the opposite edge
+    fn blit(&mut self, image: &DynamicImage, x: u32, y: u32, wrap: bool) {
+        let extrude = self.spacing.extrude as i64;
+        let source = |offset: i64, length: u32| {
+            if wrap {
+                offset.rem_euclid(length as i64) as u32
+            } else {
+                offset.clamp(0, length as i64 - 1) as u32
+            }
+        };
+
+        for offset_y in -extrude..image.height() as i64 + extrude {
+            for offset_x in -extrude..image.width() as i64 + extrude {
+                let pixel = image.get_pixel(
+                    source(offset_x, image.width()),
+                    source(offset_y, image.height()),
+                );
+
+                self.image.put_pixel(
+                    (x as i64 + offset_x) as u32,
+                    (y as i64 + offset_y) as u32,
+                    pixel,
+                );
+            }
+        }
+    }
+}
+
+/// Where [`Packer::place`] put a sprite. The allocation excludes the spacing around it and is
+/// the sprite's size turned 90 degrees when `rotated` is set.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Placement {
+    pub page: usize,
+    pub allocation: Allocation,
+    pub rotated: bool,
+}
+
+/// Places sprites on the first page with room for them, opening pages as it runs out.
+///
+/// Sprites only share a page with sprites of the same class `C`, use `()` when every sprite can
+/// go anywhere. Pages are `width` by `height` for packing, with images of the canvas size.
+///
+/// ```
+/// use atlas::pack::{Packer, Spacing};
+///
+/// let mut packer = Packer::<()>::new(atlas::Algorithm::Guillotiere, 16, 16).spacing(Spacing {
+///     padding: 2,
+///     ..Spacing::default()
+/// });
+///
+/// let first = packer.place(8, 8, ()).unwrap();
+/// let second = packer.place(8, 8, ()).unwrap();
+///
+/// assert_eq!(first.page, 0);
+/// assert_eq!(second.page, 1);
+/// ```
+pub struct Packer<C> {
+    algorithm: Algorithm,
+    options: AllocatorOptions,
+    width: u32,
+    height: u32,
+    canvas_width: u32,
+    canvas_height: u32,
+    spacing: Spacing,
+    allow_rotation: bool,
+    max_pages: Option<usize>,
+    pages: Vec<Page<C>>,
+    floor: usize,
+}
+
+impl<C: Copy + PartialEq> Packer<C> {
+    pub fn new(algorithm: Algorithm, width: u32, height: u32) -> Self {
+        Self {
+            algorithm,
+            options: AllocatorOptions::default(),
+            width,
+            height,
+            canvas_width: width,
+            canvas_height: height,
+            spacing: Spacing::default(),
+            allow_rotation: false,
+            max_pages: None,
+            pages: Vec::new(),
+            floor: 0,
+        }
+    }
+
+    /// Tuning for the chosen algorithm, options meant for other algorithms are ignored.
+    pub fn allocator_options(mut self, options: AllocatorOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// The size of the page images, when they're larger than the packed area.
+    pub fn canvas(mut self, width: u32, height: u32) -> Self {
+        self.canvas_width = width;
+        self.canvas_height = height;
+        self
+    }
+
+    /// The border has to leave room on the page, twice it must be less than either side.
+    pub fn spacing(mut self, spacing: Spacing) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    /// Lets sprites be turned 90 degrees when that's the only way they fit.
+    pub fn allow_rotation(mut self, allow_rotation: bool) -> Self {
+        self.allow_rotation = allow_rotation;
+        self
+    }
+
+    /// Sprites that don't fit on this many pages fail to place instead of opening another one.
+    pub fn max_pages(mut self, max_pages: usize) -> Self {
+        self.max_pages = Some(max_pages);
+        self
+    }
+
+    /// Sprites placed from now on never go on a page before the last one opened so far.
+    pub fn raise_floor(&mut self) {
+        self.floor = self.pages.len().saturating_sub(1);
+    }
+
+    /// Finds room for a `width` by `height` sprite of `class`, `None` when even a fresh page
+    /// can't hold it or no more pages may be opened.
+    pub fn place(&mut self, width: u32, height: u32, class: C) -> Option<Placement> {
+        // Tall sprites prefer lying down along the shelves, the other orientation is a fallback
+        let orientations: &[bool] = if !self.allow_rotation {
+            &[false]
+        } else if height > width {
+            &[true, false]
+        } else {
+            &[false, true]
+        };
+
+        let allocate = |page: &mut Page<C>| {
+            orientations.iter().find_map(|&rotated| {
+                let allocation = if rotated {
+                    page.allocate(height, width)
+                } else {
+                    page.allocate(width, height)
+                };
+
+                allocation.map(|allocation| (allocation, rotated))
+            })
+        };
+
+        let existing = self
+            .pages
+            .iter_mut()
+            .enumerate()
+            .skip(self.floor)
+            .filter(|(_, page)| page.class.is_none_or(|page_class| page_class == class))
+            .find_map(|(index, page)| {
+                allocate(page).map(|(allocation, rotated)| (index, allocation, rotated))
+            });
+
+        let (page, allocation, rotated) = match existing {
+            Some(existing) => existing,
+            None => {
+                if self
+                    .max_pages
+                    .is_some_and(|max_pages| self.pages.len() >= max_pages)
+                {
+                    return None;
+                }
+
+                let mut page = Page::new(self);
+                let (allocation, rotated) = allocate(&mut page)?;
+
+                self.pages.push(page);
+
+                (self.pages.len() - 1, allocation, rotated)
+            }
+        };
+
+        self.pages[page].class = Some(class);
+
+        Some(Placement {
+            page,
+            allocation,
+            rotated,
+        })
+    }
+
+    /// Draws a placed sprite with its extrusion, `image` already turned if the placement is
+    /// rotated. Tileable sprites extrude their opposite edges so they keep tiling when sampled.
+    pub fn draw(&mut self, placement: &Placement, image: &DynamicImage, tileable: bool) {
+        self.pages[placement.page].blit(
+            image,
+            placement.allocation.x as u32,
+            placement.allocation.y as u32,
+            tileable,
+        );
+    }
+
+    /// Every page opened, a single blank one when nothing was placed.
+    pub fn into_pages(mut self) -> Vec<Page<C>> {
+        if self.pages.is_empty() {
+            let page = Page::new(&self);
+            self.pages.push(page);
+        }
+
+        self.pages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Packer, Spacing};
+    use crate::Algorithm;
+
+    #[test]
+    fn classes_and_floors_keep_sprites_off_earlier_pages() {
+        let mut packer = Packer::new(Algorithm::Etagere, 64, 64);
+
+        assert_eq!(packer.place(4, 4, 'a').unwrap().page, 0);
+        assert_eq!(packer.place(4, 4, 'b').unwrap().page, 1);
+        assert_eq!(packer.place(4, 4, 'a').unwrap().page, 0);
+
+        packer.raise_floor();
+
+        assert_eq!(packer.place(4, 4, 'a').unwrap().page, 2);
+        assert_eq!(packer.place(4, 4, 'b').unwrap().page, 1);
+    }
+
+    #[test]
+    fn spacing_and_rotation_decide_what_fits() {
+        let spacing = Spacing {
+            padding: 1,
+            border: 1,
+            extrude: 1,
+        };
+        let mut packer = Packer::<()>::new(Algorithm::Guillotiere, 8, 16)
+            .spacing(spacing)
+            .allow_rotation(true)
+            .max_pages(1);
+
+        // The border and extrusion put the first sprite two pixels in, the tall one stays upright
+        // because it doesn't fit lying down
+        let placement = packer.place(4, 12, ()).unwrap();
+
+        assert!(!placement.rotated);
+        assert_eq!((placement.allocation.x, placement.allocation.y), (2, 2));
+        assert!(packer.place(12, 4, ()).is_none());
+    }
+}
@@ -0,0 +1,77 @@
+use std::collections::BTreeSet;
+
+use image::{DynamicImage, GenericImageView, Rgba};
+use serde::Serialize;
+
+pub struct Palette {
+    colors: Vec<[u8; 3]>,
+}
+
+#[derive(Serialize)]
+pub struct PaletteReport {
+    pub colors: Vec<String>,
+    pub outside_palette: Vec<String>,
+}
+
+impl Palette {
+    pub fn from_image(image: &DynamicImage) -> Self {
+        Self {
+            colors: extract(image).into_iter().collect(),
+        }
+    }
+
+    pub fn report(&self, image: &DynamicImage) -> PaletteReport {
+        let colors = extract(image);
+
+        let outside_palette = colors
+            .iter()
+            .filter(|color| !self.colors.contains(color))
+            .map(hex)
+            .collect();
+
+        PaletteReport {
+            colors: colors.iter().map(hex).collect(),
+            outside_palette,
+        }
+    }
+
+    pub fn remap(&self, image: &DynamicImage) -> DynamicImage {
+        let mut image = image.to_rgba8();
+
+        for pixel in image.pixels_mut() {
+            let Rgba([r, g, b, a]) = *pixel;
+
+            if a == 0 || self.colors.contains(&[r, g, b]) {
+                continue;
+            }
+
+            if let Some([r, g, b]) = self.nearest([r, g, b]) {
+                *pixel = Rgba([r, g, b, a]);
+            }
+        }
+
+        DynamicImage::ImageRgba8(image)
+    }
+
+    fn nearest(&self, color: [u8; 3]) -> Option<[u8; 3]> {
+        self.colors.iter().copied().min_by_key(|candidate| {
+            candidate
+                .iter()
+                .zip(color)
+                .map(|(&a, b)| (a as i32 - b as i32).pow(2))
+                .sum::<i32>()
+        })
+    }
+}
+
+fn extract(image: &DynamicImage) -> BTreeSet<[u8; 3]> {
+    image
+        .pixels()
+        .filter(|(_, _, pixel)| pixel.0[3] != 0)
+        .map(|(_, _, Rgba([r, g, b, _]))| [r, g, b])
+        .collect()
+}
+
+fn hex(color: &[u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
@@ -0,0 +1,40 @@
+use std::path::Path;
+
+// Glob matching over forward-slash paths: `*` and `?` stay within a segment, `**` spans segments
+pub fn matches(pattern: &str, text: &str) -> bool {
+    matches_bytes(pattern.as_bytes(), text.as_bytes())
+}
+
+// Keys match either as-is or with their extension stripped, so `ui/*` and `ui/*.png` both work
+pub fn matches_key(pattern: &str, key: &Path) -> bool {
+    let key = key.to_string_lossy().replace('\\', "/");
+    let stem = key
+        .rsplit_once('.')
+        .filter(|(_, extension)| !extension.contains('/'))
+        .map(|(stem, _)| stem);
+
+    matches(pattern, &key) || stem.is_some_and(|stem| matches(pattern, stem))
+}
+
+fn matches_bytes(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        [b'*', b'*', b'/', rest @ ..] => (0..=text.len())
+            .filter(|&index| index == 0 || text[index - 1] == b'/')
+            .any(|index| matches_bytes(rest, &text[index..])),
+        [b'*', b'*', rest @ ..] => {
+            (0..=text.len()).any(|index| matches_bytes(rest, &text[index..]))
+        }
+        [b'*', rest @ ..] => (0..=text.len())
+            .take_while(|&index| index == 0 || text[index - 1] != b'/')
+            .any(|index| matches_bytes(rest, &text[index..])),
+        [b'?', rest @ ..] => match text {
+            [character, remaining @ ..] if *character != b'/' => matches_bytes(rest, remaining),
+            _ => false,
+        },
+        [expected, rest @ ..] => match text {
+            [character, remaining @ ..] if character == expected => matches_bytes(rest, remaining),
+            _ => false,
+        },
+    }
+}
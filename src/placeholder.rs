@@ -0,0 +1,208 @@
+use std::str::FromStr;
+
+use image::{Rgba, RgbaImage};
+
+#[derive(Clone)]
+pub struct Placeholder {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub style: PlaceholderStyle,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum PlaceholderStyle {
+    Checkerboard,
+    Solid,
+    Label,
+}
+
+impl Placeholder {
+    pub fn render(&self) -> RgbaImage {
+        let color = self.color();
+
+        match self.style {
+            PlaceholderStyle::Checkerboard => {
+                let cell = (self.width.min(self.height) / 4).max(1);
+
+                RgbaImage::from_fn(self.width, self.height, |x, y| {
+                    if (x / cell + y / cell).is_multiple_of(2) {
+                        color
+                    } else {
+                        Rgba([0, 0, 0, 255])
+                    }
+                })
+            }
+            PlaceholderStyle::Solid => RgbaImage::from_pixel(self.width, self.height, color),
+            PlaceholderStyle::Label => {
+                let mut image = RgbaImage::from_pixel(self.width, self.height, color);
+
+                draw_label(&mut image, &self.name);
+
+                image
+            }
+        }
+    }
+
+    fn color(&self) -> Rgba<u8> {
+        let hash = self.name.bytes().fold(0x811c9dc5u32, |hash, byte| {
+            (hash ^ byte as u32).wrapping_mul(0x01000193)
+        });
+
+        // Keep channels bright enough for black checker cells and labels to stay readable
+        Rgba([
+            128 | (hash & 0x7f) as u8,
+            128 | ((hash >> 8) & 0x7f) as u8,
+            128 | ((hash >> 16) & 0x7f) as u8,
+            255,
+        ])
+    }
+}
+
+impl FromStr for Placeholder {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (name, spec) = value
+            .split_once('=')
+            .ok_or_else(|| format!("expected name=WxH[:style], got '{value}'"))?;
+
+        if name.is_empty() {
+            return Err("placeholder name must not be empty".to_string());
+        }
+
+        let (size, style) = match spec.split_once(':') {
+            Some((size, style)) => (size, style.parse()?),
+            None => (spec, PlaceholderStyle::Checkerboard),
+        };
+
+        let (width, height) = size
+            .split_once('x')
+            .ok_or_else(|| format!("expected WxH size, got '{size}'"))?;
+
+        let width = width
+            .parse::<u32>()
+            .map_err(|_| format!("invalid placeholder width '{width}'"))?;
+        let height = height
+            .parse::<u32>()
+            .map_err(|_| format!("invalid placeholder height '{height}'"))?;
+
+        if width == 0 || height == 0 {
+            return Err("placeholder size must be non-zero".to_string());
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            width,
+            height,
+            style,
+        })
+    }
+}
+
+impl FromStr for PlaceholderStyle {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "checkerboard" => Ok(Self::Checkerboard),
+            "solid" => Ok(Self::Solid),
+            "label" => Ok(Self::Label),
+            _ => Err(format!(
+                "unknown placeholder style '{value}', expected checkerboard, solid or label"
+            )),
+        }
+    }
+}
+
+const GLYPH_WIDTH: u32 = 3;
+const GLYPH_HEIGHT: u32 = 5;
+
+fn draw_label(image: &mut RgbaImage, text: &str) {
+    let length = text.chars().count() as u32;
+
+    if length == 0 {
+        return;
+    }
+
+    // One column of spacing between glyphs, one row of margin around the text
+    let text_width = length * (GLYPH_WIDTH + 1) - 1;
+    let scale = (image.width() / (text_width + 2)).min(image.height() / (GLYPH_HEIGHT + 2));
+
+    if scale == 0 {
+        return;
+    }
+
+    let origin_x = (image.width() - text_width * scale) / 2;
+    let origin_y = (image.height() - GLYPH_HEIGHT * scale) / 2;
+
+    for (index, character) in text.chars().enumerate() {
+        let rows = glyph(character);
+        let glyph_x = origin_x + index as u32 * (GLYPH_WIDTH + 1) * scale;
+
+        for (row, bits) in rows.iter().enumerate() {
+            for column in 0..GLYPH_WIDTH {
+                if bits & (0b100 >> column) == 0 {
+                    continue;
+                }
+
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        image.put_pixel(
+                            glyph_x + column * scale + dx,
+                            origin_y + row as u32 * scale + dy,
+                            Rgba([0, 0, 0, 255]),
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn glyph(character: char) -> [u8; 5] {
+    match character.to_ascii_uppercase() {
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b110, 0b001, 0b010, 0b100, 0b111],
+        '3' => [0b110, 0b001, 0b010, 0b001, 0b110],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b110, 0b001, 0b110],
+        '6' => [0b011, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b110],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ' ' => [0b000, 0b000, 0b000, 0b000, 0b000],
+        _ => [0b110, 0b001, 0b010, 0b000, 0b010],
+    }
+}
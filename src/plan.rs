@@ -0,0 +1,236 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use atlas::{
+    allocator::{Allocator, AllocatorOptions},
+    Algorithm,
+};
+
+use crate::{
+    aspect::AspectRatio,
+    error::{self, Context, Error},
+};
+
+#[derive(Copy, Clone)]
+struct Sprite<'a> {
+    path: &'a Path,
+    width: u32,
+    height: u32,
+}
+
+struct Simulation {
+    pages: Vec<u64>,
+    unplaceable: usize,
+}
+
+pub fn plan(
+    files: &[PathBuf],
+    algorithm: Algorithm,
+    options: AllocatorOptions,
+    max_texture_size: u32,
+    max_pages: u32,
+    aspect_ratio: AspectRatio,
+) -> Result<(), Error> {
+    let sprites = error::collect(files.iter().map(|path| {
+        let (width, height) = image::image_dimensions(path).input_context(path)?;
+
+        Ok(Sprite {
+            path,
+            width,
+            height,
+        })
+    }))?;
+
+    let page_area = max_texture_size as u64 * max_texture_size as u64;
+    let total_area = sprites
+        .iter()
+        .map(|sprite| sprite.width as u64 * sprite.height as u64)
+        .sum::<u64>();
+
+    println!(
+        "{} sprites, {} px total ({:.2} pages of {max_texture_size}x{max_texture_size} by area alone)",
+        sprites.len(),
+        total_area,
+        total_area as f64 / page_area as f64
+    );
+
+    let oversized = sprites
+        .iter()
+        .filter(|sprite| sprite.width > max_texture_size || sprite.height > max_texture_size)
+        .collect::<Vec<_>>();
+
+    if !oversized.is_empty() {
+        println!();
+        println!("Sprites larger than the maximum texture size:");
+
+        for sprite in &oversized {
+            println!(
+                "  {} ({}x{})",
+                sprite.path.display(),
+                sprite.width,
+                sprite.height
+            );
+        }
+    }
+
+    let simulation = simulate(
+        &sprites,
+        algorithm,
+        options,
+        max_texture_size,
+        max_texture_size,
+    );
+
+    println!();
+    println!(
+        "Packs into {} pages of {max_texture_size}x{max_texture_size} (limit {max_pages})",
+        simulation.pages.len()
+    );
+
+    for (index, used) in simulation.pages.iter().enumerate() {
+        println!(
+            "  page {index}: {:.2}% occupied",
+            *used as f64 / page_area as f64 * 100.0
+        );
+    }
+
+    if simulation.pages.len() == 1 {
+        if let Some((width, height)) =
+            smallest_single_page(&sprites, algorithm, options, max_texture_size, aspect_ratio)
+        {
+            println!(
+                "  smallest fitting {}:{} page: {width}x{height}",
+                aspect_ratio.width, aspect_ratio.height
+            );
+        }
+    }
+
+    let mut groups: BTreeMap<&Path, Vec<Sprite>> = BTreeMap::new();
+
+    for sprite in &sprites {
+        groups
+            .entry(sprite.path.parent().unwrap_or(Path::new("")))
+            .or_default()
+            .push(*sprite);
+    }
+
+    println!();
+    println!("By directory:");
+
+    for (directory, group) in &groups {
+        let group_simulation = simulate(
+            group,
+            algorithm,
+            options,
+            max_texture_size,
+            max_texture_size,
+        );
+
+        println!(
+            "  {}: {} sprites, {} pages",
+            if directory.as_os_str().is_empty() {
+                ".".to_string()
+            } else {
+                directory.display().to_string()
+            },
+            group.len(),
+            group_simulation.pages.len()
+        );
+    }
+
+    println!();
+
+    if simulation.unplaceable > 0 {
+        println!(
+            "{} sprites can never fit, downscale or split them before packing",
+            simulation.unplaceable
+        );
+    }
+
+    if simulation.pages.len() as u32 > max_pages {
+        println!(
+            "Over budget by {} pages, split the directories above into separate atlases",
+            simulation.pages.len() as u32 - max_pages
+        );
+    } else if simulation.unplaceable == 0 {
+        println!("Fits within the page budget");
+    }
+
+    Ok(())
+}
+
+fn simulate(
+    sprites: &[Sprite],
+    algorithm: Algorithm,
+    options: AllocatorOptions,
+    page_width: u32,
+    page_height: u32,
+) -> Simulation {
+    let mut order = sprites.iter().collect::<Vec<_>>();
+    order.sort_by_key(|sprite| std::cmp::Reverse(sprite.width as u64 * sprite.height as u64));
+
+    let mut allocators: Vec<Allocator> = Vec::new();
+    let mut simulation = Simulation {
+        pages: Vec::new(),
+        unplaceable: 0,
+    };
+
+    for sprite in order {
+        if sprite.width > page_width || sprite.height > page_height {
+            simulation.unplaceable += 1;
+            continue;
+        }
+
+        let area = sprite.width as u64 * sprite.height as u64;
+
+        let existing = allocators
+            .iter_mut()
+            .position(|allocator| allocator.allocate(sprite.width, sprite.height).is_some());
+
+        match existing {
+            Some(index) => simulation.pages[index] += area,
+            None => {
+                let mut allocator =
+                    Allocator::with_options(algorithm, page_width, page_height, options);
+
+                if allocator.allocate(sprite.width, sprite.height).is_none() {
+                    simulation.unplaceable += 1;
+                    continue;
+                }
+
+                allocators.push(allocator);
+                simulation.pages.push(area);
+            }
+        }
+    }
+
+    simulation
+}
+
+fn smallest_single_page(
+    sprites: &[Sprite],
+    algorithm: Algorithm,
+    options: AllocatorOptions,
+    max_size: u32,
+    aspect_ratio: AspectRatio,
+) -> Option<(u32, u32)> {
+    let mut size = 1;
+
+    loop {
+        let (width, height) = aspect_ratio.page(size);
+
+        if width > max_size || height > max_size {
+            return None;
+        }
+
+        let simulation = simulate(sprites, algorithm, options, width, height);
+
+        if simulation.unplaceable == 0 && simulation.pages.len() <= 1 {
+            return Some((width, height));
+        }
+
+        size *= 2;
+    }
+}
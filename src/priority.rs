@@ -0,0 +1,34 @@
+use std::{path::Path, str::FromStr};
+
+use crate::pattern;
+
+#[derive(Clone)]
+pub struct Priority {
+    pattern: Option<String>,
+    pub priority: i32,
+}
+
+impl Priority {
+    pub fn includes(&self, key: &Path) -> bool {
+        self.pattern
+            .as_ref()
+            .is_none_or(|pattern| pattern::matches_key(pattern, key))
+    }
+}
+
+impl FromStr for Priority {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (pattern, priority) = match value.rsplit_once('=') {
+            Some((pattern, priority)) => (Some(pattern.to_string()), priority),
+            None => (None, value),
+        };
+
+        let priority = priority
+            .parse::<i32>()
+            .map_err(|_| format!("invalid priority '{priority}', expected an integer"))?;
+
+        Ok(Self { pattern, priority })
+    }
+}
@@ -0,0 +1,70 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+
+use crate::{
+    error::{Context, Error},
+    sha256,
+};
+
+#[derive(Serialize)]
+pub struct Provenance {
+    tool: &'static str,
+    version: &'static str,
+    arguments_sha256: String,
+    inputs: BTreeMap<PathBuf, String>,
+    outputs: BTreeMap<PathBuf, String>,
+}
+
+impl Provenance {
+    pub fn new(arguments: Vec<String>, inputs: BTreeMap<PathBuf, String>) -> Self {
+        let arguments = arguments.join("\0");
+
+        Self {
+            tool: env!("CARGO_PKG_NAME"),
+            version: env!("CARGO_PKG_VERSION"),
+            arguments_sha256: sha256::hex_digest(arguments.as_bytes()),
+            inputs,
+            outputs: BTreeMap::new(),
+        }
+    }
+
+    pub fn hash_file(path: &Path) -> Result<String, Error> {
+        Ok(sha256::hex_digest(&fs::read(path).input_context(path)?))
+    }
+
+    pub fn add_output(&mut self, path: PathBuf) -> Result<(), Error> {
+        let hash = sha256::hex_digest(&fs::read(&path).output_context(&path)?);
+
+        self.outputs.insert(path, hash);
+
+        Ok(())
+    }
+
+    // With a key, a detached HMAC-SHA256 of the exact bytes written goes next to the document as
+    // `<output>.mac`, so anyone holding the key can check it with any HMAC tool. It's a shared
+    // secret, not a signature, checking it takes the same key that makes one
+    pub fn write(&self, output: &Path, mac_key: Option<&[u8]>) -> Result<(), Error> {
+        let document = serde_json::to_string_pretty(self).unwrap();
+
+        fs::write(output, &document).output_context(output)?;
+
+        let Some(key) = mac_key else {
+            return Ok(());
+        };
+
+        let mut mac_output = output.as_os_str().to_owned();
+        mac_output.push(".mac");
+        let mac_output = PathBuf::from(mac_output);
+
+        fs::write(
+            &mac_output,
+            sha256::hex(&sha256::hmac(key, document.as_bytes())) + "\n",
+        )
+        .output_context(&mac_output)
+    }
+}
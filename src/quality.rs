@@ -0,0 +1,57 @@
+use std::{path::Path, str::FromStr};
+
+use crate::pattern;
+
+// The quality an encoder should give the pages a sprite ends up on, atlas writes lossless pages
+// and leaves the lossy encoding to the tool that reads the meta section
+#[derive(Clone)]
+pub struct QualityArg {
+    pattern: Option<String>,
+    pub quality: u8,
+}
+
+impl QualityArg {
+    pub fn includes(&self, key: &Path) -> bool {
+        self.pattern
+            .as_ref()
+            .is_none_or(|pattern| pattern::matches_key(pattern, key))
+    }
+}
+
+impl FromStr for QualityArg {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (pattern, quality) = match value.rsplit_once('=') {
+            Some((pattern, quality)) => (Some(pattern.to_string()), quality),
+            None => (None, value),
+        };
+
+        let quality = quality
+            .parse::<u8>()
+            .ok()
+            .filter(|quality| (1..=100).contains(quality))
+            .ok_or_else(|| format!("invalid quality '{quality}', expected 1-100"))?;
+
+        Ok(Self { pattern, quality })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::QualityArg;
+
+    #[test]
+    fn qualities_are_percentages() {
+        let quality = "ui/text/*=100".parse::<QualityArg>().unwrap();
+
+        assert_eq!(quality.quality, 100);
+        assert!(quality.includes("ui/text/title.png".as_ref()));
+        assert!("backgrounds/*=0".parse::<QualityArg>().is_err());
+        assert!("backgrounds/*=101".parse::<QualityArg>().is_err());
+        assert!("60"
+            .parse::<QualityArg>()
+            .unwrap()
+            .includes("any.png".as_ref()));
+    }
+}
@@ -0,0 +1,44 @@
+use std::{path::PathBuf, str::FromStr};
+
+#[derive(Clone)]
+pub struct Region {
+    pub key: PathBuf,
+    pub parent: PathBuf,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl FromStr for Region {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let format_error = || format!("expected key=parent@x,y,WxH, got '{value}'");
+
+        let (key, rest) = value.split_once('=').ok_or_else(format_error)?;
+        let (parent, rectangle) = rest.rsplit_once('@').ok_or_else(format_error)?;
+        let (x, rest) = rectangle.split_once(',').ok_or_else(format_error)?;
+        let (y, size) = rest.split_once(',').ok_or_else(format_error)?;
+        let (width, height) = size.split_once('x').ok_or_else(format_error)?;
+
+        let parse = |name: &str, number: &str| {
+            number
+                .parse::<u32>()
+                .map_err(|_| format!("invalid region {name} '{number}'"))
+        };
+
+        if key.is_empty() || parent.is_empty() {
+            return Err(format_error());
+        }
+
+        Ok(Self {
+            key: PathBuf::from(key),
+            parent: PathBuf::from(parent),
+            x: parse("x", x)?,
+            y: parse("y", y)?,
+            width: parse("width", width)?,
+            height: parse("height", height)?,
+        })
+    }
+}
@@ -0,0 +1,184 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    str::FromStr,
+};
+
+use crate::{
+    error::{Context, Error},
+    sha256,
+};
+
+// An input fetched over http or https, optionally pinned to the SHA-256 of its contents
+#[derive(Clone)]
+pub struct Remote {
+    pub url: String,
+    sha256: Option<String>,
+}
+
+impl Remote {
+    // The URL's path, so `https://cdn.example.com/art/ui/button.png` packs as `art/ui/button.png`
+    pub fn key(&self) -> PathBuf {
+        let path = self
+            .url
+            .split_once("://")
+            .map_or(self.url.as_str(), |(_, rest)| rest);
+        let path = path.split(['?', '#']).next().unwrap_or_default();
+
+        PathBuf::from(path.split_once('/').map_or("", |(_, path)| path))
+    }
+
+    // Downloads go to a file named after the URL's hash, a cached copy is only reused while it
+    // still has the pinned hash. Fetching is left to curl, which --remote only runs together with
+    // --allow-curl
+    pub fn fetch(&self, cache: &Path) -> Result<PathBuf, Error> {
+        let mut file_name = sha256::hex_digest(self.url.as_bytes());
+
+        if let Some(extension) = self.key().extension() {
+            file_name.push('.');
+            file_name.push_str(&extension.to_string_lossy());
+        }
+
+        let path = cache.join(file_name);
+
+        if path.exists() && self.verify(&fs::read(&path).input_context(&path)?).is_ok() {
+            return Ok(path);
+        }
+
+        fs::create_dir_all(cache).output_context(cache)?;
+
+        let partial = path.with_extension("partial");
+        let output = Command::new("curl")
+            .args([
+                "--fail",
+                "--silent",
+                "--show-error",
+                "--location",
+                "--output",
+            ])
+            .arg(&partial)
+            .arg(&self.url)
+            .output()
+            .map_err(|error| Error::input(&self.url, format!("couldn't run curl: {error}")))?;
+
+        if !output.status.success() {
+            let _ = fs::remove_file(&partial);
+
+            return Err(Error::input(
+                &self.url,
+                String::from_utf8_lossy(&output.stderr).trim(),
+            ));
+        }
+
+        if let Err(message) = self.verify(&fs::read(&partial).input_context(&partial)?) {
+            let _ = fs::remove_file(&partial);
+
+            return Err(Error::input(&self.url, message));
+        }
+
+        fs::rename(&partial, &path).output_context(&path)?;
+
+        Ok(path)
+    }
+
+    fn verify(&self, contents: &[u8]) -> Result<(), String> {
+        let Some(expected) = &self.sha256 else {
+            return Ok(());
+        };
+
+        let actual = sha256::hex_digest(contents);
+
+        if &actual == expected {
+            Ok(())
+        } else {
+            Err(format!("expected SHA-256 {expected}, got {actual}"))
+        }
+    }
+}
+
+impl FromStr for Remote {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (url, sha256) = match value.rsplit_once("#sha256=") {
+            Some((url, sha256)) => (url, Some(sha256.to_ascii_lowercase())),
+            None => (value, None),
+        };
+
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(format!("expected an http or https URL, got '{url}'"));
+        }
+
+        if let Some(sha256) = &sha256 {
+            if sha256.len() != 64 || !sha256.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+                return Err(format!(
+                    "invalid SHA-256 '{sha256}', expected 64 hex digits"
+                ));
+            }
+        }
+
+        let remote = Self {
+            url: url.to_string(),
+            sha256,
+        };
+
+        if remote.key().as_os_str().is_empty() {
+            return Err(format!("'{url}' doesn't name a file"));
+        }
+
+        Ok(remote)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use super::Remote;
+    use crate::sha256;
+
+    #[test]
+    fn keys_are_the_url_path() {
+        let remote = "https://cdn.example.com/art/ui/button.png?v=3"
+            .parse::<Remote>()
+            .unwrap();
+
+        assert_eq!(remote.key(), PathBuf::from("art/ui/button.png"));
+        assert!("s3://bucket/button.png".parse::<Remote>().is_err());
+        assert!("https://cdn.example.com/".parse::<Remote>().is_err());
+        assert!("https://cdn.example.com/a.png#sha256=abc"
+            .parse::<Remote>()
+            .is_err());
+    }
+
+    #[test]
+    fn cached_copies_are_reused_only_with_the_pinned_hash() {
+        let cache = std::env::temp_dir().join(format!("atlas-remote-{}", std::process::id()));
+        let url = "https://cdn.invalid/button.png";
+        let contents = b"not really a png";
+
+        fs::create_dir_all(&cache).unwrap();
+        fs::write(
+            cache.join(format!("{}.png", sha256::hex_digest(url.as_bytes()))),
+            contents,
+        )
+        .unwrap();
+
+        let pinned = format!("{url}#sha256={}", sha256::hex_digest(contents))
+            .parse::<Remote>()
+            .unwrap();
+        let path = pinned.fetch(&cache).ok().unwrap();
+
+        assert_eq!(fs::read(path).unwrap(), contents);
+
+        // A different pin doesn't trust the cached copy and has to download, which can't work
+        let other = format!("{url}#sha256={}", sha256::hex_digest(b"other"))
+            .parse::<Remote>()
+            .unwrap();
+
+        assert!(other.fetch(&cache).is_err());
+
+        fs::remove_dir_all(&cache).unwrap();
+    }
+}
@@ -0,0 +1,151 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use atlas::META_KEY;
+use serde_json::{Map, Value};
+
+use crate::error::{Context, Error};
+
+// Fields that refer to other fragments by key and have to follow a rename
+const KEY_FIELDS: [&str; 3] = ["parent", "alias_of", "lods"];
+
+pub fn rename(
+    map: &Path,
+    metadata_path: &Path,
+    output: &Path,
+    keep_aliases: bool,
+) -> Result<(), Error> {
+    let text = fs::read_to_string(map).input_context(map)?;
+    let renames = if map.extension().is_some_and(|extension| extension == "json") {
+        serde_json::from_str::<BTreeMap<String, String>>(&text).input_context(map)?
+    } else {
+        parse_toml(&text).input_context(map)?
+    };
+
+    let metadata = fs::read_to_string(metadata_path).input_context(metadata_path)?;
+    let Value::Object(fragments) =
+        serde_json::from_str::<Value>(&metadata).input_context(metadata_path)?
+    else {
+        return Err(Error::input(metadata_path, "expected a map of fragments"));
+    };
+
+    for old in renames.keys() {
+        if old == META_KEY || !fragments.contains_key(old) {
+            return Err(Error::input(map, format!("'{old}' is not a fragment key")));
+        }
+    }
+
+    let rename_key = |key: &str| renames.get(key).cloned().unwrap_or_else(|| key.to_string());
+    let mut renamed = Map::new();
+
+    for (key, mut fragment) in fragments {
+        if key == META_KEY {
+            renamed.insert(key, fragment);
+            continue;
+        }
+
+        for field in KEY_FIELDS {
+            if let Some(value) = fragment.get_mut(field) {
+                rename_references(value, &rename_key);
+            }
+        }
+
+        let new_key = rename_key(&key);
+
+        if renamed.contains_key(&new_key) {
+            return Err(Error::input(
+                map,
+                format!("more than one fragment would be named '{new_key}'"),
+            ));
+        }
+
+        if keep_aliases && new_key != key {
+            let mut alias = fragment.clone();
+            alias["alias_of"] = Value::String(new_key.clone());
+
+            renamed.insert(key, alias);
+        }
+
+        renamed.insert(new_key, fragment);
+    }
+
+    fs::write(
+        output,
+        serde_json::to_string_pretty(&Value::Object(renamed)).unwrap(),
+    )
+    .output_context(output)
+}
+
+fn rename_references(value: &mut Value, rename_key: &impl Fn(&str) -> String) {
+    match value {
+        Value::String(key) => *key = rename_key(key),
+        Value::Array(keys) => keys
+            .iter_mut()
+            .for_each(|key| rename_references(key, rename_key)),
+        _ => {}
+    }
+}
+
+// The subset of TOML a rename map needs: `"old" = "new"` pairs, comments and an optional [renames] table
+fn parse_toml(text: &str) -> Result<BTreeMap<String, String>, String> {
+    let mut renames = BTreeMap::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') || line == "[renames]" {
+            continue;
+        }
+
+        let (key, rest) = parse_string(line)
+            .ok_or_else(|| format!("line {line_number}: expected a quoted key"))?;
+        let rest = rest
+            .trim_start()
+            .strip_prefix('=')
+            .ok_or_else(|| format!("line {line_number}: expected '=' after the key"))?;
+        let (value, rest) = parse_string(rest.trim_start())
+            .ok_or_else(|| format!("line {line_number}: expected a quoted value"))?;
+
+        let rest = rest.trim_start();
+
+        if !rest.is_empty() && !rest.starts_with('#') {
+            return Err(format!("line {line_number}: unexpected '{rest}'"));
+        }
+
+        if renames.insert(key.clone(), value).is_some() {
+            return Err(format!(
+                "line {line_number}: '{key}' is renamed more than once"
+            ));
+        }
+    }
+
+    Ok(renames)
+}
+
+// Basic and literal strings, with the escapes a path could plausibly contain
+fn parse_string(text: &str) -> Option<(String, &str)> {
+    if let Some(literal) = text.strip_prefix('\'') {
+        let end = literal.find('\'')?;
+
+        return Some((literal[..end].to_string(), &literal[end + 1..]));
+    }
+
+    let mut characters = text.strip_prefix('"')?.char_indices();
+    let mut value = String::new();
+
+    while let Some((index, character)) = characters.next() {
+        match character {
+            '"' => return Some((value, &text[index + 2..])),
+            '\\' => match characters.next()?.1 {
+                '\\' => value.push('\\'),
+                '"' => value.push('"'),
+                'n' => value.push('\n'),
+                't' => value.push('\t'),
+                _ => return None,
+            },
+            character => value.push(character),
+        }
+    }
+
+    None
+}
@@ -0,0 +1,353 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{index, Fragment, SpriteId, Trim, Vector2, META_KEY};
+
+const MAGIC: &[u8; 4] = b"ATLM";
+const VERSION: u16 = 2;
+
+const ROTATED: u8 = 1;
+const TRIMMED: u8 = 2;
+
+/// Packing results read back from metadata written by `atlas generate`.
+///
+/// Both the JSON output and the binary `--metadata-format binary` output can be loaded, the
+/// binary one is meant for games that don't want to ship a JSON parser.
+///
+/// ```no_run
+/// let metadata = atlas::runtime::AtlasMetadata::load("atlas.bin")?;
+///
+/// let player = metadata.id("sprites/player.png").unwrap();
+/// println!("player is centered at {:?}", metadata.fragment(player).center);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct AtlasMetadata {
+    pub fragments: Vec<Fragment>,
+    pub names: Vec<PathBuf>,
+    ids: HashMap<PathBuf, SpriteId>,
+}
+
+#[derive(Debug)]
+pub enum MetadataError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    UnsupportedVersion(u16),
+    Truncated,
+    InvalidKey,
+    InvalidId(u32),
+}
+
+impl AtlasMetadata {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, MetadataError> {
+        Self::from_bytes(&fs::read(path).map_err(MetadataError::Io)?)
+    }
+
+    /// Binary metadata is recognized by its magic, anything else is parsed as JSON. The JSON
+    /// meta section is skipped, the binary format has none.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MetadataError> {
+        if bytes.starts_with(MAGIC) {
+            return Self::from_binary(&bytes[MAGIC.len()..]);
+        }
+
+        let mut fragments = serde_json::from_slice::<BTreeMap<PathBuf, serde_json::Value>>(bytes)
+            .map_err(MetadataError::Json)?;
+
+        fragments.remove(Path::new(META_KEY));
+
+        fragments
+            .into_iter()
+            .map(|(name, fragment)| Ok((name, serde_json::from_value(fragment)?)))
+            .collect::<Result<BTreeMap<_, _>, serde_json::Error>>()
+            .map(Self::from)
+            .map_err(MetadataError::Json)
+    }
+
+    /// Little endian, fragments in id order:
+    ///
+    /// ```text
+    /// "ATLM" version: u16 count: u32
+    /// count x (id: u32 name_length: u32 name: [u8] center: 2 x f32 size: 2 x f32 page: u32
+    ///          flags: u8 [source_size: 2 x f32 offset: 2 x f32 if trimmed])
+    /// ```
+    ///
+    /// Every entry carries its [`SpriteId`], so a game can map ids to names without sorting
+    /// them itself. Pages are `u32::MAX` for unpaged fragments, flags are 1 for rotated and 2
+    /// for trimmed. Centers are measured from the top left of the page with y pointing down,
+    /// there is no meta section to say otherwise.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(VERSION.to_le_bytes());
+        bytes.extend((self.fragments.len() as u32).to_le_bytes());
+
+        for (index, fragment) in self.fragments.iter().enumerate() {
+            let name = self.names[index].to_string_lossy();
+
+            bytes.extend((index as u32).to_le_bytes());
+            bytes.extend((name.len() as u32).to_le_bytes());
+            bytes.extend(name.as_bytes());
+
+            for value in [
+                fragment.center.x,
+                fragment.center.y,
+                fragment.size.x,
+                fragment.size.y,
+            ] {
+                bytes.extend(value.to_le_bytes());
+            }
+
+            bytes.extend(fragment.page.unwrap_or(u32::MAX).to_le_bytes());
+            bytes.push(
+                if fragment.rotated { ROTATED } else { 0 }
+                    | if fragment.trim.is_some() { TRIMMED } else { 0 },
+            );
+
+            if let Some(trim) = &fragment.trim {
+                for value in [
+                    trim.source_size.x,
+                    trim.source_size.y,
+                    trim.offset.x,
+                    trim.offset.y,
+                ] {
+                    bytes.extend(value.to_le_bytes());
+                }
+            }
+        }
+
+        bytes
+    }
+
+    pub fn id(&self, name: impl AsRef<Path>) -> Option<SpriteId> {
+        self.ids.get(name.as_ref()).copied()
+    }
+
+    pub fn fragment(&self, id: SpriteId) -> &Fragment {
+        &self.fragments[id.0 as usize]
+    }
+
+    pub fn name(&self, id: SpriteId) -> &Path {
+        &self.names[id.0 as usize]
+    }
+
+    fn from_binary(bytes: &[u8]) -> Result<Self, MetadataError> {
+        let mut reader = Reader(bytes);
+
+        let version = u16::from_le_bytes(reader.take()?);
+
+        if version != VERSION {
+            return Err(MetadataError::UnsupportedVersion(version));
+        }
+
+        let count = u32::from_le_bytes(reader.take()?);
+        let mut entries = Vec::new();
+
+        for _ in 0..count {
+            let id = u32::from_le_bytes(reader.take()?);
+            let length = u32::from_le_bytes(reader.take()?) as usize;
+            let name = std::str::from_utf8(reader.slice(length)?)
+                .map_err(|_| MetadataError::InvalidKey)?;
+
+            let center = reader.vector()?;
+            let size = reader.vector()?;
+            let page = u32::from_le_bytes(reader.take()?);
+            let [flags] = reader.take()?;
+
+            let trim = if flags & TRIMMED != 0 {
+                Some(Trim {
+                    source_size: reader.vector()?,
+                    offset: reader.vector()?,
+                })
+            } else {
+                None
+            };
+
+            entries.push((
+                id,
+                PathBuf::from(name),
+                Fragment {
+                    center,
+                    size,
+                    page: (page != u32::MAX).then_some(page),
+                    rotated: flags & ROTATED != 0,
+                    trim,
+                },
+            ));
+        }
+
+        // Ids must cover 0..count exactly once each
+        let mut slots = (0..entries.len()).map(|_| None).collect::<Vec<_>>();
+
+        for (id, name, fragment) in entries {
+            match slots.get_mut(id as usize) {
+                Some(slot @ None) => *slot = Some((name, fragment)),
+                _ => return Err(MetadataError::InvalidId(id)),
+            }
+        }
+
+        let (names, fragments): (Vec<_>, Vec<_>) = slots.into_iter().map(Option::unwrap).unzip();
+        let ids = names
+            .iter()
+            .enumerate()
+            .map(|(index, name)| (name.clone(), SpriteId(index as u32)))
+            .collect::<HashMap<_, _>>();
+
+        if ids.len() != names.len() {
+            return Err(MetadataError::InvalidKey);
+        }
+
+        Ok(Self {
+            fragments,
+            names,
+            ids,
+        })
+    }
+}
+
+impl From<BTreeMap<PathBuf, Fragment>> for AtlasMetadata {
+    fn from(fragments: BTreeMap<PathBuf, Fragment>) -> Self {
+        let (fragments, names, ids) = index(fragments);
+
+        Self {
+            fragments,
+            names,
+            ids,
+        }
+    }
+}
+
+impl fmt::Display for MetadataError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MetadataError::Io(error) => write!(formatter, "{error}"),
+            MetadataError::Json(error) => write!(formatter, "invalid metadata: {error}"),
+            MetadataError::UnsupportedVersion(version) => {
+                write!(formatter, "unsupported binary metadata version {version}")
+            }
+            MetadataError::Truncated => write!(formatter, "binary metadata ends unexpectedly"),
+            MetadataError::InvalidKey => {
+                write!(formatter, "binary metadata has a non UTF-8 or repeated key")
+            }
+            MetadataError::InvalidId(id) => {
+                write!(
+                    formatter,
+                    "binary metadata has an out of range or repeated id {id}"
+                )
+            }
+        }
+    }
+}
+
+impl Error for MetadataError {}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn slice(&mut self, length: usize) -> Result<&'a [u8], MetadataError> {
+        if self.0.len() < length {
+            return Err(MetadataError::Truncated);
+        }
+
+        let (slice, rest) = self.0.split_at(length);
+        self.0 = rest;
+
+        Ok(slice)
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], MetadataError> {
+        Ok(self.slice(N)?.try_into().unwrap())
+    }
+
+    fn vector(&mut self) -> Result<Vector2, MetadataError> {
+        Ok(Vector2::new(
+            f32::from_le_bytes(self.take()?),
+            f32::from_le_bytes(self.take()?),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, path::PathBuf};
+
+    use image::DynamicImage;
+
+    use super::{AtlasMetadata, MetadataError};
+    use crate::{AtlasBuilder, Fragment, SpriteId, Vector2};
+
+    fn fragment(x: f32) -> Fragment {
+        Fragment {
+            center: Vector2::new(x, 0.0),
+            size: Vector2::new(1.0, 1.0),
+            page: None,
+            rotated: false,
+            trim: None,
+        }
+    }
+
+    #[test]
+    fn builder_and_loaded_metadata_number_sprites_by_name() {
+        let atlas = AtlasBuilder::new(64, 64)
+            .add_image("b", DynamicImage::new_rgba8(2, 2))
+            .add_image("a", DynamicImage::new_rgba8(2, 2))
+            .build()
+            .unwrap();
+
+        assert_eq!(atlas.id("a"), Some(SpriteId(0)));
+        assert_eq!(atlas.id("b"), Some(SpriteId(1)));
+
+        let fragments = atlas
+            .names
+            .iter()
+            .cloned()
+            .zip(atlas.fragments.iter().cloned())
+            .collect::<BTreeMap<_, _>>();
+        let metadata = AtlasMetadata::from_bytes(&serde_json::to_vec(&fragments).unwrap()).unwrap();
+
+        for name in ["a", "b"] {
+            let id = atlas.id(name).unwrap();
+
+            assert_eq!(metadata.id(name), Some(id));
+            assert_eq!(metadata.fragment(id), atlas.fragment(id));
+        }
+    }
+
+    #[test]
+    fn binary_metadata_keeps_its_ids() {
+        let metadata = AtlasMetadata::from(BTreeMap::from([
+            (PathBuf::from("b"), fragment(2.0)),
+            (PathBuf::from("a"), fragment(1.0)),
+        ]));
+
+        let bytes = metadata.to_bytes();
+
+        // The first entry starts with its id, then the name it belongs to
+        assert_eq!(bytes[10..14], 0u32.to_le_bytes());
+        assert_eq!(bytes[14..19], [1, 0, 0, 0, b'a']);
+
+        let loaded = AtlasMetadata::from_bytes(&bytes).unwrap();
+
+        assert_eq!(loaded.names, metadata.names);
+        assert_eq!(loaded.fragment(SpriteId(1)), &fragment(2.0));
+    }
+
+    #[test]
+    fn binary_ids_must_cover_every_entry_once() {
+        let mut bytes = AtlasMetadata::from(BTreeMap::from([
+            (PathBuf::from("a"), fragment(1.0)),
+            (PathBuf::from("b"), fragment(2.0)),
+        ]))
+        .to_bytes();
+
+        // Both entries claim id 0, an entry is 4 + 4 + 1 + 16 + 4 + 1 bytes
+        let second = 10 + 30;
+        bytes[second..second + 4].copy_from_slice(&0u32.to_le_bytes());
+
+        assert!(matches!(
+            AtlasMetadata::from_bytes(&bytes),
+            Err(MetadataError::InvalidId(0))
+        ));
+    }
+}
@@ -0,0 +1,106 @@
+use std::{path::Path, str::FromStr};
+
+use serde::Serialize;
+
+use crate::pattern;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Filter {
+    Nearest,
+    Linear,
+}
+
+// Repeat means within the fragment, engines wrap the sprite's own rectangle
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Wrap {
+    Clamp,
+    Repeat,
+}
+
+// Hints for the sampler an engine sets up per fragment, atlas itself never samples with them
+#[derive(Copy, Clone, PartialEq, Debug, Default, Serialize)]
+pub struct Sampling {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<Filter>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wrap: Option<Wrap>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mip_bias: Option<f32>,
+}
+
+#[derive(Clone)]
+pub struct SamplingArg {
+    pattern: Option<String>,
+    pub sampling: Sampling,
+}
+
+impl SamplingArg {
+    pub fn includes(&self, key: &Path) -> bool {
+        self.pattern
+            .as_ref()
+            .is_none_or(|pattern| pattern::matches_key(pattern, key))
+    }
+}
+
+impl FromStr for SamplingArg {
+    type Err = String;
+
+    // Hints are told apart by their value, so any of them can be left out
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (pattern, hints) = match value.rsplit_once('=') {
+            Some((pattern, hints)) => (Some(pattern.to_string()), hints),
+            None => (None, value),
+        };
+
+        let mut sampling = Sampling::default();
+
+        for hint in hints.split(',').map(str::trim) {
+            match hint {
+                "nearest" => sampling.filter = Some(Filter::Nearest),
+                "linear" => sampling.filter = Some(Filter::Linear),
+                "clamp" => sampling.wrap = Some(Wrap::Clamp),
+                "repeat" => sampling.wrap = Some(Wrap::Repeat),
+                _ => match hint.parse::<f32>() {
+                    Ok(bias) if bias.is_finite() => sampling.mip_bias = Some(bias),
+                    _ => {
+                        return Err(format!(
+                            "invalid sampling hint '{hint}', expected nearest, linear, clamp, \
+                             repeat or a mip bias"
+                        ))
+                    }
+                },
+            }
+        }
+
+        Ok(Self { pattern, sampling })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Filter, Sampling, SamplingArg, Wrap};
+
+    #[test]
+    fn hints_are_told_apart_by_value() {
+        let sampling = "ui/**=repeat,nearest,-0.5".parse::<SamplingArg>().unwrap();
+
+        assert!(sampling.includes("ui/icons/gear.png".as_ref()));
+        assert!(!sampling.includes("props/crate.png".as_ref()));
+        assert_eq!(
+            sampling.sampling,
+            Sampling {
+                filter: Some(Filter::Nearest),
+                wrap: Some(Wrap::Repeat),
+                mip_bias: Some(-0.5),
+            }
+        );
+
+        let sampling = "linear".parse::<SamplingArg>().unwrap().sampling;
+
+        assert_eq!((sampling.wrap, sampling.mip_bias), (None, None));
+        assert!("ui/*=bilinear".parse::<SamplingArg>().is_err());
+        assert!("ui/*=NaN".parse::<SamplingArg>().is_err());
+    }
+}
@@ -0,0 +1,145 @@
+use std::path::Path;
+
+use clap::ValueEnum;
+use image::{GrayImage, ImageResult, RgbaImage};
+
+// Larger than any squared distance on a sprite, small enough that differences stay finite
+const FAR: f64 = 1e20;
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum SdfChannels {
+    Single,
+    Rgba,
+}
+
+#[derive(Copy, Clone)]
+pub struct Sdf {
+    pub spread: u32,
+    pub channels: SdfChannels,
+}
+
+impl Sdf {
+    // Single channel atlases keep only the distance, RGBA ones are white with it in alpha
+    pub fn save(&self, image: &RgbaImage, path: &Path) -> ImageResult<()> {
+        match self.channels {
+            SdfChannels::Single => GrayImage::from_fn(image.width(), image.height(), |x, y| {
+                image::Luma([image.get_pixel(x, y).0[3]])
+            })
+            .save(path),
+            SdfChannels::Rgba => image.save(path),
+        }
+    }
+}
+
+// Alpha at or above half is inside. The field needs room to fall off, so the sprite grows by
+// the spread on every side, 128 is the edge and 0 or 255 are a spread or more away from it
+pub fn generate(image: &RgbaImage, spread: u32) -> RgbaImage {
+    let (width, height) = (image.width() + spread * 2, image.height() + spread * 2);
+
+    let inside = |x: u32, y: u32| {
+        x >= spread
+            && y >= spread
+            && x - spread < image.width()
+            && y - spread < image.height()
+            && image.get_pixel(x - spread, y - spread).0[3] >= 128
+    };
+
+    let mut to_inside = vec![FAR; (width * height) as usize];
+    let mut to_outside = vec![FAR; (width * height) as usize];
+
+    for y in 0..height {
+        for x in 0..width {
+            let index = (y * width + x) as usize;
+
+            if inside(x, y) {
+                to_inside[index] = 0.0;
+            } else {
+                to_outside[index] = 0.0;
+            }
+        }
+    }
+
+    distance_transform(&mut to_inside, width as usize, height as usize);
+    distance_transform(&mut to_outside, width as usize, height as usize);
+
+    RgbaImage::from_fn(width, height, |x, y| {
+        let index = (y * width + x) as usize;
+
+        // Pixel centers are half a pixel from the edge between an inside and an outside pixel
+        let distance = if inside(x, y) {
+            to_outside[index].sqrt() - 0.5
+        } else {
+            0.5 - to_inside[index].sqrt()
+        };
+
+        let value = (0.5 + distance / (spread as f64 * 2.0)).clamp(0.0, 1.0);
+
+        image::Rgba([255, 255, 255, (value * 255.0).round() as u8])
+    })
+}
+
+// Squared euclidean distances to the nearest zero, columns first and then rows
+// (Felzenszwalb and Huttenlocher, Distance Transforms of Sampled Functions)
+fn distance_transform(grid: &mut [f64], width: usize, height: usize) {
+    let length = width.max(height);
+    let mut input = vec![0.0; length];
+    let mut output = vec![0.0; length];
+    let mut parabolas = vec![0; length];
+    let mut bounds = vec![0.0; length + 1];
+
+    for x in 0..width {
+        for y in 0..height {
+            input[y] = grid[y * width + x];
+        }
+
+        transform_line(&input[..height], &mut output, &mut parabolas, &mut bounds);
+
+        for y in 0..height {
+            grid[y * width + x] = output[y];
+        }
+    }
+
+    for y in 0..height {
+        input[..width].copy_from_slice(&grid[y * width..(y + 1) * width]);
+
+        transform_line(&input[..width], &mut output, &mut parabolas, &mut bounds);
+
+        grid[y * width..(y + 1) * width].copy_from_slice(&output[..width]);
+    }
+}
+
+fn transform_line(input: &[f64], output: &mut [f64], parabolas: &mut [usize], bounds: &mut [f64]) {
+    let intersection = |q: usize, p: usize| {
+        ((input[q] + (q * q) as f64) - (input[p] + (p * p) as f64)) / (2 * q - 2 * p) as f64
+    };
+
+    let mut count = 0;
+    parabolas[0] = 0;
+    bounds[0] = f64::NEG_INFINITY;
+    bounds[1] = f64::INFINITY;
+
+    for q in 1..input.len() {
+        let mut s = intersection(q, parabolas[count]);
+
+        while s <= bounds[count] {
+            count -= 1;
+            s = intersection(q, parabolas[count]);
+        }
+
+        count += 1;
+        parabolas[count] = q;
+        bounds[count] = s;
+        bounds[count + 1] = f64::INFINITY;
+    }
+
+    count = 0;
+
+    for (q, output) in output.iter_mut().enumerate().take(input.len()) {
+        while bounds[count + 1] < q as f64 {
+            count += 1;
+        }
+
+        let p = parabolas[count];
+        *output = (q.abs_diff(p) * q.abs_diff(p)) as f64 + input[p];
+    }
+}
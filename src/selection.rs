@@ -0,0 +1,56 @@
+use atlas::{
+    allocator::{Allocator, AllocatorOptions},
+    Algorithm,
+};
+
+// Lower scores win, algorithms that can't pack everything at all lose to every other one
+pub fn best(score: impl Fn(Algorithm) -> Option<(usize, u64)>) -> Algorithm {
+    Algorithm::ALL
+        .into_iter()
+        .min_by_key(|&algorithm| {
+            let score = score(algorithm);
+
+            (score.is_none(), score)
+        })
+        .unwrap()
+}
+
+// Packs rect sizes only, spilling like the real run, and scores by pages then by how much of
+// the last page the packed sprites span
+pub fn score(
+    sizes: &[(u32, u32)],
+    algorithm: Algorithm,
+    options: AllocatorOptions,
+    (width, height): (u32, u32),
+) -> Option<(usize, u64)> {
+    let mut pages: Vec<(Allocator, u32, u32)> = Vec::new();
+
+    for &(sprite_width, sprite_height) in sizes {
+        let existing = pages.iter_mut().find_map(|(allocator, right, bottom)| {
+            allocator
+                .allocate(sprite_width, sprite_height)
+                .map(|allocation| (allocation, right, bottom))
+        });
+
+        let (allocation, right, bottom) = match existing {
+            Some(existing) => existing,
+            None => {
+                let mut allocator = Allocator::with_options(algorithm, width, height, options);
+                let allocation = allocator.allocate(sprite_width, sprite_height)?;
+
+                pages.push((allocator, 0, 0));
+
+                let (_, right, bottom) = pages.last_mut().unwrap();
+
+                (allocation, right, bottom)
+            }
+        };
+
+        *right = (*right).max(allocation.x as u32 + sprite_width);
+        *bottom = (*bottom).max(allocation.y as u32 + sprite_height);
+    }
+
+    let (_, right, bottom) = pages.last()?;
+
+    Some((pages.len(), *right as u64 * *bottom as u64))
+}
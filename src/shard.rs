@@ -0,0 +1,45 @@
+use std::{path::Path, str::FromStr};
+
+use crate::sha256;
+
+#[derive(Copy, Clone)]
+pub struct Shard {
+    index: u32,
+    count: u32,
+}
+
+impl Shard {
+    // Keyed on the fragment key rather than input order so shards stay stable as inputs are added
+    pub fn includes(&self, key: &Path) -> bool {
+        let key = key.to_string_lossy().replace('\\', "/");
+        let digest = sha256::digest(key.as_bytes());
+        let value = u64::from_be_bytes(digest[..8].try_into().unwrap());
+
+        value % self.count as u64 == self.index as u64
+    }
+}
+
+impl FromStr for Shard {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (index, count) = value
+            .split_once('/')
+            .ok_or_else(|| format!("expected i/n, got '{value}'"))?;
+
+        let index = index
+            .parse::<u32>()
+            .map_err(|_| format!("invalid shard index '{index}'"))?;
+        let count = count
+            .parse::<u32>()
+            .map_err(|_| format!("invalid shard count '{count}'"))?;
+
+        if count == 0 || index >= count {
+            return Err(format!(
+                "shard index must be below a non-zero shard count, got {index}/{count}"
+            ));
+        }
+
+        Ok(Self { index, count })
+    }
+}
@@ -0,0 +1,24 @@
+// SplitMix64, small and stable across platforms and releases so a seed always means the same order
+pub struct SplitMix64(pub u64);
+
+impl SplitMix64 {
+    pub fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+        let mut value = self.0;
+        value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+
+        value ^ (value >> 31)
+    }
+}
+
+pub fn shuffle<T>(items: &mut [T], seed: u64) {
+    let mut random = SplitMix64(seed);
+
+    for index in (1..items.len()).rev() {
+        let other = (random.next() % (index as u64 + 1)) as usize;
+
+        items.swap(index, other);
+    }
+}
@@ -0,0 +1,37 @@
+use std::{cmp::Reverse, path::PathBuf};
+
+use clap::ValueEnum;
+use image::{DynamicImage, GenericImageView};
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum SortOrder {
+    AreaDesc,
+    MaxSideDesc,
+    HeightDesc,
+    PerimeterDesc,
+    Name,
+    None,
+}
+
+// Ties fall back to the key so the order never depends on how inputs were listed
+pub fn sort(images: &mut [(PathBuf, DynamicImage)], order: SortOrder) {
+    let measure = |image: &DynamicImage| {
+        let (width, height) = image.dimensions();
+
+        match order {
+            SortOrder::AreaDesc => width as u64 * height as u64,
+            SortOrder::MaxSideDesc => width.max(height) as u64,
+            SortOrder::HeightDesc => height as u64,
+            SortOrder::PerimeterDesc => 2 * (width as u64 + height as u64),
+            SortOrder::Name | SortOrder::None => 0,
+        }
+    };
+
+    match order {
+        SortOrder::None => {}
+        SortOrder::Name => images.sort_by(|(a, _), (b, _)| a.cmp(b)),
+        _ => images.sort_by(|(a, a_image), (b, b_image)| {
+            (Reverse(measure(a_image)), a).cmp(&(Reverse(measure(b_image)), b))
+        }),
+    }
+}
@@ -0,0 +1,96 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Context, Error};
+
+const BAR_WIDTH: usize = 40;
+
+#[derive(Serialize, Deserialize)]
+pub struct RunStats {
+    pub timestamp: u64,
+    pub sprites: usize,
+    pub pages: u32,
+    pub occupancy: f32,
+    pub output_bytes: u64,
+    pub load_ms: u128,
+    pub pack_ms: u128,
+    pub write_ms: u128,
+}
+
+pub fn append(path: &Path, stats: &RunStats) -> Result<(), Error> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).output_context(path)?;
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .output_context(path)?;
+
+    writeln!(file, "{}", serde_json::to_string(stats).unwrap()).output_context(path)
+}
+
+pub fn print_trends(path: &Path, last: usize) -> Result<(), Error> {
+    let history = fs::read_to_string(path).input_context(path)?;
+    let runs = history
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str::<RunStats>(line).input_context(path))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let runs = &runs[runs.len().saturating_sub(last)..];
+
+    if runs.is_empty() {
+        println!("No runs recorded in {}", path.display());
+        return Ok(());
+    }
+
+    let max_bytes = runs.iter().map(|run| run.output_bytes).max().unwrap_or(0);
+
+    println!(
+        "{:>10}  {:>7}  {:>5}  {:>8}  {:<bar$}  {:>10}  {:>8}",
+        "timestamp",
+        "sprites",
+        "pages",
+        "occupied",
+        "occupancy",
+        "bytes",
+        "total ms",
+        bar = BAR_WIDTH
+    );
+
+    for run in runs {
+        let filled = ((run.occupancy / 100.0) * BAR_WIDTH as f32).round() as usize;
+
+        println!(
+            "{:>10}  {:>7}  {:>5}  {:>7.2}%  {:<bar$}  {:>10}  {:>8}",
+            run.timestamp,
+            run.sprites,
+            run.pages,
+            run.occupancy,
+            "#".repeat(filled.min(BAR_WIDTH)),
+            run.output_bytes,
+            run.load_ms + run.pack_ms + run.write_ms,
+            bar = BAR_WIDTH
+        );
+    }
+
+    let (first, latest) = (&runs[0], &runs[runs.len() - 1]);
+
+    println!();
+    println!(
+        "Occupancy {:+.2} points, output size {:+} bytes over {} runs (largest output {} bytes)",
+        latest.occupancy - first.occupancy,
+        latest.output_bytes as i64 - first.output_bytes as i64,
+        runs.len(),
+        max_bytes
+    );
+
+    Ok(())
+}
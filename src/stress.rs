@@ -0,0 +1,114 @@
+use std::path::PathBuf;
+
+use atlas::{
+    allocator::{Allocator, AllocatorOptions},
+    Algorithm,
+};
+
+use crate::{
+    error::{self, Context, Error},
+    shuffle,
+};
+
+struct Run {
+    seed: u64,
+    occupancy: f64,
+    unplaced: usize,
+}
+
+pub fn stress(
+    files: &[PathBuf],
+    algorithm: Algorithm,
+    options: AllocatorOptions,
+    width: u32,
+    height: u32,
+    seeds: u64,
+) -> Result<(), Error> {
+    let sprites = error::collect(
+        files
+            .iter()
+            .map(|path| image::image_dimensions(path).input_context(path)),
+    )?;
+
+    let page_area = width as f64 * height as f64;
+
+    let runs = (0..seeds)
+        .map(|seed| {
+            let mut order = sprites.clone();
+            shuffle::shuffle(&mut order, seed);
+
+            let mut allocator = Allocator::with_options(algorithm, width, height, options);
+            let mut used = 0;
+            let mut unplaced = 0;
+
+            for (sprite_width, sprite_height) in order {
+                match allocator.allocate(sprite_width, sprite_height) {
+                    Some(_) => used += sprite_width as u64 * sprite_height as u64,
+                    None => unplaced += 1,
+                }
+            }
+
+            Run {
+                seed,
+                occupancy: used as f64 / page_area * 100.0,
+                unplaced,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let Some(first) = runs.first() else {
+        println!("No seeds to run");
+        return Ok(());
+    };
+
+    let mean = runs.iter().map(|run| run.occupancy).sum::<f64>() / runs.len() as f64;
+    let variance = runs
+        .iter()
+        .map(|run| (run.occupancy - mean).powi(2))
+        .sum::<f64>()
+        / runs.len() as f64;
+
+    let (worst, best) = runs.iter().fold((first, first), |(worst, best), run| {
+        (
+            if run.occupancy < worst.occupancy {
+                run
+            } else {
+                worst
+            },
+            if run.occupancy > best.occupancy {
+                run
+            } else {
+                best
+            },
+        )
+    });
+
+    let failed = runs
+        .iter()
+        .filter(|run| run.unplaced > 0)
+        .collect::<Vec<_>>();
+
+    println!(
+        "{} sprites into {width}x{height} across {} seeds",
+        sprites.len(),
+        runs.len()
+    );
+    println!(
+        "occupancy: mean {mean:.2}%, variance {variance:.4}, standard deviation {:.4}",
+        variance.sqrt()
+    );
+    println!("  worst: {:.2}% (seed {})", worst.occupancy, worst.seed);
+    println!("  best: {:.2}% (seed {})", best.occupancy, best.seed);
+
+    if failed.is_empty() {
+        println!("Every seed placed all sprites");
+    } else {
+        println!("{} seeds failed to place every sprite:", failed.len());
+
+        for run in failed {
+            println!("  seed {}: {} unplaced", run.seed, run.unplaced);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    path::{Path, PathBuf},
+};
+
+use image::DynamicImage;
+
+use crate::{
+    decode::{self, DecodeLimits},
+    error::{Context, Error},
+    metadata,
+};
+
+pub fn load(
+    atlas: &Path,
+    metadata_path: &Path,
+    limits: Option<&DecodeLimits>,
+) -> Result<Vec<(PathBuf, DynamicImage)>, Error> {
+    let fragments = metadata::read(metadata_path)?;
+    let mut pages = HashMap::new();
+
+    // Spilled atlases keep each page in its own file, only decode the ones that are referenced
+    for page in fragments.values().map(|fragment| fragment.page) {
+        if let Entry::Vacant(entry) = pages.entry(page) {
+            let path = match page {
+                Some(page) => metadata::page_path(atlas, page),
+                None => atlas.to_path_buf(),
+            };
+
+            entry.insert(decode::open(&path, limits).input_context(&path)?);
+        }
+    }
+
+    let mut sprites = fragments
+        .into_iter()
+        .map(|(key, fragment)| {
+            if fragment.layer.is_some() {
+                return Err(Error::input(
+                    metadata_path,
+                    "describes a texture array, only single page atlases can be used as inputs",
+                ));
+            }
+
+            let rectangle = fragment.rectangle();
+            let image = pages[&fragment.page].crop_imm(
+                rectangle.x,
+                rectangle.y,
+                rectangle.width,
+                rectangle.height,
+            );
+
+            // Rotated sprites were turned clockwise when packed, turn them back
+            if fragment.rotated == Some(true) {
+                return Ok((key, image.rotate270()));
+            }
+
+            Ok((key, image))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Metadata is a map, sort to keep packing order independent of hashing
+    sprites.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    Ok(sprites)
+}
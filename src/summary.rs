@@ -0,0 +1,64 @@
+use atlas::Algorithm;
+use image::DynamicImage;
+
+use crate::{trim, warnings::Warning};
+
+pub struct Summary {
+    pub pages: u32,
+    pub width: u32,
+    pub height: u32,
+    pub sprites: usize,
+    pub auto_algorithm: Option<Algorithm>,
+    pub aliases: usize,
+    pub occupancy: f32,
+    pub wasted_area: u64,
+    pub warnings: Vec<(Warning, usize)>,
+    pub suggestions: Vec<String>,
+}
+
+impl Summary {
+    pub fn print(&self) {
+        eprintln!("Summary:");
+        eprintln!("  pages: {} ({}x{})", self.pages, self.width, self.height);
+        eprintln!("  sprites: {}", self.sprites);
+
+        if let Some(algorithm) = self.auto_algorithm {
+            eprintln!("  algorithm: {} (auto)", algorithm.name());
+        }
+
+        if self.aliases > 0 {
+            eprintln!("  deduplicated: {}", self.aliases);
+        }
+
+        eprintln!("  occupancy: {:.2}%", self.occupancy);
+        eprintln!("  wasted area: {} px", self.wasted_area);
+
+        if !self.warnings.is_empty() {
+            eprintln!(
+                "  warnings: {}",
+                self.warnings
+                    .iter()
+                    .map(|(warning, count)| format!(
+                        "{} {} x{count}",
+                        warning.code(),
+                        warning.name()
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+
+        for suggestion in &self.suggestions {
+            eprintln!("  suggestion: {suggestion}");
+        }
+    }
+}
+
+// Pixels outside the bounding box of everything that isn't fully transparent
+pub fn transparent_area(image: &DynamicImage) -> u64 {
+    let area = image.width() as u64 * image.height() as u64;
+
+    trim::bounds(image).map_or(area, |bounds| {
+        area - bounds.width as u64 * bounds.height as u64
+    })
+}
@@ -0,0 +1,70 @@
+use std::{fmt::Write, fs, io, path::Path};
+
+use crate::Placement;
+
+const PAGE_GAP: u32 = 16;
+
+pub fn write_layout(
+    path: &Path,
+    page_width: u32,
+    page_height: u32,
+    page_count: usize,
+    placements: &[Placement],
+) -> io::Result<()> {
+    let total_height =
+        page_height * page_count as u32 + PAGE_GAP * page_count.saturating_sub(1) as u32;
+
+    let mut svg = String::new();
+
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{page_width}" height="{total_height}" viewBox="0 0 {page_width} {total_height}">"#
+    )
+    .unwrap();
+
+    for page in 0..page_count {
+        writeln!(
+            svg,
+            r##"  <rect x="0" y="{}" width="{page_width}" height="{page_height}" fill="#f0f0f0" stroke="#000000"/>"##,
+            page_offset(page, page_height)
+        )
+        .unwrap();
+    }
+
+    for (index, placement) in placements.iter().enumerate() {
+        let y = page_offset(placement.page, page_height) + placement.y;
+        let hue = (index * 137) % 360;
+        let label = escape(&placement.key.display().to_string());
+        let font_size = (placement.height as f32 / 2.0).clamp(1.0, 12.0);
+
+        writeln!(
+            svg,
+            r#"  <rect x="{}" y="{y}" width="{}" height="{}" fill="hsl({hue}, 60%, 70%)" stroke="hsl({hue}, 60%, 35%)"><title>{label}</title></rect>"#,
+            placement.x, placement.width, placement.height
+        )
+        .unwrap();
+
+        writeln!(
+            svg,
+            r#"  <text x="{}" y="{}" font-family="monospace" font-size="{font_size}" text-anchor="middle" dominant-baseline="central">{label}</text>"#,
+            placement.x as f32 + placement.width as f32 / 2.0,
+            y as f32 + placement.height as f32 / 2.0
+        )
+        .unwrap();
+    }
+
+    svg.push_str("</svg>\n");
+
+    fs::write(path, svg)
+}
+
+fn page_offset(page: usize, page_height: u32) -> u32 {
+    page as u32 * (page_height + PAGE_GAP)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
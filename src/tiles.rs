@@ -0,0 +1,100 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use image::{GenericImage, GenericImageView, RgbaImage};
+use serde::Serialize;
+
+use crate::metadata::Rectangle;
+
+#[derive(Clone, Serialize)]
+pub struct TileRange {
+    pub column: u32,
+    pub row: u32,
+    pub columns: u32,
+    pub rows: u32,
+}
+
+#[derive(Serialize)]
+pub struct Tile {
+    page: usize,
+    column: u32,
+    row: u32,
+    path: PathBuf,
+}
+
+#[derive(Serialize)]
+pub struct TileIndex {
+    tile_size: u32,
+    columns: u32,
+    rows: u32,
+    tiles: Vec<Tile>,
+}
+
+pub fn range(rectangle: &Rectangle, tile_size: u32) -> TileRange {
+    let column = rectangle.x / tile_size;
+    let row = rectangle.y / tile_size;
+
+    TileRange {
+        column,
+        row,
+        columns: (rectangle.x + rectangle.width.max(1) - 1) / tile_size - column + 1,
+        rows: (rectangle.y + rectangle.height.max(1) - 1) / tile_size - row + 1,
+    }
+}
+
+// Tiles go in a directory next to the atlas, edge tiles are padded so every tile has the same size
+pub fn write(atlas: &Path, pages: &[&RgbaImage], tile_size: u32) -> io::Result<TileIndex> {
+    let mut directory_name = atlas.file_stem().unwrap_or_default().to_os_string();
+    directory_name.push("_tiles");
+
+    let directory = atlas.with_file_name(directory_name);
+    fs::create_dir_all(&directory)?;
+
+    let (width, height) = pages.first().map_or((0, 0), |page| page.dimensions());
+    let columns = width.div_ceil(tile_size);
+    let rows = height.div_ceil(tile_size);
+
+    let mut tiles = Vec::new();
+
+    for (page_index, page) in pages.iter().enumerate() {
+        for row in 0..rows {
+            for column in 0..columns {
+                let (x, y) = (column * tile_size, row * tile_size);
+
+                let mut tile = RgbaImage::new(tile_size, tile_size);
+                tile.copy_from(
+                    &*page.view(x, y, tile_size.min(width - x), tile_size.min(height - y)),
+                    0,
+                    0,
+                )
+                .map_err(io::Error::other)?;
+
+                let mut file_name = format!("{page_index}_{column}_{row}");
+
+                if let Some(extension) = atlas.extension() {
+                    file_name.push('.');
+                    file_name.push_str(&extension.to_string_lossy());
+                }
+
+                let path = directory.join(file_name);
+                tile.save(&path).map_err(io::Error::other)?;
+
+                tiles.push(Tile {
+                    page: page_index,
+                    column,
+                    row,
+                    path,
+                });
+            }
+        }
+    }
+
+    Ok(TileIndex {
+        tile_size,
+        columns,
+        rows,
+        tiles,
+    })
+}
@@ -0,0 +1,330 @@
+use std::{
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::error::{Context, Error};
+
+// Just enough XML for TexturePacker's project files: elements, text and entities. Attributes are
+// skipped, the settings never live in them
+struct Element {
+    name: String,
+    text: String,
+    children: Vec<Element>,
+}
+
+impl Element {
+    // A setting is a <key> element followed by its value, wherever the struct nests it
+    fn find(&self, key: &str) -> Option<&Element> {
+        self.children
+            .windows(2)
+            .find(|pair| pair[0].name == "key" && pair[0].text == key)
+            .map(|pair| &pair[1])
+            .or_else(|| self.children.iter().find_map(|child| child.find(key)))
+    }
+
+    fn number(&self, key: &str) -> Option<u32> {
+        self.find(key)?.text.trim().parse().ok()
+    }
+
+    fn flag(&self, key: &str) -> bool {
+        self.find(key).is_some_and(|value| value.name == "true")
+    }
+}
+
+fn parse(text: &str) -> Result<Element, String> {
+    let mut stack = vec![Element {
+        name: String::new(),
+        text: String::new(),
+        children: Vec::new(),
+    }];
+    let mut rest = text;
+
+    while let Some(start) = rest.find('<') {
+        let content = &rest[..start];
+        stack.last_mut().unwrap().text.push_str(&unescape(content));
+        rest = &rest[start..];
+
+        let end = if rest.starts_with("<!--") {
+            rest.find("-->").map(|end| end + 3)
+        } else {
+            rest.find('>').map(|end| end + 1)
+        }
+        .ok_or("unterminated tag")?;
+
+        let tag = &rest[1..end - 1];
+        rest = &rest[end..];
+
+        if tag.starts_with(['?', '!']) {
+            continue;
+        }
+
+        if let Some(name) = tag.strip_prefix('/') {
+            let element = stack
+                .pop()
+                .filter(|_| !stack.is_empty())
+                .ok_or("unexpected </")?;
+
+            if element.name != name.trim() {
+                return Err(format!("<{}> closed by </{}>", element.name, name.trim()));
+            }
+
+            stack.last_mut().unwrap().children.push(element);
+            continue;
+        }
+
+        let empty = tag.ends_with('/');
+        let name = tag
+            .trim_end_matches('/')
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        let element = Element {
+            name,
+            text: String::new(),
+            children: Vec::new(),
+        };
+
+        if empty {
+            stack.last_mut().unwrap().children.push(element);
+        } else {
+            stack.push(element);
+        }
+    }
+
+    match stack.pop() {
+        Some(root) if stack.is_empty() => Ok(root),
+        _ => Err("unclosed element".to_string()),
+    }
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+// TexturePacker's data format ids, the ones atlas writes as well
+fn metadata_format(id: &str) -> Option<&'static str> {
+    [
+        ("phaser", "phaser"),
+        ("libgdx", "libgdx"),
+        ("unity", "unity"),
+        ("cocos2d", "cocos2d"),
+        ("sparrow", "sparrow"),
+        ("starling", "sparrow"),
+        ("json-array", "texture-packer-array"),
+        ("json", "texture-packer-hash"),
+    ]
+    .into_iter()
+    .find(|(prefix, _)| id.starts_with(prefix))
+    .map(|(_, format)| format)
+}
+
+// The generate invocation that packs a TexturePacker project the same way, with notes for the
+// settings it can't carry over. Paths in the project are relative to it
+pub fn import(project: &Path) -> Result<(Vec<OsString>, Vec<String>), Error> {
+    let text = fs::read_to_string(project).input_context(project)?;
+    let root = parse(&text).map_err(|message| Error::input(project, message))?;
+    let directory = project.parent().unwrap_or(Path::new(""));
+    let path = |element: &Element| directory.join(element.text.trim()).into_os_string();
+
+    let mut arguments = vec!["atlas".into(), "generate".into()];
+    let mut notes = Vec::new();
+
+    let files = root
+        .find("fileList")
+        .map(|list| list.children.iter().map(path).collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    if files.is_empty() {
+        return Err(Error::input(
+            project,
+            "the project doesn't list any sprites",
+        ));
+    }
+
+    for file in files {
+        arguments.extend(["--files".into(), file]);
+    }
+
+    arguments.push("--recursive".into());
+
+    let texture = root
+        .find("textureFileName")
+        .filter(|texture| !texture.text.trim().is_empty())
+        .ok_or_else(|| Error::input(project, "the project has no textureFileName"))?;
+
+    arguments.extend(["--atlas-output".into(), path(texture)]);
+
+    let data_file = root
+        .find("dataFileNames")
+        .and_then(|names| names.find("name"))
+        .filter(|name| !name.text.trim().is_empty());
+
+    match data_file {
+        Some(data_file) => arguments.extend(["--metadata-output".into(), path(data_file)]),
+        None => {
+            let mut metadata_output = PathBuf::from(path(texture));
+            metadata_output.set_extension("json");
+
+            arguments.extend(["--metadata-output".into(), metadata_output.into()]);
+        }
+    }
+
+    if let Some(id) = root.find("dataFormat").map(|format| format.text.trim()) {
+        match metadata_format(id) {
+            Some(format) => arguments.extend(["--metadata-format".into(), format.into()]),
+            None => notes.push(format!(
+                "data format '{id}' isn't written by atlas, using its own json"
+            )),
+        }
+    }
+
+    for (key, flag) in [
+        ("shapePadding", "--padding"),
+        ("borderPadding", "--border"),
+        ("extrude", "--extrude"),
+    ] {
+        if let Some(value) = root.number(key).filter(|&value| value > 0) {
+            arguments.extend([flag.into(), value.to_string().into()]);
+        }
+    }
+
+    if root
+        .find("trimMode")
+        .is_some_and(|mode| mode.text.trim() != "None")
+    {
+        arguments.push("--trim".into());
+    }
+
+    if root.flag("allowRotation") {
+        arguments.push("--allow-rotation".into());
+    }
+
+    arguments.push("--auto-size".into());
+
+    if let Some(size) = root.find("maxTextureSize") {
+        for (key, flag) in [("width", "--max-width"), ("height", "--max-height")] {
+            if let Some(value) = size.number(key) {
+                arguments.extend([flag.into(), value.to_string().into()]);
+            }
+        }
+    }
+
+    if root.flag("forceSquared") {
+        arguments.push("--square".into());
+    }
+
+    if root
+        .find("sizeConstraints")
+        .is_some_and(|constraint| constraint.text.contains("POT"))
+    {
+        arguments.push("--power-of-two".into());
+    }
+
+    if root.flag("multiPack") {
+        notes.push("multipack is on, atlas opens more pages as needed anyway".to_string());
+    }
+
+    Ok((arguments, notes))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{import, parse};
+
+    const PROJECT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<data version="1.0">
+    <struct type="Settings">
+        <key>fileFormatVersion</key>
+        <int>6</int>
+        <key>dataFormat</key>
+        <string>phaser</string>
+        <key>textureFileName</key>
+        <filename>out/atlas.png</filename>
+        <key>maxTextureSize</key>
+        <QSize>
+            <key>width</key>
+            <int>2048</int>
+            <key>height</key>
+            <int>1024</int>
+        </QSize>
+        <key>allowRotation</key>
+        <false/>
+        <key>sizeConstraints</key>
+        <enum type="AlgorithmSettings::SizeConstraints">POT</enum>
+        <key>shapePadding</key>
+        <uint>2</uint>
+        <key>borderPadding</key>
+        <uint>0</uint>
+        <key>dataFileNames</key>
+        <map type="GFileNameMap">
+            <key>data</key>
+            <struct type="DataFile">
+                <key>name</key>
+                <filename>out/atlas.json</filename>
+            </struct>
+        </map>
+        <key>globalSpriteSettings</key>
+        <struct type="SpriteSettings">
+            <key>extrude</key>
+            <uint>1</uint>
+            <key>trimMode</key>
+            <enum type="SpriteSettings::TrimMode">Trim</enum>
+        </struct>
+        <key>fileList</key>
+        <array>
+            <filename>sprites</filename>
+            <filename>ui &amp; hud</filename>
+        </array>
+    </struct>
+</data>
+"#;
+
+    #[test]
+    fn settings_become_generate_arguments() {
+        let directory =
+            std::env::temp_dir().join(format!("atlas-import-tps-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("game.tps"), PROJECT).unwrap();
+
+        let (arguments, notes) = import(&directory.join("game.tps")).ok().unwrap();
+        let arguments = arguments
+            .iter()
+            .map(|argument| {
+                argument
+                    .to_string_lossy()
+                    .replace(&*directory.to_string_lossy(), "DIR")
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        assert_eq!(
+            arguments,
+            "atlas generate --files DIR/sprites --files DIR/ui & hud --recursive \
+             --atlas-output DIR/out/atlas.png --metadata-output DIR/out/atlas.json \
+             --metadata-format phaser --padding 2 --extrude 1 --trim --auto-size \
+             --max-width 2048 --max-height 1024 --power-of-two"
+        );
+        assert!(notes.is_empty());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn mismatched_tags_are_rejected() {
+        assert!(parse("<data><struct></data>").is_err());
+        assert!(parse("<data>").is_err());
+        assert_eq!(
+            parse("<a><b/></a>").ok().unwrap().children[0].children[0].name,
+            "b"
+        );
+    }
+}
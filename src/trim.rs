@@ -0,0 +1,39 @@
+use atlas::{Trim, Vector2};
+use image::{DynamicImage, GenericImageView};
+
+use crate::metadata::Rectangle;
+
+// The bounding box of every pixel that isn't fully transparent, None for an empty image
+pub fn bounds(image: &DynamicImage) -> Option<Rectangle> {
+    let (left, top, right, bottom) = image.pixels().filter(|(_, _, pixel)| pixel.0[3] > 0).fold(
+        (u32::MAX, u32::MAX, 0, 0),
+        |(left, top, right, bottom), (x, y, _)| {
+            (left.min(x), top.min(y), right.max(x + 1), bottom.max(y + 1))
+        },
+    );
+
+    (left < right).then(|| Rectangle {
+        x: left,
+        y: top,
+        width: right - left,
+        height: bottom - top,
+    })
+}
+
+// Fully transparent images keep a single pixel, so they still get a fragment to look up
+pub fn trim(image: &DynamicImage) -> (DynamicImage, Trim) {
+    let bounds = bounds(image).unwrap_or(Rectangle {
+        x: 0,
+        y: 0,
+        width: 1,
+        height: 1,
+    });
+
+    (
+        image.crop_imm(bounds.x, bounds.y, bounds.width, bounds.height),
+        Trim {
+            source_size: Vector2::new(image.width() as f32, image.height() as f32),
+            offset: Vector2::new(bounds.x as f32, bounds.y as f32),
+        },
+    )
+}
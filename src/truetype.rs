@@ -0,0 +1,910 @@
+use image::{Rgba, RgbaImage};
+
+const ARGS_ARE_WORDS: u16 = 0x0001;
+const ARGS_ARE_XY_VALUES: u16 = 0x0002;
+const HAS_SCALE: u16 = 0x0008;
+const MORE_COMPONENTS: u16 = 0x0020;
+const HAS_X_AND_Y_SCALE: u16 = 0x0040;
+const HAS_TWO_BY_TWO: u16 = 0x0080;
+
+const ON_CURVE: u8 = 0x01;
+const X_SHORT: u8 = 0x02;
+const Y_SHORT: u8 = 0x04;
+const REPEAT: u8 = 0x08;
+const X_SAME_OR_POSITIVE: u8 = 0x10;
+const Y_SAME_OR_POSITIVE: u8 = 0x20;
+
+// Composite glyphs can nest, bad fonts could nest forever
+const MAX_COMPONENT_DEPTH: u32 = 8;
+
+#[derive(Copy, Clone)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+    pub on_curve: bool,
+}
+
+pub struct Bitmap {
+    pub image: RgbaImage,
+    // Pixels from the pen position to the left edge, and from the baseline up to the top edge
+    pub left: i32,
+    pub top: i32,
+}
+
+// Only TrueType outlines from the glyf table are read, CFF based OpenType fonts are rejected
+pub struct Font<'a> {
+    pub units_per_em: u16,
+    pub ascender: i16,
+    pub descender: i16,
+    pub line_gap: i16,
+    glyph_count: u16,
+    long_offsets: bool,
+    horizontal_metrics: u16,
+    cmap: &'a [u8],
+    glyf: &'a [u8],
+    loca: &'a [u8],
+    hmtx: &'a [u8],
+    kern: Option<&'a [u8]>,
+}
+
+impl<'a> Font<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, String> {
+        match data.get(..4) {
+            Some([0, 1, 0, 0] | b"true") => {}
+            Some(b"OTTO") => {
+                return Err("only fonts with TrueType outlines are supported, not CFF".to_string())
+            }
+            Some(b"ttcf") => return Err("font collections are not supported".to_string()),
+            _ => return Err("not a TrueType font".to_string()),
+        }
+
+        let table_count = read_u16(data, 4)? as usize;
+        let table = |tag: &[u8; 4]| -> Result<Option<&'a [u8]>, String> {
+            for index in 0..table_count {
+                let record = 12 + index * 16;
+
+                if data.get(record..record + 4) == Some(tag) {
+                    let offset = read_u32(data, record + 8)? as usize;
+                    let length = read_u32(data, record + 12)? as usize;
+
+                    return data
+                        .get(offset..offset.saturating_add(length))
+                        .map(Some)
+                        .ok_or_else(truncated);
+                }
+            }
+
+            Ok(None)
+        };
+        let required = |tag: &[u8; 4]| {
+            table(tag)?.ok_or_else(|| format!("font has no {} table", String::from_utf8_lossy(tag)))
+        };
+
+        let head = required(b"head")?;
+        let hhea = required(b"hhea")?;
+        let maxp = required(b"maxp")?;
+
+        Ok(Self {
+            units_per_em: read_u16(head, 18)?.max(1),
+            ascender: read_u16(hhea, 4)? as i16,
+            descender: read_u16(hhea, 6)? as i16,
+            line_gap: read_u16(hhea, 8)? as i16,
+            glyph_count: read_u16(maxp, 4)?,
+            long_offsets: read_u16(head, 50)? != 0,
+            horizontal_metrics: read_u16(hhea, 34)?,
+            cmap: unicode_cmap(required(b"cmap")?)?,
+            glyf: required(b"glyf")?,
+            loca: required(b"loca")?,
+            hmtx: required(b"hmtx")?,
+            kern: table(b"kern")?,
+        })
+    }
+
+    // 0 is .notdef, which is what fonts map every character they don't have to
+    pub fn glyph_index(&self, character: u32) -> u16 {
+        let cmap = self.cmap;
+
+        let index = match read_u16(cmap, 0) {
+            Ok(4) => (|| {
+                let segment_count = read_u16(cmap, 6)? as usize / 2;
+                let ends = 14;
+                let starts = ends + segment_count * 2 + 2;
+                let deltas = starts + segment_count * 2;
+                let range_offsets = deltas + segment_count * 2;
+
+                for segment in 0..segment_count {
+                    let end = read_u16(cmap, ends + segment * 2)? as u32;
+
+                    if character > end {
+                        continue;
+                    }
+
+                    let start = read_u16(cmap, starts + segment * 2)? as u32;
+
+                    if character < start {
+                        return Ok(0);
+                    }
+
+                    let delta = read_u16(cmap, deltas + segment * 2)?;
+                    let range_offset = read_u16(cmap, range_offsets + segment * 2)? as usize;
+
+                    if range_offset == 0 {
+                        return Ok((character as u16).wrapping_add(delta));
+                    }
+
+                    let glyph = read_u16(
+                        cmap,
+                        range_offsets
+                            + segment * 2
+                            + range_offset
+                            + (character - start) as usize * 2,
+                    )?;
+
+                    return Ok(if glyph == 0 {
+                        0
+                    } else {
+                        glyph.wrapping_add(delta)
+                    });
+                }
+
+                Ok(0)
+            })(),
+            Ok(12) => (|| {
+                let group_count = read_u32(cmap, 12)? as usize;
+
+                for group in 0..group_count {
+                    let record = 16 + group * 12;
+                    let start = read_u32(cmap, record)?;
+                    let end = read_u32(cmap, record + 4)?;
+
+                    if (start..=end).contains(&character) {
+                        return Ok((read_u32(cmap, record + 8)? + character - start) as u16);
+                    }
+                }
+
+                Ok(0)
+            })(),
+            _ => Ok::<_, String>(0),
+        };
+
+        index.unwrap_or(0).min(self.glyph_count.saturating_sub(1))
+    }
+
+    pub fn advance(&self, glyph: u16) -> u16 {
+        let metric = glyph.min(self.horizontal_metrics.saturating_sub(1)) as usize;
+
+        read_u16(self.hmtx, metric * 4).unwrap_or(0)
+    }
+
+    // Pairs from the legacy kern table's horizontal format 0 subtables, GPOS kerning isn't read
+    pub fn kerning(&self) -> Vec<(u16, u16, i16)> {
+        let Some(kern) = self.kern else {
+            return Vec::new();
+        };
+
+        let mut pairs = Vec::new();
+        let subtable_count = read_u16(kern, 2).unwrap_or(0);
+        let mut offset = 4;
+
+        for _ in 0..subtable_count {
+            let (Ok(length), Ok(coverage)) =
+                (read_u16(kern, offset + 2), read_u16(kern, offset + 4))
+            else {
+                break;
+            };
+
+            // Horizontal, format 0, not minimum values and not cross-stream
+            if coverage & 0xff07 == 0x0001 {
+                let pair_count = read_u16(kern, offset + 6).unwrap_or(0) as usize;
+
+                for pair in 0..pair_count {
+                    let record = offset + 14 + pair * 6;
+
+                    if let (Ok(left), Ok(right), Ok(value)) = (
+                        read_u16(kern, record),
+                        read_u16(kern, record + 2),
+                        read_u16(kern, record + 4),
+                    ) {
+                        pairs.push((left, right, value as i16));
+                    }
+                }
+            }
+
+            offset += length.max(6) as usize;
+        }
+
+        pairs
+    }
+
+    // Contours in font units with y pointing up, empty for glyphs without an outline
+    pub fn outline(&self, glyph: u16) -> Result<Vec<Vec<Point>>, String> {
+        let mut contours = Vec::new();
+        self.append_outline(glyph, [1.0, 0.0, 0.0, 1.0, 0.0, 0.0], 0, &mut contours)?;
+
+        Ok(contours)
+    }
+
+    fn glyph_data(&self, glyph: u16) -> Result<&'a [u8], String> {
+        let index = glyph as usize;
+
+        let (start, end) = if self.long_offsets {
+            (
+                read_u32(self.loca, index * 4)? as usize,
+                read_u32(self.loca, index * 4 + 4)? as usize,
+            )
+        } else {
+            (
+                read_u16(self.loca, index * 2)? as usize * 2,
+                read_u16(self.loca, index * 2 + 2)? as usize * 2,
+            )
+        };
+
+        if start >= end {
+            return Ok(&[]);
+        }
+
+        self.glyf.get(start..end).ok_or_else(truncated)
+    }
+
+    fn append_outline(
+        &self,
+        glyph: u16,
+        transform: [f32; 6],
+        depth: u32,
+        contours: &mut Vec<Vec<Point>>,
+    ) -> Result<(), String> {
+        let data = self.glyph_data(glyph)?;
+
+        if data.is_empty() {
+            return Ok(());
+        }
+
+        let contour_count = read_u16(data, 0)? as i16;
+
+        if contour_count >= 0 {
+            contours.extend(simple_outline(data, contour_count as usize, transform)?);
+
+            return Ok(());
+        }
+
+        if depth >= MAX_COMPONENT_DEPTH {
+            return Err("composite glyphs are nested too deeply".to_string());
+        }
+
+        let mut offset = 10;
+
+        loop {
+            let flags = read_u16(data, offset)?;
+            let component = read_u16(data, offset + 2)?;
+            offset += 4;
+
+            let (dx, dy) = if flags & ARGS_ARE_WORDS != 0 {
+                offset += 4;
+                (
+                    read_u16(data, offset - 4)? as i16 as f32,
+                    read_u16(data, offset - 2)? as i16 as f32,
+                )
+            } else {
+                offset += 2;
+                (
+                    *data.get(offset - 2).ok_or_else(truncated)? as i8 as f32,
+                    *data.get(offset - 1).ok_or_else(truncated)? as i8 as f32,
+                )
+            };
+
+            // Components positioned by matching points are rare, they are placed at the origin
+            let (dx, dy) = if flags & ARGS_ARE_XY_VALUES != 0 {
+                (dx, dy)
+            } else {
+                (0.0, 0.0)
+            };
+
+            let f2dot14 = |offset: usize| -> Result<f32, String> {
+                Ok(read_u16(data, offset)? as i16 as f32 / 16384.0)
+            };
+
+            let [a, b, c, d] = if flags & HAS_SCALE != 0 {
+                offset += 2;
+                let scale = f2dot14(offset - 2)?;
+                [scale, 0.0, 0.0, scale]
+            } else if flags & HAS_X_AND_Y_SCALE != 0 {
+                offset += 4;
+                [f2dot14(offset - 4)?, 0.0, 0.0, f2dot14(offset - 2)?]
+            } else if flags & HAS_TWO_BY_TWO != 0 {
+                offset += 8;
+                [
+                    f2dot14(offset - 8)?,
+                    f2dot14(offset - 6)?,
+                    f2dot14(offset - 4)?,
+                    f2dot14(offset - 2)?,
+                ]
+            } else {
+                [1.0, 0.0, 0.0, 1.0]
+            };
+
+            // The component's own transform applies first, then the one it is placed with
+            let [ta, tb, tc, td, te, tf] = transform;
+            let combined = [
+                ta * a + tc * b,
+                tb * a + td * b,
+                ta * c + tc * d,
+                tb * c + td * d,
+                ta * dx + tc * dy + te,
+                tb * dx + td * dy + tf,
+            ];
+
+            self.append_outline(component, combined, depth + 1, contours)?;
+
+            if flags & MORE_COMPONENTS == 0 {
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn simple_outline(
+    data: &[u8],
+    contour_count: usize,
+    transform: [f32; 6],
+) -> Result<Vec<Vec<Point>>, String> {
+    let mut ends = Vec::with_capacity(contour_count);
+
+    for contour in 0..contour_count {
+        ends.push(read_u16(data, 10 + contour * 2)? as usize);
+    }
+
+    let point_count = ends.last().map_or(0, |end| end + 1);
+    let instructions = read_u16(data, 10 + contour_count * 2)? as usize;
+    let mut offset = 12 + contour_count * 2 + instructions;
+
+    let mut flags = Vec::with_capacity(point_count);
+
+    while flags.len() < point_count {
+        let flag = *data.get(offset).ok_or_else(truncated)?;
+        offset += 1;
+
+        let repeat = if flag & REPEAT != 0 {
+            offset += 1;
+            *data.get(offset - 1).ok_or_else(truncated)? as usize
+        } else {
+            0
+        };
+
+        for _ in 0..=repeat {
+            flags.push(flag);
+        }
+    }
+
+    flags.truncate(point_count);
+
+    let mut coordinates = |short: u8, same_or_positive: u8| -> Result<Vec<f32>, String> {
+        let mut value = 0i32;
+        let mut values = Vec::with_capacity(point_count);
+
+        for &flag in &flags {
+            if flag & short != 0 {
+                let delta = *data.get(offset).ok_or_else(truncated)? as i32;
+                offset += 1;
+                value += if flag & same_or_positive != 0 {
+                    delta
+                } else {
+                    -delta
+                };
+            } else if flag & same_or_positive == 0 {
+                value += read_u16(data, offset)? as i16 as i32;
+                offset += 2;
+            }
+
+            values.push(value as f32);
+        }
+
+        Ok(values)
+    };
+
+    let xs = coordinates(X_SHORT, X_SAME_OR_POSITIVE)?;
+    let ys = coordinates(Y_SHORT, Y_SAME_OR_POSITIVE)?;
+
+    let [a, b, c, d, e, f] = transform;
+    let mut contours = Vec::with_capacity(contour_count);
+    let mut start = 0;
+
+    for end in ends {
+        if end < start || end >= point_count {
+            return Err("glyph has invalid contours".to_string());
+        }
+
+        contours.push(
+            (start..=end)
+                .map(|index| Point {
+                    x: a * xs[index] + c * ys[index] + e,
+                    y: b * xs[index] + d * ys[index] + f,
+                    on_curve: flags[index] & ON_CURVE != 0,
+                })
+                .collect(),
+        );
+
+        start = end + 1;
+    }
+
+    Ok(contours)
+}
+
+fn unicode_cmap(cmap: &[u8]) -> Result<&[u8], String> {
+    let subtable_count = read_u16(cmap, 2)? as usize;
+    let mut best = None;
+
+    // Full repertoire subtables first, then the BMP ones
+    for index in 0..subtable_count {
+        let record = 4 + index * 8;
+        let platform = read_u16(cmap, record)?;
+        let encoding = read_u16(cmap, record + 2)?;
+        let offset = read_u32(cmap, record + 4)? as usize;
+
+        let rank = match (platform, encoding, read_u16(cmap, offset)?) {
+            (3, 10, 12) | (0, 4 | 6, 12) => 0,
+            (3, 1, 4) | (0, 0..=3, 4) => 1,
+            _ => continue,
+        };
+
+        if best.is_none_or(|(best_rank, _)| rank < best_rank) {
+            best = Some((rank, offset));
+        }
+    }
+
+    let (_, offset) = best.ok_or("font has no Unicode character map")?;
+
+    cmap.get(offset..).ok_or_else(truncated)
+}
+
+// Quadratic contours are flattened to lines and rasterized with exact area coverage
+pub fn rasterize(contours: &[Vec<Point>], scale: f32) -> Option<Bitmap> {
+    let mut lines = Vec::new();
+
+    for contour in contours {
+        flatten(contour, scale, &mut lines);
+    }
+
+    let points = lines.iter().flat_map(|&(start, end)| [start, end]);
+    let (min_x, min_y, max_x, max_y) = points.fold(
+        (f32::MAX, f32::MAX, f32::MIN, f32::MIN),
+        |(min_x, min_y, max_x, max_y), (x, y)| {
+            (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y))
+        },
+    );
+
+    if lines.is_empty() || min_x >= max_x || min_y >= max_y {
+        return None;
+    }
+
+    let left = min_x.floor();
+    let top = min_y.floor();
+    let width = (max_x - left).ceil() as usize;
+    let height = (max_y - top).ceil() as usize;
+
+    let mut accumulation = vec![0.0f32; width * height + 2];
+
+    for ((x0, y0), (x1, y1)) in lines {
+        draw_line(
+            &mut accumulation,
+            width,
+            height,
+            (x0 - left, y0 - top),
+            (x1 - left, y1 - top),
+        );
+    }
+
+    let mut image = RgbaImage::new(width as u32, height as u32);
+    let mut coverage = 0.0;
+
+    for (index, pixel) in image.pixels_mut().enumerate() {
+        coverage += accumulation[index];
+        *pixel = Rgba([
+            255,
+            255,
+            255,
+            (coverage.abs().min(1.0) * 255.0).round() as u8,
+        ]);
+    }
+
+    Some(Bitmap {
+        image,
+        left: left as i32,
+        top: -top as i32,
+    })
+}
+
+type Line = ((f32, f32), (f32, f32));
+
+#[derive(Copy, Clone)]
+pub enum Segment {
+    Line((f32, f32), (f32, f32)),
+    Quadratic((f32, f32), (f32, f32), (f32, f32)),
+}
+
+// Points come out in pixels with y pointing down
+pub fn segments(contour: &[Point], scale: f32) -> Vec<Segment> {
+    let mut points = Vec::with_capacity(contour.len() * 2);
+
+    // Two off curve points in a row imply an on curve point halfway between them
+    for (index, point) in contour.iter().enumerate() {
+        let previous = contour[(index + contour.len() - 1) % contour.len()];
+
+        if !point.on_curve && !previous.on_curve {
+            points.push(Point {
+                x: (previous.x + point.x) / 2.0,
+                y: (previous.y + point.y) / 2.0,
+                on_curve: true,
+            });
+        }
+
+        points.push(*point);
+    }
+
+    let Some(first) = points.iter().position(|point| point.on_curve) else {
+        return Vec::new();
+    };
+
+    points.rotate_left(first);
+    points.push(points[0]);
+
+    let pixel = |point: &Point| (point.x * scale, -point.y * scale);
+    let mut segments = Vec::new();
+    let mut current = pixel(&points[0]);
+    let mut index = 1;
+
+    while index < points.len() {
+        if points[index].on_curve {
+            let next = pixel(&points[index]);
+
+            if next != current {
+                segments.push(Segment::Line(current, next));
+            }
+
+            current = next;
+            index += 1;
+        } else {
+            let control = pixel(&points[index]);
+            let end = pixel(&points[(index + 1).min(points.len() - 1)]);
+
+            segments.push(Segment::Quadratic(current, control, end));
+            current = end;
+            index += 2;
+        }
+    }
+
+    segments
+}
+
+// Curves are split into lines close enough to them that the difference doesn't show
+fn flatten(contour: &[Point], scale: f32, lines: &mut Vec<Line>) {
+    for segment in segments(contour, scale) {
+        match segment {
+            Segment::Line(start, end) => lines.push((start, end)),
+            Segment::Quadratic(start, control, end) => {
+                let deviation = ((start.0 - 2.0 * control.0 + end.0).powi(2)
+                    + (start.1 - 2.0 * control.1 + end.1).powi(2))
+                .sqrt();
+                let count = ((deviation / 0.3).sqrt().ceil() as usize).clamp(1, 64);
+                let mut current = start;
+
+                for index in 1..=count {
+                    let t = index as f32 / count as f32;
+                    let point = (
+                        (1.0 - t).powi(2) * start.0
+                            + 2.0 * (1.0 - t) * t * control.0
+                            + t * t * end.0,
+                        (1.0 - t).powi(2) * start.1
+                            + 2.0 * (1.0 - t) * t * control.1
+                            + t * t * end.1,
+                    );
+
+                    lines.push((current, point));
+                    current = point;
+                }
+            }
+        }
+    }
+}
+
+// Signed area accumulation, summing the buffer in order gives the coverage of every pixel
+fn draw_line(
+    accumulation: &mut [f32],
+    width: usize,
+    height: usize,
+    start: (f32, f32),
+    end: (f32, f32),
+) {
+    if start.1 == end.1 {
+        return;
+    }
+
+    let (direction, start, end) = if start.1 < end.1 {
+        (1.0, start, end)
+    } else {
+        (-1.0, end, start)
+    };
+
+    let slope = (end.0 - start.0) / (end.1 - start.1);
+    let clamp = |x: f32| x.clamp(0.0, width as f32);
+    let mut x = start.0;
+
+    for row in start.1.max(0.0) as usize..(end.1.ceil() as usize).min(height) {
+        let row_start = row * width;
+        let dy = end.1.min(row as f32 + 1.0) - start.1.max(row as f32);
+        let next_x = x + slope * dy;
+        let d = dy * direction;
+
+        let (x0, x1) = if x < next_x {
+            (clamp(x), clamp(next_x))
+        } else {
+            (clamp(next_x), clamp(x))
+        };
+
+        let x0_floor = x0.floor();
+        let x0_index = x0_floor as usize;
+        let x1_ceil = x1.ceil();
+        let x1_index = x1_ceil as usize;
+
+        if x1_index <= x0_index + 1 {
+            let middle = 0.5 * (x0 + x1) - x0_floor;
+            accumulation[row_start + x0_index] += d - d * middle;
+            accumulation[row_start + x0_index + 1] += d * middle;
+        } else {
+            let inverse = (x1 - x0).recip();
+            let x0_fraction = x0 - x0_floor;
+            let first = 0.5 * inverse * (1.0 - x0_fraction).powi(2);
+            let x1_fraction = x1 - x1_ceil + 1.0;
+            let last = 0.5 * inverse * x1_fraction.powi(2);
+
+            accumulation[row_start + x0_index] += d * first;
+
+            if x1_index == x0_index + 2 {
+                accumulation[row_start + x0_index + 1] += d * (1.0 - first - last);
+            } else {
+                let second = inverse * (1.5 - x0_fraction);
+                accumulation[row_start + x0_index + 1] += d * (second - first);
+
+                for column in x0_index + 2..x1_index - 1 {
+                    accumulation[row_start + column] += d * inverse;
+                }
+
+                let before_last = second + (x1_index - x0_index - 3) as f32 * inverse;
+                accumulation[row_start + x1_index - 1] += d * (1.0 - before_last - last);
+            }
+
+            accumulation[row_start + x1_index] += d * last;
+        }
+
+        x = next_x;
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, String> {
+    data.get(offset..offset + 2)
+        .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+        .ok_or_else(truncated)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, String> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .ok_or_else(truncated)
+}
+
+fn truncated() -> String {
+    "font data ends unexpectedly".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{rasterize, Font, Point};
+
+    fn u16s(values: &[u16]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect()
+    }
+
+    // Table directory first, then the tables in the order given
+    fn font(tables: &[(&[u8; 4], Vec<u8>)]) -> Vec<u8> {
+        let mut data = vec![0, 1, 0, 0];
+        data.extend(u16s(&[tables.len() as u16, 0, 0, 0]));
+
+        let mut offset = 12 + tables.len() * 16;
+
+        for (tag, table) in tables {
+            data.extend(*tag);
+            data.extend([0; 4]);
+            data.extend((offset as u32).to_be_bytes());
+            data.extend((table.len() as u32).to_be_bytes());
+            offset += table.len();
+        }
+
+        for (_, table) in tables {
+            data.extend(table);
+        }
+
+        data
+    }
+
+    // A 100 unit square with its corner at the origin, every point on the curve
+    fn square() -> Vec<u8> {
+        let mut glyph = u16s(&[1, 0, 0, 100, 100, 3, 0]);
+        glyph.extend([0x01; 4]);
+        glyph.extend(u16s(&[0, 100, 0, (-100i16) as u16]));
+        glyph.extend(u16s(&[0, 0, 100, 0]));
+
+        glyph
+    }
+
+    // The square moved by (200, 50), then the square at half size
+    fn compound() -> Vec<u8> {
+        u16s(&[
+            (-1i16) as u16,
+            0,
+            0,
+            300,
+            150,
+            0x0001 | 0x0002 | 0x0020,
+            1,
+            200,
+            50,
+            0x0002 | 0x0008,
+            1,
+            0,
+            8192,
+        ])
+    }
+
+    // 'A' and 'B' through a delta, 'C' through the glyph id array, 'D' is in no segment
+    fn cmap_format_4() -> Vec<u8> {
+        let mut subtable = u16s(&[4, 0, 0, 6, 4, 1, 2]);
+        subtable.extend(u16s(&[66, 67, 0xffff, 0]));
+        subtable.extend(u16s(&[65, 67, 0xffff]));
+        subtable.extend(u16s(&[(-64i16) as u16, 0, 1]));
+        subtable.extend(u16s(&[0, 4, 0]));
+        subtable.extend(u16s(&[2]));
+
+        subtable
+    }
+
+    fn cmap_format_12() -> Vec<u8> {
+        let mut subtable = u16s(&[12, 0]);
+        subtable.extend(40u32.to_be_bytes());
+        subtable.extend(0u32.to_be_bytes());
+        subtable.extend(2u32.to_be_bytes());
+
+        for (start, end, glyph) in [(65u32, 66u32, 1u32), (0x1f600, 0x1f600, 2)] {
+            subtable.extend(start.to_be_bytes());
+            subtable.extend(end.to_be_bytes());
+            subtable.extend(glyph.to_be_bytes());
+        }
+
+        subtable
+    }
+
+    fn cmap(subtables: &[(u16, u16, Vec<u8>)]) -> Vec<u8> {
+        let mut cmap = u16s(&[0, subtables.len() as u16]);
+        let mut offset = 4 + subtables.len() * 8;
+
+        for (platform, encoding, subtable) in subtables {
+            cmap.extend(u16s(&[*platform, *encoding]));
+            cmap.extend((offset as u32).to_be_bytes());
+            offset += subtable.len();
+        }
+
+        for (_, _, subtable) in subtables {
+            cmap.extend(subtable);
+        }
+
+        cmap
+    }
+
+    // Glyph 0 is an empty .notdef, 1 the square and 2 the compound glyph built from it
+    fn test_font(cmap: Vec<u8>) -> Vec<u8> {
+        let mut head = vec![0; 54];
+        head[18..20].copy_from_slice(&1000u16.to_be_bytes());
+        head[50..52].copy_from_slice(&1u16.to_be_bytes());
+
+        let mut hhea = vec![0; 36];
+        hhea[4..6].copy_from_slice(&800u16.to_be_bytes());
+        hhea[6..8].copy_from_slice(&(-200i16).to_be_bytes());
+        hhea[34..36].copy_from_slice(&3u16.to_be_bytes());
+
+        let (square, compound) = (square(), compound());
+        let mut glyf = square.clone();
+        glyf.extend(&compound);
+
+        let loca = [0, 0, square.len(), square.len() + compound.len()]
+            .iter()
+            .flat_map(|&offset| (offset as u32).to_be_bytes())
+            .collect();
+
+        let mut kern = u16s(&[0, 1, 0, 20, 0x0001, 1, 6, 0, 0]);
+        kern.extend(u16s(&[1, 2, (-50i16) as u16]));
+
+        font(&[
+            (b"head", head),
+            (b"hhea", hhea),
+            (b"maxp", u16s(&[0, 0, 3])),
+            (b"cmap", cmap),
+            (b"glyf", glyf),
+            (b"loca", loca),
+            (b"hmtx", u16s(&[500, 0, 600, 0, 700, 0])),
+            (b"kern", kern),
+        ])
+    }
+
+    fn points(contour: &[Point]) -> Vec<(f32, f32)> {
+        contour.iter().map(|point| (point.x, point.y)).collect()
+    }
+
+    #[test]
+    fn cmap_format_4_maps_through_deltas_and_the_glyph_id_array() {
+        let data = test_font(cmap(&[(3, 1, cmap_format_4())]));
+        let font = Font::parse(&data).unwrap();
+
+        assert_eq!(font.glyph_index('A' as u32), 1);
+        assert_eq!(font.glyph_index('B' as u32), 2);
+        assert_eq!(font.glyph_index('C' as u32), 2);
+        assert_eq!(font.glyph_index('D' as u32), 0);
+        assert_eq!(font.glyph_index(0x1f600), 0);
+    }
+
+    #[test]
+    fn cmap_format_12_is_preferred_and_reaches_past_the_bmp() {
+        let data = test_font(cmap(&[(3, 1, cmap_format_4()), (3, 10, cmap_format_12())]));
+        let font = Font::parse(&data).unwrap();
+
+        assert_eq!(font.glyph_index('A' as u32), 1);
+        assert_eq!(font.glyph_index('B' as u32), 2);
+        assert_eq!(font.glyph_index('C' as u32), 0);
+        assert_eq!(font.glyph_index(0x1f600), 2);
+    }
+
+    #[test]
+    fn compound_glyphs_place_and_scale_their_components() {
+        let data = test_font(cmap(&[(3, 1, cmap_format_4())]));
+        let font = Font::parse(&data).unwrap();
+
+        let outline = font.outline(2).unwrap();
+
+        assert_eq!(outline.len(), 2);
+        assert_eq!(
+            points(&outline[0]),
+            [(200.0, 50.0), (300.0, 50.0), (300.0, 150.0), (200.0, 150.0)]
+        );
+        assert_eq!(
+            points(&outline[1]),
+            [(0.0, 0.0), (50.0, 0.0), (50.0, 50.0), (0.0, 50.0)]
+        );
+        assert!(font.outline(0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn metrics_and_kern_pairs_are_read() {
+        let data = test_font(cmap(&[(3, 1, cmap_format_4())]));
+        let font = Font::parse(&data).unwrap();
+
+        assert_eq!(
+            (font.units_per_em, font.ascender, font.descender),
+            (1000, 800, -200)
+        );
+        assert_eq!(font.advance(1), 600);
+        assert_eq!(font.kerning(), [(1, 2, -50)]);
+    }
+
+    #[test]
+    fn squares_rasterize_to_full_coverage() {
+        let data = test_font(cmap(&[(3, 1, cmap_format_4())]));
+        let font = Font::parse(&data).unwrap();
+
+        // 100 units at 1000 per em and 80px is 8 pixels a side
+        let bitmap = rasterize(&font.outline(1).unwrap(), 0.08).unwrap();
+
+        assert_eq!(bitmap.image.dimensions(), (8, 8));
+        assert!(bitmap.image.pixels().all(|pixel| pixel.0[3] == 255));
+        assert_eq!((bitmap.left, bitmap.top), (0, 8));
+    }
+}
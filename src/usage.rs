@@ -0,0 +1,95 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+
+use crate::{view::View, Fragment};
+
+#[derive(Serialize, Default)]
+struct Usage {
+    sprites: usize,
+    area: u64,
+    atlas_percent: f64,
+    used_percent: f64,
+}
+
+#[derive(Serialize)]
+struct UsageReport {
+    total_area: u64,
+    used_area: u64,
+    directories: BTreeMap<String, Usage>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    views: BTreeMap<String, Usage>,
+}
+
+pub fn write_report(
+    path: &Path,
+    fragments: &HashMap<PathBuf, Fragment>,
+    views: &[View],
+    total_area: u64,
+) -> io::Result<()> {
+    let area = |fragment: &Fragment| fragment.size.x as u64 * fragment.size.y as u64;
+    // Regions and aliases point into other fragments' pixels and would count the same area twice
+    let fragments = fragments
+        .iter()
+        .filter(|(_, fragment)| fragment.parent.is_none() && fragment.alias_of.is_none())
+        .collect::<Vec<_>>();
+
+    let used_area = fragments
+        .iter()
+        .map(|(_, fragment)| area(fragment))
+        .sum::<u64>();
+
+    let mut directories = BTreeMap::<String, Usage>::new();
+
+    for &(key, fragment) in &fragments {
+        let directory = key
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .map(|parent| parent.to_string_lossy().replace('\\', "/"))
+            .unwrap_or_else(|| ".".to_string());
+
+        let usage = directories.entry(directory).or_default();
+        usage.sprites += 1;
+        usage.area += area(fragment);
+    }
+
+    // Views may overlap, so their shares are not expected to add up to the total
+    let mut view_usage = BTreeMap::<String, Usage>::new();
+
+    for view in views {
+        let usage = view_usage.entry(view.name.clone()).or_default();
+
+        for &(key, fragment) in &fragments {
+            if view.includes(key) {
+                usage.sprites += 1;
+                usage.area += area(fragment);
+            }
+        }
+    }
+
+    for usage in directories.values_mut().chain(view_usage.values_mut()) {
+        usage.atlas_percent = percent(usage.area, total_area);
+        usage.used_percent = percent(usage.area, used_area);
+    }
+
+    let report = UsageReport {
+        total_area,
+        used_area,
+        directories,
+        views: view_usage,
+    };
+
+    fs::write(path, serde_json::to_string_pretty(&report).unwrap())
+}
+
+fn percent(area: u64, total: u64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+
+    area as f64 / total as f64 * 100.0
+}
@@ -0,0 +1,175 @@
+use std::str::FromStr;
+
+type Version = (u64, u64, u64);
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Operator {
+    Exact,
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+    Tilde,
+    Caret,
+}
+
+#[derive(Clone, Debug)]
+struct Comparator {
+    operator: Operator,
+    // Only the components that were written, `1.2` leaves the patch open
+    parts: Vec<u64>,
+}
+
+impl Comparator {
+    fn lowest(&self) -> Version {
+        let part = |index: usize| self.parts.get(index).copied().unwrap_or(0);
+
+        (part(0), part(1), part(2))
+    }
+
+    // The first version past the written ones, `1.2` stops before 1.3.0
+    fn past(&self, parts: usize) -> Version {
+        match parts {
+            1 => (self.parts[0] + 1, 0, 0),
+            2 => (self.parts[0], self.parts[1] + 1, 0),
+            _ => (self.parts[0], self.parts[1], self.parts[2] + 1),
+        }
+    }
+
+    fn matches(&self, version: Version) -> bool {
+        let written = self.parts.len();
+
+        match self.operator {
+            Operator::Exact => self.lowest() <= version && version < self.past(written),
+            Operator::Greater => version >= self.past(written),
+            Operator::GreaterEq => version >= self.lowest(),
+            Operator::Less => version < self.lowest(),
+            Operator::LessEq => version < self.past(written),
+            Operator::Tilde => self.lowest() <= version && version < self.past(written.min(2)),
+            // Compatible as long as the leftmost non-zero written component stays the same
+            Operator::Caret => {
+                let significant = self
+                    .parts
+                    .iter()
+                    .position(|&part| part != 0)
+                    .map_or(written, |index| index + 1);
+
+                self.lowest() <= version && version < self.past(significant)
+            }
+        }
+    }
+}
+
+// Cargo's requirement syntax without pre-releases: comma separated comparators that all have
+// to hold, a bare version means `^`
+#[derive(Clone, Debug)]
+pub struct VersionReq {
+    text: String,
+    comparators: Vec<Comparator>,
+}
+
+impl VersionReq {
+    pub fn matches(&self, version: &str) -> bool {
+        let Some(parts) = parse_parts(version).filter(|parts| parts.len() == 3) else {
+            return false;
+        };
+
+        let version = (parts[0], parts[1], parts[2]);
+
+        self.comparators
+            .iter()
+            .all(|comparator| comparator.matches(version))
+    }
+}
+
+impl std::fmt::Display for VersionReq {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "{}", self.text)
+    }
+}
+
+impl FromStr for VersionReq {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let comparators = value
+            .split(',')
+            .map(|comparator| {
+                let comparator = comparator.trim();
+                let (operator, version) = [
+                    (">=", Operator::GreaterEq),
+                    ("<=", Operator::LessEq),
+                    (">", Operator::Greater),
+                    ("<", Operator::Less),
+                    ("=", Operator::Exact),
+                    ("~", Operator::Tilde),
+                    ("^", Operator::Caret),
+                ]
+                .into_iter()
+                .find_map(|(prefix, operator)| {
+                    comparator
+                        .strip_prefix(prefix)
+                        .map(|version| (operator, version))
+                })
+                .unwrap_or((Operator::Caret, comparator));
+
+                let parts = parse_parts(version.trim())
+                    .ok_or_else(|| format!("invalid version requirement '{comparator}'"))?;
+
+                Ok(Comparator { operator, parts })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(Self {
+            text: value.to_string(),
+            comparators,
+        })
+    }
+}
+
+fn parse_parts(version: &str) -> Option<Vec<u64>> {
+    let parts = version
+        .split('.')
+        .map(|part| part.parse().ok())
+        .collect::<Option<Vec<_>>>()?;
+
+    (1..=3).contains(&parts.len()).then_some(parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VersionReq;
+
+    fn matches(requirement: &str, version: &str) -> bool {
+        requirement.parse::<VersionReq>().unwrap().matches(version)
+    }
+
+    #[test]
+    fn bare_versions_are_caret_requirements() {
+        assert!(matches("1.2", "1.9.0"));
+        assert!(!matches("1.2", "2.0.0"));
+        assert!(matches("0.1", "0.1.7"));
+        assert!(!matches("0.1", "0.2.0"));
+        assert!(!matches("^0.0.3", "0.0.4"));
+    }
+
+    #[test]
+    fn comparators_follow_cargo() {
+        assert!(matches("=0.1", "0.1.5"));
+        assert!(!matches("=0.1.0", "0.1.1"));
+        assert!(matches(">0.1", "0.2.0"));
+        assert!(!matches(">0.1", "0.1.9"));
+        assert!(matches("<=0.1", "0.1.9"));
+        assert!(matches("~1.2.3", "1.2.9"));
+        assert!(!matches("~1.2.3", "1.3.0"));
+        assert!(matches(">=0.1, <0.3", "0.2.4"));
+        assert!(!matches(">=0.1, <0.3", "0.3.0"));
+    }
+
+    #[test]
+    fn malformed_requirements_are_rejected() {
+        assert!("".parse::<VersionReq>().is_err());
+        assert!(">=1.2.3.4".parse::<VersionReq>().is_err());
+        assert!("1.0.0-beta".parse::<VersionReq>().is_err());
+    }
+}
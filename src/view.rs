@@ -0,0 +1,46 @@
+use std::{path::Path, str::FromStr};
+
+use crate::pattern;
+
+#[derive(Clone)]
+pub struct View {
+    pub name: String,
+    patterns: Vec<String>,
+}
+
+impl View {
+    pub fn includes(&self, key: &Path) -> bool {
+        self.patterns
+            .iter()
+            .any(|pattern| pattern::matches_key(pattern, key))
+    }
+}
+
+impl FromStr for View {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (name, patterns) = value
+            .split_once('=')
+            .ok_or_else(|| format!("expected name=pattern[,pattern...], got '{value}'"))?;
+
+        Ok(Self {
+            name: output_name(name)?,
+            patterns: patterns.split(',').map(str::to_string).collect(),
+        })
+    }
+}
+
+// View and locale names end up in a file name next to the metadata, `{stem}.{name}.{extension}`,
+// so they can't reach into another directory
+pub fn output_name(name: &str) -> Result<String, String> {
+    if name.is_empty() {
+        return Err("name must not be empty".to_string());
+    }
+
+    if name.contains(['/', '\\']) || name.contains("..") || name == "." {
+        return Err(format!("'{name}' can't contain path separators or '..'"));
+    }
+
+    Ok(name.to_string())
+}
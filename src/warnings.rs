@@ -0,0 +1,141 @@
+use std::str::FromStr;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Warning {
+    DuplicateInput,
+    OutsidePalette,
+    LowOccupancy,
+    NonPowerOfTwo,
+    EmptyView,
+    AdjustedSize,
+}
+
+impl Warning {
+    const ALL: [Warning; 6] = [
+        Warning::DuplicateInput,
+        Warning::OutsidePalette,
+        Warning::LowOccupancy,
+        Warning::NonPowerOfTwo,
+        Warning::EmptyView,
+        Warning::AdjustedSize,
+    ];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Warning::DuplicateInput => "W001",
+            Warning::OutsidePalette => "W002",
+            Warning::LowOccupancy => "W003",
+            Warning::NonPowerOfTwo => "W004",
+            Warning::EmptyView => "W005",
+            Warning::AdjustedSize => "W006",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Warning::DuplicateInput => "duplicate-input",
+            Warning::OutsidePalette => "outside-palette",
+            Warning::LowOccupancy => "low-occupancy",
+            Warning::NonPowerOfTwo => "non-power-of-two",
+            Warning::EmptyView => "empty-view",
+            Warning::AdjustedSize => "adjusted-size",
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Lint {
+    All,
+    Only(Warning),
+}
+
+impl Lint {
+    fn matches(self, warning: Warning) -> bool {
+        match self {
+            Lint::All => true,
+            Lint::Only(only) => only == warning,
+        }
+    }
+}
+
+impl FromStr for Lint {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if value == "warnings" {
+            return Ok(Lint::All);
+        }
+
+        Warning::ALL
+            .into_iter()
+            .find(|warning| warning.code().eq_ignore_ascii_case(value) || warning.name() == value)
+            .map(Lint::Only)
+            .ok_or_else(|| format!("unknown warning '{value}'"))
+    }
+}
+
+pub struct Warnings {
+    allow: Vec<Lint>,
+    deny: Vec<Lint>,
+    denied: usize,
+    reported: Vec<Warning>,
+}
+
+impl Warnings {
+    pub fn new(allow: Vec<Lint>, deny: Vec<Lint>) -> Self {
+        Self {
+            allow,
+            deny,
+            denied: 0,
+            reported: Vec::new(),
+        }
+    }
+
+    pub fn emit(&mut self, warning: Warning, message: impl AsRef<str>) {
+        // Like rustc, an explicit deny of a single warning wins over a blanket allow
+        let denied = self.deny.contains(&Lint::Only(warning))
+            || (self.deny.contains(&Lint::All) && !self.allow.contains(&Lint::Only(warning)));
+
+        if denied {
+            eprintln!(
+                "error[{}]: {} ({})",
+                warning.code(),
+                message.as_ref(),
+                warning.name()
+            );
+
+            self.denied += 1;
+            self.reported.push(warning);
+        } else if !self.allow.iter().any(|lint| lint.matches(warning)) {
+            eprintln!(
+                "warning[{}]: {} ({})",
+                warning.code(),
+                message.as_ref(),
+                warning.name()
+            );
+
+            self.reported.push(warning);
+        }
+    }
+
+    pub fn denied(&self) -> usize {
+        self.denied
+    }
+
+    // Allowed warnings aren't counted, the summary should only repeat what was actually shown
+    pub fn reported(&self) -> Vec<(Warning, usize)> {
+        Warning::ALL
+            .into_iter()
+            .map(|warning| {
+                (
+                    warning,
+                    self.reported
+                        .iter()
+                        .filter(|reported| **reported == warning)
+                        .count(),
+                )
+            })
+            .filter(|(_, count)| *count > 0)
+            .collect()
+    }
+}
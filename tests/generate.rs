@@ -0,0 +1,1322 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+use serde_json::Value;
+
+// A scratch directory per test, so tests running in parallel don't share outputs
+fn directory(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("atlas-{name}-{}", std::process::id()));
+
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+
+    directory
+}
+
+fn atlas(directory: &Path, arguments: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_atlas"))
+        .current_dir(directory)
+        .args(arguments)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn invalid_allocator_options_leave_no_lock_behind() {
+    let directory = directory("allocator-options");
+    let arguments = [
+        "generate",
+        "--generate",
+        "white=8x8",
+        "--atlas-output",
+        "atlas.png",
+        "--metadata-output",
+        "atlas.json",
+        "--width",
+        "16",
+        "--height",
+        "16",
+        "--lock-timeout",
+        "0",
+    ];
+
+    let output = atlas(
+        &directory,
+        &[&arguments[..], &["--small-size-threshold", "300"]].concat(),
+    );
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--small-size-threshold"));
+
+    let output = atlas(&directory, &arguments);
+
+    assert!(output.status.success(), "{output:?}");
+    assert!(directory.join("atlas.json").exists());
+
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn budget_violations_exit_with_1_and_release_the_lock() {
+    let directory = directory("budget");
+
+    let output = atlas(
+        &directory,
+        &[
+            "generate",
+            "--generate",
+            "white=8x8",
+            "--atlas-output",
+            "atlas.png",
+            "--metadata-output",
+            "atlas.json",
+            "--width",
+            "16",
+            "--height",
+            "16",
+            "--max-pages",
+            "0",
+        ],
+    );
+
+    assert_eq!(output.status.code(), Some(1), "{output:?}");
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("error: aborting due to 1 budget violations"));
+    assert!(directory.join("atlas.json").exists());
+    assert!(!directory.join(".atlas.lock").exists());
+
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+// Floored centers are min + size / 2 in whole pixels
+fn frame(fragment: &Value) -> (u32, u32, u32, u32) {
+    let number = |field: &str, axis: &str| fragment[field][axis].as_f64().unwrap() as u32;
+    let (width, height) = (number("size", "x"), number("size", "y"));
+
+    (
+        number("center", "x") - width / 2,
+        number("center", "y") - height / 2,
+        width,
+        height,
+    )
+}
+
+#[test]
+fn rotated_sprites_are_described_the_way_each_format_turns_them() {
+    let directory = directory("rotation");
+    // A 4x16 sprite only fits the 16x4 page turned
+    let arguments = [
+        "generate",
+        "--generate",
+        "tall=4x16",
+        "--width",
+        "16",
+        "--height",
+        "4",
+        "--allow-rotation",
+        "--atlas-output",
+        "atlas.png",
+        "--metadata-output",
+        "atlas.json",
+        "--metadata-format",
+    ];
+
+    let output = atlas(
+        &directory,
+        &[&arguments[..], &["texture-packer-hash"]].concat(),
+    );
+
+    assert!(output.status.success(), "{output:?}");
+
+    let document =
+        serde_json::from_slice::<Value>(&fs::read(directory.join("atlas.json")).unwrap()).unwrap();
+
+    assert_eq!(document["frames"]["tall"]["rotated"], true);
+    assert_eq!(
+        document["frames"]["tall"]["frame"],
+        serde_json::json!({"x": 0, "y": 0, "w": 4, "h": 16})
+    );
+
+    let output = atlas(&directory, &[&arguments[..], &["json"]].concat());
+
+    assert!(output.status.success(), "{output:?}");
+
+    let document =
+        serde_json::from_slice::<Value>(&fs::read(directory.join("atlas.json")).unwrap()).unwrap();
+
+    assert_eq!(document["$meta"]["rotation"], "clockwise");
+    assert_eq!(document["tall"]["rotated"], true);
+
+    // These turn sprites counter-clockwise or not at all
+    for format in ["libgdx", "sparrow", "unity"] {
+        let output = atlas(&directory, &[&arguments[..], &[format]].concat());
+
+        assert_eq!(output.status.code(), Some(2), "{format}");
+        assert!(String::from_utf8_lossy(&output.stderr).contains("drop --allow-rotation"));
+    }
+
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn converted_metadata_matches_what_generate_writes() {
+    let directory = directory("convert");
+    let arguments = [
+        "generate",
+        "--generate",
+        "wide=12x4",
+        "--generate",
+        "square=6x6",
+        "--width",
+        "64",
+        "--height",
+        "64",
+        "--atlas-output",
+        "atlas.png",
+    ];
+
+    let output = atlas(
+        &directory,
+        &[
+            &arguments[..],
+            &[
+                "--metadata-output",
+                "direct.json",
+                "--metadata-format",
+                "texture-packer-hash",
+            ],
+        ]
+        .concat(),
+    );
+
+    assert!(output.status.success(), "{output:?}");
+
+    // Centers measured the other way still describe the same frames once converted
+    let output = atlas(
+        &directory,
+        &[
+            &arguments[..],
+            &[
+                "--metadata-output",
+                "atlas.json",
+                "--origin",
+                "center",
+                "--flip-y",
+            ],
+        ]
+        .concat(),
+    );
+
+    assert!(output.status.success(), "{output:?}");
+
+    let output = atlas(
+        &directory,
+        &[
+            "convert",
+            "atlas.json",
+            "converted.json",
+            "--to",
+            "texture-packer-hash",
+        ],
+    );
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--atlas"));
+
+    let output = atlas(
+        &directory,
+        &[
+            "convert",
+            "atlas.json",
+            "converted.json",
+            "--to",
+            "texture-packer-hash",
+            "--atlas",
+            "atlas.png",
+        ],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+
+    let read = |name: &str| {
+        serde_json::from_slice::<Value>(&fs::read(directory.join(name)).unwrap()).unwrap()
+    };
+
+    assert_eq!(
+        read("converted.json")["frames"],
+        read("direct.json")["frames"]
+    );
+    assert_eq!(read("converted.json")["meta"]["image"], "atlas.png");
+
+    let output = atlas(
+        &directory,
+        &[
+            "convert",
+            "direct.json",
+            "godot",
+            "--from",
+            "texture-packer",
+            "--to",
+            "godot",
+        ],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+
+    let frame = &read("direct.json")["frames"]["wide"]["frame"];
+    let resource = fs::read_to_string(directory.join("godot/wide.tres")).unwrap();
+
+    assert!(resource.contains(&format!(
+        "region = Rect2({}, {}, 12, 4)",
+        frame["x"], frame["y"]
+    )));
+    assert!(resource.contains(r#"path="../atlas.png""#));
+
+    // AtlasTexture regions can't be rotated, just like generate refuses --godot-output with them
+    let mut rotated = read("direct.json");
+    rotated["frames"]["wide"]["rotated"] = Value::Bool(true);
+    fs::write(directory.join("rotated.json"), rotated.to_string()).unwrap();
+
+    let output = atlas(
+        &directory,
+        &[
+            "convert",
+            "rotated.json",
+            "godot",
+            "--from",
+            "texture-packer",
+            "--to",
+            "godot",
+        ],
+    );
+
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("godot cannot describe"));
+
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn binary_metadata_is_never_written_centered_or_flipped() {
+    let directory = directory("binary-origin");
+    let arguments = [
+        "generate",
+        "--generate",
+        "white=8x8",
+        "--width",
+        "16",
+        "--height",
+        "16",
+        "--atlas-output",
+        "atlas.png",
+    ];
+
+    let output = atlas(
+        &directory,
+        &[
+            &arguments[..],
+            &[
+                "--metadata-output",
+                "atlas.bin",
+                "--metadata-format",
+                "binary",
+                "--flip-y",
+            ],
+        ]
+        .concat(),
+    );
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(!directory.join("atlas.bin").exists());
+
+    let output = atlas(
+        &directory,
+        &[
+            &arguments[..],
+            &["--metadata-output", "atlas.json", "--origin", "center"],
+        ]
+        .concat(),
+    );
+
+    assert!(output.status.success(), "{output:?}");
+
+    let output = atlas(
+        &directory,
+        &["convert", "atlas.json", "atlas.bin", "--to", "binary"],
+    );
+
+    assert_eq!(output.status.code(), Some(3));
+    assert!(!directory.join("atlas.bin").exists());
+
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn overlapping_atlases_report_their_shared_sprites() {
+    let directory = directory("overlap");
+    let sprite = |name: &str, color: [u8; 4]| {
+        let path = directory.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        image::RgbaImage::from_pixel(8, 4, image::Rgba(color))
+            .save(path)
+            .unwrap();
+    };
+
+    sprite("hud/button.png", [255, 0, 0, 255]);
+    sprite("hud/health.png", [0, 255, 0, 255]);
+    sprite("menu/common/button.png", [255, 0, 0, 255]);
+    sprite("menu/logo.png", [0, 0, 255, 255]);
+
+    for job in ["hud", "menu"] {
+        let output = atlas(
+            &directory,
+            &[
+                "generate",
+                "--files",
+                job,
+                "--recursive",
+                "--width",
+                "64",
+                "--height",
+                "64",
+                "--atlas-output",
+                &format!("{job}.png"),
+                "--metadata-output",
+                &format!("{job}.json"),
+            ],
+        );
+
+        assert!(output.status.success(), "{output:?}");
+    }
+
+    let output = atlas(
+        &directory,
+        &["overlap", "hud.png", "hud.json", "menu.png", "menu.json"],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+
+    let report = String::from_utf8_lossy(&output.stdout);
+
+    assert!(
+        report.starts_with("Sprites packed in more than one atlas: 1,"),
+        "{report}"
+    );
+    assert!(report.contains("save 32 px"), "{report}");
+    assert!(
+        report.contains("8x4: hud/button.png in hud.json, menu/common/button.png in menu.json"),
+        "{report}"
+    );
+
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn snapped_pages_record_their_used_area_in_the_meta_section() {
+    let directory = directory("snap-pot-up");
+
+    let output = atlas(
+        &directory,
+        &[
+            "generate",
+            "--generate",
+            "white=5x3",
+            "--width",
+            "10",
+            "--height",
+            "6",
+            "--snap-pot-up",
+            "--atlas-output",
+            "atlas.png",
+            "--metadata-output",
+            "atlas.json",
+        ],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+
+    let document =
+        serde_json::from_slice::<Value>(&fs::read(directory.join("atlas.json")).unwrap()).unwrap();
+
+    assert_eq!(
+        document["$meta"]["pages"],
+        serde_json::json!([{
+            "page": 0,
+            "width": 16,
+            "height": 8,
+            "used": {"x": 0, "y": 0, "width": 5, "height": 3},
+        }])
+    );
+    assert!(!directory.join("atlas.pages.json").exists());
+
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn split_opaque_pages_hold_one_blend_each() {
+    let directory = directory("split-opaque");
+
+    image::RgbaImage::from_pixel(4, 4, image::Rgba([255, 255, 255, 128]))
+        .save(directory.join("glass.png"))
+        .unwrap();
+
+    let output = atlas(
+        &directory,
+        &[
+            "generate",
+            "--files",
+            "glass.png",
+            "--generate",
+            "wall=4x4",
+            "--generate",
+            "floor=4x4",
+            "--width",
+            "64",
+            "--height",
+            "64",
+            "--split-opaque",
+            "--atlas-output",
+            "atlas.png",
+            "--metadata-output",
+            "atlas.json",
+        ],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+
+    let document =
+        serde_json::from_slice::<Value>(&fs::read(directory.join("atlas.json")).unwrap()).unwrap();
+    let pages = document["$meta"]["pages"].as_array().unwrap();
+
+    assert_eq!(pages.len(), 2);
+
+    for (key, blend) in [
+        ("glass.png", "translucent"),
+        ("wall", "opaque"),
+        ("floor", "opaque"),
+    ] {
+        let page = document[key]["page"].as_u64().unwrap() as usize;
+
+        assert_eq!(pages[page]["blend"], blend, "{key}");
+    }
+
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn volatile_sprites_get_their_own_page() {
+    let directory = directory("volatile");
+
+    let output = atlas(
+        &directory,
+        &[
+            "generate",
+            "--generate",
+            "ui/logo=4x4",
+            "--generate",
+            "events/banner=4x4",
+            "--generate",
+            "ui/frame=4x4",
+            "--volatile",
+            "events/*",
+            "--width",
+            "64",
+            "--height",
+            "64",
+            "--atlas-output",
+            "atlas.png",
+            "--metadata-output",
+            "atlas.json",
+        ],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+
+    let document =
+        serde_json::from_slice::<Value>(&fs::read(directory.join("atlas.json")).unwrap()).unwrap();
+    let pages = document["$meta"]["pages"].as_array().unwrap();
+    let page = |key: &str| document[key]["page"].as_u64().unwrap() as usize;
+
+    assert_eq!(pages.len(), 2);
+    assert_eq!(page("ui/logo"), page("ui/frame"));
+    assert_eq!(pages[page("events/banner")]["volatile"], true);
+    assert!(pages[page("ui/logo")].get("volatile").is_none());
+
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn locale_pages_keep_each_locale_apart_from_the_shared_base() {
+    let directory = directory("locale-pages");
+
+    let output = atlas(
+        &directory,
+        &[
+            "generate",
+            "--generate",
+            "logo=4x4",
+            "--generate",
+            "button=4x4",
+            "--generate",
+            "button_ja=4x4",
+            "--generate",
+            "title_en=4x4",
+            "--generate",
+            "title_ja=4x4",
+            "--locale",
+            "en",
+            "--locale",
+            "ja",
+            "--locale-pages",
+            "--width",
+            "64",
+            "--height",
+            "64",
+            "--atlas-output",
+            "atlas.png",
+            "--metadata-output",
+            "atlas.json",
+        ],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+
+    let read = |name: &str| {
+        serde_json::from_slice::<Value>(&fs::read(directory.join(name)).unwrap()).unwrap()
+    };
+    let document = read("atlas.json");
+    let pages = document["$meta"]["pages"].as_array().unwrap();
+    let page = |key: &str| document[key]["page"].as_u64().unwrap() as usize;
+
+    assert_eq!(pages.len(), 3);
+    assert_eq!(page("logo"), page("button"));
+    assert!(pages[page("logo")].get("locale").is_none());
+    assert_eq!(page("button_ja"), page("title_ja"));
+    assert_eq!(pages[page("title_ja")]["locale"], "ja");
+    assert_eq!(pages[page("title_en")]["locale"], "en");
+
+    // The Japanese view only reaches the shared pages and the Japanese ones
+    let japanese = read("atlas.ja.json");
+
+    for key in ["logo", "button", "title"] {
+        let page = japanese[key]["page"].as_u64().unwrap() as usize;
+
+        assert!(
+            pages[page]
+                .get("locale")
+                .is_none_or(|locale| locale == "ja"),
+            "{key}"
+        );
+    }
+
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn diff_events_list_the_fragments_to_update() {
+    let directory = directory("diff-events");
+    let generate = |metadata_output: &str, placeholders: &[&str]| {
+        let mut arguments = vec![
+            "generate",
+            "--width",
+            "64",
+            "--height",
+            "64",
+            "--sort",
+            "name",
+            "--atlas-output",
+            "atlas.png",
+            "--metadata-output",
+            metadata_output,
+        ];
+
+        for placeholder in placeholders {
+            arguments.extend(["--generate", placeholder]);
+        }
+
+        let output = atlas(&directory, &arguments);
+
+        assert!(output.status.success(), "{output:?}");
+    };
+
+    generate("old.json", &["a=8x8", "b=8x8", "c=4x4"]);
+    generate("new.json", &["b=8x8", "c=6x4", "d=4x4"]);
+
+    let output = atlas(
+        &directory,
+        &[
+            "diff",
+            "atlas.png",
+            "old.json",
+            "atlas.png",
+            "new.json",
+            "--events",
+            "events.json",
+        ],
+    );
+
+    assert_eq!(output.status.code(), Some(1), "{output:?}");
+
+    let events =
+        serde_json::from_slice::<Value>(&fs::read(directory.join("events.json")).unwrap()).unwrap();
+    let changes = events
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|event| (event["key"].as_str().unwrap(), event["changes"].clone()))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        changes,
+        [
+            ("a", serde_json::json!(["removed"])),
+            ("b", serde_json::json!(["moved"])),
+            ("c", serde_json::json!(["resized"])),
+            ("d", serde_json::json!(["added"])),
+        ]
+    );
+    assert_eq!(events[1]["new"]["x"], 0);
+    assert!(events[3].get("old").is_none());
+
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn tileable_sprites_wrap_their_extrusion_and_keep_their_borders() {
+    let directory = directory("tileable");
+    let (red, blue) = (image::Rgba([255, 0, 0, 255]), image::Rgba([0, 0, 255, 255]));
+
+    // A transparent top row that --trim would cut, above a red and a blue column
+    let mut tile = image::RgbaImage::new(2, 2);
+    tile.put_pixel(0, 1, red);
+    tile.put_pixel(1, 1, blue);
+
+    for name in ["tile.png", "plain.png"] {
+        tile.save(directory.join(name)).unwrap();
+    }
+
+    let output = atlas(
+        &directory,
+        &[
+            "generate",
+            "--files",
+            "tile.png",
+            "--files",
+            "plain.png",
+            "--trim",
+            "--extrude",
+            "1",
+            "--tileable",
+            "tile",
+            "--width",
+            "64",
+            "--height",
+            "64",
+            "--atlas-output",
+            "atlas.png",
+            "--metadata-output",
+            "atlas.json",
+        ],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+
+    let document =
+        serde_json::from_slice::<Value>(&fs::read(directory.join("atlas.json")).unwrap()).unwrap();
+    let atlas = image::open(directory.join("atlas.png")).unwrap().to_rgba8();
+
+    assert_eq!(document["tile.png"]["tileable"], true);
+    assert!(document["tile.png"].get("source_size").is_none());
+
+    let (x, y, width, height) = frame(&document["tile.png"]);
+    assert_eq!((width, height), (2, 2));
+    assert_eq!(atlas.get_pixel(x - 1, y + 1), &blue);
+    assert_eq!(atlas.get_pixel(x + 2, y + 1), &red);
+
+    let (x, y, _, height) = frame(&document["plain.png"]);
+    assert_eq!(height, 1);
+    assert_eq!(atlas.get_pixel(x - 1, y), &red);
+
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn key_commands_name_and_tag_every_input() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let directory = directory("key-command");
+
+    for name in ["hero_idle.png", "hero_run.png"] {
+        image::RgbaImage::from_pixel(4, 4, image::Rgba([255, 0, 0, 255]))
+            .save(directory.join(name))
+            .unwrap();
+    }
+
+    // hero_idle.png becomes hero/idle, tagged with both halves of its name
+    let script = directory.join("keys.sh");
+    fs::write(
+        &script,
+        "#!/bin/sh\nawk -F '\\t' '{ split($2, parts, \"[_.]\"); \
+         printf \"%s/%s\\t%s,%s\\n\", parts[1], parts[2], parts[1], parts[2] }'\n",
+    )
+    .unwrap();
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let output = atlas(
+        &directory,
+        &[
+            "generate",
+            "--files",
+            "hero_idle.png",
+            "--files",
+            "hero_run.png",
+            "--key-command",
+            "./keys.sh",
+            "--width",
+            "64",
+            "--height",
+            "64",
+            "--atlas-output",
+            "atlas.png",
+            "--metadata-output",
+            "atlas.json",
+        ],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+
+    let document =
+        serde_json::from_slice::<Value>(&fs::read(directory.join("atlas.json")).unwrap()).unwrap();
+
+    assert_eq!(
+        document["hero/idle"]["tags"],
+        serde_json::json!(["hero", "idle"])
+    );
+    assert_eq!(
+        document["hero/run"]["tags"],
+        serde_json::json!(["hero", "run"])
+    );
+    assert!(document.get("hero_idle.png").is_none());
+
+    // Keys the command gives more than one input are refused like any other duplicate
+    fs::write(&script, "#!/bin/sh\nsed 's/.*/hero/'\n").unwrap();
+
+    let output = atlas(
+        &directory,
+        &[
+            "generate",
+            "--files",
+            "hero_idle.png",
+            "--files",
+            "hero_run.png",
+            "--key-command",
+            "./keys.sh",
+            "--width",
+            "64",
+            "--height",
+            "64",
+            "--atlas-output",
+            "atlas.png",
+            "--metadata-output",
+            "atlas.json",
+        ],
+    );
+
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("key 'hero' is already used"));
+
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn lower_priorities_never_go_before_a_higher_priority_page() {
+    let directory = directory("priority");
+
+    let output = atlas(
+        &directory,
+        &[
+            "generate",
+            "--generate",
+            "ui/font=16x12",
+            "--generate",
+            "ui/icons=16x12",
+            "--generate",
+            "props/dot=4x4",
+            "--priority",
+            "ui/*=10",
+            "--algorithm",
+            "max-rects",
+            "--width",
+            "16",
+            "--height",
+            "16",
+            "--atlas-output",
+            "atlas.png",
+            "--metadata-output",
+            "atlas.json",
+        ],
+    );
+
+    assert!(output.status.success(), "{output:?}");
+
+    let document =
+        serde_json::from_slice::<Value>(&fs::read(directory.join("atlas.json")).unwrap()).unwrap();
+    let page = |key: &str| document[key]["page"].as_u64().unwrap();
+
+    // The dot would fit under the first UI sprite, but the UI spilled onto the second page
+    assert_eq!([page("ui/font"), page("ui/icons")], [0, 1]);
+    assert_eq!(page("props/dot"), 1);
+    assert_eq!(document["ui/font"]["priority"], 10);
+
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn provenance_macs_depend_on_the_key_and_the_document() {
+    let directory = directory("provenance-mac");
+    let arguments = [
+        "generate",
+        "--generate",
+        "white=5x3",
+        "--width",
+        "8",
+        "--height",
+        "8",
+        "--atlas-output",
+        "atlas.png",
+        "--metadata-output",
+        "atlas.json",
+        "--provenance",
+        "provenance.json",
+        "--mac-key",
+        "key",
+    ];
+    let mut macs = Vec::new();
+
+    for key in ["first", "second", "first"] {
+        fs::write(directory.join("key"), key).unwrap();
+
+        let output = atlas(&directory, &arguments);
+
+        assert!(output.status.success(), "{output:?}");
+
+        let mac = fs::read_to_string(directory.join("provenance.json.mac")).unwrap();
+
+        assert_eq!(mac.len(), 65, "{mac}");
+        assert!(mac.trim_end().bytes().all(|byte| byte.is_ascii_hexdigit()));
+
+        macs.push(mac);
+    }
+
+    assert_ne!(macs[0], macs[1]);
+    assert_eq!(macs[0], macs[2]);
+
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn example_pipeline_packs_every_sprite_where_the_metadata_says() {
+    let directory = directory("examples");
+
+    let output = atlas(&directory, &["examples", "corpus"]);
+
+    assert!(output.status.success(), "{output:?}");
+
+    let corpus = directory.join("corpus");
+    let contact_sheet = fs::read_to_string(corpus.join("contact_sheet.html")).unwrap();
+
+    assert!(contact_sheet.starts_with("<!DOCTYPE html>"));
+    assert!(!corpus.join("contact_sheet.png").exists());
+
+    for report in ["layout.svg", "gpu.json"] {
+        assert!(corpus.join(report).exists(), "{report} was not written");
+    }
+
+    let metadata =
+        serde_json::from_str::<Value>(&fs::read_to_string(corpus.join("atlas.json")).unwrap())
+            .unwrap();
+    let fragments = metadata.as_object().unwrap();
+    let atlas_image = image::open(corpus.join("atlas.png")).unwrap().to_rgba8();
+
+    assert_eq!(fragments["$meta"]["rounding"], "floor");
+
+    let sprites = fragments
+        .iter()
+        .filter(|(key, _)| key.as_str() != "$meta")
+        .collect::<Vec<_>>();
+
+    // 12 crates, 6 orbs, a duplicate crate, 8 walk frames, a panel and an empty image
+    assert_eq!(sprites.len(), 29);
+
+    let mut packed = Vec::new();
+
+    for (key, fragment) in &sprites {
+        let source = image::open(directory.join(key)).unwrap().to_rgba8();
+        let (x, y, width, height) = frame(fragment);
+        let (offset_x, offset_y) = (
+            fragment["offset"]["x"].as_f64().unwrap_or(0.0) as u32,
+            fragment["offset"]["y"].as_f64().unwrap_or(0.0) as u32,
+        );
+
+        assert!(x + width <= atlas_image.width() && y + height <= atlas_image.height());
+
+        let in_atlas = image::imageops::crop_imm(&atlas_image, x, y, width, height).to_image();
+        let in_source =
+            image::imageops::crop_imm(&source, offset_x, offset_y, width, height).to_image();
+
+        assert!(in_atlas == in_source, "{key} doesn't match its source");
+
+        if fragment.get("alias_of").is_none() {
+            packed.push((key, (x, y, width, height)));
+        }
+    }
+
+    for (index, (key, (x, y, width, height))) in packed.iter().enumerate() {
+        for (other_key, (other_x, other_y, other_width, other_height)) in &packed[index + 1..] {
+            let overlaps = x < &(other_x + other_width)
+                && other_x < &(x + width)
+                && y < &(other_y + other_height)
+                && other_y < &(y + height);
+
+            assert!(!overlaps, "{key} overlaps {other_key}");
+        }
+    }
+
+    let (_, copy) = sprites
+        .iter()
+        .find(|(key, _)| key.ends_with("props/crate_copy.png"))
+        .unwrap();
+
+    assert!(copy["alias_of"]
+        .as_str()
+        .is_some_and(|original| original.ends_with("props/crate_0.png")));
+
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn remote_inputs_only_run_curl_when_allowed_to() {
+    let directory = directory("allow-curl");
+
+    let output = atlas(
+        &directory,
+        &[
+            "generate",
+            "--remote",
+            "https://cdn.invalid/white.png",
+            "--remote-cache",
+            "cache",
+            "--width",
+            "16",
+            "--height",
+            "16",
+            "--atlas-output",
+            "atlas.png",
+            "--metadata-output",
+            "atlas.json",
+        ],
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(2), "{stderr}");
+    assert!(stderr.contains("--allow-curl"), "{stderr}");
+    assert!(!directory.join("cache").exists());
+
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn untrusted_runs_refuse_everything_reaching_past_the_inputs() {
+    let directory = directory("untrusted");
+    let arguments = [
+        "generate",
+        "--generate",
+        "white=8x8",
+        "--width",
+        "16",
+        "--height",
+        "16",
+        "--atlas-output",
+        "atlas.png",
+        "--metadata-output",
+        "atlas.json",
+        "--untrusted",
+    ];
+
+    for (flag, values) in [
+        ("--stats-history", &["history.json"][..]),
+        ("--key-command", &["/bin/cat"]),
+        (
+            "--remote",
+            &["https://cdn.invalid/white.png", "--allow-curl"],
+        ),
+        ("--mask", &["*=/etc/mask.png"]),
+        ("--palette", &["/etc/palette.png"]),
+        ("--compose", &["hero=/etc/base.png+/etc/overlay.png"]),
+        ("--sub-atlas", &["/etc/atlas.png", "/etc/atlas.json"]),
+    ] {
+        let output = atlas(&directory, &[&arguments[..], &[flag], values].concat());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert_eq!(output.status.code(), Some(2), "{flag}: {stderr}");
+        assert!(
+            stderr.contains(flag) && stderr.contains("--untrusted"),
+            "{stderr}"
+        );
+    }
+
+    assert!(!directory.join("atlas.json").exists());
+
+    let output = atlas(&directory, &arguments);
+
+    assert!(output.status.success(), "{output:?}");
+
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn view_and_locale_outputs_stay_next_to_the_metadata_and_never_overlap() {
+    let directory = directory("view-names");
+    let arguments = [
+        "generate",
+        "--generate",
+        "white=8x8",
+        "--width",
+        "16",
+        "--height",
+        "16",
+        "--atlas-output",
+        "atlas.png",
+        "--metadata-output",
+        "atlas.json",
+    ];
+
+    for extra in [
+        &["--view", "../x=*"][..],
+        &["--view", "a/b=*"],
+        &["--locale", "..\\ja"],
+        &["--locale", ".."],
+    ] {
+        let output = atlas(&directory, &[&arguments[..], extra].concat());
+
+        assert_eq!(output.status.code(), Some(2), "{extra:?}: {output:?}");
+    }
+
+    for extra in [
+        &["--view", "ja=*", "--locale", "ja"][..],
+        &["--view", "ui=*", "--view", "ui=white"],
+        &["--view", "tiles=*", "--tile-size", "8"],
+    ] {
+        let output = atlas(&directory, &[&arguments[..], extra].concat());
+
+        assert_eq!(output.status.code(), Some(3), "{extra:?}: {output:?}");
+        assert!(String::from_utf8_lossy(&output.stderr).contains("would both be written"));
+    }
+
+    assert!(!directory.join("atlas.json").exists());
+
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn daemon_requests_pack_inspect_append_and_unpack() {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        os::unix::net::UnixStream,
+        process::Stdio,
+        thread,
+        time::Duration,
+    };
+
+    let directory = directory("daemon");
+    let socket = directory.join("atlas.sock");
+    let mut daemon = Command::new(env!("CARGO_BIN_EXE_atlas"))
+        .current_dir(&directory)
+        .args(["daemon", "--socket", "atlas.sock"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let stream = (0..100)
+        .find_map(|_| {
+            thread::sleep(Duration::from_millis(20));
+            UnixStream::connect(&socket).ok()
+        })
+        .expect("the daemon never started listening");
+    let mut responses = BufReader::new(stream.try_clone().unwrap()).lines();
+    let mut request = |id: u32, method: &str, params: Value| {
+        let request =
+            serde_json::json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+        writeln!(&stream, "{request}").unwrap();
+
+        let response = serde_json::from_str::<Value>(&responses.next().unwrap().unwrap()).unwrap();
+        assert_eq!(response["id"], id);
+
+        response
+    };
+
+    let size = ["--width", "64", "--height", "64"];
+    let pack = [
+        &["--generate", "white=8x8", "--atlas-output", "atlas.png"][..],
+        &["--metadata-output", "atlas.json"],
+        &size,
+    ]
+    .concat();
+
+    let response = request(1, "pack", serde_json::json!(pack));
+    assert_eq!(response["result"], Value::Null, "{response}");
+
+    // A usage error fails the request, the daemon stays up for the next one
+    let response = request(
+        2,
+        "pack",
+        serde_json::json!([
+            &pack[..],
+            &["--layout", "array", "--css-output", "atlas.css"]
+        ]
+        .concat()),
+    );
+    assert_eq!(response["error"]["code"], 2, "{response}");
+
+    let arguments = [&["--generate", "black=4x4:checkerboard"][..], &size].concat();
+    let response = request(
+        3,
+        "append",
+        serde_json::json!({
+            "atlas": "atlas.png",
+            "metadata": "atlas.json",
+            "arguments": arguments,
+        }),
+    );
+    assert_eq!(response["result"], Value::Null, "{response}");
+
+    let response = request(4, "inspect", serde_json::json!({"metadata": "atlas.json"}));
+    let mut keys = response["result"]
+        .as_object()
+        .unwrap()
+        .keys()
+        .cloned()
+        .collect::<Vec<_>>();
+    keys.sort();
+    assert_eq!(keys, ["black", "white"], "{response}");
+
+    let response = request(
+        5,
+        "unpack",
+        serde_json::json!({"atlas": "atlas.png", "metadata": "atlas.json", "output": "sprites"}),
+    );
+    assert_eq!(
+        response["result"].as_array().unwrap().len(),
+        2,
+        "{response}"
+    );
+    assert_eq!(
+        image::image_dimensions(directory.join("sprites/white.png")).unwrap(),
+        (8, 8)
+    );
+
+    let response = request(6, "repack", Value::Null);
+    assert_eq!(response["error"]["code"], -32601, "{response}");
+
+    request(7, "shutdown", Value::Null);
+
+    assert!(daemon.wait().unwrap().success());
+    assert!(!socket.exists());
+
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[cfg(unix)]
+fn interrupt(child: &std::process::Child) {
+    let status = Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+
+    assert!(status.success());
+}
+
+#[cfg(unix)]
+fn wait_for(path: &Path) {
+    use std::{thread, time::Duration};
+
+    assert!(
+        (0..250).any(|_| {
+            thread::sleep(Duration::from_millis(20));
+            path.exists()
+        }),
+        "{} never appeared",
+        path.display()
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn ctrl_c_stops_generate_and_releases_the_locks_it_took() {
+    use std::process::Stdio;
+
+    let directory = directory("interrupt-generate");
+    fs::create_dir_all(directory.join("first")).unwrap();
+    fs::create_dir_all(directory.join("second")).unwrap();
+
+    // Locks are taken in order, so generate waits for the second directory holding the first
+    fs::write(directory.join("second/.atlas.lock"), "0\n").unwrap();
+
+    let child = Command::new(env!("CARGO_BIN_EXE_atlas"))
+        .current_dir(&directory)
+        .args(["generate", "--generate", "white=8x8"])
+        .args(["--atlas-output", "second/atlas.png"])
+        .args(["--metadata-output", "first/atlas.json"])
+        .args(["--width", "16", "--height", "16"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    wait_for(&directory.join("first/.atlas.lock"));
+    interrupt(&child);
+
+    let output = child.wait_with_output().unwrap();
+
+    assert_eq!(output.status.code(), Some(130), "{output:?}");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("interrupted"));
+    assert!(!directory.join("first/.atlas.lock").exists());
+    assert!(directory.join("second/.atlas.lock").exists());
+    assert!(!directory.join("second/atlas.png").exists());
+
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn ctrl_c_shuts_the_daemon_down_and_removes_its_socket() {
+    use std::{os::unix::net::UnixStream, process::Stdio};
+
+    let directory = directory("interrupt-daemon");
+    let socket = directory.join("atlas.sock");
+    let mut daemon = Command::new(env!("CARGO_BIN_EXE_atlas"))
+        .current_dir(&directory)
+        .args(["daemon", "--socket", "atlas.sock"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    wait_for(&socket);
+
+    // An idle client doesn't keep it running
+    let _client = UnixStream::connect(&socket).unwrap();
+
+    interrupt(&daemon);
+
+    assert!(daemon.wait().unwrap().success());
+    assert!(!socket.exists());
+
+    fs::remove_dir_all(&directory).unwrap();
+}